@@ -0,0 +1,77 @@
+-- Choice groups, paid add-ons and per-guest course selections
+-- A choice group collects menu items a guest picks from (e.g. "Choose one main"),
+-- add-ons are optional paid extras (wine pairing, extra course) offered with a menu,
+-- and bookings record each guest's picks so the chef knows what to prepare.
+
+CREATE TABLE menu_choice_groups (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    menu_id UUID NOT NULL REFERENCES menus(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL, -- e.g., 'Choose your main'
+    description TEXT,
+    course_type VARCHAR(50), -- e.g., 'appetizer', 'main', 'dessert'
+    min_choices INTEGER NOT NULL DEFAULT 1,
+    max_choices INTEGER NOT NULL DEFAULT 1,
+    display_order INTEGER DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT check_choice_bounds CHECK (min_choices >= 0 AND max_choices >= 1 AND max_choices >= min_choices)
+);
+
+CREATE INDEX idx_menu_choice_groups_menu_id ON menu_choice_groups(menu_id);
+
+-- Items belonging to a choice group are picked per guest; items without one are served to everyone
+ALTER TABLE menu_items
+ADD COLUMN IF NOT EXISTS choice_group_id UUID REFERENCES menu_choice_groups(id) ON DELETE SET NULL;
+
+ALTER TABLE menu_items
+ADD COLUMN IF NOT EXISTS price_adjustment DECIMAL(10, 2) DEFAULT 0;
+
+COMMENT ON COLUMN menu_items.price_adjustment IS 'Per-guest supplement charged when a guest picks this item';
+
+CREATE INDEX idx_menu_items_choice_group_id ON menu_items(choice_group_id);
+
+CREATE TABLE menu_add_ons (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    menu_id UUID NOT NULL REFERENCES menus(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL, -- e.g., 'Wine pairing'
+    description TEXT,
+    price DECIMAL(10, 2) NOT NULL,
+    pricing_unit VARCHAR(20) NOT NULL DEFAULT 'per_guest', -- per_guest, per_booking
+    is_active BOOLEAN DEFAULT true,
+    display_order INTEGER DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT check_add_on_pricing_unit CHECK (pricing_unit IN ('per_guest', 'per_booking')),
+    CONSTRAINT check_add_on_price CHECK (price >= 0)
+);
+
+CREATE INDEX idx_menu_add_ons_menu_id ON menu_add_ons(menu_id);
+
+-- Names and prices are copied at booking time so later menu edits don't change past bookings
+CREATE TABLE booking_guest_selections (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    booking_id UUID NOT NULL REFERENCES bookings(id) ON DELETE CASCADE,
+    guest_number INTEGER NOT NULL,
+    guest_name VARCHAR(255),
+    choice_group_id UUID REFERENCES menu_choice_groups(id) ON DELETE SET NULL,
+    menu_item_id UUID REFERENCES menu_items(id) ON DELETE SET NULL,
+    item_name VARCHAR(255) NOT NULL,
+    price_adjustment DECIMAL(10, 2) NOT NULL DEFAULT 0,
+    dietary_notes TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_booking_guest_selections_booking_id ON booking_guest_selections(booking_id);
+
+CREATE TABLE booking_add_ons (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    booking_id UUID NOT NULL REFERENCES bookings(id) ON DELETE CASCADE,
+    add_on_id UUID REFERENCES menu_add_ons(id) ON DELETE SET NULL,
+    name VARCHAR(255) NOT NULL,
+    quantity INTEGER NOT NULL DEFAULT 1,
+    unit_price DECIMAL(10, 2) NOT NULL,
+    total_price DECIMAL(10, 2) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_booking_add_ons_booking_id ON booking_add_ons(booking_id);
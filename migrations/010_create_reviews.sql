@@ -0,0 +1,17 @@
+-- Diner reviews shown on public chef pages
+-- A review belongs to a chef and, when left after an event, to the booking it rates.
+
+CREATE TABLE reviews (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    chef_id UUID NOT NULL REFERENCES chefs(id) ON DELETE CASCADE,
+    booking_id UUID UNIQUE REFERENCES bookings(id) ON DELETE SET NULL, -- One review per booking
+    customer_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    reviewer_name VARCHAR(255) NOT NULL,
+    rating INTEGER NOT NULL,
+    comment TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT check_rating CHECK (rating BETWEEN 1 AND 5)
+);
+
+CREATE INDEX idx_reviews_chef_id ON reviews(chef_id, created_at DESC);
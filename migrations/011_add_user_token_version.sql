@@ -0,0 +1,5 @@
+-- Version stamped into access tokens and sessions
+-- Bumping it (e.g. on a role change) invalidates everything issued before
+
+ALTER TABLE users
+ADD COLUMN token_version INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,18 @@
+-- Email verification and password reset
+-- Only a SHA-256 hash of each emailed token is stored; tokens are single use
+
+ALTER TABLE users
+ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE user_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose VARCHAR(32) NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT check_purpose CHECK (purpose IN ('email_verification', 'password_reset'))
+);
+
+CREATE INDEX idx_user_tokens_user_id ON user_tokens(user_id, purpose);
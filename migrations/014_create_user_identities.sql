@@ -0,0 +1,20 @@
+-- Logins through OpenID Connect providers
+-- Accounts created through a provider have no password until the user
+-- sets one with a password reset
+
+ALTER TABLE users
+ALTER COLUMN password_hash DROP NOT NULL;
+
+-- One row per provider account linked to a user
+CREATE TABLE user_identities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(64) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    email VARCHAR(255),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    last_login_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (provider, subject)
+);
+
+CREATE INDEX idx_user_identities_user_id ON user_identities(user_id);
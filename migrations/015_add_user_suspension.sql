@@ -0,0 +1,6 @@
+-- Suspended accounts can't log in, and their tokens and sessions stop
+-- working; set and cleared through the admin API
+
+ALTER TABLE users
+ADD COLUMN suspended_at TIMESTAMP WITH TIME ZONE,
+ADD COLUMN suspension_reason TEXT;
@@ -0,0 +1,33 @@
+-- Moderation by platform staff
+-- Staff deactivate chefs and hide menus and reviews; the owners can't undo
+-- either. Users report content through content_flags.
+
+ALTER TABLE chefs
+ADD COLUMN deactivated_at TIMESTAMP WITH TIME ZONE,
+ADD COLUMN deactivation_reason TEXT;
+
+ALTER TABLE menus
+ADD COLUMN hidden_at TIMESTAMP WITH TIME ZONE;
+
+ALTER TABLE reviews
+ADD COLUMN hidden_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE content_flags (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    content_type VARCHAR(20) NOT NULL, -- chef, menu, review
+    content_id UUID NOT NULL,
+    reporter_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'open', -- open, resolved, dismissed
+    resolved_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    resolved_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT check_content_type CHECK (content_type IN ('chef', 'menu', 'review')),
+    CONSTRAINT check_flag_status CHECK (status IN ('open', 'resolved', 'dismissed'))
+);
+
+CREATE INDEX idx_content_flags_status ON content_flags(status, created_at DESC);
+CREATE INDEX idx_content_flags_content ON content_flags(content_type, content_id);
+-- One open report per user per item
+CREATE UNIQUE INDEX idx_content_flags_open_report
+ON content_flags(content_type, content_id, reporter_id) WHERE status = 'open';
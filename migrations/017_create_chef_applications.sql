@@ -0,0 +1,40 @@
+-- Chef applications
+-- Diners apply to become chefs with their credentials; staff approve or
+-- reject. Approval is the only way besides a staff role change to get the
+-- chef role, and so to publish a profile. Chefs who registered before this
+-- keep their role and profiles.
+
+CREATE TABLE chef_applications (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    experience TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- pending, approved, rejected
+    reviewed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMP WITH TIME ZONE,
+    rejection_reason TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT check_application_status CHECK (status IN ('pending', 'approved', 'rejected'))
+);
+
+CREATE INDEX idx_chef_applications_status ON chef_applications(status, created_at);
+CREATE INDEX idx_chef_applications_user ON chef_applications(user_id, created_at DESC);
+-- One application under review per user at a time
+CREATE UNIQUE INDEX idx_chef_applications_pending
+    ON chef_applications(user_id) WHERE status = 'pending';
+
+-- Stored under the private prefix of the blob store; staff download them
+-- through the app
+CREATE TABLE chef_application_documents (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    application_id UUID NOT NULL REFERENCES chef_applications(id) ON DELETE CASCADE,
+    kind VARCHAR(40) NOT NULL, -- food_safety_certificate, insurance
+    file_name TEXT NOT NULL,
+    content_type VARCHAR(100) NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    storage_key TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT check_document_kind CHECK (kind IN ('food_safety_certificate', 'insurance'))
+);
+
+CREATE INDEX idx_chef_application_documents_application ON chef_application_documents(application_id);
@@ -0,0 +1,39 @@
+-- Append-only record of who did what to what
+-- Rows are never updated or deleted; the triggers below refuse both, so the
+-- application can't rewrite history through a bug or a stray query. They
+-- don't bind the table's owner, which can drop or disable them: when the app
+-- runs migrations with its own account, that is the app. See
+-- docs/AUDIT_LOG.md for giving the table a separate owner. actor_id and
+-- target_id aren't foreign keys, so events outlive the rows they mention.
+
+CREATE TABLE audit_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    actor_id UUID,
+    actor_role VARCHAR(20),
+    action VARCHAR(100) NOT NULL,
+    target_type VARCHAR(50),
+    target_id UUID,
+    before JSONB,
+    after JSONB,
+    metadata JSONB NOT NULL DEFAULT '{}'
+);
+
+CREATE INDEX idx_audit_events_occurred_at ON audit_events(occurred_at DESC);
+CREATE INDEX idx_audit_events_actor ON audit_events(actor_id, occurred_at DESC);
+CREATE INDEX idx_audit_events_target ON audit_events(target_type, target_id, occurred_at DESC);
+CREATE INDEX idx_audit_events_action ON audit_events(action, occurred_at DESC);
+
+CREATE FUNCTION audit_events_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_no_update_or_delete
+BEFORE UPDATE OR DELETE ON audit_events
+FOR EACH ROW EXECUTE FUNCTION audit_events_append_only();
+
+CREATE TRIGGER audit_events_no_truncate
+BEFORE TRUNCATE ON audit_events
+FOR EACH STATEMENT EXECUTE FUNCTION audit_events_append_only();
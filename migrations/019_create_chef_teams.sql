@@ -0,0 +1,55 @@
+-- Chef teams
+-- Several users can run one chef business. The user in chefs.user_id is
+-- the team's owner; owners invite managers and staff by email. A user
+-- belongs to at most one team, so "my chef profile" stays unambiguous.
+
+CREATE TABLE chef_members (
+    chef_id UUID NOT NULL REFERENCES chefs(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL UNIQUE REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(20) NOT NULL, -- owner, manager, staff
+    -- Whether joining made a diner a chef, so leaving can undo it
+    granted_chef_role BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (chef_id, user_id),
+    CONSTRAINT check_team_role CHECK (role IN ('owner', 'manager', 'staff'))
+);
+
+-- One owner per team
+CREATE UNIQUE INDEX idx_chef_members_owner ON chef_members(chef_id) WHERE role = 'owner';
+
+-- Existing chefs are owned by the user who created them...
+INSERT INTO chef_members (chef_id, user_id, role, created_at, updated_at)
+SELECT id, user_id, 'owner', created_at, created_at FROM chefs;
+
+-- ...and so are new ones, whichever handler creates them
+CREATE FUNCTION chefs_add_owner() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO chef_members (chef_id, user_id, role) VALUES (NEW.id, NEW.user_id, 'owner');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER chefs_add_owner
+AFTER INSERT ON chefs
+FOR EACH ROW EXECUTE FUNCTION chefs_add_owner();
+
+-- Only a SHA-256 hash of each emailed token is stored, as for user_tokens
+CREATE TABLE chef_invitations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    chef_id UUID NOT NULL REFERENCES chefs(id) ON DELETE CASCADE,
+    email VARCHAR(255) NOT NULL, -- lowercased
+    role VARCHAR(20) NOT NULL, -- manager, staff
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    invited_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    accepted_at TIMESTAMP WITH TIME ZONE,
+    accepted_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT check_invitation_role CHECK (role IN ('manager', 'staff'))
+);
+
+CREATE INDEX idx_chef_invitations_chef ON chef_invitations(chef_id, created_at DESC);
+-- One open invitation per address per team; inviting again replaces it
+CREATE UNIQUE INDEX idx_chef_invitations_open
+    ON chef_invitations(chef_id, email) WHERE accepted_at IS NULL;
@@ -0,0 +1,42 @@
+-- Staff roster
+-- Chefs keep a roster of helpers (sous chefs, servers, bartenders) they hire
+-- for large events, and assign them to bookings. A helper's cost is added to
+-- the booking's price. Helpers who sign up with the email on the roster see
+-- the events they're assigned to.
+
+CREATE TABLE chef_helpers (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    chef_id UUID NOT NULL REFERENCES chefs(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    email VARCHAR(255), -- lowercased
+    phone VARCHAR(50),
+    role VARCHAR(20) NOT NULL, -- sous_chef, server, bartender
+    hourly_rate DECIMAL(10, 2) NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT check_helper_role CHECK (role IN ('sous_chef', 'server', 'bartender')),
+    CONSTRAINT check_helper_rate CHECK (hourly_rate >= 0)
+);
+
+CREATE INDEX idx_chef_helpers_chef_id ON chef_helpers(chef_id);
+CREATE INDEX idx_chef_helpers_email ON chef_helpers(email);
+
+-- Name, role and rate are copied at assignment so later roster edits don't
+-- change what a booking costs
+CREATE TABLE booking_staff (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    booking_id UUID NOT NULL REFERENCES bookings(id) ON DELETE CASCADE,
+    helper_id UUID REFERENCES chef_helpers(id) ON DELETE SET NULL,
+    name VARCHAR(255) NOT NULL,
+    role VARCHAR(20) NOT NULL,
+    hourly_rate DECIMAL(10, 2) NOT NULL,
+    hours DECIMAL(4, 2) NOT NULL,
+    total_cost DECIMAL(10, 2) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT check_booking_staff_hours CHECK (hours > 0),
+    UNIQUE (booking_id, helper_id)
+);
+
+CREATE INDEX idx_booking_staff_booking_id ON booking_staff(booking_id);
+CREATE INDEX idx_booking_staff_helper_id ON booking_staff(helper_id);
@@ -1,6 +1,11 @@
 // Simple script to make a user an admin
 // Run with: cargo run --bin make_admin -- mattbraun@example.com
 
+use privatechefspace_backend::audit::AuditEvent;
+use privatechefspace_backend::config::RedisConfig;
+use privatechefspace_backend::handlers::auth::change_user_role;
+use privatechefspace_backend::models::Role;
+use privatechefspace_backend::RedisClient;
 use sqlx::PgPool;
 use std::env;
 
@@ -8,25 +13,50 @@ use std::env;
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv::dotenv().ok();
     
-    let email_pattern = env::args()
+    // The address must match exactly (ignoring case), and nothing is changed
+    // if that matches more than one account
+    let email = env::args()
         .nth(1)
-        .unwrap_or_else(|| "mattbraun".to_string());
+        .map(|email| email.trim().to_string())
+        .filter(|email| !email.is_empty())
+        .ok_or("Usage: make_admin <email>")?;
     
     let database_url = env::var("DATABASE_URL")
         .expect("DATABASE_URL must be set");
     
     let pool = PgPool::connect(&database_url).await?;
-    
-    // Update user role to admin
-    let result = sqlx::query(
-        "UPDATE users SET role = 'admin' WHERE email LIKE $1 OR email ILIKE $2"
-    )
-    .bind(format!("%{}%", email_pattern))
-    .bind(format!("%{}%", email_pattern))
-    .execute(&pool)
+    let redis = RedisClient::new(&RedisConfig {
+        url: env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string()),
+    })
     .await?;
     
-    println!("Updated {} user(s) to admin role", result.rows_affected());
+    let users = sqlx::query_as::<_, (uuid::Uuid, Role)>("SELECT id, role FROM users WHERE LOWER(email) = LOWER($1)")
+        .bind(&email)
+        .fetch_all(&pool)
+        .await?;
+    let user = match users.as_slice() {
+        [] => return Err(format!("No user with email {}", email).into()),
+        [user] => *user,
+        _ => return Err(format!("{} accounts match {} ignoring case; not changing any", users.len(), email).into()),
+    };
+    
+    match user {
+        (_, Role::Admin) => println!("{} is already an admin", email),
+        (user_id, previous) => {
+            // Goes through change_user_role so existing sessions and tokens are
+            // signed out and pick up the new role on their next login
+            change_user_role(&pool, &redis, user_id, Role::Admin).await?;
+            // No actor: the change was made from the command line
+            AuditEvent::new("user_role_changed")
+                .on("user", user_id)
+                .before(serde_json::json!({ "role": previous }))
+                .after(serde_json::json!({ "role": Role::Admin }))
+                .detail("via", "make_admin")
+                .record(&pool)
+                .await;
+            println!("Updated {} to admin role", email);
+        }
+    }
     
     // Verify the update
     let users = sqlx::query_as::<_, (uuid::Uuid, String, String)>(
@@ -42,4 +72,3 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     Ok(())
 }
-
@@ -2,9 +2,10 @@
 -- Usage: Update the email below and run: psql $DATABASE_URL -f scripts/make_admin.sql
 
 -- Update user role to admin (replace 'mattbraun@example.com' with actual email)
+-- Bumping token_version signs the user out so their sessions pick up the role
 UPDATE users 
-SET role = 'admin' 
-WHERE email LIKE '%mattbraun%' OR email LIKE '%matt%braun%';
+SET role = 'admin', token_version = token_version + 1, updated_at = NOW()
+WHERE LOWER(email) = LOWER('mattbraun@example.com');
 
 -- Verify the update
 SELECT id, email, role FROM users WHERE role = 'admin';
@@ -0,0 +1,277 @@
+// Audit trail
+// Security- and money-relevant changes are appended to the `audit_events`
+// table, which the database keeps append-only. Staff read it through
+// `GET /api/admin/audit-events`.
+
+use actix_web::dev::Payload;
+use actix_web::{http::header, FromRequest, HttpRequest};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::FromRow;
+use std::future::{ready, Ready};
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::middleware::auth::Identity;
+use crate::models::Role;
+
+/// Longest user agent kept with an event
+const MAX_USER_AGENT_LEN: usize = 512;
+
+/// Where a request came from, recorded with every event it causes. Take it
+/// as a handler argument and pass it down to whatever does the auditing.
+#[derive(Debug, Clone, Default)]
+pub struct RequestMeta {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl RequestMeta {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        RequestMeta {
+            ip: req.connection_info().peer_addr().map(str::to_string),
+            user_agent: req
+                .headers()
+                .get(header::USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(|agent| agent.chars().take(MAX_USER_AGENT_LEN).collect()),
+        }
+    }
+}
+
+impl FromRequest for RequestMeta {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(RequestMeta::from_request(req)))
+    }
+}
+
+/// One entry in the audit trail, built up and then recorded:
+///
+/// ```ignore
+/// AuditEvent::new("user_role_changed")
+///     .by(&staff)
+///     .on("user", user.id)
+///     .before(json!({ "role": previous }))
+///     .after(json!({ "role": user.role }))
+///     .from(&meta)
+///     .record(&pool)
+///     .await;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AuditEvent {
+    pub action: String,
+    pub actor_id: Option<Uuid>,
+    pub actor_role: Option<Role>,
+    pub target_type: Option<String>,
+    pub target_id: Option<Uuid>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    /// Request metadata plus anything else worth keeping that isn't part of
+    /// the target's state
+    pub metadata: Map<String, Value>,
+}
+
+impl AuditEvent {
+    pub fn new(action: &str) -> Self {
+        AuditEvent { action: action.to_string(), ..Default::default() }
+    }
+
+    /// The signed-in user who did it. While an admin is impersonating them,
+    /// the admin is kept as `impersonated_by`.
+    pub fn by(self, actor: &Identity) -> Self {
+        let event = self.by_user(actor.id, actor.role);
+        match actor.impersonator() {
+            Some(admin_id) => event.detail("impersonated_by", admin_id),
+            None => event,
+        }
+    }
+
+    /// Who did it, for events such as logins where there's no identity yet
+    pub fn by_user(mut self, id: Uuid, role: Role) -> Self {
+        self.actor_id = Some(id);
+        self.actor_role = Some(role);
+        self
+    }
+
+    /// What it was done to, e.g. `("booking", booking.id)`
+    pub fn on(mut self, target_type: &str, target_id: Uuid) -> Self {
+        self.target_type = Some(target_type.to_string());
+        self.target_id = Some(target_id);
+        self
+    }
+
+    /// The part of the target's state that changed, as it was
+    pub fn before(mut self, state: Value) -> Self {
+        self.before = Some(state);
+        self
+    }
+
+    /// The part of the target's state that changed, as it is now
+    pub fn after(mut self, state: Value) -> Self {
+        self.after = Some(state);
+        self
+    }
+
+    /// Before and after as just the top-level fields that differ between two
+    /// versions of the target, leaving out `updated_at`
+    pub fn changes(self, before: &impl Serialize, after: &impl Serialize) -> Self {
+        let (old, new) = match (serde_json::to_value(before), serde_json::to_value(after)) {
+            (Ok(Value::Object(old)), Ok(Value::Object(new))) => (old, new),
+            (Ok(old), Ok(new)) => return self.before(old).after(new),
+            _ => return self,
+        };
+        let changed: Vec<&String> = new
+            .iter()
+            .filter(|(key, value)| key.as_str() != "updated_at" && old.get(key.as_str()) != Some(value))
+            .map(|(key, _)| key)
+            .collect();
+        let pick = |fields: &Map<String, Value>| -> Value {
+            changed
+                .iter()
+                .map(|key| (key.to_string(), fields.get(key.as_str()).cloned().unwrap_or(Value::Null)))
+                .collect::<Map<String, Value>>()
+                .into()
+        };
+        let (before, after) = (pick(&old), pick(&new));
+        self.before(before).after(after)
+    }
+
+    pub fn detail(mut self, key: &str, value: impl Serialize) -> Self {
+        self.metadata.insert(key.to_string(), serde_json::to_value(value).unwrap_or(Value::Null));
+        self
+    }
+
+    pub fn from(self, meta: &RequestMeta) -> Self {
+        self.detail("ip", &meta.ip).detail("user_agent", &meta.user_agent)
+    }
+
+    /// Append the event. A failure is logged rather than returned: the
+    /// change it describes has already happened, and failing the request
+    /// would only hide that from the user.
+    pub async fn record(self, pool: &DbPool) {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO audit_events
+                (actor_id, actor_role, action, target_type, target_id, before, after, metadata)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#
+        )
+        .bind(self.actor_id)
+        .bind(self.actor_role.map(|role| role.to_string()))
+        .bind(&self.action)
+        .bind(&self.target_type)
+        .bind(self.target_id)
+        .bind(&self.before)
+        .bind(&self.after)
+        .bind(Value::Object(self.metadata.clone()))
+        .execute(pool)
+        .await;
+
+        if let Err(e) = result {
+            let metadata = Value::Object(self.metadata);
+            tracing::error!(
+                target: "audit",
+                action = %self.action,
+                actor_id = ?self.actor_id,
+                target_type = ?self.target_type,
+                target_id = ?self.target_id,
+                before = ?self.before,
+                after = ?self.after,
+                metadata = %metadata,
+                error = ?e,
+                "Failed to record audit event"
+            );
+        }
+    }
+}
+
+/// A recorded event, as returned by the admin API
+#[derive(Debug, Serialize, FromRow)]
+pub struct AuditRecord {
+    pub id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    pub actor_id: Option<Uuid>,
+    pub actor_role: Option<String>,
+    pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<Uuid>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub metadata: Value,
+}
+
+/// Most events returned by one query
+const MAX_PAGE_SIZE: i64 = 200;
+const DEFAULT_PAGE_SIZE: i64 = 50;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    pub actor_id: Option<Uuid>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<Uuid>,
+    /// Only events at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only events before this time
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl AuditQuery {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let (Some(since), Some(until)) = (self.since, self.until) {
+            if since >= until {
+                return Err(AppError::ValidationError("since must be before until".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Events matching `query`, newest first, and how many match in total
+pub async fn search_events(pool: &DbPool, query: &AuditQuery) -> Result<(Vec<AuditRecord>, i64), AppError> {
+    query.validate()?;
+    let action = query.action.as_deref().map(str::trim).filter(|a| !a.is_empty());
+    let target_type = query.target_type.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let filter = r#"
+        WHERE ($1::uuid IS NULL OR actor_id = $1)
+          AND ($2::varchar IS NULL OR action = $2)
+          AND ($3::varchar IS NULL OR target_type = $3)
+          AND ($4::uuid IS NULL OR target_id = $4)
+          AND ($5::timestamptz IS NULL OR occurred_at >= $5)
+          AND ($6::timestamptz IS NULL OR occurred_at < $6)
+    "#;
+    let events = sqlx::query_as::<_, AuditRecord>(&format!(
+        "SELECT * FROM audit_events {} ORDER BY occurred_at DESC, id LIMIT $7 OFFSET $8",
+        filter
+    ))
+    .bind(query.actor_id)
+    .bind(action)
+    .bind(target_type)
+    .bind(query.target_id)
+    .bind(query.since)
+    .bind(query.until)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    let (total,) = sqlx::query_as::<_, (i64,)>(&format!("SELECT COUNT(*) FROM audit_events {}", filter))
+        .bind(query.actor_id)
+        .bind(action)
+        .bind(target_type)
+        .bind(query.target_id)
+        .bind(query.since)
+        .bind(query.until)
+        .fetch_one(pool)
+        .await?;
+    Ok((events, total))
+}
@@ -1,4 +1,12 @@
+pub mod oidc;
+pub mod rate_limit;
+pub mod refresh;
 pub mod session;
+pub mod two_factor;
+pub mod user_auth;
 
+pub use oidc::*;
+pub use refresh::*;
 pub use session::*;
-
+pub use two_factor::*;
+pub use user_auth::*;
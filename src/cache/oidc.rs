@@ -0,0 +1,43 @@
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::RedisClient;
+use crate::errors::AppError;
+
+/// How long the user has to finish logging in at the provider
+const OIDC_STATE_TTL_SECONDS: u64 = 10 * 60;
+
+/// What the callback needs to finish an OIDC login, keyed by the `state`
+/// parameter sent to the provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OidcLoginState {
+    pub provider: String,
+    pub pkce_verifier: String,
+    pub nonce: String,
+    pub redirect_uri: String,
+}
+
+fn oidc_state_key(state: &str) -> String {
+    format!("oidc_state:{}", state)
+}
+
+impl RedisClient {
+    pub async fn store_oidc_state(&self, state: &str, login: &OidcLoginState) -> Result<(), AppError> {
+        let json = serde_json::to_string(login)
+            .map_err(|e| AppError::InternalError(format!("Failed to serialize OIDC state: {}", e)))?;
+        let mut conn = self.get_connection().await?;
+        let _: () = conn.set_ex(oidc_state_key(state), json, OIDC_STATE_TTL_SECONDS).await?;
+        Ok(())
+    }
+
+    /// Fetch and delete the login for `state`, so each callback is used once
+    pub async fn take_oidc_state(&self, state: &str) -> Result<Option<OidcLoginState>, AppError> {
+        let mut conn = self.get_connection().await?;
+        let json: Option<String> = conn.get_del(oidc_state_key(state)).await?;
+        json.map(|json| {
+            serde_json::from_str(&json)
+                .map_err(|e| AppError::InternalError(format!("Failed to deserialize OIDC state: {}", e)))
+        })
+        .transpose()
+    }
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use redis::AsyncCommands;
+use uuid::Uuid;
+
+use crate::cache::RedisClient;
+use crate::errors::AppError;
+
+/// Sliding window log: drops entries older than the window, then records
+/// this hit if there is room. Returns `{allowed, retry_after_ms}`.
+const SLIDING_WINDOW_SCRIPT: &str = r#"
+local key = KEYS[1]
+local now = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local limit = tonumber(ARGV[3])
+redis.call('ZREMRANGEBYSCORE', key, 0, now - window)
+if redis.call('ZCARD', key) < limit then
+    redis.call('ZADD', key, now, ARGV[4])
+    redis.call('PEXPIRE', key, window)
+    return {1, 0}
+end
+local oldest = redis.call('ZRANGE', key, 0, 0, 'WITHSCORES')
+return {0, tonumber(oldest[2]) + window - now}
+"#;
+
+/// Records an entry and returns how many are in the window
+const COUNT_IN_WINDOW_SCRIPT: &str = r#"
+local key = KEYS[1]
+local now = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+redis.call('ZREMRANGEBYSCORE', key, 0, now - window)
+redis.call('ZADD', key, now, ARGV[3])
+redis.call('PEXPIRE', key, window)
+return redis.call('ZCARD', key)
+"#;
+
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+fn lockout_key(account: &str) -> String {
+    format!("lockout:{}", account)
+}
+
+fn login_failures_key(account: &str) -> String {
+    format!("login_failures:{}", account)
+}
+
+impl RedisClient {
+    /// Count a hit against `key`, allowing `limit` hits per sliding `window`.
+    /// Returns how long to wait when the limit has been reached.
+    pub async fn check_rate_limit(&self, key: &str, limit: u32, window: Duration) -> Result<Option<Duration>, AppError> {
+        let mut conn = self.get_connection().await?;
+        let (allowed, retry_after_ms): (i64, i64) = redis::Script::new(SLIDING_WINDOW_SCRIPT)
+            .key(key)
+            .arg(now_millis())
+            .arg(window.as_millis() as i64)
+            .arg(limit)
+            .arg(Uuid::new_v4().to_string())
+            .invoke_async(&mut conn)
+            .await?;
+
+        Ok(if allowed == 1 {
+            None
+        } else {
+            Some(Duration::from_millis(retry_after_ms.max(0) as u64))
+        })
+    }
+
+    /// Record a failed login for `account` and return the failures in `window`
+    pub async fn record_login_failure(&self, account: &str, window: Duration) -> Result<u32, AppError> {
+        let mut conn = self.get_connection().await?;
+        let failures: u32 = redis::Script::new(COUNT_IN_WINDOW_SCRIPT)
+            .key(login_failures_key(account))
+            .arg(now_millis())
+            .arg(window.as_millis() as i64)
+            .arg(Uuid::new_v4().to_string())
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(failures)
+    }
+
+    pub async fn clear_login_failures(&self, account: &str) -> Result<(), AppError> {
+        self.delete_session(&login_failures_key(account)).await
+    }
+
+    /// Refuse logins to `account` for `duration`
+    pub async fn lock_account(&self, account: &str, duration: Duration) -> Result<(), AppError> {
+        let mut conn = self.get_connection().await?;
+        let _: () = conn.set_ex(lockout_key(account), "1", duration.as_secs()).await?;
+        let _: () = conn.del(login_failures_key(account)).await?;
+        Ok(())
+    }
+
+    /// Time left on a lockout of `account`, if there is one
+    pub async fn account_lockout(&self, account: &str) -> Result<Option<Duration>, AppError> {
+        let mut conn = self.get_connection().await?;
+        let ttl: i64 = conn.ttl(lockout_key(account)).await?;
+        Ok((ttl > 0).then(|| Duration::from_secs(ttl as u64)))
+    }
+}
@@ -0,0 +1,131 @@
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::cache::RedisClient;
+use crate::errors::AppError;
+
+/// The refresh token family started by one login. Only the latest token in
+/// the family (`current_jti`) may be exchanged; presenting an older one
+/// means it was copied, and the whole family is revoked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshFamily {
+    pub user_id: Uuid,
+    pub current_jti: String,
+}
+
+/// Moves a family from the presented token to the next one, but only if the
+/// presented token is still the latest. Otherwise it was already exchanged,
+/// and the family is revoked. Returns 1 when rotated, -1 when revoked for
+/// reuse and 0 when the family is gone or belongs to someone else.
+const ROTATE_FAMILY_SCRIPT: &str = r#"
+local raw = redis.call('GET', KEYS[1])
+if not raw then
+    return 0
+end
+local family = cjson.decode(raw)
+if family.user_id ~= ARGV[1] then
+    return 0
+end
+if family.current_jti ~= ARGV[2] then
+    redis.call('DEL', KEYS[1])
+    redis.call('SREM', KEYS[2], ARGV[5])
+    return -1
+end
+redis.call('SET', KEYS[1], ARGV[3], 'EX', ARGV[4])
+redis.call('SADD', KEYS[2], ARGV[5])
+redis.call('EXPIRE', KEYS[2], ARGV[4])
+return 1
+"#;
+
+/// Outcome of [`RedisClient::rotate_refresh_family`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshRotation {
+    Rotated,
+    /// The presented token was already exchanged; the family is now revoked
+    Reused,
+    /// The family was revoked, expired or isn't the user's
+    Revoked,
+}
+
+fn family_key(family_id: Uuid) -> String {
+    format!("refresh_family:{}", family_id)
+}
+
+fn user_families_key(user_id: Uuid) -> String {
+    format!("user_refresh_families:{}", user_id)
+}
+
+impl RedisClient {
+    /// Store a family and its latest token id, (re)starting its expiry
+    pub async fn store_refresh_family(&self, family_id: Uuid, family: &RefreshFamily, ttl: usize) -> Result<(), AppError> {
+        let json = serde_json::to_string(family)
+            .map_err(|e| AppError::InternalError(format!("Failed to serialize refresh family: {}", e)))?;
+        let user_key = user_families_key(family.user_id);
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::pipe()
+            .atomic()
+            .set_ex(family_key(family_id), json, ttl as u64)
+            .sadd(&user_key, family_id.to_string())
+            .expire(&user_key, ttl as i64)
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Swap the family over to `next` if `presented_jti` is still its latest
+    /// token, in one step so two requests can't both exchange the same token
+    pub async fn rotate_refresh_family(
+        &self,
+        family_id: Uuid,
+        presented_jti: &str,
+        next: &RefreshFamily,
+        ttl: usize,
+    ) -> Result<RefreshRotation, AppError> {
+        let json = serde_json::to_string(next)
+            .map_err(|e| AppError::InternalError(format!("Failed to serialize refresh family: {}", e)))?;
+        let mut conn = self.get_connection().await?;
+        let outcome: i64 = redis::Script::new(ROTATE_FAMILY_SCRIPT)
+            .key(family_key(family_id))
+            .key(user_families_key(next.user_id))
+            .arg(next.user_id.to_string())
+            .arg(presented_jti)
+            .arg(json)
+            .arg(ttl)
+            .arg(family_id.to_string())
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(match outcome {
+            1 => RefreshRotation::Rotated,
+            -1 => RefreshRotation::Reused,
+            _ => RefreshRotation::Revoked,
+        })
+    }
+
+    pub async fn revoke_refresh_family(&self, family_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::pipe()
+            .atomic()
+            .del(family_key(family_id))
+            .srem(user_families_key(user_id), family_id.to_string())
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Revoke every refresh token family of a user (all devices)
+    pub async fn revoke_user_refresh_families(&self, user_id: Uuid) -> Result<(), AppError> {
+        let user_key = user_families_key(user_id);
+        let mut conn = self.get_connection().await?;
+        let family_ids: Vec<String> = conn.smembers(&user_key).await?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for family_id in &family_ids {
+            pipe.del(format!("refresh_family:{}", family_id));
+        }
+        pipe.del(&user_key);
+        let _: () = pipe.query_async(&mut conn).await?;
+        Ok(())
+    }
+}
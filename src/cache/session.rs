@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use crate::config::RedisConfig;
 use crate::errors::AppError;
+use crate::models::Impersonation;
 
 #[derive(Clone)]
 pub struct RedisClient {
@@ -14,6 +15,19 @@ pub struct SessionData {
     pub user_id: Uuid,
     pub email: String,
     pub role: String,
+    /// Token web forms must echo back; sessions created before CSRF
+    /// protection get one on their next request
+    #[serde(default)]
+    pub csrf_token: String,
+    /// The user's `token_version` at login; the session ends once it changes
+    #[serde(default)]
+    pub token_version: i32,
+    /// Set when an admin is signed in as this user
+    #[serde(default)]
+    pub impersonation: Option<Impersonation>,
+    /// The admin's own session, resumed when the impersonation ends
+    #[serde(default)]
+    pub admin_session_id: Option<String>,
 }
 
 impl RedisClient {
@@ -71,6 +85,20 @@ impl RedisClient {
         }
     }
 
+    /// Replace the data of an existing session without extending its expiry
+    pub async fn update_session_data(&self, session_id: &str, session_data: &SessionData) -> Result<(), AppError> {
+        let session_json = serde_json::to_string(session_data)
+            .map_err(|e| AppError::InternalError(format!("Failed to serialize session: {}", e)))?;
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::cmd("SET")
+            .arg(format!("session:{}", session_id))
+            .arg(session_json)
+            .arg("KEEPTTL")
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
     /// Delete a session
     pub async fn destroy_session(&self, session_id: &str) -> Result<(), AppError> {
         self.delete_session(&format!("session:{}", session_id)).await
@@ -0,0 +1,50 @@
+use redis::AsyncCommands;
+use uuid::Uuid;
+
+use crate::cache::RedisClient;
+use crate::errors::AppError;
+use crate::utils::generate_secret_token;
+
+/// How long a user has to enter their code after the password step
+pub const TWO_FACTOR_CHALLENGE_TTL_SECONDS: usize = 5 * 60;
+
+fn challenge_key(challenge: &str) -> String {
+    format!("two_factor_challenge:{}", challenge)
+}
+
+fn attempts_key(challenge: &str) -> String {
+    format!("two_factor_attempts:{}", challenge)
+}
+
+impl RedisClient {
+    /// Start the second login step for a user whose password checked out.
+    /// The returned id stands in for the password until the code is entered.
+    pub async fn create_two_factor_challenge(&self, user_id: Uuid) -> Result<String, AppError> {
+        let challenge = generate_secret_token();
+        self.set_session(&challenge_key(&challenge), &user_id.to_string(), TWO_FACTOR_CHALLENGE_TTL_SECONDS)
+            .await?;
+        Ok(challenge)
+    }
+
+    /// The user a pending challenge belongs to
+    pub async fn get_two_factor_challenge(&self, challenge: &str) -> Result<Option<Uuid>, AppError> {
+        Ok(self
+            .get_session(&challenge_key(challenge))
+            .await?
+            .and_then(|user_id| Uuid::parse_str(&user_id).ok()))
+    }
+
+    /// Count a wrong code against a challenge and return the attempts so far
+    pub async fn record_two_factor_attempt(&self, challenge: &str) -> Result<u32, AppError> {
+        let mut conn = self.get_connection().await?;
+        let key = attempts_key(challenge);
+        let attempts: u32 = conn.incr(&key, 1).await?;
+        let _: () = conn.expire(&key, TWO_FACTOR_CHALLENGE_TTL_SECONDS as i64).await?;
+        Ok(attempts)
+    }
+
+    pub async fn clear_two_factor_challenge(&self, challenge: &str) -> Result<(), AppError> {
+        self.delete_session(&challenge_key(challenge)).await?;
+        self.delete_session(&attempts_key(challenge)).await
+    }
+}
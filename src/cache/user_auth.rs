@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::cache::RedisClient;
+use crate::errors::AppError;
+use crate::models::Role;
+
+/// How long a cached role/version is trusted. Changes made through the app
+/// invalidate the entry straight away; this bounds changes made elsewhere.
+const USER_AUTH_TTL_SECONDS: usize = 15 * 60;
+
+/// What a token or session is checked against on each request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserAuthState {
+    pub role: Role,
+    pub token_version: i32,
+    #[serde(default)]
+    pub two_factor_enabled: bool,
+    #[serde(default)]
+    pub suspended: bool,
+}
+
+fn user_auth_key(user_id: Uuid) -> String {
+    format!("user_auth:{}", user_id)
+}
+
+impl RedisClient {
+    /// Cached role and token version for a user
+    pub async fn get_user_auth(&self, user_id: Uuid) -> Result<Option<UserAuthState>, AppError> {
+        match self.get_cache(&user_auth_key(user_id)).await? {
+            Some(json) => serde_json::from_str(&json)
+                .map(Some)
+                .map_err(|e| AppError::InternalError(format!("Failed to deserialize user auth state: {}", e))),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn cache_user_auth(&self, user_id: Uuid, state: &UserAuthState) -> Result<(), AppError> {
+        let json = serde_json::to_string(state)
+            .map_err(|e| AppError::InternalError(format!("Failed to serialize user auth state: {}", e)))?;
+        self.set_cache(&user_auth_key(user_id), &json, USER_AUTH_TTL_SECONDS).await
+    }
+
+    /// Drop the cached state so the next request reads it from the database
+    pub async fn invalidate_user_auth(&self, user_id: Uuid) -> Result<(), AppError> {
+        self.delete_session(&user_auth_key(user_id)).await
+    }
+}
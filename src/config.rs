@@ -1,12 +1,18 @@
 use serde::Deserialize;
 use std::env;
 
+use crate::models::Role;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub redis: RedisConfig,
     pub jwt: JwtConfig,
+    pub storage: StorageConfig,
+    pub mail: MailConfig,
+    pub two_factor: TwoFactorConfig,
+    pub oidc: OidcConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -33,6 +39,95 @@ pub struct JwtConfig {
     pub refresh_expiration: i64,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageConfig {
+    pub backend: String, // "local" or "s3"
+    pub local_path: String,
+    pub public_url: String, // URL prefix uploaded files are served from
+    pub max_upload_bytes: usize,
+    pub s3_bucket: Option<String>,
+    pub s3_region: String,
+    pub s3_endpoint: Option<String>, // For S3-compatible services (MinIO, R2, ...)
+    pub s3_access_key: Option<String>,
+    pub s3_secret_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MailConfig {
+    pub backend: String, // "log" or "smtp"
+    pub from: String,
+    pub smtp_url: Option<String>,
+    pub app_url: String, // Public site URL, used for links in emails, OIDC redirects and canonical page URLs
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TwoFactorConfig {
+    pub issuer: String, // Account label shown in authenticator apps
+    pub required_roles: Vec<Role>,
+}
+
+impl TwoFactorConfig {
+    /// Whether users with `role` must enable 2FA. Always true for admins.
+    pub fn requires(&self, role: Role) -> bool {
+        role == Role::Admin || self.required_roles.contains(&role)
+    }
+}
+
+/// OpenID Connect providers users can log in with
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OidcConfig {
+    pub providers: Vec<OidcProviderConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcProviderConfig {
+    pub name: String, // Used in URLs, e.g. /auth/oidc/google
+    pub display_name: String,
+    pub issuer_url: String, // Discovery document is read from here
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub scopes: Vec<String>,
+}
+
+impl OidcConfig {
+    pub fn provider(&self, name: &str) -> Option<&OidcProviderConfig> {
+        self.providers.iter().find(|provider| provider.name == name)
+    }
+
+    /// Providers are listed in `OIDC_PROVIDERS` (e.g. `google,okta`), each
+    /// configured by `OIDC_<NAME>_*` variables
+    fn from_env() -> Result<Self, String> {
+        let names = env::var("OIDC_PROVIDERS").unwrap_or_default();
+        let providers = names
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let var = |key: &str| env::var(format!("OIDC_{}_{}", name.to_uppercase(), key)).ok();
+                let required = |key: &str| {
+                    var(key).ok_or_else(|| format!("OIDC_{}_{} must be set", name.to_uppercase(), key))
+                };
+                Ok(OidcProviderConfig {
+                    display_name: var("DISPLAY_NAME").unwrap_or_else(|| {
+                        let mut chars = name.chars();
+                        chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+                    }),
+                    issuer_url: required("ISSUER_URL")?,
+                    client_id: required("CLIENT_ID")?,
+                    client_secret: var("CLIENT_SECRET"),
+                    scopes: var("SCOPES")
+                        .unwrap_or_else(|| "openid email profile".to_string())
+                        .split_whitespace()
+                        .map(str::to_string)
+                        .collect(),
+                    name,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(OidcConfig { providers })
+    }
+}
+
 impl Config {
     pub fn from_env() -> Result<Self, String> {
         dotenv::dotenv().ok();
@@ -68,6 +163,38 @@ impl Config {
                     .parse()
                     .unwrap_or(86400),
             },
+            storage: StorageConfig {
+                backend: env::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string()),
+                local_path: env::var("UPLOADS_DIR").unwrap_or_else(|_| "./uploads".to_string()),
+                public_url: env::var("STORAGE_PUBLIC_URL").unwrap_or_else(|_| "/uploads".to_string()),
+                max_upload_bytes: env::var("MAX_UPLOAD_BYTES")
+                    .unwrap_or_else(|_| "10485760".to_string())
+                    .parse()
+                    .unwrap_or(10 * 1024 * 1024),
+                s3_bucket: env::var("S3_BUCKET").ok(),
+                s3_region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                s3_endpoint: env::var("S3_ENDPOINT").ok(),
+                s3_access_key: env::var("S3_ACCESS_KEY").ok(),
+                s3_secret_key: env::var("S3_SECRET_KEY").ok(),
+            },
+            mail: MailConfig {
+                backend: env::var("MAIL_BACKEND").unwrap_or_else(|_| "log".to_string()),
+                from: env::var("MAIL_FROM")
+                    .unwrap_or_else(|_| "PrivateChefSpace <no-reply@privatechefspace.com>".to_string()),
+                smtp_url: env::var("SMTP_URL").ok(),
+                app_url: env::var("APP_URL").unwrap_or_else(|_| "http://localhost:8080".to_string()),
+            },
+            two_factor: TwoFactorConfig {
+                issuer: env::var("TOTP_ISSUER").unwrap_or_else(|_| "PrivateChefSpace".to_string()),
+                required_roles: env::var("TWO_FACTOR_REQUIRED_ROLES")
+                    .unwrap_or_else(|_| "admin".to_string())
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|role| !role.is_empty())
+                    .map(|role| role.parse().map_err(|_| format!("Invalid role in TWO_FACTOR_REQUIRED_ROLES: {}", role)))
+                    .collect::<Result<_, _>>()?,
+            },
+            oidc: OidcConfig::from_env()?,
         })
     }
 }
@@ -9,7 +9,9 @@ pub enum AppError {
     BcryptError(bcrypt::BcryptError),
     ValidationError(String),
     Unauthorized(String),
+    Forbidden(String),
     NotFound(String),
+    TooManyRequests(String),
     InternalError(String),
 }
 
@@ -22,12 +24,16 @@ impl fmt::Display for AppError {
             AppError::BcryptError(e) => write!(f, "Bcrypt error: {}", e),
             AppError::ValidationError(msg) => write!(f, "Validation error: {}", msg),
             AppError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
             AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
+            AppError::TooManyRequests(msg) => write!(f, "Too many requests: {}", msg),
             AppError::InternalError(msg) => write!(f, "Internal error: {}", msg),
         }
     }
 }
 
+impl std::error::Error for AppError {}
+
 impl ResponseError for AppError {
     fn error_response(&self) -> HttpResponse {
         match self {
@@ -59,6 +65,11 @@ impl ResponseError for AppError {
                     "error": self.to_string()
                 }))
             }
+            AppError::Forbidden(_) => {
+                HttpResponse::Forbidden().json(serde_json::json!({
+                    "error": self.to_string()
+                }))
+            }
             AppError::ValidationError(_) => {
                 HttpResponse::BadRequest().json(serde_json::json!({
                     "error": self.to_string()
@@ -69,6 +80,11 @@ impl ResponseError for AppError {
                     "error": self.to_string()
                 }))
             }
+            AppError::TooManyRequests(_) => {
+                HttpResponse::TooManyRequests().json(serde_json::json!({
+                    "error": self.to_string()
+                }))
+            }
             AppError::BcryptError(_) | AppError::InternalError(_) => {
                 HttpResponse::InternalServerError().json(serde_json::json!({
                     "error": "Internal server error"
@@ -0,0 +1,291 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::audit::{self, AuditEvent, AuditQuery, RequestMeta};
+use crate::cache::RedisClient;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::auth::{change_user_role, revoke_all_sessions, revoke_cached_credentials};
+use crate::middleware::auth::{Identity, RequireRole};
+use crate::middleware::roles::{has_permission, role, Permission};
+use crate::models::{Role, User, UserResponse};
+
+/// Most users returned by one search
+const MAX_PAGE_SIZE: i64 = 100;
+const DEFAULT_PAGE_SIZE: i64 = 25;
+
+/// Whether `actor` may suspend, sign out or change the role of a user with
+/// role `target`. Moderators manage users below them, never admins or other
+/// moderators.
+pub fn can_manage_user(actor: Role, target: Role) -> bool {
+    match actor {
+        Role::Admin => true,
+        _ => has_permission(actor, Permission::ManageUsers) && !matches!(target, Role::Admin | Role::Mod),
+    }
+}
+
+/// Whether `actor` may give someone `role`; moderators can't hand out
+/// their own or higher privileges
+pub fn can_assign_role(actor: Role, role: Role) -> bool {
+    can_manage_user(actor, role)
+}
+
+/// A user as staff see it, including the suspension state
+#[derive(Debug, Serialize)]
+pub struct AdminUserResponse {
+    #[serde(flatten)]
+    pub user: UserResponse,
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspension_reason: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<User> for AdminUserResponse {
+    fn from(user: User) -> Self {
+        AdminUserResponse {
+            suspended_at: user.suspended_at,
+            suspension_reason: user.suspension_reason.clone(),
+            updated_at: user.updated_at,
+            user: UserResponse::from(user),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct UserSearch {
+    /// Part of an email address, matched case-insensitively
+    pub q: Option<String>,
+    pub role: Option<Role>,
+    pub suspended: Option<bool>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Users matching `search`, newest first, and how many match in total
+pub async fn search_users(pool: &DbPool, search: &UserSearch) -> Result<(Vec<User>, i64), AppError> {
+    let pattern = search
+        .q
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(|q| format!("%{}%", q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")));
+    let role = search.role.map(|role| role.to_string());
+    let limit = search.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = search.offset.unwrap_or(0).max(0);
+
+    let filter = r#"
+        WHERE ($1::text IS NULL OR email ILIKE $1)
+          AND ($2::varchar IS NULL OR role = $2)
+          AND ($3::boolean IS NULL OR (suspended_at IS NOT NULL) = $3)
+    "#;
+    let users = sqlx::query_as::<_, User>(&format!(
+        "SELECT * FROM users {} ORDER BY created_at DESC LIMIT $4 OFFSET $5",
+        filter
+    ))
+    .bind(&pattern)
+    .bind(&role)
+    .bind(search.suspended)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    let (total,) = sqlx::query_as::<_, (i64,)>(&format!("SELECT COUNT(*) FROM users {}", filter))
+        .bind(&pattern)
+        .bind(&role)
+        .bind(search.suspended)
+        .fetch_one(pool)
+        .await?;
+    Ok((users, total))
+}
+
+/// The user `actor` wants to act on, if they're allowed to. Staff can't act
+/// on their own account here, so an admin can't lock themselves out.
+pub async fn managed_user(pool: &DbPool, actor: &Identity, user_id: Uuid) -> Result<User, AppError> {
+    if actor.id == user_id {
+        return Err(AppError::Forbidden("You can't change your own account here".to_string()));
+    }
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    if !can_manage_user(actor.role, user.role) {
+        return Err(AppError::Forbidden("Moderators can't manage admins or other moderators".to_string()));
+    }
+    Ok(user)
+}
+
+/// Suspend an account: it can't log in, and its tokens and sessions stop
+/// working straight away
+pub async fn suspend_user(
+    pool: &DbPool,
+    redis: &RedisClient,
+    user_id: Uuid,
+    reason: Option<&str>,
+) -> Result<User, AppError> {
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users
+        SET suspended_at = COALESCE(suspended_at, NOW()), suspension_reason = $2,
+            token_version = token_version + 1, updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#
+    )
+    .bind(user_id)
+    .bind(reason)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    revoke_cached_credentials(redis, user_id).await?;
+    Ok(user)
+}
+
+pub async fn unsuspend_user(pool: &DbPool, redis: &RedisClient, user_id: Uuid) -> Result<User, AppError> {
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users
+        SET suspended_at = NULL, suspension_reason = NULL, updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    redis.invalidate_user_auth(user_id).await?;
+    Ok(user)
+}
+
+pub async fn list_users(
+    _staff: RequireRole<role::Staff>,
+    pool: web::Data<DbPool>,
+    search: web::Query<UserSearch>,
+) -> Result<HttpResponse, AppError> {
+    let (users, total) = search_users(&pool, &search).await?;
+    let users: Vec<AdminUserResponse> = users.into_iter().map(AdminUserResponse::from).collect();
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "users": users,
+        "total": total
+    })))
+}
+
+pub async fn get_user(
+    _staff: RequireRole<role::Staff>,
+    pool: web::Data<DbPool>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(path.into_inner())
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    Ok(HttpResponse::Ok().json(AdminUserResponse::from(user)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleChangeRequest {
+    pub role: Role,
+}
+
+pub async fn update_user_role(
+    staff: RequireRole<role::Staff>,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    meta: RequestMeta,
+    path: web::Path<Uuid>,
+    body: web::Json<RoleChangeRequest>,
+) -> Result<HttpResponse, AppError> {
+    if !can_assign_role(staff.role, body.role) {
+        return Err(AppError::Forbidden(format!("You can't give users the {} role", body.role)));
+    }
+    let user = managed_user(&pool, &staff, path.into_inner()).await?;
+    if user.role == body.role {
+        return Ok(HttpResponse::Ok().json(AdminUserResponse::from(user)));
+    }
+
+    let previous = user.role;
+    let user = change_user_role(&pool, &redis, user.id, body.role).await?;
+    AuditEvent::new("user_role_changed")
+        .by(&staff)
+        .on("user", user.id)
+        .before(serde_json::json!({ "role": previous }))
+        .after(serde_json::json!({ "role": user.role }))
+        .from(&meta)
+        .record(&pool)
+        .await;
+    Ok(HttpResponse::Ok().json(AdminUserResponse::from(user)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SuspendRequest {
+    pub reason: Option<String>,
+}
+
+pub async fn suspend(
+    staff: RequireRole<role::Staff>,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    meta: RequestMeta,
+    path: web::Path<Uuid>,
+    body: Option<web::Json<SuspendRequest>>,
+) -> Result<HttpResponse, AppError> {
+    let user = managed_user(&pool, &staff, path.into_inner()).await?;
+    let reason = body.and_then(|body| body.into_inner().reason).filter(|r| !r.trim().is_empty());
+
+    let user = suspend_user(&pool, &redis, user.id, reason.as_deref()).await?;
+    AuditEvent::new("user_suspended")
+        .by(&staff)
+        .on("user", user.id)
+        .after(serde_json::json!({ "suspended_at": user.suspended_at, "reason": reason }))
+        .from(&meta)
+        .record(&pool)
+        .await;
+    Ok(HttpResponse::Ok().json(AdminUserResponse::from(user)))
+}
+
+pub async fn unsuspend(
+    staff: RequireRole<role::Staff>,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    meta: RequestMeta,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user = managed_user(&pool, &staff, path.into_inner()).await?;
+
+    let user = unsuspend_user(&pool, &redis, user.id).await?;
+    AuditEvent::new("user_unsuspended").by(&staff).on("user", user.id).from(&meta).record(&pool).await;
+    Ok(HttpResponse::Ok().json(AdminUserResponse::from(user)))
+}
+
+/// Sign a user out of every device without suspending them
+pub async fn force_logout(
+    staff: RequireRole<role::Staff>,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    meta: RequestMeta,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user = managed_user(&pool, &staff, path.into_inner()).await?;
+
+    revoke_all_sessions(&pool, &redis, user.id).await?;
+    AuditEvent::new("user_logged_out").by(&staff).on("user", user.id).from(&meta).record(&pool).await;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// The audit trail, newest first. Admins only: events carry IP addresses
+/// and the before and after state of accounts and payments.
+pub async fn list_audit_events(
+    _admin: RequireRole<role::Admin>,
+    pool: web::Data<DbPool>,
+    query: web::Query<AuditQuery>,
+) -> Result<HttpResponse, AppError> {
+    let (events, total) = audit::search_events(&pool, &query).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "events": events,
+        "total": total
+    })))
+}
@@ -0,0 +1,476 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use askama::Template;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::audit::{AuditEvent, RequestMeta};
+use crate::cache::RedisClient;
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::admin::{
+    can_assign_role, can_manage_user, managed_user, search_users, suspend_user, unsuspend_user, AdminUserResponse,
+    UserSearch,
+};
+use crate::handlers::auth::{change_user_role, revoke_all_sessions};
+use crate::handlers::chef_application::{
+    approve_application, download_document, list_applications, reject_application, APPLICATION_STATUSES,
+};
+use crate::handlers::impersonation::{begin_impersonation_session, can_impersonate, start_impersonation};
+use crate::handlers::moderation::{
+    deactivate_chef, dismiss_flag, list_flags, reactivate_chef, refund_booking, search_bookings, search_chefs,
+    search_reviews, set_content_hidden, CONSOLE_PAGE_SIZE,
+};
+use crate::mail::Mailer;
+use crate::middleware::auth::{AuthUser, RequireRole};
+use crate::middleware::flash::{redirect_with_flash, FlashMessage};
+use crate::middleware::roles::{has_permission, role, Permission};
+use crate::models::{ContentType, Role, UserResponse};
+use crate::storage::BlobStore;
+use crate::templates::{
+    AdminApplicationsTemplate, AdminBookingsTemplate, AdminChefsTemplate, AdminFlagsTemplate, AdminQuery,
+    AdminReviewsTemplate, AdminUserRow, AdminUsersTemplate, PageContext,
+};
+
+type Form = web::Form<HashMap<String, String>>;
+type Query = web::Query<HashMap<String, String>>;
+
+/// Flag statuses the reports page can list
+const FLAG_STATUSES: [&str; 3] = ["open", "resolved", "dismissed"];
+
+pub async fn admin_home() -> HttpResponse {
+    HttpResponse::SeeOther().append_header(("Location", "/admin/users")).finish()
+}
+
+pub async fn admin_users_page(
+    staff: RequireRole<role::Staff>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    query: Query,
+) -> Result<HttpResponse, AppError> {
+    let query = AdminQuery(query.into_inner());
+    let search = UserSearch {
+        q: query.filter("q").map(str::to_string),
+        role: query.filter("role").and_then(|r| r.parse().ok()),
+        suspended: query.filter("suspended").and_then(|s| s.parse().ok()),
+        limit: Some(CONSOLE_PAGE_SIZE),
+        offset: None,
+    };
+    let (users, total) = search_users(&pool, &search).await?;
+    let users = users
+        .into_iter()
+        .map(|u| AdminUserRow {
+            manageable: u.id != staff.id && can_manage_user(staff.role, u.role),
+            impersonatable: can_impersonate(&staff, &u),
+            user: AdminUserResponse::from(u),
+        })
+        .collect();
+    let assignable_roles = [Role::Admin, Role::Mod, Role::Chef, Role::Diner]
+        .into_iter()
+        .filter(|&r| can_assign_role(staff.role, r))
+        .collect();
+
+    render(AdminUsersTemplate {
+        ctx: PageContext::new(&req, Some(UserResponse::from(user))),
+        section: "users",
+        query,
+        users,
+        total,
+        assignable_roles,
+    })
+}
+
+pub async fn admin_chefs_page(
+    _staff: RequireRole<role::Staff>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    query: Query,
+) -> Result<HttpResponse, AppError> {
+    let query = AdminQuery(query.into_inner());
+    let chefs = search_chefs(&pool, query.filter("q"), query.filter("active").and_then(|a| a.parse().ok())).await?;
+
+    render(AdminChefsTemplate {
+        ctx: PageContext::new(&req, Some(UserResponse::from(user))),
+        section: "chefs",
+        query,
+        chefs,
+    })
+}
+
+pub async fn admin_bookings_page(
+    staff: RequireRole<role::Staff>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    query: Query,
+) -> Result<HttpResponse, AppError> {
+    let query = AdminQuery(query.into_inner());
+    let bookings =
+        search_bookings(&pool, query.filter("q"), query.filter("status"), query.filter("payment_status")).await?;
+
+    render(AdminBookingsTemplate {
+        ctx: PageContext::new(&req, Some(UserResponse::from(user))),
+        section: "bookings",
+        query,
+        bookings,
+        can_refund: has_permission(staff.role, Permission::RefundBookings),
+    })
+}
+
+pub async fn admin_reviews_page(
+    _staff: RequireRole<role::Staff>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    query: Query,
+) -> Result<HttpResponse, AppError> {
+    let query = AdminQuery(query.into_inner());
+    let reviews = search_reviews(&pool, query.filter("q"), query.filter("hidden").and_then(|h| h.parse().ok())).await?;
+
+    render(AdminReviewsTemplate {
+        ctx: PageContext::new(&req, Some(UserResponse::from(user))),
+        section: "reviews",
+        query,
+        reviews,
+    })
+}
+
+pub async fn admin_flags_page(
+    _staff: RequireRole<role::Staff>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    query: Query,
+) -> Result<HttpResponse, AppError> {
+    let mut query = AdminQuery(query.into_inner());
+    let status = match query.filter("status") {
+        Some(status) if FLAG_STATUSES.contains(&status) => status.to_string(),
+        _ => "open".to_string(),
+    };
+    let flags = list_flags(&pool, &status).await?;
+    query.0.insert("status".to_string(), status);
+
+    render(AdminFlagsTemplate {
+        ctx: PageContext::new(&req, Some(UserResponse::from(user))),
+        section: "flags",
+        query,
+        flags,
+    })
+}
+
+pub async fn admin_applications_page(
+    _staff: RequireRole<role::Staff>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    query: Query,
+) -> Result<HttpResponse, AppError> {
+    let mut query = AdminQuery(query.into_inner());
+    let status = match query.filter("status") {
+        Some(status) if APPLICATION_STATUSES.contains(&status) => status.to_string(),
+        _ => "pending".to_string(),
+    };
+    let applications = list_applications(&pool, &status).await?;
+    query.0.insert("status".to_string(), status);
+
+    render(AdminApplicationsTemplate {
+        ctx: PageContext::new(&req, Some(UserResponse::from(user))),
+        section: "applications",
+        query,
+        applications,
+    })
+}
+
+/// Serve a credential uploaded with an application
+pub async fn admin_application_document(
+    staff: RequireRole<role::Staff>,
+    pool: web::Data<DbPool>,
+    blob_store: web::Data<dyn BlobStore>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (application_id, document_id) = path.into_inner();
+    download_document(&pool, blob_store.get_ref(), &staff, application_id, document_id).await
+}
+
+pub async fn handle_admin_approve_application(
+    staff: RequireRole<role::Staff>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    mailer: web::Data<dyn Mailer>,
+    config: web::Data<Config>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let result = approve_application(&pool, &redis, mailer.get_ref(), &config, &staff, &meta, path.into_inner())
+        .await
+        .map(|_| "Application approved; the applicant is now a chef".to_string());
+    finish(&req, "/admin/applications", result)
+}
+
+pub async fn handle_admin_reject_application(
+    staff: RequireRole<role::Staff>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    mailer: web::Data<dyn Mailer>,
+    path: web::Path<Uuid>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let reason = form.get("reason").map(String::as_str).unwrap_or_default();
+    let result = reject_application(&pool, mailer.get_ref(), &staff, &meta, path.into_inner(), reason)
+        .await
+        .map(|_| "Application rejected".to_string());
+    finish(&req, "/admin/applications", result)
+}
+
+pub async fn handle_admin_role_change(
+    staff: RequireRole<role::Staff>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    path: web::Path<Uuid>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let result = async {
+        let new_role: Role = form
+            .get("role")
+            .map(String::as_str)
+            .unwrap_or_default()
+            .parse()
+            .map_err(AppError::ValidationError)?;
+        if !can_assign_role(staff.role, new_role) {
+            return Err(AppError::Forbidden(format!("You can't give users the {} role", new_role)));
+        }
+        let user = managed_user(&pool, &staff, path.into_inner()).await?;
+        if user.role == new_role {
+            return Ok(format!("{} is already {}", user.email, new_role));
+        }
+
+        let previous = user.role;
+        let user = change_user_role(&pool, &redis, user.id, new_role).await?;
+        AuditEvent::new("user_role_changed")
+            .by(&staff)
+            .on("user", user.id)
+            .before(serde_json::json!({ "role": previous }))
+            .after(serde_json::json!({ "role": user.role }))
+            .from(&meta)
+            .record(&pool)
+            .await;
+        Ok(format!("{} is now {}", user.email, user.role))
+    }
+    .await;
+    finish(&req, "/admin/users", result)
+}
+
+pub async fn handle_admin_suspend(
+    staff: RequireRole<role::Staff>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    path: web::Path<Uuid>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let result = async {
+        let user = managed_user(&pool, &staff, path.into_inner()).await?;
+        let reason = form.get("reason").map(|r| r.trim()).filter(|r| !r.is_empty());
+
+        let user = suspend_user(&pool, &redis, user.id, reason).await?;
+        AuditEvent::new("user_suspended")
+            .by(&staff)
+            .on("user", user.id)
+            .after(serde_json::json!({ "suspended_at": user.suspended_at, "reason": reason }))
+            .from(&meta)
+            .record(&pool)
+            .await;
+        Ok(format!("{} suspended", user.email))
+    }
+    .await;
+    finish(&req, "/admin/users", result)
+}
+
+/// Sign in as a chef or diner to see what they see. The admin's own session
+/// is resumed when they stop.
+pub async fn handle_admin_impersonate(
+    admin: RequireRole<role::Admin>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    path: web::Path<Uuid>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let reason = form.get("reason").map(String::as_str).unwrap_or_default();
+    match start_impersonation(&pool, &admin, &meta, path.into_inner(), reason, "session").await {
+        Ok((user, impersonation)) => begin_impersonation_session(&req, &redis, &user, impersonation).await,
+        Err(e) => finish(&req, "/admin/users", Err(e)),
+    }
+}
+
+pub async fn handle_admin_unsuspend(
+    staff: RequireRole<role::Staff>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let result = async {
+        let user = managed_user(&pool, &staff, path.into_inner()).await?;
+
+        let user = unsuspend_user(&pool, &redis, user.id).await?;
+        AuditEvent::new("user_unsuspended")
+            .by(&staff)
+            .on("user", user.id)
+            .from(&meta)
+            .record(&pool)
+            .await;
+        Ok(format!("{} can log in again", user.email))
+    }
+    .await;
+    finish(&req, "/admin/users", result)
+}
+
+pub async fn handle_admin_force_logout(
+    staff: RequireRole<role::Staff>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let result = async {
+        let user = managed_user(&pool, &staff, path.into_inner()).await?;
+
+        revoke_all_sessions(&pool, &redis, user.id).await?;
+        AuditEvent::new("user_logged_out")
+            .by(&staff)
+            .on("user", user.id)
+            .from(&meta)
+            .record(&pool)
+            .await;
+        Ok(format!("{} has been signed out everywhere", user.email))
+    }
+    .await;
+    finish(&req, "/admin/users", result)
+}
+
+pub async fn handle_admin_deactivate_chef(
+    staff: RequireRole<role::Staff>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    path: web::Path<Uuid>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let reason = form.get("reason").map(|r| r.trim()).filter(|r| !r.is_empty());
+    let result = deactivate_chef(&pool, &staff, &meta, path.into_inner(), reason)
+        .await
+        .map(|_| "Chef deactivated".to_string());
+    finish(&req, return_to(&form, "/admin/chefs"), result)
+}
+
+pub async fn handle_admin_reactivate_chef(
+    staff: RequireRole<role::Staff>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let result = reactivate_chef(&pool, &staff, &meta, path.into_inner())
+        .await
+        .map(|_| "Chef reactivated".to_string());
+    finish(&req, "/admin/chefs", result)
+}
+
+pub async fn handle_admin_refund(
+    staff: RequireRole<role::Staff>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let result = refund_booking(&pool, &staff, &meta, path.into_inner())
+        .await
+        .map(|booking| format!("Booking for {} refunded", booking.customer_name));
+    finish(&req, "/admin/bookings", result)
+}
+
+/// Hide or show a menu or review. `path` is (content type, id, "hide" or
+/// "unhide").
+pub async fn handle_admin_content_visibility(
+    staff: RequireRole<role::Staff>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    path: web::Path<(String, Uuid, String)>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let (content_type, content_id, action) = path.into_inner();
+    let back = return_to(&form, "/admin/reviews");
+    let result = async {
+        let content_type: ContentType = content_type.parse().map_err(AppError::ValidationError)?;
+        let hidden = match action.as_str() {
+            "hide" => true,
+            "unhide" => false,
+            _ => return Err(AppError::NotFound("Unknown action".to_string())),
+        };
+        set_content_hidden(&pool, &staff, &meta, content_type, content_id, hidden).await?;
+        Ok(format!("{} {}", capitalize(&content_type.to_string()), if hidden { "hidden" } else { "shown again" }))
+    }
+    .await;
+    finish(&req, back, result)
+}
+
+pub async fn handle_admin_dismiss_flag(
+    staff: RequireRole<role::Staff>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let result = dismiss_flag(&pool, &staff, &meta, path.into_inner())
+        .await
+        .map(|_| "Report dismissed".to_string());
+    finish(&req, "/admin/flags", result)
+}
+
+/// Where to send the user after an action: the form's `return_to` when it
+/// points inside the console, otherwise `default`
+fn return_to<'a>(form: &'a Form, default: &'a str) -> &'a str {
+    form.get("return_to")
+        .map(String::as_str)
+        .filter(|path| path.starts_with("/admin/") && !path.starts_with("/admin//"))
+        .unwrap_or(default)
+}
+
+/// Redirect back to the console with the outcome of an action. Anything the
+/// staff member can fix or should know about is flashed; database and
+/// other internal errors go through the usual error response.
+fn finish(req: &HttpRequest, path: &str, result: Result<String, AppError>) -> Result<HttpResponse, AppError> {
+    match result {
+        Ok(message) => Ok(redirect_with_flash(req, path, FlashMessage::success(message))),
+        Err(AppError::Forbidden(message) | AppError::NotFound(message) | AppError::ValidationError(message)) => {
+            Ok(redirect_with_flash(req, path, FlashMessage::error(message)))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn render(template: impl Template) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok()
+        .content_type("text/html")
+        .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
+}
@@ -1,18 +1,55 @@
+use std::time::Duration;
+
 use actix_web::{web, HttpResponse};
 use bcrypt::{hash, verify, DEFAULT_COST};
+use serde::Deserialize;
+
 use uuid::Uuid;
 
+use crate::cache::{RefreshFamily, RefreshRotation, RedisClient};
 use crate::db::DbPool;
-use crate::models::{CreateUser, User, UserResponse, Role};
+use crate::models::{CreateUser, TokenPurpose, User, UserResponse, Role};
 use crate::errors::AppError;
-use crate::utils::{create_refresh_token, create_token};
+use crate::mail::{Email, Mailer};
+use crate::middleware::auth::{AuthUser, Identity, NotImpersonating, ACCOUNT_SUSPENDED};
+use crate::middleware::roles::{has_permission, Permission};
+use crate::handlers::two_factor::complete_two_factor_login;
+use crate::utils::{create_token, generate_secret_token, hash_token, verify_token, Claims, TokenKind};
 use crate::config::Config;
+use crate::audit::{AuditEvent, RequestMeta};
+
+/// Shortest password accepted when one is set or reset
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+/// Failed logins to one account are counted over this window
+const LOGIN_FAILURE_WINDOW: Duration = Duration::from_secs(15 * 60);
+/// Failures within the window before the account is locked
+const MAX_LOGIN_FAILURES: u32 = 10;
+const ACCOUNT_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// The role a new account gets. Everyone starts as a diner: chefs apply
+/// through a chef application, and staff roles are handed out by admins.
+/// Asking for anything else is an error rather than silently ignored, so
+/// clients notice.
+pub fn registration_role(requested: Option<Role>) -> Result<Role, AppError> {
+    match requested {
+        None | Some(Role::Diner) => Ok(Role::Diner),
+        Some(Role::Chef) => Err(AppError::ValidationError(
+            "Register as a diner, then apply to become a chef".to_string(),
+        )),
+        Some(role) => Err(AppError::ValidationError(format!("The {} role can't be chosen at registration", role))),
+    }
+}
 
 pub async fn register(
     pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    mailer: web::Data<dyn Mailer>,
     config: web::Data<Config>,
+    meta: RequestMeta,
     user_data: web::Json<CreateUser>,
 ) -> Result<HttpResponse, AppError> {
+    let role = registration_role(user_data.role)?;
+
     // Check if user already exists
     let existing_user = sqlx::query_as::<_, User>(
         "SELECT * FROM users WHERE email = $1"
@@ -25,10 +62,6 @@ pub async fn register(
         return Err(AppError::ValidationError("User with this email already exists".to_string()));
     }
 
-    // Default role to diner if not specified
-    // Only admins can create users with other roles (handled in admin endpoint)
-    let role = user_data.role.unwrap_or(Role::Diner);
-
     // Hash password
     let password_hash = hash(&user_data.password, DEFAULT_COST)?;
 
@@ -47,10 +80,15 @@ pub async fn register(
     .bind(&role_str)
     .fetch_one(pool.get_ref())
     .await?;
+    record_registration(&pool, &user, &meta).await;
+
+    // The account works straight away; a failed email can be resent later
+    if let Err(e) = send_verification_email(&pool, &redis, mailer.get_ref(), &config, &user).await {
+        tracing::error!(user_id = %user.id, error = ?e, "Failed to send verification email");
+    }
 
     // Generate tokens
-    let token = create_token(user.id.to_string(), &config.jwt)?;
-    let refresh_token = create_refresh_token(user.id.to_string(), &config.jwt)?;
+    let (token, refresh_token) = issue_tokens(&redis, &config, &user).await?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "user": UserResponse::from(user),
@@ -61,7 +99,9 @@ pub async fn register(
 
 pub async fn login(
     pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
     config: web::Data<Config>,
+    meta: RequestMeta,
     credentials: web::Json<serde_json::Value>,
 ) -> Result<HttpResponse, AppError> {
     let email = credentials.get("email")
@@ -72,23 +112,33 @@ pub async fn login(
         .and_then(|v| v.as_str())
         .ok_or_else(|| AppError::ValidationError("Password is required".to_string()))?;
 
-    // Find user
-    let user = sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE email = $1"
-    )
-    .bind(email)
-    .fetch_optional(pool.get_ref())
-    .await?
-    .ok_or_else(|| AppError::Unauthorized("Invalid credentials".to_string()))?;
+    let user = authenticate(&pool, &redis, &meta, email, password).await?;
 
-    // Verify password
-    if !verify(password, &user.password_hash)? {
-        return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+    login_response(&pool, &redis, &config, &meta, user, "password").await
+}
+
+/// The response to a successful first login step, password or OIDC: tokens,
+/// or a challenge for `login_two_factor` if the account has 2FA on. `method`
+/// is recorded with the login, e.g. `password` or `oidc:google`.
+pub async fn login_response(
+    pool: &DbPool,
+    redis: &RedisClient,
+    config: &Config,
+    meta: &RequestMeta,
+    user: User,
+    method: &str,
+) -> Result<HttpResponse, AppError> {
+    if user.totp_enabled {
+        let challenge = redis.create_two_factor_challenge(user.id).await?;
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "two_factor_required": true,
+            "challenge": challenge
+        })));
     }
 
     // Generate tokens
-    let token = create_token(user.id.to_string(), &config.jwt)?;
-    let refresh_token = create_refresh_token(user.id.to_string(), &config.jwt)?;
+    let (token, refresh_token) = issue_tokens(redis, config, &user).await?;
+    record_login(pool, &user, method, meta).await;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "user": UserResponse::from(user),
@@ -97,18 +147,620 @@ pub async fn login(
     })))
 }
 
-pub async fn get_me(
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge: String,
+    /// A code from the authenticator app, or a recovery code
+    pub code: String,
+}
+
+/// Second step of logging in to an account with 2FA: exchange the challenge
+/// from `login` and a code for tokens
+pub async fn login_two_factor(
     pool: web::Data<DbPool>,
-    user_id: web::Path<Uuid>,
+    redis: web::Data<RedisClient>,
+    config: web::Data<Config>,
+    meta: RequestMeta,
+    body: web::Json<TwoFactorLoginRequest>,
 ) -> Result<HttpResponse, AppError> {
+    let user = complete_two_factor_login(&pool, &redis, &config, &meta, &body.challenge, &body.code).await?;
+    let (token, refresh_token) = issue_tokens(&redis, &config, &user).await?;
+    record_login(&pool, &user, "two_factor", &meta).await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "user": UserResponse::from(user),
+        "token": token,
+        "refresh_token": refresh_token
+    })))
+}
+
+/// Check an email and password, enforcing the per-account lockout.
+///
+/// Each failure is counted against the account (whether or not it exists,
+/// so responses don't reveal which emails are registered) and slows the
+/// response down progressively. After `MAX_LOGIN_FAILURES` within
+/// `LOGIN_FAILURE_WINDOW` the account is locked for `ACCOUNT_LOCKOUT`. If
+/// Redis is unavailable, logins are checked without the lockout.
+pub async fn authenticate(
+    pool: &DbPool,
+    redis: &RedisClient,
+    meta: &RequestMeta,
+    email: &str,
+    password: &str,
+) -> Result<User, AppError> {
+    let account = hash_token(&email.trim().to_lowercase());
+
+    match redis.account_lockout(&account).await {
+        Ok(Some(remaining)) => {
+            return Err(AppError::TooManyRequests(format!(
+                "Too many failed login attempts. Try again in {} minutes.",
+                remaining.as_secs().div_ceil(60)
+            )));
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!(error = ?e, "Login lockout check unavailable"),
+    }
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_optional(pool)
+        .await?;
+
+    let valid = match &user {
+        Some(User { password_hash: Some(hash), .. }) => verify(password, hash)?,
+        _ => false,
+    };
+    let user = match (user, valid) {
+        (Some(user), true) => user,
+        (user, _) => {
+            login_failed(pool, redis, meta, &account, user.as_ref()).await;
+            return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+        }
+    };
+
+    if let Err(e) = redis.clear_login_failures(&account).await {
+        tracing::warn!(error = ?e, "Failed to clear login failures");
+    }
+    ensure_not_suspended(&user)?;
+    Ok(user)
+}
+
+/// Suspended accounts get past the password or provider but aren't signed in
+pub(crate) fn ensure_not_suspended(user: &User) -> Result<(), AppError> {
+    if user.suspended_at.is_some() {
+        tracing::warn!(user_id = %user.id, "Login to suspended account refused");
+        return Err(AppError::Forbidden(ACCOUNT_SUSPENDED.to_string()));
+    }
+    Ok(())
+}
+
+/// Record a new password account. Accounts created through an OIDC
+/// provider are recorded as `oidc_account_created` instead.
+pub(crate) async fn record_registration(pool: &DbPool, user: &User, meta: &RequestMeta) {
+    AuditEvent::new("user_registered")
+        .by_user(user.id, user.role)
+        .on("user", user.id)
+        .after(serde_json::json!({ "email": user.email, "role": user.role }))
+        .from(meta)
+        .record(pool)
+        .await;
+}
+
+/// Record a completed sign-in. `method` is how the user proved who they
+/// are: `password`, `oidc:<provider>` or `two_factor`.
+pub(crate) async fn record_login(pool: &DbPool, user: &User, method: &str, meta: &RequestMeta) {
+    AuditEvent::new("login_succeeded")
+        .by_user(user.id, user.role)
+        .on("user", user.id)
+        .detail("method", method)
+        .from(meta)
+        .record(pool)
+        .await;
+}
+
+/// A failed password check against a known account or an unknown email.
+/// Failures against unknown emails are recorded without a target, and
+/// without the email, which is often a mistyped password.
+fn failed_login_event(action: &str, user: Option<&User>, meta: &RequestMeta) -> AuditEvent {
+    let event = AuditEvent::new(action);
+    match user {
+        Some(user) => event.on("user", user.id),
+        None => event,
+    }
+    .from(meta)
+}
+
+/// Counts a failed login, locking the account once there are too many,
+/// and waits out the delay for this many failures
+async fn login_failed(pool: &DbPool, redis: &RedisClient, meta: &RequestMeta, account: &str, user: Option<&User>) {
+    let failures = match redis.record_login_failure(account, LOGIN_FAILURE_WINDOW).await {
+        Ok(failures) => failures,
+        Err(e) => {
+            tracing::warn!(error = ?e, "Failed to record login failure");
+            failed_login_event("login_failed", user, meta).record(pool).await;
+            return;
+        }
+    };
+    tracing::warn!(user_id = ?user.map(|u| u.id), failures, "Failed login attempt");
+    failed_login_event("login_failed", user, meta).detail("failures", failures).record(pool).await;
+
+    if failures >= MAX_LOGIN_FAILURES {
+        if let Err(e) = redis.lock_account(account, ACCOUNT_LOCKOUT).await {
+            tracing::error!(error = ?e, "Failed to lock account");
+        }
+        failed_login_event("account_locked", user, meta)
+            .detail("failures", failures)
+            .detail("lockout_seconds", ACCOUNT_LOCKOUT.as_secs())
+            .record(pool)
+            .await;
+    }
+
+    tokio::time::sleep(login_failure_delay(failures)).await;
+}
+
+/// Extra wait before answering the `failures`th failed login: none for the
+/// first three, then doubling from half a second up to eight seconds
+pub fn login_failure_delay(failures: u32) -> Duration {
+    match failures {
+        0..=3 => Duration::ZERO,
+        n => Duration::from_millis(500 << (n - 4).min(4)),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// Exchange a refresh token for a new access token and refresh token.
+///
+/// Refresh tokens are single use: each exchange rotates the family to the
+/// new token. Presenting a token that was already exchanged means it has
+/// leaked, so the whole family is revoked and that login has to start over.
+pub async fn refresh(
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    config: web::Data<Config>,
+    body: web::Json<RefreshRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (claims, user_id, family_id) = verify_refresh_token(&body.refresh_token, &config)?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool.get_ref())
+        .await?;
+    let user = match user {
+        Some(user) if user.token_version == claims.ver => user,
+        _ => {
+            redis.revoke_refresh_family(family_id, user_id).await?;
+            return Err(AppError::Unauthorized("Your access has changed. Please log in again.".to_string()));
+        }
+    };
+
+    let next = Claims::refresh(&user, family_id, &config.jwt);
+    let family = RefreshFamily { user_id, current_jti: next.jti.clone() };
+    match redis
+        .rotate_refresh_family(family_id, &claims.jti, &family, config.jwt.refresh_expiration as usize)
+        .await?
+    {
+        RefreshRotation::Rotated => {}
+        RefreshRotation::Reused => {
+            tracing::warn!(user_id = %user_id, family_id = %family_id, "Refresh token reuse detected, revoked token family");
+            return Err(AppError::Unauthorized("Refresh token has been revoked".to_string()));
+        }
+        RefreshRotation::Revoked => {
+            return Err(AppError::Unauthorized("Refresh token has been revoked".to_string()));
+        }
+    }
+    let refresh_token = next.encode(&config.jwt.secret)?;
+    let token = create_token(&user, &config.jwt)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "token": token,
+        "refresh_token": refresh_token
+    })))
+}
+
+/// Log out one device by revoking its refresh token family. The access
+/// token stays valid until it expires, so clients should discard it too.
+pub async fn logout(
+    redis: web::Data<RedisClient>,
+    config: web::Data<Config>,
+    body: web::Json<RefreshRequest>,
+) -> Result<HttpResponse, AppError> {
+    let (_, user_id, family_id) = verify_refresh_token(&body.refresh_token, &config)?;
+    redis.revoke_refresh_family(family_id, user_id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Log out every device: all refresh tokens are revoked, and access tokens
+/// and web sessions stop working on their next request.
+pub async fn logout_all(
+    _: NotImpersonating,
+    identity: Identity,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+) -> Result<HttpResponse, AppError> {
+    revoke_all_sessions(&pool, &redis, identity.id).await?;
+
+    tracing::info!(user_id = %identity.id, "Logged out of all devices");
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Bump the token version and forget everything cached under the old one,
+/// signing the user out everywhere
+pub async fn revoke_all_sessions(pool: &DbPool, redis: &RedisClient, user_id: Uuid) -> Result<(), AppError> {
+    let updated = sqlx::query("UPDATE users SET token_version = token_version + 1, updated_at = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+    revoke_cached_credentials(redis, user_id).await
+}
+
+/// Forget the cached token version and every refresh token family, after
+/// the version has been bumped in the database
+pub(crate) async fn revoke_cached_credentials(redis: &RedisClient, user_id: Uuid) -> Result<(), AppError> {
+    redis.invalidate_user_auth(user_id).await?;
+    redis.revoke_user_refresh_families(user_id).await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordResetConfirm {
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+/// Email a password reset link. The response is the same whether or not
+/// the account exists.
+pub async fn request_password_reset(
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    mailer: web::Data<dyn Mailer>,
+    config: web::Data<Config>,
+    body: web::Json<PasswordResetRequest>,
+) -> Result<HttpResponse, AppError> {
+    send_password_reset(&pool, &redis, mailer.get_ref(), &config, body.email.trim()).await?;
+    Ok(HttpResponse::Accepted().json(serde_json::json!({
+        "message": "If an account exists for that email, a password reset link has been sent"
+    })))
+}
+
+/// Set a new password with the token from a reset email
+pub async fn confirm_password_reset(
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    meta: RequestMeta,
+    body: web::Json<PasswordResetConfirm>,
+) -> Result<HttpResponse, AppError> {
+    reset_password(&pool, &redis, &meta, &body.token, &body.password).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Password updated. Please log in again."
+    })))
+}
+
+/// Mark the email address verified with the token from a verification email
+pub async fn confirm_email(
+    pool: web::Data<DbPool>,
+    body: web::Json<VerifyEmailRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user = verify_email(&pool, &body.token).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "user": UserResponse::from(user) })))
+}
+
+/// Send a new verification email to the signed-in user
+pub async fn resend_verification_email(
+    user: AuthUser,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    mailer: web::Data<dyn Mailer>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, AppError> {
+    if user.email_verified {
+        return Err(AppError::ValidationError("Email address is already verified".to_string()));
+    }
+    send_verification_email(&pool, &redis, mailer.get_ref(), &config, &user).await?;
+    Ok(HttpResponse::Accepted().json(serde_json::json!({
+        "message": "Verification email sent"
+    })))
+}
+
+/// Emails sent to one address per hour, counting reset and verification links
+const ACCOUNT_EMAILS_PER_HOUR: u32 = 3;
+
+/// Whether another account email may be sent to `email`, so nobody can flood
+/// an inbox by asking for links. Allowed if Redis is unavailable, as with the
+/// per-IP limits.
+async fn account_email_allowed(redis: &RedisClient, email: &str) -> bool {
+    let key = format!("rate:account_email:{}", hash_token(&email.trim().to_lowercase()));
+    match redis.check_rate_limit(&key, ACCOUNT_EMAILS_PER_HOUR, Duration::from_secs(60 * 60)).await {
+        Ok(None) => true,
+        Ok(Some(_)) => {
+            tracing::warn!("Too many account emails requested for one address");
+            false
+        }
+        Err(e) => {
+            tracing::warn!(error = ?e, "Account email limit unavailable, allowing email");
+            true
+        }
+    }
+}
+
+/// Email `user` a link that verifies their address. Earlier links stop working.
+pub async fn send_verification_email(
+    pool: &DbPool,
+    redis: &RedisClient,
+    mailer: &dyn Mailer,
+    config: &Config,
+    user: &User,
+) -> Result<(), AppError> {
+    if !account_email_allowed(redis, &user.email).await {
+        return Err(AppError::TooManyRequests(
+            "We've sent several emails to this address recently. Please try again in an hour.".to_string(),
+        ));
+    }
+    let token = create_user_token(pool, user.id, TokenPurpose::EmailVerification).await?;
+    let link = format!("{}/verify-email?token={}", config.mail.app_url.trim_end_matches('/'), token);
+    mailer
+        .send(Email {
+            to: user.email.clone(),
+            subject: "Confirm your PrivateChefSpace email address".to_string(),
+            body: format!(
+                "Welcome to PrivateChefSpace!\n\nConfirm your email address by opening this link:\n{}\n\nThe link expires in {} days.\n",
+                link,
+                TokenPurpose::EmailVerification.lifetime().num_days()
+            ),
+        })
+        .await
+}
+
+/// Email a password reset link if an account exists for `email`
+pub async fn send_password_reset(
+    pool: &DbPool,
+    redis: &RedisClient,
+    mailer: &dyn Mailer,
+    config: &Config,
+    email: &str,
+) -> Result<(), AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_optional(pool)
+        .await?;
+    let user = match user {
+        Some(user) => user,
+        None => {
+            tracing::debug!("Password reset requested for an unknown email");
+            return Ok(());
+        }
+    };
+    // Answered as usual: an error would reveal that the account exists
+    if !account_email_allowed(redis, &user.email).await {
+        return Ok(());
+    }
+
+    let token = create_user_token(pool, user.id, TokenPurpose::PasswordReset).await?;
+    let link = format!("{}/reset-password?token={}", config.mail.app_url.trim_end_matches('/'), token);
+    // Failures are only logged: an error here would reveal that the account exists
+    let sent = mailer
+        .send(Email {
+            to: user.email,
+            subject: "Reset your PrivateChefSpace password".to_string(),
+            body: format!(
+                "Someone asked to reset the password for your PrivateChefSpace account.\n\nChoose a new password here:\n{}\n\nThe link expires in {} minutes. If you didn't ask for this, you can ignore this email.\n",
+                link,
+                TokenPurpose::PasswordReset.lifetime().num_minutes()
+            ),
+        })
+        .await;
+    match sent {
+        Ok(()) => tracing::info!(user_id = %user.id, "Password reset email sent"),
+        Err(e) => tracing::error!(user_id = %user.id, error = ?e, "Failed to send password reset email"),
+    }
+    Ok(())
+}
+
+/// Use a reset token to set a new password. The address is marked verified
+/// (the link reached it), and every existing session and token is signed out.
+pub async fn reset_password(
+    pool: &DbPool,
+    redis: &RedisClient,
+    meta: &RequestMeta,
+    token: &str,
+    password: &str,
+) -> Result<User, AppError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(AppError::ValidationError(format!(
+            "Password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        )));
+    }
+
+    let user_id = consume_user_token(pool, token, TokenPurpose::PasswordReset)
+        .await?
+        .ok_or_else(|| AppError::ValidationError("This password reset link is invalid or has expired".to_string()))?;
+
+    let password_hash = hash(password, DEFAULT_COST)?;
     let user = sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE id = $1"
+        r#"
+        UPDATE users
+        SET password_hash = $2, email_verified = TRUE, token_version = token_version + 1, updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#
     )
-    .bind(*user_id)
-    .fetch_optional(pool.get_ref())
+    .bind(user_id)
+    .bind(&password_hash)
+    .fetch_optional(pool)
     .await?
     .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    revoke_cached_credentials(redis, user_id).await?;
 
-    Ok(HttpResponse::Ok().json(UserResponse::from(user)))
+    AuditEvent::new("password_reset")
+        .by_user(user.id, user.role)
+        .on("user", user.id)
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(user)
 }
 
+/// Use a verification token to mark the user's email address verified
+pub async fn verify_email(pool: &DbPool, token: &str) -> Result<User, AppError> {
+    let user_id = consume_user_token(pool, token, TokenPurpose::EmailVerification)
+        .await?
+        .ok_or_else(|| AppError::ValidationError("This verification link is invalid or has expired".to_string()))?;
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET email_verified = TRUE, updated_at = NOW() WHERE id = $1 RETURNING *"
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    tracing::info!(user_id = %user_id, "Email address verified");
+    Ok(user)
+}
+
+/// Issue a new single-use token, replacing any unused one for the same purpose.
+/// Only its hash is stored.
+async fn create_user_token(pool: &DbPool, user_id: Uuid, purpose: TokenPurpose) -> Result<String, AppError> {
+    let token = generate_secret_token();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM user_tokens WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL")
+        .bind(user_id)
+        .bind(purpose.as_str())
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#
+    )
+    .bind(user_id)
+    .bind(purpose.as_str())
+    .bind(hash_token(&token))
+    .bind(chrono::Utc::now() + purpose.lifetime())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(token)
+}
+
+/// Mark a token used and return its user, if it is valid for `purpose`.
+/// The update is atomic, so a token can't be used twice.
+async fn consume_user_token(pool: &DbPool, token: &str, purpose: TokenPurpose) -> Result<Option<Uuid>, AppError> {
+    let user_id = sqlx::query_as::<_, (Uuid,)>(
+        r#"
+        UPDATE user_tokens
+        SET used_at = NOW()
+        WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > NOW()
+        RETURNING user_id
+        "#
+    )
+    .bind(hash_token(token.trim()))
+    .bind(purpose.as_str())
+    .fetch_optional(pool)
+    .await?;
+    Ok(user_id.map(|(id,)| id))
+}
+
+/// A new access token, and a refresh token that starts a new family
+async fn issue_tokens(
+    redis: &RedisClient,
+    config: &Config,
+    user: &User,
+) -> Result<(String, String), AppError> {
+    let family_id = Uuid::new_v4();
+    let claims = Claims::refresh(user, family_id, &config.jwt);
+    let refresh_token = claims.encode(&config.jwt.secret)?;
+    let token = create_token(user, &config.jwt)?;
+
+    let family = RefreshFamily { user_id: user.id, current_jti: claims.jti };
+    redis
+        .store_refresh_family(family_id, &family, config.jwt.refresh_expiration as usize)
+        .await?;
+    Ok((token, refresh_token))
+}
+
+/// Claims, user id and family id of a valid refresh token
+fn verify_refresh_token(token: &str, config: &Config) -> Result<(Claims, Uuid, Uuid), AppError> {
+    let invalid = || AppError::Unauthorized("Invalid or expired refresh token".to_string());
+    let claims = verify_token(token, &config.jwt.secret).map_err(|_| invalid())?;
+    if claims.typ != TokenKind::Refresh {
+        return Err(invalid());
+    }
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| invalid())?;
+    let family_id = claims.family().ok_or_else(invalid)?;
+    Ok((claims, user_id, family_id))
+}
+
+pub async fn get_me(user: AuthUser) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(UserResponse::from(user.0)))
+}
+
+
+/// Give a user a new role. The token version is bumped so tokens and
+/// sessions issued under the old role stop working, and the cached role is
+/// dropped so the change applies on the user's next request.
+pub async fn change_user_role(
+    pool: &DbPool,
+    redis: &RedisClient,
+    user_id: Uuid,
+    role: Role,
+) -> Result<User, AppError> {
+    let mut tx = pool.begin().await?;
+    let user = set_user_role(&mut tx, user_id, role).await?;
+    tx.commit().await?;
+
+    redis.invalidate_user_auth(user_id).await?;
+    tracing::info!(user_id = %user_id, role = %role, "User role changed");
+    Ok(user)
+}
+
+/// The database half of [`change_user_role`], for callers changing a role
+/// as part of a larger transaction. They must call
+/// `RedisClient::invalidate_user_auth` once it commits.
+pub(crate) async fn set_user_role(
+    conn: &mut sqlx::PgConnection,
+    user_id: Uuid,
+    role: Role,
+) -> Result<User, AppError> {
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users
+        SET role = $2::varchar, token_version = token_version + 1, updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#
+    )
+    .bind(user_id)
+    .bind(role.to_string())
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    // Only approved chefs (and admins) may have a public profile
+    if !has_permission(role, Permission::ManageOwnChefProfile) {
+        sqlx::query("UPDATE chefs SET is_active = false, updated_at = NOW() WHERE user_id = $1 AND is_active")
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(user)
+}
@@ -2,32 +2,42 @@ use actix_web::{web, HttpResponse};
 use uuid::Uuid;
 use chrono::{NaiveDate, NaiveTime};
 
+use crate::audit::{AuditEvent, RequestMeta};
 use crate::db::DbPool;
-use crate::models::{Booking, CreateBooking, UpdateBooking, BookingAvailability};
+use crate::models::{
+    Booking, BookingStatus, CreateBooking, UpdateBooking, BookingAvailability, BookingAddOn,
+    BookingGuestSelection, MenuAddOn, MenuChoiceGroup, MenuItem, PrepSheet, UserResponse,
+};
+use crate::pricing;
 use crate::errors::AppError;
-use crate::middleware::auth::extract_user_id;
-use actix_web::HttpRequest;
+use crate::middleware::auth::Identity;
+use crate::middleware::roles::Permission;
+use crate::policy::{allows, authorize, Resource};
 
 pub async fn create_booking(
     pool: web::Data<DbPool>,
+    meta: RequestMeta,
     chef_id: web::Path<Uuid>,
     data: web::Json<CreateBooking>,
 ) -> Result<HttpResponse, AppError> {
-    // Get chef info for pricing
-    let chef: Option<(Option<f64>, i32)> = sqlx::query_as(
-        "SELECT hourly_rate, minimum_hours FROM chefs WHERE id = $1 AND is_active = true"
-    )
-    .bind(*chef_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
-    
-    let (hourly_rate, minimum_hours) = chef
-        .ok_or_else(|| AppError::NotFound("Chef not found".to_string()))?;
+    let booking = place_booking(&pool, &meta, *chef_id, None, &data).await?;
+    Ok(HttpResponse::Created().json(booking))
+}
 
-    let duration_hours = data.duration_hours as i32;
-    let duration = minimum_hours.max(duration_hours);
-    let hourly_rate = hourly_rate.unwrap_or(100.0);
-    let total_price = hourly_rate * duration as f64 * data.number_of_guests as f64;
+/// Validates, prices and stores a booking with its guest selections and
+/// add-ons. Shared by the JSON API and the web booking form. `customer` is
+/// the signed-in diner, if any; guests book with just their contact details.
+pub(crate) async fn place_booking(
+    pool: &web::Data<DbPool>,
+    meta: &RequestMeta,
+    chef_id: Uuid,
+    customer: Option<&UserResponse>,
+    data: &CreateBooking,
+) -> Result<Booking, AppError> {
+    let context = load_booking_context(pool, chef_id, data).await?;
+    let priced_add_ons = pricing::resolve_add_ons(&context.add_ons, &data.add_ons, data.number_of_guests)?;
+    let quote = pricing::quote_booking(context.base_price, &context.items, &data.guest_selections, &priced_add_ons);
+    let items = context.items;
 
     // Check for conflicts - simplified check
     let conflict: Option<(uuid::Uuid,)> = sqlx::query_as(
@@ -39,7 +49,7 @@ pub async fn create_booking(
         AND status IN ('pending', 'confirmed')
         "#
     )
-    .bind(*chef_id)
+    .bind(chef_id)
     .bind(data.event_date)
     .bind(data.event_time)
     .fetch_optional(pool.get_ref())
@@ -49,6 +59,8 @@ pub async fn create_booking(
         return Err(AppError::ValidationError("Time slot is already booked".to_string()));
     }
 
+    let mut tx = pool.begin().await?;
+
     let booking = sqlx::query_as::<_, Booking>(
         r#"
         INSERT INTO bookings (
@@ -57,12 +69,13 @@ pub async fn create_booking(
             number_of_guests, location_address, special_requests,
             total_price, status, payment_status, created_at, updated_at
         )
-        VALUES ($1, NULL, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, 'pending', 'pending', NOW(), NOW())
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, 'pending', 'pending', NOW(), NOW())
         RETURNING *
         "#
     )
-    .bind(*chef_id)
-    .bind(&data.menu_id)
+    .bind(chef_id)
+    .bind(customer.map(|c| c.id))
+    .bind(data.menu_id)
     .bind(&data.customer_name)
     .bind(&data.customer_email)
     .bind(&data.customer_phone)
@@ -72,11 +85,188 @@ pub async fn create_booking(
     .bind(data.number_of_guests)
     .bind(&data.location_address)
     .bind(&data.special_requests)
-    .bind(total_price)
-    .fetch_one(pool.get_ref())
+    .bind(quote.total_price)
+    .fetch_one(&mut *tx)
     .await?;
 
-    Ok(HttpResponse::Created().json(booking))
+    for selection in &data.guest_selections {
+        // validate_guest_selections guarantees the item is on the menu
+        let item = items
+            .iter()
+            .find(|item| item.id == selection.menu_item_id)
+            .ok_or_else(|| AppError::ValidationError("Unknown menu item".to_string()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO booking_guest_selections (
+                booking_id, guest_number, guest_name, choice_group_id, menu_item_id,
+                item_name, price_adjustment, dietary_notes, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+            "#
+        )
+        .bind(booking.id)
+        .bind(selection.guest_number)
+        .bind(&selection.guest_name)
+        .bind(item.choice_group_id)
+        .bind(item.id)
+        .bind(&item.name)
+        .bind(item.price_adjustment.unwrap_or(0.0))
+        .bind(&selection.dietary_notes)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for priced in &priced_add_ons {
+        sqlx::query(
+            r#"
+            INSERT INTO booking_add_ons (
+                booking_id, add_on_id, name, quantity, unit_price, total_price, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, NOW())
+            "#
+        )
+        .bind(booking.id)
+        .bind(priced.add_on.id)
+        .bind(&priced.add_on.name)
+        .bind(priced.quantity)
+        .bind(priced.add_on.price)
+        .bind(priced.total_price)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(
+        booking_id = %booking.id,
+        chef_id = %chef_id,
+        "Booking created successfully"
+    );
+    let event = AuditEvent::new("booking_created").on("booking", booking.id);
+    match customer {
+        Some(customer) => event.by_user(customer.id, customer.role),
+        None => event,
+    }
+    .after(serde_json::json!({
+        "chef_id": booking.chef_id,
+        "event_date": booking.event_date,
+        "event_time": booking.event_time,
+        "number_of_guests": booking.number_of_guests,
+        "total_price": booking.total_price,
+        "status": booking.status,
+        "payment_status": booking.payment_status,
+    }))
+    .from(meta)
+    .record(pool)
+    .await;
+
+    Ok(booking)
+}
+
+/// Chef pricing and menu options needed to price a booking
+struct BookingContext {
+    base_price: f64,
+    items: Vec<MenuItem>,
+    add_ons: Vec<MenuAddOn>,
+}
+
+/// Loads what a booking is priced from and validates its guest selections
+async fn load_booking_context(
+    pool: &web::Data<DbPool>,
+    chef_id: Uuid,
+    data: &CreateBooking,
+) -> Result<BookingContext, AppError> {
+    if data.number_of_guests < 1 {
+        return Err(AppError::ValidationError("At least one guest is required".to_string()));
+    }
+    if data.duration_hours <= 0.0 {
+        return Err(AppError::ValidationError("Duration must be positive".to_string()));
+    }
+
+    // Get chef info for pricing
+    let chef: Option<(Option<f64>, i32)> = sqlx::query_as(
+        "SELECT hourly_rate, minimum_hours FROM chefs WHERE id = $1 AND is_active = true"
+    )
+    .bind(chef_id)
+    .fetch_optional(pool.get_ref())
+    .await?;
+    
+    let (hourly_rate, minimum_hours) = chef
+        .ok_or_else(|| AppError::NotFound("Chef not found".to_string()))?;
+
+    let base_price = pricing::base_price(
+        hourly_rate,
+        minimum_hours,
+        data.duration_hours,
+        data.number_of_guests,
+    );
+
+    // Load the menu's choices and add-ons when the diner picked any
+    let has_options = !data.guest_selections.is_empty() || !data.add_ons.is_empty();
+    let (items, groups, available_add_ons) = match (data.menu_id, has_options) {
+        (_, false) => (Vec::new(), Vec::new(), Vec::new()),
+        (None, true) => {
+            return Err(AppError::ValidationError(
+                "A menu is required to choose dishes or add-ons".to_string(),
+            ));
+        }
+        (Some(menu_id), true) => {
+            let menu: Option<(Uuid,)> = sqlx::query_as(
+                "SELECT id FROM menus WHERE id = $1 AND chef_id = $2 AND is_active = true AND hidden_at IS NULL"
+            )
+            .bind(menu_id)
+            .bind(chef_id)
+            .fetch_optional(pool.get_ref())
+            .await?;
+
+            if menu.is_none() {
+                return Err(AppError::NotFound("Menu not found".to_string()));
+            }
+
+            let items = sqlx::query_as::<_, MenuItem>(
+                "SELECT * FROM menu_items WHERE menu_id = $1"
+            )
+            .bind(menu_id)
+            .fetch_all(pool.get_ref())
+            .await?;
+
+            let groups = sqlx::query_as::<_, MenuChoiceGroup>(
+                "SELECT * FROM menu_choice_groups WHERE menu_id = $1"
+            )
+            .bind(menu_id)
+            .fetch_all(pool.get_ref())
+            .await?;
+
+            let add_ons = sqlx::query_as::<_, MenuAddOn>(
+                "SELECT * FROM menu_add_ons WHERE menu_id = $1"
+            )
+            .bind(menu_id)
+            .fetch_all(pool.get_ref())
+            .await?;
+
+            (items, groups, add_ons)
+        }
+    };
+
+    pricing::validate_guest_selections(&groups, &items, &data.guest_selections, data.number_of_guests)?;
+
+    Ok(BookingContext {
+        base_price,
+        items,
+        add_ons: available_add_ons,
+    })
+}
+
+/// Prices a booking without storing it, for review steps
+pub(crate) async fn quote_booking(
+    pool: &web::Data<DbPool>,
+    chef_id: Uuid,
+    data: &CreateBooking,
+) -> Result<pricing::BookingQuote, AppError> {
+    let context = load_booking_context(pool, chef_id, data).await?;
+    let priced_add_ons = pricing::resolve_add_ons(&context.add_ons, &data.add_ons, data.number_of_guests)?;
+    Ok(pricing::quote_booking(context.base_price, &context.items, &data.guest_selections, &priced_add_ons))
 }
 
 pub async fn get_chef_availability(
@@ -92,24 +282,35 @@ pub async fn get_chef_availability(
         .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
         .unwrap_or_else(|| start_date + chrono::Duration::days(30));
 
+    let availability = load_availability(&pool, *chef_id, start_date, end_date).await?;
+    Ok(HttpResponse::Ok().json(availability))
+}
+
+/// Open time slots per day between `start_date` and `end_date` (inclusive)
+pub(crate) async fn load_availability(
+    pool: &web::Data<DbPool>,
+    chef_id: Uuid,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> Result<Vec<BookingAvailability>, AppError> {
+
     // Get existing bookings
     #[derive(sqlx::FromRow)]
     struct BookingTime {
         event_date: chrono::NaiveDate,
         event_time: chrono::NaiveTime,
-        duration_hours: f64,
     }
     
     let bookings: Vec<BookingTime> = sqlx::query_as(
         r#"
-        SELECT event_date, event_time, duration_hours
+        SELECT event_date, event_time
         FROM bookings
         WHERE chef_id = $1
         AND event_date BETWEEN $2 AND $3
         AND status IN ('pending', 'confirmed')
         "#
     )
-    .bind(*chef_id)
+    .bind(chef_id)
     .bind(start_date)
     .bind(end_date)
     .fetch_all(pool.get_ref())
@@ -149,28 +350,15 @@ pub async fn get_chef_availability(
         current_date = current_date.succ_opt().unwrap_or(current_date);
     }
 
-    Ok(HttpResponse::Ok().json(availability))
+    Ok(availability)
 }
 
 pub async fn get_chef_bookings(
-    req: HttpRequest,
+    identity: Identity,
     pool: web::Data<DbPool>,
     chef_id: web::Path<Uuid>,
 ) -> Result<HttpResponse, AppError> {
-    let user_id = extract_user_id(&req)?;
-
-    // Verify ownership
-    let _chef: Option<(uuid::Uuid,)> = sqlx::query_as(
-        "SELECT id FROM chefs WHERE id = $1 AND user_id = $2"
-    )
-    .bind(*chef_id)
-    .bind(user_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
-    
-    if _chef.is_none() {
-        return Err(AppError::Unauthorized("Not authorized".to_string()));
-    }
+    authorize(&pool, &identity, Permission::ManageOwnBookings, Resource::Chef(*chef_id)).await?;
 
     let bookings = sqlx::query_as::<_, Booking>(
         "SELECT * FROM bookings WHERE chef_id = $1 ORDER BY event_date DESC, event_time DESC"
@@ -183,45 +371,183 @@ pub async fn get_chef_bookings(
 }
 
 pub async fn update_booking(
-    req: HttpRequest,
+    identity: Identity,
+    meta: RequestMeta,
     pool: web::Data<DbPool>,
     booking_id: web::Path<Uuid>,
     data: web::Json<UpdateBooking>,
 ) -> Result<HttpResponse, AppError> {
-    let user_id = extract_user_id(&req)?;
+    let owner = authorize(&pool, &identity, Permission::ManageOwnBookings, Resource::Booking(*booking_id)).await?;
+    let booking = find_booking(&pool, *booking_id).await?;
 
-    // Verify ownership
-    let _chef: Option<(uuid::Uuid,)> = sqlx::query_as(
-        "SELECT c.id FROM chefs c
-         INNER JOIN bookings b ON b.chef_id = c.id
-         WHERE c.user_id = $1 AND b.id = $2"
-    )
-    .bind(user_id)
-    .bind(*booking_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
-    
-    if _chef.is_none() {
-        return Err(AppError::Unauthorized("Not authorized".to_string()));
+    let status = data
+        .status
+        .as_deref()
+        .map(str::parse::<BookingStatus>)
+        .transpose()
+        .map_err(AppError::ValidationError)?;
+
+    // Checked before anything changes. Money movements stay with the
+    // account holder, and with team members who may handle payments.
+    let payment_status = data.payment_status.as_ref().filter(|&p| *p != booking.payment_status);
+    if payment_status.is_some() {
+        identity.forbid_impersonation()?;
+        if !allows(&identity, Permission::ManageOwnPayments, &owner) {
+            return Err(AppError::Forbidden("Your team role doesn't allow changing payment status".to_string()));
+        }
     }
 
-    let booking = sqlx::query_as::<_, Booking>(
+    let booking = match status {
+        Some(status) => change_booking_status(&pool, &identity, &meta, &booking, status).await?,
+        None => booking,
+    };
+
+    let booking = match payment_status {
+        Some(payment_status) => {
+            let updated = sqlx::query_as::<_, Booking>(
+                "UPDATE bookings SET payment_status = $1, updated_at = NOW() WHERE id = $2 RETURNING *"
+            )
+            .bind(payment_status)
+            .bind(booking.id)
+            .fetch_optional(pool.get_ref())
+            .await?
+            .ok_or_else(|| AppError::NotFound("Booking not found".to_string()))?;
+            AuditEvent::new("booking_payment_status_changed")
+                .by(&identity)
+                .on("booking", booking.id)
+                .before(serde_json::json!({ "payment_status": booking.payment_status }))
+                .after(serde_json::json!({ "payment_status": updated.payment_status }))
+                .detail("total_price", updated.total_price)
+                .from(&meta)
+                .record(&pool)
+                .await;
+            updated
+        }
+        None => booking,
+    };
+
+    Ok(HttpResponse::Ok().json(booking))
+}
+
+pub(crate) async fn find_booking(pool: &web::Data<DbPool>, booking_id: Uuid) -> Result<Booking, AppError> {
+    sqlx::query_as::<_, Booking>("SELECT * FROM bookings WHERE id = $1")
+        .bind(booking_id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("Booking not found".to_string()))
+}
+
+/// Moves a booking to `next` if its lifecycle allows it.
+///
+/// The update only applies if the status hasn't changed since `booking`
+/// was read, so a diner cancelling while the chef confirms can't both win.
+/// Callers are responsible for checking who may make the change; `actor`
+/// is recorded as having made it.
+pub(crate) async fn change_booking_status(
+    pool: &web::Data<DbPool>,
+    actor: &Identity,
+    meta: &RequestMeta,
+    booking: &Booking,
+    next: BookingStatus,
+) -> Result<Booking, AppError> {
+    let current = booking.booking_status();
+    if current == next {
+        return Ok(booking.clone());
+    }
+    if !current.can_transition_to(next) {
+        return Err(AppError::ValidationError(format!(
+            "A {} booking can't be marked {}",
+            current, next
+        )));
+    }
+    if !booking.can_become(next, chrono::Utc::now().date_naive()) {
+        return Err(AppError::ValidationError(
+            "A booking can't be marked completed before the event".to_string(),
+        ));
+    }
+
+    let updated = sqlx::query_as::<_, Booking>(
         r#"
         UPDATE bookings
-        SET status = COALESCE($1, status),
-            payment_status = COALESCE($2, payment_status),
-            updated_at = NOW()
-        WHERE id = $3
+        SET status = $1, updated_at = NOW()
+        WHERE id = $2 AND status = $3
         RETURNING *
         "#
     )
-    .bind(&data.status)
-    .bind(&data.payment_status)
-    .bind(*booking_id)
+    .bind(next.to_string())
+    .bind(booking.id)
+    .bind(&booking.status)
     .fetch_optional(pool.get_ref())
     .await?
-    .ok_or_else(|| AppError::NotFound("Booking not found".to_string()))?;
+    .ok_or_else(|| {
+        AppError::ValidationError("The booking was changed in the meantime; please reload and try again".to_string())
+    })?;
 
-    Ok(HttpResponse::Ok().json(booking))
+    tracing::info!(
+        booking_id = %booking.id,
+        from = %current,
+        to = %next,
+        "Booking status changed"
+    );
+    AuditEvent::new("booking_status_changed")
+        .by(actor)
+        .on("booking", booking.id)
+        .before(serde_json::json!({ "status": booking.status }))
+        .after(serde_json::json!({ "status": updated.status }))
+        .from(meta)
+        .record(pool)
+        .await;
+
+    Ok(updated)
 }
 
+
+/// Returns what the chef needs to prepare for a booking: servings per dish
+/// (fixed courses for every guest, choice-group dishes per selection),
+/// booked add-ons and guests' dietary notes.
+pub async fn get_booking_prep_sheet(
+    identity: Identity,
+    pool: web::Data<DbPool>,
+    booking_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    authorize(&pool, &identity, Permission::ManageOwnBookings, Resource::Booking(*booking_id)).await?;
+    let booking = find_booking(&pool, *booking_id).await?;
+    Ok(HttpResponse::Ok().json(load_prep_sheet(&pool, &booking).await?))
+}
+
+/// Builds the prep sheet from the booked menu and the guests' selections
+pub(crate) async fn load_prep_sheet(pool: &DbPool, booking: &Booking) -> Result<PrepSheet, AppError> {
+    let items = match booking.menu_id {
+        Some(menu_id) => {
+            sqlx::query_as::<_, MenuItem>(
+                "SELECT * FROM menu_items WHERE menu_id = $1 ORDER BY display_order ASC, created_at ASC"
+            )
+            .bind(menu_id)
+            .fetch_all(pool)
+            .await?
+        }
+        None => Vec::new(),
+    };
+
+    let selections = sqlx::query_as::<_, BookingGuestSelection>(
+        "SELECT * FROM booking_guest_selections WHERE booking_id = $1 ORDER BY guest_number ASC"
+    )
+    .bind(booking.id)
+    .fetch_all(pool)
+    .await?;
+
+    let add_ons = sqlx::query_as::<_, BookingAddOn>(
+        "SELECT * FROM booking_add_ons WHERE booking_id = $1 ORDER BY created_at ASC"
+    )
+    .bind(booking.id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(PrepSheet::build(
+        booking.id,
+        booking.number_of_guests,
+        &items,
+        &selections,
+        add_ons,
+    ))
+}
@@ -0,0 +1,252 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use askama::Template;
+use chrono::{Duration, Utc};
+
+use crate::audit::RequestMeta;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::booking::{load_availability, place_booking, quote_booking};
+use crate::handlers::chef_page::find_active_chef;
+use crate::handlers::menu::load_public_menus;
+use crate::handlers::web::query_params;
+use crate::middleware::auth::AuthUser;
+use crate::middleware::flash::{redirect_with_flash, FlashMessage};
+use crate::models::{BookingDraft, Chef, ChefPublicProfile, Menu, PublicMenu, UserResponse, WizardStep};
+use crate::templates::{BookingWizardTemplate, PageContext};
+
+/// How far ahead the wizard offers dates
+const BOOKING_WINDOW_DAYS: i64 = 60;
+
+/// Serve the first step of the booking wizard. `?menu=` preselects a menu.
+pub async fn booking_wizard_page(
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    slug: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let user = UserResponse::from(user);
+    let chef = find_active_chef(&pool, &slug).await?;
+
+    let draft = BookingDraft {
+        menu_id: query_params(&req).get("menu").and_then(|id| id.parse().ok()),
+        ..BookingDraft::default()
+    };
+
+    render_step(&req, &pool, user, chef, WizardStep::Date, draft, None).await
+}
+
+/// Handle a wizard step: `nav=back` returns to the previous step, anything
+/// else validates the submitted step and moves on.
+pub async fn handle_booking_wizard(
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    slug: web::Path<String>,
+    form: web::Form<Vec<(String, String)>>,
+) -> Result<HttpResponse, AppError> {
+    let user = UserResponse::from(user);
+    let chef = find_active_chef(&pool, &slug).await?;
+
+    let field = |name: &str| form.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+    let step = field("step")
+        .and_then(|n| n.parse().ok())
+        .and_then(WizardStep::from_number)
+        .unwrap_or(WizardStep::Date);
+    let draft = BookingDraft::from_form(&form);
+
+    if field("nav") == Some("back") {
+        return render_step(&req, &pool, user, chef, step.previous(), draft, None).await;
+    }
+
+    match check_step(&pool, &chef, &draft, step).await? {
+        Ok(()) => render_step(&req, &pool, user, chef, step.next(), draft, None).await,
+        Err(message) => render_step(&req, &pool, user, chef, step, draft, Some(message)).await,
+    }
+}
+
+/// Submit the reviewed booking through the shared booking logic and send
+/// the diner to their dashboard.
+pub async fn handle_booking_wizard_submit(
+    user: AuthUser,
+    req: HttpRequest,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    slug: web::Path<String>,
+    form: web::Form<Vec<(String, String)>>,
+) -> Result<HttpResponse, AppError> {
+    let user = UserResponse::from(user);
+    let chef = find_active_chef(&pool, &slug).await?;
+    let draft = BookingDraft::from_form(&form);
+
+    if let Err(message) = check_step(&pool, &chef, &draft, WizardStep::Review).await? {
+        return render_step(&req, &pool, user, chef, WizardStep::Review, draft, Some(message)).await;
+    }
+    let booking = match draft.to_create_booking(&user.email) {
+        Ok(booking) => booking,
+        Err(message) => return render_step(&req, &pool, user, chef, WizardStep::Review, draft, Some(message)).await,
+    };
+
+    match place_booking(&pool, &meta, chef.id, Some(&user), &booking).await {
+        Ok(created) => {
+            tracing::info!(
+                booking_id = %created.id,
+                chef_id = %chef.id,
+                user_id = %user.id,
+                "Booking created through booking wizard"
+            );
+            let message = format!(
+                "Booking request sent to {}! Total: ${:.2}. You'll see it here once the chef confirms.",
+                chef.chef_name, created.total_price
+            );
+            Ok(redirect_with_flash(&req, "/dashboard", FlashMessage::success(message)))
+        }
+        Err(AppError::ValidationError(message)) | Err(AppError::NotFound(message)) => {
+            render_step(&req, &pool, user, chef, WizardStep::Review, draft, Some(message)).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+async fn load_menu(pool: &web::Data<DbPool>, chef: &Chef, draft: &BookingDraft) -> Result<Option<PublicMenu>, AppError> {
+    let Some(menu_id) = draft.menu_id else {
+        return Ok(None);
+    };
+    let menu = sqlx::query_as::<_, Menu>(
+        "SELECT * FROM menus WHERE id = $1 AND chef_id = $2 AND is_active = true AND hidden_at IS NULL"
+    )
+    .bind(menu_id)
+    .bind(chef.id)
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    match menu {
+        Some(menu) => Ok(load_public_menus(pool, vec![menu]).await?.pop()),
+        None => Ok(None),
+    }
+}
+
+/// Validates `step` (and for the review step, everything) including the
+/// checks that need the database. The outer error is for infrastructure
+/// failures; the inner one is a message for the diner.
+async fn check_step(
+    pool: &web::Data<DbPool>,
+    chef: &Chef,
+    draft: &BookingDraft,
+    step: WizardStep,
+) -> Result<Result<(), String>, AppError> {
+    if let Err(message) = draft.validate_step(step) {
+        return Ok(Err(message));
+    }
+
+    if matches!(step, WizardStep::Date | WizardStep::Review) {
+        if let Some((date, time)) = draft.event_slot() {
+            let open = load_availability(pool, chef.id, date, date).await?;
+            let slot = time.format("%H:%M").to_string();
+            if date <= Utc::now().date_naive() || !open.iter().any(|d| d.available_times.contains(&slot)) {
+                return Ok(Err("That time slot is no longer available".to_string()));
+            }
+        }
+    }
+
+    if matches!(step, WizardStep::Menu | WizardStep::Guests | WizardStep::Review) {
+        let menu = load_menu(pool, chef, draft).await?;
+        if draft.menu_id.is_some() && menu.is_none() {
+            return Ok(Err("That menu is no longer available".to_string()));
+        }
+        if let Some(menu) = menu {
+            if draft.add_on_ids.iter().any(|id| !menu.add_ons.iter().any(|a| a.id == *id)) {
+                return Ok(Err("One of the selected add-ons is no longer available".to_string()));
+            }
+            let guests = draft.number_of_guests.unwrap_or(menu.minimum_guests);
+            if step != WizardStep::Menu && guests < menu.minimum_guests {
+                return Ok(Err(format!("{} requires at least {} guests", menu.name, menu.minimum_guests)));
+            }
+        }
+    }
+
+    Ok(Ok(()))
+}
+
+async fn render_step(
+    req: &HttpRequest,
+    pool: &web::Data<DbPool>,
+    user: UserResponse,
+    chef: Chef,
+    step: WizardStep,
+    mut draft: BookingDraft,
+    error: Option<String>,
+) -> Result<HttpResponse, AppError> {
+    let availability = if step == WizardStep::Date {
+        let start = Utc::now().date_naive() + Duration::days(1);
+        let end = start + Duration::days(BOOKING_WINDOW_DAYS - 1);
+        load_availability(pool, chef.id, start, end)
+            .await?
+            .into_iter()
+            .filter(|d| d.available)
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let menus = if step == WizardStep::Menu {
+        let menus = sqlx::query_as::<_, Menu>(
+            "SELECT * FROM menus WHERE chef_id = $1 AND is_active = true AND hidden_at IS NULL ORDER BY created_at ASC"
+        )
+        .bind(chef.id)
+        .fetch_all(pool.get_ref())
+        .await?;
+        load_public_menus(pool, menus).await?
+    } else {
+        Vec::new()
+    };
+
+    let selected_menu = load_menu(pool, &chef, &draft).await?;
+
+    // Suggest the menu's minimum guests and duration on the guests step
+    if step == WizardStep::Guests {
+        if let Some(menu) = &selected_menu {
+            draft.number_of_guests.get_or_insert(menu.minimum_guests);
+            if let Some(hours) = menu.duration_hours {
+                draft.duration_hours.get_or_insert(hours);
+            }
+        }
+        draft.number_of_guests.get_or_insert(2);
+        draft.duration_hours.get_or_insert(3.0);
+    }
+
+    let mut error = error;
+    let quote = if step == WizardStep::Review {
+        match draft.to_create_booking(&user.email) {
+            Ok(booking) => match quote_booking(pool, chef.id, &booking).await {
+                Ok(quote) => Some(quote),
+                Err(AppError::ValidationError(message)) | Err(AppError::NotFound(message)) => {
+                    error.get_or_insert(message);
+                    None
+                }
+                Err(e) => return Err(e),
+            },
+            Err(message) => {
+                error.get_or_insert(message);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let template = BookingWizardTemplate {
+        ctx: PageContext::new(req, Some(user)),
+        chef: ChefPublicProfile::from(chef),
+        step,
+        hidden_fields: draft.hidden_fields(step),
+        draft,
+        availability,
+        menus,
+        selected_menu,
+        quote,
+        error,
+    };
+    Ok(HttpResponse::Ok()
+        .content_type("text/html")
+        .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
+}
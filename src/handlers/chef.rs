@@ -1,25 +1,24 @@
 use actix_web::{web, HttpResponse};
-use uuid::Uuid;
 use slug::slugify;
 
+use crate::audit::{AuditEvent, RequestMeta};
 use crate::db::DbPool;
 use crate::models::{Chef, CreateChef, UpdateChef, ChefPublicProfile, MenuItem, MenuItemPublic};
 use crate::errors::AppError;
+use crate::middleware::auth::RequireRole;
+use crate::middleware::roles::{role, Permission};
+use crate::policy::{acting_chef, membership, require, ChefScope};
 
 pub async fn create_chef_profile(
+    user: RequireRole<role::Chef>,
     pool: web::Data<DbPool>,
-    user_id: web::Path<Uuid>,
+    meta: RequestMeta,
     data: web::Json<CreateChef>,
 ) -> Result<HttpResponse, AppError> {
-    // Check if chef profile already exists
-    let existing = sqlx::query_as::<_, Chef>(
-        "SELECT * FROM chefs WHERE user_id = $1"
-    )
-    .bind(*user_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
+    require(&user, Permission::ManageOwnChefProfile)?;
 
-    if existing.is_some() {
+    // Members of a team, owners included, work under that team's profile
+    if membership(&pool, user.id).await?.is_some() {
         return Err(AppError::ValidationError("Chef profile already exists".to_string()));
     }
 
@@ -37,7 +36,7 @@ pub async fn create_chef_profile(
         RETURNING *
         "#
     )
-    .bind(*user_id)
+    .bind(user.id)
     .bind(&data.business_name)
     .bind(&data.chef_name)
     .bind(&data.bio)
@@ -48,24 +47,34 @@ pub async fn create_chef_profile(
     .bind(&data.website)
     .bind(&data.profile_image_url)
     .bind(&data.cover_image_url)
-    .bind(&data.hourly_rate)
+    .bind(data.hourly_rate)
     .bind(data.minimum_hours.unwrap_or(2))
-    .bind(&data.travel_radius)
+    .bind(data.travel_radius)
     .bind(&slug)
     .fetch_one(pool.get_ref())
     .await?;
+    AuditEvent::new("chef_profile_created")
+        .by(&user)
+        .on("chef", chef.id)
+        .after(serde_json::to_value(&chef).unwrap_or_default())
+        .from(&meta)
+        .record(&pool)
+        .await;
 
     Ok(HttpResponse::Created().json(chef))
 }
 
+/// Returns the signed-in chef's profile, or that of `?chef_id=` (admins).
 pub async fn get_chef_profile(
+    user: RequireRole<role::Chef>,
     pool: web::Data<DbPool>,
-    user_id: web::Path<Uuid>,
+    scope: web::Query<ChefScope>,
 ) -> Result<HttpResponse, AppError> {
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnChefProfile, scope.chef_id).await?;
     let chef = sqlx::query_as::<_, Chef>(
-        "SELECT * FROM chefs WHERE user_id = $1"
+        "SELECT * FROM chefs WHERE id = $1"
     )
-    .bind(*user_id)
+    .bind(chef_id)
     .fetch_optional(pool.get_ref())
     .await?
     .ok_or_else(|| AppError::NotFound("Chef profile not found".to_string()))?;
@@ -73,11 +82,15 @@ pub async fn get_chef_profile(
     Ok(HttpResponse::Ok().json(chef))
 }
 
+/// Updates the signed-in chef's profile, or that of `?chef_id=` (admins).
 pub async fn update_chef_profile(
+    user: RequireRole<role::Chef>,
     pool: web::Data<DbPool>,
-    user_id: web::Path<Uuid>,
+    meta: RequestMeta,
+    scope: web::Query<ChefScope>,
     data: web::Json<UpdateChef>,
 ) -> Result<HttpResponse, AppError> {
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnChefProfile, scope.chef_id).await?;
     let mut update_fields = Vec::new();
     let mut bind_index = 1;
 
@@ -101,12 +114,41 @@ pub async fn update_chef_profile(
         update_fields.push(format!("location = ${}", bind_index));
         bind_index += 1;
     }
+    if data.phone.is_some() {
+        update_fields.push(format!("phone = ${}", bind_index));
+        bind_index += 1;
+    }
+    if data.email.is_some() {
+        update_fields.push(format!("email = ${}", bind_index));
+        bind_index += 1;
+    }
+    if data.website.is_some() {
+        update_fields.push(format!("website = ${}", bind_index));
+        bind_index += 1;
+    }
+    if data.profile_image_url.is_some() {
+        update_fields.push(format!("profile_image_url = ${}", bind_index));
+        bind_index += 1;
+    }
+    if data.cover_image_url.is_some() {
+        update_fields.push(format!("cover_image_url = ${}", bind_index));
+        bind_index += 1;
+    }
     if data.hourly_rate.is_some() {
         update_fields.push(format!("hourly_rate = ${}", bind_index));
         bind_index += 1;
     }
+    if data.minimum_hours.is_some() {
+        update_fields.push(format!("minimum_hours = ${}", bind_index));
+        bind_index += 1;
+    }
+    if data.travel_radius.is_some() {
+        update_fields.push(format!("travel_radius = ${}", bind_index));
+        bind_index += 1;
+    }
     if data.is_active.is_some() {
-        update_fields.push(format!("is_active = ${}", bind_index));
+        // Chefs can't reactivate a profile staff have deactivated
+        update_fields.push(format!("is_active = (${} AND deactivated_at IS NULL)", bind_index));
         bind_index += 1;
     }
 
@@ -114,10 +156,10 @@ pub async fn update_chef_profile(
         return Err(AppError::ValidationError("No fields to update".to_string()));
     }
 
-    update_fields.push(format!("updated_at = NOW()"));
+    update_fields.push("updated_at = NOW()".to_string());
 
     let query = format!(
-        "UPDATE chefs SET {} WHERE user_id = ${} RETURNING *",
+        "UPDATE chefs SET {} WHERE id = ${} RETURNING *",
         update_fields.join(", "),
         bind_index
     );
@@ -139,19 +181,52 @@ pub async fn update_chef_profile(
     if let Some(ref location) = data.location {
         query_builder = query_builder.bind(location);
     }
+    if let Some(ref phone) = data.phone {
+        query_builder = query_builder.bind(phone);
+    }
+    if let Some(ref email) = data.email {
+        query_builder = query_builder.bind(email);
+    }
+    if let Some(ref website) = data.website {
+        query_builder = query_builder.bind(website);
+    }
+    if let Some(ref url) = data.profile_image_url {
+        query_builder = query_builder.bind(url);
+    }
+    if let Some(ref url) = data.cover_image_url {
+        query_builder = query_builder.bind(url);
+    }
     if let Some(ref rate) = data.hourly_rate {
         query_builder = query_builder.bind(rate);
     }
+    if let Some(ref hours) = data.minimum_hours {
+        query_builder = query_builder.bind(hours);
+    }
+    if let Some(ref radius) = data.travel_radius {
+        query_builder = query_builder.bind(radius);
+    }
     if let Some(ref active) = data.is_active {
         query_builder = query_builder.bind(active);
     }
     
-    query_builder = query_builder.bind(*user_id);
+    query_builder = query_builder.bind(chef_id);
 
+    let before = sqlx::query_as::<_, Chef>("SELECT * FROM chefs WHERE id = $1")
+        .bind(chef_id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("Chef profile not found".to_string()))?;
     let chef = query_builder
         .fetch_optional(pool.get_ref())
         .await?
         .ok_or_else(|| AppError::NotFound("Chef profile not found".to_string()))?;
+    AuditEvent::new("chef_profile_updated")
+        .by(&user)
+        .on("chef", chef.id)
+        .changes(&before, &chef)
+        .from(&meta)
+        .record(&pool)
+        .await;
 
     Ok(HttpResponse::Ok().json(chef))
 }
@@ -173,7 +248,7 @@ pub async fn get_public_chef_profile(
         r#"
         SELECT mi.* FROM menu_items mi
         INNER JOIN menus m ON mi.menu_id = m.id
-        WHERE m.chef_id = $1 AND mi.is_featured = true AND m.is_active = true
+        WHERE m.chef_id = $1 AND mi.is_featured = true AND m.is_active = true AND m.hidden_at IS NULL
         ORDER BY mi.display_order ASC
         LIMIT 6
         "#
@@ -185,13 +260,7 @@ pub async fn get_public_chef_profile(
     let mut public_profile: ChefPublicProfile = chef.into();
     public_profile.featured_menu_items = featured_items
         .into_iter()
-        .map(|item| MenuItemPublic {
-            id: item.id,
-            name: item.name,
-            description: item.description,
-            course_type: item.course_type,
-            image_url: item.image_url,
-        })
+        .map(MenuItemPublic::from)
         .collect();
 
     Ok(HttpResponse::Ok().json(public_profile))
@@ -0,0 +1,620 @@
+use actix_multipart::Multipart;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpResponse};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::audit::{AuditEvent, RequestMeta};
+use crate::cache::RedisClient;
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::admin::managed_user;
+use crate::handlers::auth::set_user_role;
+use crate::mail::{Email, Mailer};
+use crate::middleware::auth::{AuthUser, Identity, NotImpersonating, RequireRole};
+use crate::middleware::roles::{has_permission, role, Permission};
+use crate::models::{
+    ChefApplication, ChefApplicationDetail, ChefApplicationDocument, DocumentKind, RejectApplication, Role, User,
+};
+use crate::storage::{BlobStore, PRIVATE_PREFIX};
+
+/// Longest experience statement or rejection reason accepted
+const MAX_TEXT_LENGTH: usize = 5000;
+/// Longest file name kept for a document
+const MAX_FILE_NAME_LENGTH: usize = 200;
+/// Applications shown per list
+const APPLICATIONS_PAGE_SIZE: i64 = 50;
+/// Application statuses that can be listed
+pub const APPLICATION_STATUSES: [&str; 3] = ["pending", "approved", "rejected"];
+
+/// A credential file read from the application form
+#[derive(Debug)]
+pub struct UploadedDocument {
+    pub kind: DocumentKind,
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub data: Vec<u8>,
+}
+
+/// A submitted application before it is stored
+#[derive(Debug)]
+pub struct ApplicationForm {
+    pub experience: String,
+    pub documents: Vec<UploadedDocument>,
+}
+
+/// The content type of a credential file, judged from its first bytes.
+/// PDFs and photos or scans as JPEG or PNG are accepted.
+pub fn document_content_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else {
+        None
+    }
+}
+
+/// Keep only the last path segment of an uploaded file name, so it can't
+/// carry a directory into the download header
+fn clean_file_name(name: Option<&str>, kind: DocumentKind) -> String {
+    let name = name
+        .and_then(|name| name.rsplit(['/', '\\']).next())
+        .map(|name| name.chars().filter(|c| !c.is_control()).take(MAX_FILE_NAME_LENGTH).collect::<String>())
+        .unwrap_or_default();
+    if name.trim().is_empty() {
+        kind.to_string()
+    } else {
+        name
+    }
+}
+
+fn multipart_error(e: impl std::fmt::Display) -> AppError {
+    AppError::ValidationError(format!("Invalid multipart body: {}", e))
+}
+
+/// Read the application form: an `experience` text field and one file for
+/// each document kind, named after the kind. Each file may be at most
+/// `max_bytes`.
+pub async fn read_application_form(mut payload: Multipart, max_bytes: usize) -> Result<ApplicationForm, AppError> {
+    let mut experience = None;
+    let mut documents: Vec<UploadedDocument> = Vec::new();
+
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(multipart_error)?;
+        let name = field.content_disposition().get_name().unwrap_or_default().to_string();
+        let kind = name.parse::<DocumentKind>().ok();
+        if name != "experience" && kind.is_none() {
+            // Drain fields we don't use
+            while let Some(chunk) = field.next().await {
+                chunk.map_err(multipart_error)?;
+            }
+            continue;
+        }
+
+        let limit = if kind.is_some() { max_bytes } else { MAX_TEXT_LENGTH * 4 };
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(multipart_error)?;
+            if bytes.len() + chunk.len() > limit {
+                return Err(AppError::ValidationError(match kind {
+                    Some(kind) => format!("{} is too large; the limit is {} bytes", kind.label(), max_bytes),
+                    None => "Experience is too long".to_string(),
+                }));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        match kind {
+            None => {
+                let text = String::from_utf8(bytes)
+                    .map_err(|_| AppError::ValidationError("Experience must be text".to_string()))?;
+                experience = Some(text);
+            }
+            Some(kind) => {
+                if documents.iter().any(|d| d.kind == kind) {
+                    return Err(AppError::ValidationError(format!("Only one {} can be uploaded", kind.label())));
+                }
+                let content_type = document_content_type(&bytes).ok_or_else(|| {
+                    AppError::ValidationError(format!("{} must be a PDF, JPEG or PNG file", kind.label()))
+                })?;
+                documents.push(UploadedDocument {
+                    kind,
+                    file_name: clean_file_name(field.content_disposition().get_filename(), kind),
+                    content_type,
+                    data: bytes,
+                });
+            }
+        }
+    }
+
+    let experience = experience.map(|e| e.trim().to_string()).unwrap_or_default();
+    if experience.is_empty() {
+        return Err(AppError::ValidationError("Please tell us about your cooking experience".to_string()));
+    }
+    if experience.chars().count() > MAX_TEXT_LENGTH {
+        return Err(AppError::ValidationError(format!("Experience can be at most {} characters", MAX_TEXT_LENGTH)));
+    }
+    for kind in DocumentKind::ALL {
+        if !documents.iter().any(|d| d.kind == kind) {
+            return Err(AppError::ValidationError(format!("{} is required", kind.label())));
+        }
+    }
+
+    Ok(ApplicationForm { experience, documents })
+}
+
+/// Store a diner's application and its documents. The files are written
+/// first, under the private prefix, and removed again if the application
+/// can't be saved. Only diners apply, so `user_id` is a diner's.
+pub async fn submit_application(
+    pool: &DbPool,
+    blob_store: &dyn BlobStore,
+    meta: &RequestMeta,
+    user_id: Uuid,
+    form: ApplicationForm,
+) -> Result<ChefApplicationDetail, AppError> {
+    if latest_application(pool, user_id).await?.is_some_and(|a| a.is_pending()) {
+        return Err(AppError::ValidationError("Your application is already being reviewed".to_string()));
+    }
+
+    let application_id = Uuid::new_v4();
+    let mut stored = Vec::with_capacity(form.documents.len());
+    for document in form.documents {
+        let id = Uuid::new_v4();
+        let key = format!("{}/chef-applications/{}/{}", PRIVATE_PREFIX, application_id, id);
+        let size = document.data.len();
+        if let Err(e) = blob_store.put(&key, document.data, document.content_type).await {
+            remove_files(blob_store, &stored).await;
+            return Err(e);
+        }
+        stored.push((id, key, document.kind, document.file_name, document.content_type, size));
+    }
+
+    let saved = async {
+        let mut tx = pool.begin().await?;
+        let application = sqlx::query_as::<_, ChefApplication>(
+            "INSERT INTO chef_applications (id, user_id, experience) VALUES ($1, $2, $3) RETURNING *"
+        )
+        .bind(application_id)
+        .bind(user_id)
+        .bind(&form.experience)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut documents = Vec::with_capacity(stored.len());
+        for (id, key, kind, file_name, content_type, size) in &stored {
+            documents.push(
+                sqlx::query_as::<_, ChefApplicationDocument>(
+                    r#"
+                    INSERT INTO chef_application_documents
+                        (id, application_id, kind, file_name, content_type, size_bytes, storage_key)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    RETURNING *
+                    "#
+                )
+                .bind(id)
+                .bind(application_id)
+                .bind(kind.to_string())
+                .bind(file_name)
+                .bind(content_type)
+                .bind(*size as i32)
+                .bind(key)
+                .fetch_one(&mut *tx)
+                .await?,
+            );
+        }
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>((application, documents))
+    }
+    .await;
+
+    let (application, documents) = match saved {
+        Ok(saved) => saved,
+        Err(e) => {
+            remove_files(blob_store, &stored).await;
+            return Err(match e {
+                // Another submission got in first
+                sqlx::Error::Database(db) if db.is_unique_violation() => {
+                    AppError::ValidationError("Your application is already being reviewed".to_string())
+                }
+                e => e.into(),
+            });
+        }
+    };
+
+    let (applicant_email,) = sqlx::query_as::<_, (String,)>("SELECT email FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    AuditEvent::new("chef_application_submitted")
+        .by_user(user_id, Role::Diner)
+        .on("chef_application", application.id)
+        .after(serde_json::json!({ "status": application.status }))
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(ChefApplicationDetail { application, applicant_email, documents })
+}
+
+async fn remove_files(blob_store: &dyn BlobStore, stored: &[(Uuid, String, DocumentKind, String, &str, usize)]) {
+    for (_, key, ..) in stored {
+        if let Err(e) = blob_store.delete(key).await {
+            tracing::warn!(key = %key, error = ?e, "Failed to remove chef application document");
+        }
+    }
+}
+
+/// The user's most recent application, if they've applied
+pub async fn latest_application(pool: &DbPool, user_id: Uuid) -> Result<Option<ChefApplication>, AppError> {
+    Ok(sqlx::query_as::<_, ChefApplication>(
+        "SELECT * FROM chef_applications WHERE user_id = $1 ORDER BY created_at DESC LIMIT 1"
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?)
+}
+
+/// Applications with the given status, with their applicants and
+/// documents. Pending ones are listed oldest first, so nobody waits too
+/// long; reviewed ones newest first.
+pub async fn list_applications(pool: &DbPool, status: &str) -> Result<Vec<ChefApplicationDetail>, AppError> {
+    let rows = sqlx::query_as::<_, (Uuid, String)>(
+        r#"
+        SELECT a.id, u.email
+        FROM chef_applications a
+        INNER JOIN users u ON u.id = a.user_id
+        WHERE a.status = $1
+        ORDER BY CASE WHEN a.status = 'pending' THEN a.created_at END ASC, a.reviewed_at DESC
+        LIMIT $2
+        "#
+    )
+    .bind(status)
+    .bind(APPLICATIONS_PAGE_SIZE)
+    .fetch_all(pool)
+    .await?;
+    let ids: Vec<Uuid> = rows.iter().map(|(id, _)| *id).collect();
+
+    let mut applications: HashMap<Uuid, ChefApplication> =
+        sqlx::query_as::<_, ChefApplication>("SELECT * FROM chef_applications WHERE id = ANY($1)")
+            .bind(&ids)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|a| (a.id, a))
+            .collect();
+    let mut documents: HashMap<Uuid, Vec<ChefApplicationDocument>> = HashMap::new();
+    for document in sqlx::query_as::<_, ChefApplicationDocument>(
+        "SELECT * FROM chef_application_documents WHERE application_id = ANY($1) ORDER BY kind"
+    )
+    .bind(&ids)
+    .fetch_all(pool)
+    .await?
+    {
+        documents.entry(document.application_id).or_default().push(document);
+    }
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(id, applicant_email)| {
+            applications.remove(&id).map(|application| ChefApplicationDetail {
+                application,
+                applicant_email,
+                documents: documents.remove(&id).unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+pub async fn application_detail(pool: &DbPool, application_id: Uuid) -> Result<ChefApplicationDetail, AppError> {
+    let application = sqlx::query_as::<_, ChefApplication>("SELECT * FROM chef_applications WHERE id = $1")
+        .bind(application_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Application not found".to_string()))?;
+    let (applicant_email,) = sqlx::query_as::<_, (String,)>("SELECT email FROM users WHERE id = $1")
+        .bind(application.user_id)
+        .fetch_one(pool)
+        .await?;
+    let documents = sqlx::query_as::<_, ChefApplicationDocument>(
+        "SELECT * FROM chef_application_documents WHERE application_id = $1 ORDER BY kind"
+    )
+    .bind(application_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(ChefApplicationDetail { application, applicant_email, documents })
+}
+
+fn require_reviewer(actor: &Identity) -> Result<(), AppError> {
+    if has_permission(actor.role, Permission::ReviewChefApplications) {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden("You don't have permission to review chef applications".to_string()))
+    }
+}
+
+/// Mark a pending application reviewed. Fails if someone else reviewed it
+/// first.
+async fn close_application(
+    conn: &mut sqlx::PgConnection,
+    actor: &Identity,
+    application_id: Uuid,
+    status: &str,
+    reason: Option<&str>,
+) -> Result<ChefApplication, AppError> {
+    sqlx::query_as::<_, ChefApplication>(
+        r#"
+        UPDATE chef_applications
+        SET status = $2, reviewed_by = $3, reviewed_at = NOW(), rejection_reason = $4, updated_at = NOW()
+        WHERE id = $1 AND status = 'pending'
+        RETURNING *
+        "#
+    )
+    .bind(application_id)
+    .bind(status)
+    .bind(actor.id)
+    .bind(reason)
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| AppError::ValidationError("This application has already been reviewed".to_string()))
+}
+
+/// The pending application and its applicant, if `actor` may review it.
+/// Goes through the same checks as the admin API, so nobody reviews their
+/// own application.
+async fn reviewable_application(
+    pool: &DbPool,
+    actor: &Identity,
+    application_id: Uuid,
+) -> Result<(ChefApplication, User), AppError> {
+    require_reviewer(actor)?;
+    let application = sqlx::query_as::<_, ChefApplication>("SELECT * FROM chef_applications WHERE id = $1")
+        .bind(application_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Application not found".to_string()))?;
+    if !application.is_pending() {
+        return Err(AppError::ValidationError("This application has already been reviewed".to_string()));
+    }
+    let applicant = managed_user(pool, actor, application.user_id).await?;
+    Ok((application, applicant))
+}
+
+/// Approve an application and make the applicant a chef. Their sessions
+/// end with the role change, and they sign in again as a chef.
+pub async fn approve_application(
+    pool: &DbPool,
+    redis: &RedisClient,
+    mailer: &dyn Mailer,
+    config: &Config,
+    actor: &Identity,
+    meta: &RequestMeta,
+    application_id: Uuid,
+) -> Result<ChefApplication, AppError> {
+    let (application, applicant) = reviewable_application(pool, actor, application_id).await?;
+    // In one transaction, so an approved applicant is never left a diner.
+    // Staff may have given them the role already.
+    let promote = applicant.role == Role::Diner;
+    let mut tx = pool.begin().await?;
+    let application = close_application(&mut tx, actor, application.id, "approved", None).await?;
+    if promote {
+        set_user_role(&mut tx, applicant.id, Role::Chef).await?;
+    }
+    tx.commit().await?;
+
+    if promote {
+        redis.invalidate_user_auth(applicant.id).await?;
+        tracing::info!(user_id = %applicant.id, role = %Role::Chef, "User role changed");
+        AuditEvent::new("user_role_changed")
+            .by(actor)
+            .on("user", applicant.id)
+            .before(serde_json::json!({ "role": applicant.role }))
+            .after(serde_json::json!({ "role": Role::Chef }))
+            .detail("application_id", application.id)
+            .from(meta)
+            .record(pool)
+            .await;
+    }
+
+    AuditEvent::new("chef_application_approved")
+        .by(actor)
+        .on("chef_application", application.id)
+        .before(serde_json::json!({ "status": "pending" }))
+        .after(serde_json::json!({ "status": application.status }))
+        .detail("applicant_id", applicant.id)
+        .from(meta)
+        .record(pool)
+        .await;
+    notify_applicant(
+        mailer,
+        &applicant,
+        "Your PrivateChefSpace chef application was approved",
+        format!(
+            "Welcome aboard! Your application to cook on PrivateChefSpace was approved.\n\nSign in again and set up your chef profile:\n{}/chef-dashboard\n",
+            config.mail.app_url.trim_end_matches('/')
+        ),
+    )
+    .await;
+    Ok(application)
+}
+
+pub async fn reject_application(
+    pool: &DbPool,
+    mailer: &dyn Mailer,
+    actor: &Identity,
+    meta: &RequestMeta,
+    application_id: Uuid,
+    reason: &str,
+) -> Result<ChefApplication, AppError> {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::ValidationError("Please give the applicant a reason".to_string()));
+    }
+    if reason.chars().count() > MAX_TEXT_LENGTH {
+        return Err(AppError::ValidationError(format!("Reasons can be at most {} characters", MAX_TEXT_LENGTH)));
+    }
+    let (application, applicant) = reviewable_application(pool, actor, application_id).await?;
+    let application =
+        close_application(&mut *pool.acquire().await?, actor, application.id, "rejected", Some(reason)).await?;
+
+    AuditEvent::new("chef_application_rejected")
+        .by(actor)
+        .on("chef_application", application.id)
+        .before(serde_json::json!({ "status": "pending" }))
+        .after(serde_json::json!({ "status": application.status, "rejection_reason": reason }))
+        .detail("applicant_id", applicant.id)
+        .from(meta)
+        .record(pool)
+        .await;
+    notify_applicant(
+        mailer,
+        &applicant,
+        "Your PrivateChefSpace chef application",
+        format!(
+            "Thank you for applying to cook on PrivateChefSpace. We couldn't approve your application this time:\n\n{}\n\nYou're welcome to apply again.\n",
+            reason
+        ),
+    )
+    .await;
+    Ok(application)
+}
+
+/// The decision has been made either way, so a failed email is only logged
+async fn notify_applicant(mailer: &dyn Mailer, applicant: &User, subject: &str, body: String) {
+    let email = Email { to: applicant.email.clone(), subject: subject.to_string(), body };
+    if let Err(e) = mailer.send(email).await {
+        tracing::error!(user_id = %applicant.id, error = ?e, "Failed to send chef application email");
+    }
+}
+
+/// A document's file, for staff reviewing the application
+pub async fn download_document(
+    pool: &DbPool,
+    blob_store: &dyn BlobStore,
+    actor: &Identity,
+    application_id: Uuid,
+    document_id: Uuid,
+) -> Result<HttpResponse, AppError> {
+    require_reviewer(actor)?;
+    let document = sqlx::query_as::<_, ChefApplicationDocument>(
+        "SELECT * FROM chef_application_documents WHERE id = $1 AND application_id = $2"
+    )
+    .bind(document_id)
+    .bind(application_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Document not found".to_string()))?;
+    let data = blob_store.get(&document.storage_key).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(document.content_type.as_str())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(document.file_name)],
+        })
+        .insert_header(("Cache-Control", "private, no-store"))
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .body(data))
+}
+
+/// Apply to become a chef. Expects a multipart body with `experience` and
+/// a `food_safety_certificate` and `insurance` file.
+///
+/// # Returns
+/// * `HttpResponse::Created` with the application and its documents
+/// * `AppError::Forbidden` unless the user is a diner applying for themselves,
+///   not an admin impersonating them
+/// * `AppError::ValidationError` if a field is missing or invalid, or an application is already pending
+pub async fn apply_to_be_chef(
+    _: NotImpersonating,
+    identity: Identity,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    blob_store: web::Data<dyn BlobStore>,
+    payload: Multipart,
+) -> Result<HttpResponse, AppError> {
+    if identity.role != Role::Diner {
+        return Err(AppError::Forbidden("Only diners can apply to become chefs".to_string()));
+    }
+    let form = read_application_form(payload, config.storage.max_upload_bytes).await?;
+    let application = submit_application(&pool, blob_store.get_ref(), &meta, identity.id, form).await?;
+    Ok(HttpResponse::Created().json(application))
+}
+
+/// The signed-in user's latest application
+pub async fn get_my_application(user: AuthUser, pool: web::Data<DbPool>) -> Result<HttpResponse, AppError> {
+    let application = latest_application(&pool, user.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("You haven't applied to become a chef".to_string()))?;
+    Ok(HttpResponse::Ok().json(application_detail(&pool, application.id).await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApplicationFilter {
+    pub status: Option<String>,
+}
+
+pub async fn list_chef_applications(
+    staff: RequireRole<role::Staff>,
+    pool: web::Data<DbPool>,
+    filter: web::Query<ApplicationFilter>,
+) -> Result<HttpResponse, AppError> {
+    require_reviewer(&staff)?;
+    let status = filter.status.as_deref().unwrap_or("pending");
+    if !APPLICATION_STATUSES.contains(&status) {
+        return Err(AppError::ValidationError(format!("Invalid status: {}", status)));
+    }
+    Ok(HttpResponse::Ok().json(list_applications(&pool, status).await?))
+}
+
+pub async fn get_chef_application(
+    staff: RequireRole<role::Staff>,
+    pool: web::Data<DbPool>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    require_reviewer(&staff)?;
+    Ok(HttpResponse::Ok().json(application_detail(&pool, path.into_inner()).await?))
+}
+
+pub async fn approve_chef_application(
+    staff: RequireRole<role::Staff>,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    mailer: web::Data<dyn Mailer>,
+    config: web::Data<Config>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let application =
+        approve_application(&pool, &redis, mailer.get_ref(), &config, &staff, &meta, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(application))
+}
+
+pub async fn reject_chef_application(
+    staff: RequireRole<role::Staff>,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    mailer: web::Data<dyn Mailer>,
+    path: web::Path<Uuid>,
+    body: web::Json<RejectApplication>,
+) -> Result<HttpResponse, AppError> {
+    let application =
+        reject_application(&pool, mailer.get_ref(), &staff, &meta, path.into_inner(), &body.reason).await?;
+    Ok(HttpResponse::Ok().json(application))
+}
+
+pub async fn get_chef_application_document(
+    staff: RequireRole<role::Staff>,
+    pool: web::Data<DbPool>,
+    blob_store: web::Data<dyn BlobStore>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (application_id, document_id) = path.into_inner();
+    download_document(&pool, blob_store.get_ref(), &staff, application_id, document_id).await
+}
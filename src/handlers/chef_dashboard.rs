@@ -0,0 +1,446 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use askama::Template;
+use uuid::Uuid;
+
+use crate::audit::{AuditEvent, RequestMeta};
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::menu_item::verify_choice_group_in_menu;
+use crate::middleware::auth::{AuthUser, RequireRole};
+use crate::middleware::flash::{redirect_with_flash, FlashMessage};
+use crate::middleware::roles::{role, Permission};
+use crate::models::{Chef, Menu, MenuChoiceGroup, MenuItem, Role, UpdateChef, UpdateMenu, UpdateMenuItem, UserResponse};
+use crate::policy::membership;
+use crate::templates::{ChefProfileEditTemplate, ConfirmDeleteTemplate, MenuEditTemplate, MenuItemEditTemplate, PageContext};
+
+const DASHBOARD: &str = "/chef-dashboard";
+
+type Form = web::Form<std::collections::HashMap<String, String>>;
+
+/// Serve the chef profile edit form
+pub async fn edit_chef_profile_page(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, AppError> {
+    let (user, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnChefProfile).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+
+    render(ChefProfileEditTemplate {
+        ctx: PageContext::new(&req, Some(user)),
+        chef,
+    })
+}
+
+/// Handle the chef profile edit form. Every `UpdateChef` field is on the
+/// form, so the whole profile is written; emptied fields are cleared.
+pub async fn handle_update_chef_profile(
+    identity: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnChefProfile).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let edit_path = "/chef-dashboard/profile/edit";
+
+    let data = match UpdateChef::from_form(&form) {
+        Ok(data) => data,
+        Err(message) => return Ok(redirect_with_flash(&req, edit_path, FlashMessage::error(message))),
+    };
+
+    // The slug stays as it is so shared profile links keep working
+    let updated = sqlx::query_as::<_, Chef>(
+        r#"
+        UPDATE chefs
+        SET business_name = $1, chef_name = $2, bio = $3, cuisine_types = $4,
+            location = $5, phone = $6, email = $7, website = $8,
+            profile_image_url = $9, cover_image_url = $10, hourly_rate = $11,
+            minimum_hours = $12, travel_radius = $13, is_active = ($14 AND deactivated_at IS NULL), updated_at = NOW()
+        WHERE id = $15
+        RETURNING *
+        "#
+    )
+    .bind(&data.business_name)
+    .bind(&data.chef_name)
+    .bind(&data.bio)
+    .bind(&data.cuisine_types)
+    .bind(&data.location)
+    .bind(&data.phone)
+    .bind(&data.email)
+    .bind(&data.website)
+    .bind(&data.profile_image_url)
+    .bind(&data.cover_image_url)
+    .bind(data.hourly_rate)
+    .bind(data.minimum_hours)
+    .bind(data.travel_radius)
+    .bind(data.is_active)
+    .bind(chef.id)
+    .fetch_one(pool.get_ref())
+    .await?;
+    AuditEvent::new("chef_profile_updated")
+        .by(&identity)
+        .on("chef", chef.id)
+        .changes(&chef, &updated)
+        .from(&meta)
+        .record(&pool)
+        .await;
+
+    tracing::info!(chef_id = %chef.id, "Chef profile updated from dashboard");
+    Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::success("Profile updated")))
+}
+
+/// Serve the menu edit form
+pub async fn edit_menu_page(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    menu_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let (user, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnMenus).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let Some(menu) = find_menu(&pool, &chef, *menu_id).await? else {
+        return Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::error("Menu not found")));
+    };
+
+    render(MenuEditTemplate {
+        ctx: PageContext::new(&req, Some(user)),
+        menu,
+    })
+}
+
+/// Handle the menu edit form
+pub async fn handle_update_menu(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    menu_id: web::Path<Uuid>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnMenus).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let Some(menu) = find_menu(&pool, &chef, *menu_id).await? else {
+        return Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::error("Menu not found")));
+    };
+    let edit_path = format!("/chef-dashboard/menus/{}/edit", menu.id);
+
+    let data = match UpdateMenu::from_form(&form) {
+        Ok(data) => data,
+        Err(message) => return Ok(redirect_with_flash(&req, &edit_path, FlashMessage::error(message))),
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE menus
+        SET name = $1, description = $2, price_per_person = $3, minimum_guests = $4,
+            cuisine_type = $5, dietary_options = $6, duration_hours = $7, is_active = $8,
+            updated_at = NOW()
+        WHERE id = $9
+        "#
+    )
+    .bind(&data.name)
+    .bind(&data.description)
+    .bind(data.price_per_person)
+    .bind(data.minimum_guests)
+    .bind(&data.cuisine_type)
+    .bind(&data.dietary_options)
+    .bind(data.duration_hours)
+    .bind(data.is_active)
+    .bind(menu.id)
+    .execute(pool.get_ref())
+    .await?;
+
+    tracing::info!(menu_id = %menu.id, chef_id = %chef.id, "Menu updated from dashboard");
+    Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::success("Menu updated")))
+}
+
+/// Ask for confirmation before deleting a menu
+pub async fn delete_menu_page(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    menu_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let (user, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnMenus).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let Some(menu) = find_menu(&pool, &chef, *menu_id).await? else {
+        return Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::error("Menu not found")));
+    };
+
+    let (item_count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM menu_items WHERE menu_id = $1")
+        .bind(menu.id)
+        .fetch_one(pool.get_ref())
+        .await?;
+    let (booking_count,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM bookings WHERE menu_id = $1 AND status IN ('pending', 'confirmed')"
+    )
+    .bind(menu.id)
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    let mut message = format!(
+        "\"{}\" and its {} dish(es), choice groups and add-ons will be deleted. This can't be undone.",
+        menu.name, item_count
+    );
+    if booking_count > 0 {
+        message.push_str(&format!(
+            " {} open booking(s) use this menu; they keep their details but will no longer link to it.",
+            booking_count
+        ));
+    }
+
+    render(ConfirmDeleteTemplate {
+        ctx: PageContext::new(&req, Some(user)),
+        title: "Delete menu?".to_string(),
+        message,
+        action: format!("/chef-dashboard/menus/{}/delete", menu.id),
+        cancel_path: format!("/chef-dashboard/menus/{}/edit", menu.id),
+    })
+}
+
+/// Delete a menu after confirmation
+pub async fn handle_delete_menu(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    menu_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnMenus).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let Some(menu) = find_menu(&pool, &chef, *menu_id).await? else {
+        return Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::error("Menu not found")));
+    };
+
+    sqlx::query("DELETE FROM menus WHERE id = $1")
+        .bind(menu.id)
+        .execute(pool.get_ref())
+        .await?;
+
+    tracing::info!(menu_id = %menu.id, chef_id = %chef.id, "Menu deleted from dashboard");
+    Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::success(format!("Menu \"{}\" deleted", menu.name))))
+}
+
+/// Serve the menu item edit form
+pub async fn edit_menu_item_page(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (menu_id, item_id) = path.into_inner();
+    let (user, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnMenus).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let Some((menu, item)) = find_menu_item(&pool, &chef, menu_id, item_id).await? else {
+        return Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::error("Dish not found")));
+    };
+
+    let choice_groups = sqlx::query_as::<_, MenuChoiceGroup>(
+        "SELECT * FROM menu_choice_groups WHERE menu_id = $1 ORDER BY display_order ASC, created_at ASC"
+    )
+    .bind(menu.id)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    render(MenuItemEditTemplate {
+        ctx: PageContext::new(&req, Some(user)),
+        menu,
+        item,
+        choice_groups,
+    })
+}
+
+/// Handle the menu item edit form
+pub async fn handle_update_menu_item(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let (menu_id, item_id) = path.into_inner();
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnMenus).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let Some((menu, item)) = find_menu_item(&pool, &chef, menu_id, item_id).await? else {
+        return Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::error("Dish not found")));
+    };
+    let edit_path = format!("/chef-dashboard/menus/{}/items/{}/edit", menu.id, item.id);
+
+    let data = match UpdateMenuItem::from_form(&form) {
+        Ok(data) => data,
+        Err(message) => return Ok(redirect_with_flash(&req, &edit_path, FlashMessage::error(message))),
+    };
+    if let Some(group_id) = data.choice_group_id {
+        match verify_choice_group_in_menu(&pool, menu.id, group_id).await {
+            Ok(()) => {}
+            Err(AppError::ValidationError(message)) => return Ok(redirect_with_flash(&req, &edit_path, FlashMessage::error(message))),
+            Err(e) => return Err(e),
+        }
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE menu_items
+        SET name = $1, description = $2, course_type = $3, image_url = $4,
+            is_featured = $5, display_order = $6, quantity = $7, choice_group_id = $8,
+            price_adjustment = $9, updated_at = NOW()
+        WHERE id = $10 AND menu_id = $11
+        "#
+    )
+    .bind(&data.name)
+    .bind(&data.description)
+    .bind(&data.course_type)
+    .bind(&data.image_url)
+    .bind(data.is_featured)
+    .bind(data.display_order)
+    .bind(data.quantity)
+    .bind(data.choice_group_id)
+    .bind(data.price_adjustment)
+    .bind(item.id)
+    .bind(menu.id)
+    .execute(pool.get_ref())
+    .await?;
+
+    tracing::info!(item_id = %item.id, menu_id = %menu.id, "Menu item updated from dashboard");
+    Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::success("Dish updated")))
+}
+
+/// Ask for confirmation before deleting a menu item
+pub async fn delete_menu_item_page(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (menu_id, item_id) = path.into_inner();
+    let (user, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnMenus).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let Some((menu, item)) = find_menu_item(&pool, &chef, menu_id, item_id).await? else {
+        return Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::error("Dish not found")));
+    };
+
+    render(ConfirmDeleteTemplate {
+        ctx: PageContext::new(&req, Some(user)),
+        title: "Delete dish?".to_string(),
+        message: format!("\"{}\" will be removed from {}. This can't be undone.", item.name, menu.name),
+        action: format!("/chef-dashboard/menus/{}/items/{}/delete", menu.id, item.id),
+        cancel_path: format!("/chef-dashboard/menus/{}/items/{}/edit", menu.id, item.id),
+    })
+}
+
+/// Delete a menu item after confirmation
+pub async fn handle_delete_menu_item(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (menu_id, item_id) = path.into_inner();
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnMenus).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let Some((menu, item)) = find_menu_item(&pool, &chef, menu_id, item_id).await? else {
+        return Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::error("Dish not found")));
+    };
+
+    sqlx::query("DELETE FROM menu_items WHERE id = $1 AND menu_id = $2")
+        .bind(item.id)
+        .bind(menu.id)
+        .execute(pool.get_ref())
+        .await?;
+
+    tracing::info!(item_id = %item.id, menu_id = %menu.id, "Menu item deleted from dashboard");
+    Ok(redirect_with_flash(&req, DASHBOARD, FlashMessage::success(format!("\"{}\" deleted", item.name))))
+}
+
+/// The logged-in user's team's chef profile, if their team role allows
+/// `permission`. The inner error redirects to the dashboard when they
+/// aren't on a team yet or their role doesn't allow it.
+pub(crate) async fn current_chef(
+    req: &HttpRequest,
+    pool: &web::Data<DbPool>,
+    user: AuthUser,
+    permission: Permission,
+) -> Result<Result<(UserResponse, Chef), HttpResponse>, AppError> {
+    let user = UserResponse::from(user);
+    let member = match membership(pool, user.id).await? {
+        Some(member) => member,
+        None => {
+            return Ok(Err(redirect_with_flash(req, DASHBOARD, FlashMessage::error("Please create a chef profile first"))))
+        }
+    };
+    if user.role != Role::Admin && !member.role.allows(permission) {
+        return Ok(Err(redirect_with_flash(
+            req,
+            DASHBOARD,
+            FlashMessage::error("Your team role doesn't allow that"),
+        )));
+    }
+
+    let chef = sqlx::query_as::<_, Chef>("SELECT * FROM chefs WHERE id = $1")
+        .bind(member.chef_id)
+        .fetch_one(pool.get_ref())
+        .await?;
+    Ok(Ok((user, chef)))
+}
+
+async fn find_menu(pool: &web::Data<DbPool>, chef: &Chef, menu_id: Uuid) -> Result<Option<Menu>, AppError> {
+    let menu = sqlx::query_as::<_, Menu>("SELECT * FROM menus WHERE id = $1 AND chef_id = $2")
+        .bind(menu_id)
+        .bind(chef.id)
+        .fetch_optional(pool.get_ref())
+        .await?;
+    Ok(menu)
+}
+
+async fn find_menu_item(
+    pool: &web::Data<DbPool>,
+    chef: &Chef,
+    menu_id: Uuid,
+    item_id: Uuid,
+) -> Result<Option<(Menu, MenuItem)>, AppError> {
+    let Some(menu) = find_menu(pool, chef, menu_id).await? else {
+        return Ok(None);
+    };
+    let item = sqlx::query_as::<_, MenuItem>("SELECT * FROM menu_items WHERE id = $1 AND menu_id = $2")
+        .bind(item_id)
+        .bind(menu.id)
+        .fetch_optional(pool.get_ref())
+        .await?;
+    Ok(item.map(|item| (menu, item)))
+}
+
+fn render(template: impl Template) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok()
+        .content_type("text/html")
+        .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
+}
@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use askama::Template;
+use chrono::{Duration, NaiveDate, NaiveTime, Utc};
+use uuid::Uuid;
+
+use crate::audit::RequestMeta;
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::booking::{load_availability, place_booking};
+use crate::handlers::menu::load_public_menus;
+use crate::handlers::web::query_params;
+use crate::middleware::auth::AuthUser;
+use crate::middleware::flash::{redirect_with_flash, FlashMessage};
+use crate::models::{
+    BookingAvailability, Chef, ChefPublicProfile, CreateBooking, Menu, PublicMenu, RatingSummary, Review,
+    UserResponse,
+};
+use crate::templates::{BookingForm, ChefMenuTemplate, ChefProfileTemplate, PageContext, PageMeta};
+
+/// How far ahead the booking form offers time slots
+const BOOKING_WINDOW_DAYS: i64 = 21;
+const REVIEWS_SHOWN: i64 = 20;
+/// Search engines cut descriptions at roughly this length
+const META_DESCRIPTION_LENGTH: usize = 160;
+
+/// Serve the public chef profile page
+pub async fn chef_profile_page(
+    user: Option<AuthUser>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    slug: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let user = user.map(UserResponse::from);
+    let chef = find_active_chef(&pool, &slug).await?;
+
+    let menus = sqlx::query_as::<_, Menu>(
+        "SELECT * FROM menus WHERE chef_id = $1 AND is_active = true AND hidden_at IS NULL ORDER BY created_at ASC"
+    )
+    .bind(chef.id)
+    .fetch_all(pool.get_ref())
+    .await?;
+    let menus = load_public_menus(&pool, menus).await?;
+
+    let reviews = sqlx::query_as::<_, Review>(
+        "SELECT * FROM reviews WHERE chef_id = $1 AND hidden_at IS NULL ORDER BY created_at DESC LIMIT $2"
+    )
+    .bind(chef.id)
+    .bind(REVIEWS_SHOWN)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let (count, average): (i64, Option<f64>) = sqlx::query_as(
+        "SELECT COUNT(*), ROUND(AVG(rating), 1)::float8 FROM reviews WHERE chef_id = $1 AND hidden_at IS NULL"
+    )
+    .bind(chef.id)
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    let availability = upcoming_availability(&pool, chef.id).await?;
+    let query = query_params(&req);
+    let selected_menu_id = query.get("menu").and_then(|id| id.parse::<Uuid>().ok());
+    let selected = menus.iter().find(|m| Some(m.id) == selected_menu_id);
+
+    let slug = slug.into_inner();
+    let booking_form = booking_form(&slug, format!("/chef/{}", slug), &menus, selected, user.as_ref());
+
+    let image = chef.cover_image_url.as_ref().or(chef.profile_image_url.as_ref());
+    let meta = PageMeta {
+        title: format!("{} - Private Chef | PrivateChefSpace", chef.chef_name),
+        description: meta_description(chef.bio.as_deref(), || {
+            let cuisines = chef.cuisine_types.as_ref().map(|c| c.join(", ")).unwrap_or_default();
+            format!("Book {} as your private chef. {}", chef.chef_name, cuisines)
+        }),
+        url: absolute_url(&config, &format!("/chef/{}", slug)),
+        image: image.map(|url| absolute_url(&config, url)),
+        og_type: "profile",
+    };
+
+    let template = ChefProfileTemplate {
+        ctx: PageContext::new(&req, user),
+        meta,
+        chef: ChefPublicProfile::from(chef),
+        menus,
+        reviews,
+        rating: RatingSummary { average, count: count as usize },
+        availability,
+        booking_form,
+    };
+    Ok(HttpResponse::Ok()
+        .content_type("text/html")
+        .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
+}
+
+/// Serve a public menu page with its courses and a booking form for it
+pub async fn chef_menu_page(
+    user: Option<AuthUser>,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    path: web::Path<(String, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (slug, menu_id) = path.into_inner();
+    let user = user.map(UserResponse::from);
+    let chef = find_active_chef(&pool, &slug).await?;
+
+    let menu = sqlx::query_as::<_, Menu>(
+        "SELECT * FROM menus WHERE id = $1 AND chef_id = $2 AND is_active = true AND hidden_at IS NULL"
+    )
+    .bind(menu_id)
+    .bind(chef.id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Menu not found".to_string()))?;
+
+    let menu = load_public_menus(&pool, vec![menu])
+        .await?
+        .pop()
+        .ok_or_else(|| AppError::NotFound("Menu not found".to_string()))?;
+
+    let availability = upcoming_availability(&pool, chef.id).await?;
+    let page_path = format!("/chef/{}/menus/{}", slug, menu.id);
+    let booking_form = booking_form(
+        &slug,
+        page_path.clone(),
+        std::slice::from_ref(&menu),
+        Some(&menu),
+        user.as_ref(),
+    );
+
+    let image = menu
+        .courses
+        .iter()
+        .flat_map(|c| c.items.iter())
+        .find_map(|item| item.image_url.clone())
+        .or_else(|| chef.cover_image_url.clone());
+    let meta = PageMeta {
+        title: format!("{} by {} | PrivateChefSpace", menu.name, chef.chef_name),
+        description: meta_description(menu.description.as_deref(), || {
+            format!("{} - a private dining menu by {}", menu.name, chef.chef_name)
+        }),
+        url: absolute_url(&config, &page_path),
+        image: image.map(|url| absolute_url(&config, &url)),
+        og_type: "website",
+    };
+
+    let template = ChefMenuTemplate {
+        ctx: PageContext::new(&req, user),
+        meta,
+        chef: ChefPublicProfile::from(chef),
+        menu,
+        availability,
+        booking_form,
+    };
+    Ok(HttpResponse::Ok()
+        .content_type("text/html")
+        .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
+}
+
+/// Handle the public booking form
+///
+/// The form is turned into a `CreateBooking` and goes through the same
+/// validation, pricing and conflict checks as `POST /api/chefs/{chef_id}/bookings`.
+/// Logged-in diners get the booking linked to their account.
+pub async fn handle_chef_booking(
+    user: Option<AuthUser>,
+    req: HttpRequest,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    slug: web::Path<String>,
+    form: web::Form<HashMap<String, String>>,
+) -> Result<HttpResponse, AppError> {
+    let chef = find_active_chef(&pool, &slug).await?;
+    let user = user.map(UserResponse::from);
+
+    // Only redirect back to this chef's own pages
+    let chef_path = format!("/chef/{}", slug);
+    let return_path = form
+        .get("return_path")
+        .filter(|p| *p == &chef_path || p.starts_with(&format!("{}/menus/", chef_path)))
+        .cloned()
+        .unwrap_or(chef_path);
+
+    let booking = match booking_from_form(&form) {
+        Ok(booking) => booking,
+        Err(message) => return Ok(redirect_with_flash(&req, &return_path, FlashMessage::error(message))),
+    };
+
+    // The slot must still be open; place_booking re-checks for conflicts
+    let open = load_availability(&pool, chef.id, booking.event_date, booking.event_date).await?;
+    let slot = booking.event_time.format("%H:%M").to_string();
+    if booking.event_date <= Utc::now().date_naive() || !open.iter().any(|d| d.available_times.contains(&slot)) {
+        return Ok(redirect_with_flash(&req, &return_path, FlashMessage::error("That time slot is no longer available")));
+    }
+
+    match place_booking(&pool, &meta, chef.id, user.as_ref(), &booking).await {
+        Ok(created) => {
+            tracing::info!(
+                booking_id = %created.id,
+                chef_id = %chef.id,
+                "Booking requested from public chef page"
+            );
+            let message = format!(
+                "Booking request sent! {} will confirm your {} event. Total: ${:.2}",
+                chef.chef_name,
+                created.event_date.format("%B %-d"),
+                created.total_price
+            );
+            Ok(redirect_with_flash(&req, &return_path, FlashMessage::success(message)))
+        }
+        Err(AppError::ValidationError(message)) | Err(AppError::NotFound(message)) => {
+            Ok(redirect_with_flash(&req, &return_path, FlashMessage::error(message)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create booking from chef page: {:?}", e);
+            Ok(redirect_with_flash(&req, &return_path, FlashMessage::error("Failed to create booking. Please try again.")))
+        }
+    }
+}
+
+/// The chef behind a public page slug, or NotFound if there isn't an active one
+pub(crate) async fn find_active_chef(pool: &web::Data<DbPool>, slug: &str) -> Result<Chef, AppError> {
+    sqlx::query_as::<_, Chef>(
+        "SELECT * FROM chefs WHERE slug = $1 AND is_active = true"
+    )
+    .bind(slug)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Chef profile not found".to_string()))
+}
+
+/// Days with at least one open slot, starting tomorrow
+async fn upcoming_availability(
+    pool: &web::Data<DbPool>,
+    chef_id: Uuid,
+) -> Result<Vec<BookingAvailability>, AppError> {
+    let start = Utc::now().date_naive() + Duration::days(1);
+    let end = start + Duration::days(BOOKING_WINDOW_DAYS - 1);
+    let days = load_availability(pool, chef_id, start, end).await?;
+    Ok(days.into_iter().filter(|d| d.available).collect())
+}
+
+fn booking_form(
+    slug: &str,
+    return_path: String,
+    menus: &[PublicMenu],
+    selected: Option<&PublicMenu>,
+    user: Option<&UserResponse>,
+) -> BookingForm {
+    BookingForm {
+        action: format!("/chef/{}/book", slug),
+        return_path,
+        menus: menus.iter().map(|m| (m.id, m.name.clone())).collect(),
+        selected_menu_id: selected.map(|m| m.id),
+        number_of_guests: selected.map(|m| m.minimum_guests).unwrap_or(2).max(1),
+        duration_hours: selected.and_then(|m| m.duration_hours).unwrap_or(3.0),
+        customer_email: user.map(|u| u.email.clone()).unwrap_or_default(),
+    }
+}
+
+/// Parse the booking form; `slot` is "YYYY-MM-DD HH:MM" from the availability picker
+fn booking_from_form(form: &HashMap<String, String>) -> Result<CreateBooking, String> {
+    let field = |name: &str| {
+        form.get(name)
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+
+    let slot = field("slot").ok_or("Please pick a date and time")?;
+    let (date, time) = slot.split_once(' ').ok_or("Please pick a date and time")?;
+    let event_date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| "Invalid date")?;
+    let event_time = NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| "Invalid time")?;
+
+    let number_of_guests = field("number_of_guests")
+        .and_then(|v| v.parse::<i32>().ok())
+        .ok_or("Number of guests is required")?;
+    let duration_hours = field("duration_hours")
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(3.0);
+    let menu_id = match field("menu_id") {
+        Some(id) => Some(id.parse::<Uuid>().map_err(|_| "Invalid menu")?),
+        None => None,
+    };
+
+    Ok(CreateBooking {
+        menu_id,
+        customer_name: field("customer_name").ok_or("Your name is required")?,
+        customer_email: field("customer_email").ok_or("Your email is required")?,
+        customer_phone: field("customer_phone"),
+        event_date,
+        event_time,
+        duration_hours,
+        number_of_guests,
+        location_address: field("location_address").ok_or("The event address is required")?,
+        special_requests: field("special_requests"),
+        guest_selections: Vec::new(),
+        add_ons: Vec::new(),
+    })
+}
+
+/// Absolute URL for canonical links and Open Graph tags, which don't accept
+/// relative paths. Built from `APP_URL` rather than the request's Host header,
+/// which the client controls.
+fn absolute_url(config: &Config, path: &str) -> String {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        return path.to_string();
+    }
+    format!("{}{}", config.mail.app_url.trim_end_matches('/'), path)
+}
+
+/// First sentences of `text` cut to a search-result-sized description
+fn meta_description(text: Option<&str>, fallback: impl FnOnce() -> String) -> String {
+    let text = text
+        .map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(fallback);
+
+    if text.chars().count() <= META_DESCRIPTION_LENGTH {
+        return text;
+    }
+    let cut: String = text.chars().take(META_DESCRIPTION_LENGTH - 1).collect();
+    match cut.rfind(' ') {
+        Some(end) => format!("{}…", &cut[..end]),
+        None => format!("{}…", cut),
+    }
+}
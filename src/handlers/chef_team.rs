@@ -0,0 +1,502 @@
+use actix_web::{web, HttpResponse};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::audit::{AuditEvent, RequestMeta};
+use crate::cache::RedisClient;
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::auth::set_user_role;
+use crate::mail::{Email, Mailer};
+use crate::middleware::auth::{AuthUser, Identity, NotImpersonating, RequireRole};
+use crate::middleware::roles::{role, Permission};
+use crate::models::{
+    AcceptInvitation, ChangeTeamRole, ChefInvitation, InviteTeamMember, Role, TeamMember, TeamRole, User,
+    UserResponse,
+};
+use crate::policy::{acting_chef, membership, ChefScope};
+use crate::utils::{generate_secret_token, hash_token};
+
+/// How long an invitation link works
+pub const INVITATION_DAYS: i64 = 7;
+
+/// The members of a chef's team, owner first
+pub async fn team_members(pool: &DbPool, chef_id: Uuid) -> Result<Vec<TeamMember>, AppError> {
+    let members = sqlx::query_as::<_, TeamMember>(
+        r#"
+        SELECT m.chef_id, m.user_id, u.email, m.role, m.created_at
+        FROM chef_members m
+        INNER JOIN users u ON u.id = m.user_id
+        WHERE m.chef_id = $1
+        ORDER BY (m.role = 'owner') DESC, m.created_at ASC
+        "#
+    )
+    .bind(chef_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(members)
+}
+
+/// Invitations to a chef's team that haven't been accepted, newest first.
+/// Expired ones are included so the owner can see to send them again.
+pub async fn open_invitations(pool: &DbPool, chef_id: Uuid) -> Result<Vec<ChefInvitation>, AppError> {
+    let invitations = sqlx::query_as::<_, ChefInvitation>(
+        "SELECT * FROM chef_invitations WHERE chef_id = $1 AND accepted_at IS NULL ORDER BY created_at DESC"
+    )
+    .bind(chef_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(invitations)
+}
+
+/// Email the invitee a link to join the chef's team. Inviting the same
+/// address again replaces the earlier invitation.
+pub async fn invite_member(
+    pool: &DbPool,
+    mailer: &dyn Mailer,
+    config: &Config,
+    actor: &Identity,
+    meta: &RequestMeta,
+    chef_id: Uuid,
+    invite: &InviteTeamMember,
+) -> Result<ChefInvitation, AppError> {
+    let role = invite.role;
+    if role == TeamRole::Owner {
+        return Err(AppError::ValidationError(
+            "A team has one owner. Invite people as a manager or staff.".to_string(),
+        ));
+    }
+    let email = invite.email.trim().to_lowercase();
+    if email.is_empty() || !email.contains('@') {
+        return Err(AppError::ValidationError("Please enter a valid email address".to_string()));
+    }
+
+    let already_member: Option<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT m.user_id FROM chef_members m
+        INNER JOIN users u ON u.id = m.user_id
+        WHERE m.chef_id = $1 AND LOWER(u.email) = $2
+        "#
+    )
+    .bind(chef_id)
+    .bind(&email)
+    .fetch_optional(pool)
+    .await?;
+    if already_member.is_some() {
+        return Err(AppError::ValidationError(format!("{} is already on the team", email)));
+    }
+
+    let token = generate_secret_token();
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM chef_invitations WHERE chef_id = $1 AND email = $2 AND accepted_at IS NULL")
+        .bind(chef_id)
+        .bind(&email)
+        .execute(&mut *tx)
+        .await?;
+    let invitation = sqlx::query_as::<_, ChefInvitation>(
+        r#"
+        INSERT INTO chef_invitations (chef_id, email, role, token_hash, invited_by, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#
+    )
+    .bind(chef_id)
+    .bind(&email)
+    .bind(role.to_string())
+    .bind(hash_token(&token))
+    .bind(actor.id)
+    .bind(Utc::now() + Duration::days(INVITATION_DAYS))
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let (chef_name,): (String,) = sqlx::query_as("SELECT chef_name FROM chefs WHERE id = $1")
+        .bind(chef_id)
+        .fetch_one(pool)
+        .await?;
+    let link = format!("{}/team/join?token={}", config.mail.app_url.trim_end_matches('/'), token);
+    mailer
+        .send(Email {
+            to: email.clone(),
+            subject: format!("Join {} on PrivateChefSpace", chef_name),
+            body: format!(
+                "You've been invited to join {} on PrivateChefSpace as {}.\n\nSign in or create an account with this email address, then accept the invitation here:\n{}\n\nThe link expires in {} days.\n",
+                chef_name,
+                role.label().to_lowercase(),
+                link,
+                INVITATION_DAYS
+            ),
+        })
+        .await?;
+
+    AuditEvent::new("team_member_invited")
+        .by(actor)
+        .on("chef", chef_id)
+        .detail("invitation_id", invitation.id)
+        .detail("email", &email)
+        .detail("role", role)
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(invitation)
+}
+
+/// Withdraw an invitation that hasn't been accepted
+pub async fn revoke_invitation(
+    pool: &DbPool,
+    actor: &Identity,
+    meta: &RequestMeta,
+    chef_id: Uuid,
+    invitation_id: Uuid,
+) -> Result<ChefInvitation, AppError> {
+    let invitation = sqlx::query_as::<_, ChefInvitation>(
+        "DELETE FROM chef_invitations WHERE id = $1 AND chef_id = $2 AND accepted_at IS NULL RETURNING *"
+    )
+    .bind(invitation_id)
+    .bind(chef_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Invitation not found".to_string()))?;
+
+    AuditEvent::new("team_invitation_revoked")
+        .by(actor)
+        .on("chef", chef_id)
+        .detail("invitation_id", invitation.id)
+        .detail("email", &invitation.email)
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(invitation)
+}
+
+/// An open, unexpired invitation and the name of the chef it's for
+pub async fn find_invitation(pool: &DbPool, token: &str) -> Result<Option<(ChefInvitation, String)>, AppError> {
+    let invitation = sqlx::query_as::<_, ChefInvitation>(
+        "SELECT * FROM chef_invitations WHERE token_hash = $1 AND accepted_at IS NULL AND expires_at > NOW()"
+    )
+    .bind(hash_token(token.trim()))
+    .fetch_optional(pool)
+    .await?;
+    let invitation = match invitation {
+        Some(invitation) => invitation,
+        None => return Ok(None),
+    };
+    let (chef_name,): (String,) = sqlx::query_as("SELECT chef_name FROM chefs WHERE id = $1")
+        .bind(invitation.chef_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(Some((invitation, chef_name)))
+}
+
+/// Put `user` on the team an invitation is for. Diners become chefs, which
+/// signs them out; the returned user carries their new role.
+pub async fn accept_invitation(
+    pool: &DbPool,
+    redis: &RedisClient,
+    user: &User,
+    meta: &RequestMeta,
+    token: &str,
+) -> Result<(ChefInvitation, User), AppError> {
+    let invalid = || AppError::ValidationError("This invitation is invalid or has expired".to_string());
+    let (invitation, _) = find_invitation(pool, token).await?.ok_or_else(invalid)?;
+    let role = invitation.team_role().ok_or_else(invalid)?;
+
+    if !invitation.email.eq_ignore_ascii_case(user.email.trim()) {
+        return Err(AppError::Forbidden(format!(
+            "This invitation was sent to {}. Sign in with that address to accept it.",
+            invitation.email
+        )));
+    }
+    if !matches!(user.role, Role::Chef | Role::Diner) {
+        return Err(AppError::Forbidden("Staff accounts can't join a chef team".to_string()));
+    }
+    if membership(pool, user.id).await?.is_some() {
+        return Err(AppError::ValidationError(
+            "You're already on a chef team. Leave it before joining another.".to_string(),
+        ));
+    }
+
+    let grants_chef_role = user.role == Role::Diner;
+    let mut tx = pool.begin().await?;
+    let invitation = sqlx::query_as::<_, ChefInvitation>(
+        r#"
+        UPDATE chef_invitations
+        SET accepted_at = NOW(), accepted_by = $2
+        WHERE id = $1 AND accepted_at IS NULL AND expires_at > NOW()
+        RETURNING *
+        "#
+    )
+    .bind(invitation.id)
+    .bind(user.id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(invalid)?;
+    sqlx::query("INSERT INTO chef_members (chef_id, user_id, role, granted_chef_role) VALUES ($1, $2, $3, $4)")
+        .bind(invitation.chef_id)
+        .bind(user.id)
+        .bind(role.to_string())
+        .bind(grants_chef_role)
+        .execute(&mut *tx)
+        .await?;
+    // In the same transaction, so a diner is never left on the team without
+    // the chef role
+    let updated = if grants_chef_role {
+        Some(set_user_role(&mut tx, user.id, Role::Chef).await?)
+    } else {
+        None
+    };
+    tx.commit().await?;
+
+    let user = if let Some(updated) = updated {
+        redis.invalidate_user_auth(user.id).await?;
+        tracing::info!(user_id = %user.id, role = %Role::Chef, "User role changed");
+        AuditEvent::new("user_role_changed")
+            .by_user(user.id, user.role)
+            .on("user", user.id)
+            .before(serde_json::json!({ "role": user.role }))
+            .after(serde_json::json!({ "role": Role::Chef }))
+            .detail("chef_id", invitation.chef_id)
+            .from(meta)
+            .record(pool)
+            .await;
+        updated
+    } else {
+        user.clone()
+    };
+
+    tracing::info!(user_id = %user.id, chef_id = %invitation.chef_id, role = %role, "Joined chef team");
+    AuditEvent::new("team_member_joined")
+        .by_user(user.id, user.role)
+        .on("chef", invitation.chef_id)
+        .detail("invitation_id", invitation.id)
+        .detail("role", role)
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok((invitation, user))
+}
+
+/// A member of the chef's team other than its owner
+async fn non_owner_member(pool: &DbPool, chef_id: Uuid, user_id: Uuid) -> Result<TeamRole, AppError> {
+    let member: Option<(String,)> = sqlx::query_as("SELECT role FROM chef_members WHERE chef_id = $1 AND user_id = $2")
+        .bind(chef_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    let role = member
+        .and_then(|(role,)| role.parse::<TeamRole>().ok())
+        .ok_or_else(|| AppError::NotFound("Team member not found".to_string()))?;
+    if role == TeamRole::Owner {
+        return Err(AppError::Forbidden("The team owner's membership can't be changed".to_string()));
+    }
+    Ok(role)
+}
+
+/// Make a manager staff or the other way round
+pub async fn change_member_role(
+    pool: &DbPool,
+    actor: &Identity,
+    meta: &RequestMeta,
+    chef_id: Uuid,
+    user_id: Uuid,
+    role: TeamRole,
+) -> Result<(), AppError> {
+    if role == TeamRole::Owner {
+        return Err(AppError::ValidationError("A team has one owner".to_string()));
+    }
+    let before = non_owner_member(pool, chef_id, user_id).await?;
+    if before == role {
+        return Ok(());
+    }
+
+    sqlx::query("UPDATE chef_members SET role = $3, updated_at = NOW() WHERE chef_id = $1 AND user_id = $2")
+        .bind(chef_id)
+        .bind(user_id)
+        .bind(role.to_string())
+        .execute(pool)
+        .await?;
+    AuditEvent::new("team_member_role_changed")
+        .by(actor)
+        .on("chef", chef_id)
+        .before(serde_json::json!({ "role": before }))
+        .after(serde_json::json!({ "role": role }))
+        .detail("user_id", user_id)
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(())
+}
+
+/// Take `user_id` off the chef's team, whether the owner removed them or
+/// they left. Members who became chefs by joining go back to being diners.
+pub async fn remove_member(
+    pool: &DbPool,
+    redis: &RedisClient,
+    actor: &Identity,
+    meta: &RequestMeta,
+    chef_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), AppError> {
+    let role = non_owner_member(pool, chef_id, user_id).await?;
+    let mut tx = pool.begin().await?;
+    let (granted_chef_role,): (bool,) = sqlx::query_as(
+        "DELETE FROM chef_members WHERE chef_id = $1 AND user_id = $2 RETURNING granted_chef_role"
+    )
+    .bind(chef_id)
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    let demoted = if granted_chef_role {
+        let (current,): (Role,) = sqlx::query_as("SELECT role FROM users WHERE id = $1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        // Staff may have made them a chef in their own right since
+        current == Role::Chef
+    } else {
+        false
+    };
+    if demoted {
+        set_user_role(&mut tx, user_id, Role::Diner).await?;
+    }
+    tx.commit().await?;
+
+    if demoted {
+        redis.invalidate_user_auth(user_id).await?;
+        tracing::info!(user_id = %user_id, role = %Role::Diner, "User role changed");
+        AuditEvent::new("user_role_changed")
+            .by(actor)
+            .on("user", user_id)
+            .before(serde_json::json!({ "role": Role::Chef }))
+            .after(serde_json::json!({ "role": Role::Diner }))
+            .detail("chef_id", chef_id)
+            .from(meta)
+            .record(pool)
+            .await;
+    }
+
+    let action = if actor.id == user_id { "team_member_left" } else { "team_member_removed" };
+    tracing::info!(user_id = %user_id, chef_id = %chef_id, action = action, "Chef team member removed");
+    AuditEvent::new(action)
+        .by(actor)
+        .on("chef", chef_id)
+        .detail("user_id", user_id)
+        .detail("role", role)
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(())
+}
+
+/// Members and open invitations of the signed-in owner's team, or of
+/// `?chef_id=` (admins)
+pub async fn get_team(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    scope: web::Query<ChefScope>,
+) -> Result<HttpResponse, AppError> {
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnTeam, scope.chef_id).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "chef_id": chef_id,
+        "members": team_members(&pool, chef_id).await?,
+        "invitations": open_invitations(&pool, chef_id).await?
+    })))
+}
+
+pub async fn invite_team_member(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    mailer: web::Data<dyn Mailer>,
+    config: web::Data<Config>,
+    meta: RequestMeta,
+    scope: web::Query<ChefScope>,
+    data: web::Json<InviteTeamMember>,
+) -> Result<HttpResponse, AppError> {
+    // Who joins the team is the owner's call, not support's
+    user.forbid_impersonation()?;
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnTeam, scope.chef_id).await?;
+    let invitation =
+        invite_member(&pool, mailer.get_ref(), &config, &user, &meta, chef_id, &data).await?;
+    Ok(HttpResponse::Created().json(invitation))
+}
+
+pub async fn revoke_team_invitation(
+    _: NotImpersonating,
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    meta: RequestMeta,
+    scope: web::Query<ChefScope>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnTeam, scope.chef_id).await?;
+    revoke_invitation(&pool, &user, &meta, chef_id, path.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn change_team_member_role(
+    _: NotImpersonating,
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    meta: RequestMeta,
+    scope: web::Query<ChefScope>,
+    path: web::Path<Uuid>,
+    data: web::Json<ChangeTeamRole>,
+) -> Result<HttpResponse, AppError> {
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnTeam, scope.chef_id).await?;
+    change_member_role(&pool, &user, &meta, chef_id, path.into_inner(), data.role).await?;
+    Ok(HttpResponse::Ok().json(team_members(&pool, chef_id).await?))
+}
+
+pub async fn remove_team_member(
+    _: NotImpersonating,
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    meta: RequestMeta,
+    scope: web::Query<ChefScope>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnTeam, scope.chef_id).await?;
+    remove_member(&pool, &redis, &user, &meta, chef_id, path.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Leave the team the signed-in user is on. Owners can't leave their own.
+pub async fn leave_team(
+    _: NotImpersonating,
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    meta: RequestMeta,
+) -> Result<HttpResponse, AppError> {
+    let member = membership(&pool, user.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("You aren't on a chef team".to_string()))?;
+    remove_member(&pool, &redis, &user, &meta, member.chef_id, user.id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Accept an emailed invitation. Any signed-in chef or diner may call it
+/// with the token from their link.
+pub async fn accept_team_invitation(
+    _: NotImpersonating,
+    user: AuthUser,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    meta: RequestMeta,
+    data: web::Json<AcceptInvitation>,
+) -> Result<HttpResponse, AppError> {
+    let (invitation, updated) = accept_invitation(&pool, &redis, &user, &meta, &data.token).await?;
+    let role_changed = updated.role != user.role;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "chef_id": invitation.chef_id,
+        "role": invitation.role,
+        "user": UserResponse::from(updated),
+        "message": if role_changed {
+            "You've joined the team. Please log in again to continue as a chef."
+        } else {
+            "You've joined the team."
+        }
+    })))
+}
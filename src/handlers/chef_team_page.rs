@@ -0,0 +1,231 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use askama::Template;
+use uuid::Uuid;
+
+use crate::audit::RequestMeta;
+use crate::cache::RedisClient;
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::chef_dashboard::current_chef;
+use crate::handlers::chef_team::{
+    accept_invitation, change_member_role, find_invitation, invite_member, open_invitations, remove_member,
+    revoke_invitation, team_members, INVITATION_DAYS,
+};
+use crate::handlers::web::{create_user_session, query_params, session_cookie, SESSION_TTL_SECONDS};
+use crate::mail::Mailer;
+use crate::middleware::auth::{AuthUser, NotImpersonating, RequireRole};
+use crate::middleware::flash::{redirect_with_flash, FlashMessage};
+use crate::middleware::roles::{role, Permission};
+use crate::models::{InviteTeamMember, TeamRole, UserResponse};
+use crate::policy::membership;
+use crate::templates::{ChefTeamTemplate, PageContext, TeamInvitationView, TeamJoinTemplate};
+
+const TEAM_PAGE: &str = "/chef-dashboard/team";
+
+type Form = web::Form<std::collections::HashMap<String, String>>;
+
+fn render(template: impl Template) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok()
+        .content_type("text/html")
+        .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
+}
+
+/// Flash the outcome of a team action and go back to `path`
+fn finish(req: &HttpRequest, path: &str, result: Result<String, AppError>) -> Result<HttpResponse, AppError> {
+    match result {
+        Ok(message) => Ok(redirect_with_flash(req, path, FlashMessage::success(message))),
+        Err(AppError::Forbidden(message) | AppError::NotFound(message) | AppError::ValidationError(message)) => {
+            Ok(redirect_with_flash(req, path, FlashMessage::error(message)))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn team_role(form: &Form) -> Result<TeamRole, AppError> {
+    form.get("role")
+        .and_then(|role| role.parse().ok())
+        .ok_or_else(|| AppError::ValidationError("Please choose a role".to_string()))
+}
+
+/// The team's members and invitations, for its owner
+pub async fn chef_team_page(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, AppError> {
+    let (user, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnTeam).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+
+    render(ChefTeamTemplate {
+        ctx: PageContext::new(&req, Some(user)),
+        members: team_members(&pool, chef.id).await?,
+        invitations: open_invitations(&pool, chef.id).await?,
+        chef,
+        roles: TeamRole::INVITABLE,
+        invitation_days: INVITATION_DAYS,
+    })
+}
+
+pub async fn handle_invite_team_member(
+    identity: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    mailer: web::Data<dyn Mailer>,
+    config: web::Data<Config>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnTeam).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let meta = RequestMeta::from_request(&req);
+
+    let result = match identity.forbid_impersonation().and_then(|()| team_role(&form)) {
+        Ok(role) => {
+            let invite = InviteTeamMember { email: form.get("email").cloned().unwrap_or_default(), role };
+            invite_member(&pool, mailer.get_ref(), &config, &identity, &meta, chef.id, &invite)
+                .await
+                .map(|invitation| format!("Invitation sent to {}", invitation.email))
+        }
+        Err(e) => Err(e),
+    };
+    finish(&req, TEAM_PAGE, result)
+}
+
+pub async fn handle_revoke_team_invitation(
+    _: NotImpersonating,
+    identity: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnTeam).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let result = revoke_invitation(&pool, &identity, &meta, chef.id, path.into_inner())
+        .await
+        .map(|invitation| format!("Invitation to {} revoked", invitation.email));
+    finish(&req, TEAM_PAGE, result)
+}
+
+pub async fn handle_change_team_role(
+    _: NotImpersonating,
+    identity: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    path: web::Path<Uuid>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnTeam).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let meta = RequestMeta::from_request(&req);
+    let result = match team_role(&form) {
+        Ok(role) => change_member_role(&pool, &identity, &meta, chef.id, path.into_inner(), role)
+            .await
+            .map(|()| format!("Role changed to {}", role.label().to_lowercase())),
+        Err(e) => Err(e),
+    };
+    finish(&req, TEAM_PAGE, result)
+}
+
+pub async fn handle_remove_team_member(
+    _: NotImpersonating,
+    identity: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnTeam).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let meta = RequestMeta::from_request(&req);
+    let result = remove_member(&pool, &redis, &identity, &meta, chef.id, path.into_inner())
+        .await
+        .map(|()| "Removed from the team".to_string());
+    finish(&req, TEAM_PAGE, result)
+}
+
+/// Leave the team from the chef dashboard. Members who became chefs by
+/// joining are signed out as they go back to being diners.
+pub async fn handle_leave_team(
+    _: NotImpersonating,
+    identity: RequireRole<role::Chef>,
+    req: HttpRequest,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+) -> Result<HttpResponse, AppError> {
+    let result = match membership(&pool, identity.id).await? {
+        Some(member) => remove_member(&pool, &redis, &identity, &meta, member.chef_id, identity.id)
+            .await
+            .map(|()| "You've left the team".to_string()),
+        None => Err(AppError::NotFound("You aren't on a chef team".to_string())),
+    };
+    finish(&req, "/dashboard", result)
+}
+
+/// Where the link in an invitation email lands. Anyone can open it; only
+/// signed-in users get the button to accept.
+pub async fn team_join_page(
+    req: HttpRequest,
+    user: Option<AuthUser>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, AppError> {
+    let token = query_params(&req).remove("token").unwrap_or_default();
+    let invitation = if token.is_empty() { None } else { find_invitation(&pool, &token).await? };
+
+    render(TeamJoinTemplate {
+        ctx: PageContext::new(&req, user.map(UserResponse::from)),
+        invitation: invitation.map(|(invitation, chef_name)| TeamInvitationView {
+            chef_name,
+            role_label: invitation.role_label().to_string(),
+            email: invitation.email,
+        }),
+        token,
+    })
+}
+
+/// Accept an invitation. Diners who become chefs by joining get a new
+/// session, as the role change signs out their old one.
+pub async fn handle_team_join(
+    _: NotImpersonating,
+    user: AuthUser,
+    req: HttpRequest,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let token = form.get("token").map(String::as_str).unwrap_or_default();
+    let (_, updated) = match accept_invitation(&pool, &redis, &user, &meta, token).await {
+        Ok(accepted) => accepted,
+        Err(AppError::Forbidden(message) | AppError::ValidationError(message)) => {
+            return Ok(redirect_with_flash(&req, "/dashboard", FlashMessage::error(message)))
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut response =
+        redirect_with_flash(&req, "/chef-dashboard", FlashMessage::success("Welcome to the team!"));
+    if updated.role != user.role {
+        let session_id = create_user_session(&redis, &updated).await?;
+        response
+            .add_cookie(&session_cookie(&session_id, SESSION_TTL_SECONDS))
+            .map_err(|e| AppError::InternalError(format!("Failed to set session cookie: {}", e)))?;
+    }
+    Ok(response)
+}
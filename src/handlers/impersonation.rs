@@ -0,0 +1,210 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::audit::{AuditEvent, RequestMeta};
+use crate::cache::session::SessionData;
+use crate::cache::RedisClient;
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::web::{session_cookie, SESSION_TTL_SECONDS};
+use crate::middleware::auth::{Identity, RequireRole};
+use crate::middleware::csrf::generate_csrf_token;
+use crate::middleware::flash::{redirect_with_flash, FlashMessage};
+use crate::middleware::roles::role;
+use crate::models::{Impersonation, Role, StartImpersonation, User, UserResponse};
+use crate::utils::Claims;
+
+/// How long an impersonation lasts before the admin has to start another
+pub const IMPERSONATION_MINUTES: i64 = 30;
+
+/// Longest reason kept with an impersonation
+const MAX_REASON_LENGTH: usize = 500;
+
+/// Whether `actor` may sign in as `target`. Only admins can, and only as
+/// chefs and diners whose accounts are active: impersonating staff would
+/// hand out their privileges.
+pub fn can_impersonate(actor: &Identity, target: &User) -> bool {
+    actor.role == Role::Admin
+        && actor.impersonation.is_none()
+        && actor.id != target.id
+        && matches!(target.role, Role::Chef | Role::Diner)
+        && target.suspended_at.is_none()
+}
+
+/// Checks that `admin` may impersonate `user_id` and records that they
+/// started to. `via` says whether it's for a web session or an API token.
+pub async fn start_impersonation(
+    pool: &DbPool,
+    admin: &Identity,
+    meta: &RequestMeta,
+    user_id: Uuid,
+    reason: &str,
+    via: &str,
+) -> Result<(User, Impersonation), AppError> {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::ValidationError("Please say why you need to impersonate this user".to_string()));
+    }
+    if reason.chars().count() > MAX_REASON_LENGTH {
+        return Err(AppError::ValidationError(format!(
+            "The reason can be at most {} characters",
+            MAX_REASON_LENGTH
+        )));
+    }
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    if !can_impersonate(admin, &user) {
+        return Err(AppError::Forbidden(
+            "Only active chef and diner accounts other than your own can be impersonated".to_string(),
+        ));
+    }
+
+    let (admin_token_version,): (i32,) = sqlx::query_as("SELECT token_version FROM users WHERE id = $1")
+        .bind(admin.id)
+        .fetch_one(pool)
+        .await?;
+    let impersonation = Impersonation {
+        admin_id: admin.id,
+        admin_token_version,
+        expires_at: Utc::now() + Duration::minutes(IMPERSONATION_MINUTES),
+    };
+
+    tracing::info!(admin_id = %admin.id, user_id = %user.id, via = via, "Impersonation started");
+    AuditEvent::new("impersonation_started")
+        .by(admin)
+        .on("user", user.id)
+        .detail("reason", reason)
+        .detail("via", via)
+        .detail("expires_at", impersonation.expires_at)
+        .from(meta)
+        .record(pool)
+        .await;
+
+    Ok((user, impersonation))
+}
+
+/// Issue an access token for acting as a user through the API. It expires
+/// with the impersonation and can't be refreshed.
+pub async fn impersonate_user(
+    admin: RequireRole<role::Admin>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    meta: RequestMeta,
+    path: web::Path<Uuid>,
+    body: web::Json<StartImpersonation>,
+) -> Result<HttpResponse, AppError> {
+    let (user, impersonation) =
+        start_impersonation(&pool, &admin, &meta, path.into_inner(), &body.reason, "token").await?;
+    let token = Claims::impersonation(&user, impersonation).encode(&config.jwt.secret)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "user": UserResponse::from(user),
+        "token": token,
+        "expires_at": impersonation.expires_at
+    })))
+}
+
+/// Replace the admin's session with one for `user`, keeping the admin's
+/// session to return to, and send them to the user's dashboard
+pub(crate) async fn begin_impersonation_session(
+    req: &HttpRequest,
+    redis: &RedisClient,
+    user: &User,
+    impersonation: Impersonation,
+) -> Result<HttpResponse, AppError> {
+    let session_id = Uuid::new_v4().to_string();
+    let session = SessionData {
+        user_id: user.id,
+        email: user.email.clone(),
+        role: user.role.to_string(),
+        csrf_token: generate_csrf_token(),
+        token_version: user.token_version,
+        impersonation: Some(impersonation),
+        admin_session_id: req.cookie("session_id").map(|cookie| cookie.value().to_string()),
+    };
+    let ttl = IMPERSONATION_MINUTES * 60;
+    redis.create_session(&session_id, &session, ttl as usize).await?;
+
+    let dashboard = match user.role {
+        Role::Chef => "/chef-dashboard",
+        _ => "/dashboard",
+    };
+    let mut response = redirect_with_flash(
+        req,
+        dashboard,
+        FlashMessage::info(format!(
+            "You're signed in as {} until {}",
+            user.email,
+            impersonation.expires_at_display()
+        )),
+    );
+    response
+        .add_cookie(&session_cookie(&session_id, ttl))
+        .map_err(|e| AppError::InternalError(format!("Failed to set session cookie: {}", e)))?;
+    Ok(response)
+}
+
+/// End the impersonation `session` belongs to and go back to the admin's
+/// own session if it's still valid, or to the login page if not
+pub(crate) async fn end_impersonation_session(
+    req: &HttpRequest,
+    pool: &DbPool,
+    redis: &RedisClient,
+    meta: &RequestMeta,
+    session_id: &str,
+    session: &SessionData,
+) -> Result<HttpResponse, AppError> {
+    redis.destroy_session(session_id).await?;
+
+    if let Some(impersonation) = session.impersonation {
+        tracing::info!(admin_id = %impersonation.admin_id, user_id = %session.user_id, "Impersonation ended");
+        AuditEvent::new("impersonation_ended")
+            .by_user(impersonation.admin_id, Role::Admin)
+            .on("user", session.user_id)
+            .detail("via", "session")
+            .from(meta)
+            .record(pool)
+            .await;
+    }
+
+    let admin_session = match &session.admin_session_id {
+        Some(id) => redis.get_session_data(id).await?.map(|data| (id.clone(), data)),
+        None => None,
+    };
+    let (location, cookie) = match admin_session {
+        Some((id, data)) if data.impersonation.is_none() && Some(data.user_id) == session.impersonation.map(|i| i.admin_id) => {
+            ("/admin/users", session_cookie(&id, SESSION_TTL_SECONDS))
+        }
+        _ => ("/login", session_cookie("", 0)),
+    };
+    let mut response = redirect_with_flash(req, location, FlashMessage::success("Impersonation ended"));
+    response
+        .add_cookie(&cookie)
+        .map_err(|e| AppError::InternalError(format!("Failed to set session cookie: {}", e)))?;
+    Ok(response)
+}
+
+/// "Stop impersonating" from the banner
+pub async fn handle_stop_impersonating(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    meta: RequestMeta,
+) -> Result<HttpResponse, AppError> {
+    let session_id = match req.cookie("session_id") {
+        Some(cookie) => cookie.value().to_string(),
+        None => return Ok(HttpResponse::SeeOther().append_header(("Location", "/login")).finish()),
+    };
+    match redis.get_session_data(&session_id).await? {
+        Some(session) if session.impersonation.is_some() => {
+            end_impersonation_session(&req, &pool, &redis, &meta, &session_id, &session).await
+        }
+        _ => Ok(HttpResponse::SeeOther().append_header(("Location", "/dashboard")).finish()),
+    }
+}
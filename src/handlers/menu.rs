@@ -1,30 +1,25 @@
+use actix_web::http::header;
 use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 use crate::db::DbPool;
-use crate::models::{Menu, CreateMenu, UpdateMenu};
+use crate::models::{Menu, CreateMenu, UpdateMenu, DuplicateMenu, MenuAddOn, MenuChoiceGroup, MenuItem, PublicMenu};
 use crate::errors::AppError;
-use crate::middleware::auth::extract_user_id;
+use crate::middleware::auth::RequireRole;
+use crate::middleware::roles::{role, Permission};
+use crate::policy::{acting_chef, authorize, ChefScope, Resource};
 use actix_web::HttpRequest;
 
+/// Creates a menu for the signed-in chef, or for `?chef_id=` (admins).
 pub async fn create_menu(
-    req: HttpRequest,
+    user: RequireRole<role::Chef>,
     pool: web::Data<DbPool>,
+    scope: web::Query<ChefScope>,
     data: web::Json<CreateMenu>,
 ) -> Result<HttpResponse, AppError> {
-    let user_id = extract_user_id(&req)?;
-
-    // Verify user is a chef
-    let chef: Option<(uuid::Uuid,)> = sqlx::query_as(
-        "SELECT id FROM chefs WHERE user_id = $1"
-    )
-    .bind(user_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
-    
-    let chef_id = chef
-        .map(|(id,)| id)
-        .ok_or_else(|| AppError::Unauthorized("User is not a chef".to_string()))?;
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnMenus, scope.chef_id).await?;
 
     let menu = sqlx::query_as::<_, Menu>(
         r#"
@@ -39,11 +34,11 @@ pub async fn create_menu(
     .bind(chef_id)
     .bind(&data.name)
     .bind(&data.description)
-    .bind(&data.price_per_person)
+    .bind(data.price_per_person)
     .bind(data.minimum_guests.unwrap_or(2))
     .bind(&data.cuisine_type)
     .bind(&data.dietary_options)
-    .bind(&data.duration_hours)
+    .bind(data.duration_hours)
     .bind(true)
     .fetch_one(pool.get_ref())
     .await?;
@@ -51,22 +46,13 @@ pub async fn create_menu(
     Ok(HttpResponse::Created().json(menu))
 }
 
+/// Lists the signed-in chef's menus, or those of `?chef_id=` (admins).
 pub async fn get_chef_menus(
-    req: HttpRequest,
+    user: RequireRole<role::Chef>,
     pool: web::Data<DbPool>,
+    scope: web::Query<ChefScope>,
 ) -> Result<HttpResponse, AppError> {
-    let user_id = extract_user_id(&req)?;
-
-    let chef: Option<(uuid::Uuid,)> = sqlx::query_as(
-        "SELECT id FROM chefs WHERE user_id = $1"
-    )
-    .bind(user_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
-    
-    let chef_id = chef
-        .map(|(id,)| id)
-        .ok_or_else(|| AppError::Unauthorized("User is not a chef".to_string()))?;
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnMenus, scope.chef_id).await?;
 
     let menus = sqlx::query_as::<_, Menu>(
         "SELECT * FROM menus WHERE chef_id = $1 ORDER BY created_at DESC"
@@ -79,27 +65,12 @@ pub async fn get_chef_menus(
 }
 
 pub async fn update_menu(
-    req: HttpRequest,
+    user: RequireRole<role::Chef>,
     pool: web::Data<DbPool>,
     menu_id: web::Path<Uuid>,
     data: web::Json<UpdateMenu>,
 ) -> Result<HttpResponse, AppError> {
-    let user_id = extract_user_id(&req)?;
-
-    // Verify ownership
-    let chef: Option<(uuid::Uuid,)> = sqlx::query_as(
-        "SELECT c.id FROM chefs c
-         INNER JOIN menus m ON m.chef_id = c.id
-         WHERE c.user_id = $1 AND m.id = $2"
-    )
-    .bind(user_id)
-    .bind(*menu_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
-    
-    if chef.is_none() {
-        return Err(AppError::Unauthorized("Not authorized to update this menu".to_string()));
-    }
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(*menu_id)).await?;
 
     // Build update query dynamically
     let mut updates = Vec::new();
@@ -117,8 +88,8 @@ pub async fn update_menu(
     )
     .bind(&data.name)
     .bind(&data.description)
-    .bind(&data.price_per_person)
-    .bind(&data.is_active)
+    .bind(data.price_per_person)
+    .bind(data.is_active)
     .bind(*menu_id)
     .fetch_optional(pool.get_ref())
     .await?
@@ -128,26 +99,11 @@ pub async fn update_menu(
 }
 
 pub async fn delete_menu(
-    req: HttpRequest,
+    user: RequireRole<role::Chef>,
     pool: web::Data<DbPool>,
     menu_id: web::Path<Uuid>,
 ) -> Result<HttpResponse, AppError> {
-    let user_id = extract_user_id(&req)?;
-
-    // Verify ownership
-    let chef: Option<(uuid::Uuid,)> = sqlx::query_as(
-        "SELECT c.id FROM chefs c
-         INNER JOIN menus m ON m.chef_id = c.id
-         WHERE c.user_id = $1 AND m.id = $2"
-    )
-    .bind(user_id)
-    .bind(*menu_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
-    
-    if chef.is_none() {
-        return Err(AppError::Unauthorized("Not authorized to delete this menu".to_string()));
-    }
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(*menu_id)).await?;
 
     sqlx::query("DELETE FROM menus WHERE id = $1")
         .bind(*menu_id)
@@ -157,3 +113,294 @@ pub async fn delete_menu(
     Ok(HttpResponse::NoContent().finish())
 }
 
+
+/// Copies a menu with all its items, choice groups and add-ons.
+/// The copy starts inactive so the chef can edit it before publishing.
+pub async fn duplicate_menu(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    menu_id: web::Path<Uuid>,
+    data: Option<web::Json<DuplicateMenu>>,
+) -> Result<HttpResponse, AppError> {
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(*menu_id)).await?;
+    let original = sqlx::query_as::<_, Menu>("SELECT * FROM menus WHERE id = $1")
+        .bind(*menu_id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("Menu not found".to_string()))?;
+
+    let name = data
+        .and_then(|d| d.into_inner().name)
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| format!("{} (copy)", original.name));
+
+    let mut tx = pool.begin().await?;
+
+    let menu = sqlx::query_as::<_, Menu>(
+        r#"
+        INSERT INTO menus (
+            chef_id, name, description, price_per_person, minimum_guests,
+            cuisine_type, dietary_options, duration_hours, is_active, created_at, updated_at
+        )
+        SELECT chef_id, $2, description, price_per_person, minimum_guests,
+               cuisine_type, dietary_options, duration_hours, false, NOW(), NOW()
+        FROM menus WHERE id = $1
+        RETURNING *
+        "#
+    )
+    .bind(original.id)
+    .bind(&name)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Copy choice groups first so items can point at their new group
+    let groups = sqlx::query_as::<_, MenuChoiceGroup>(
+        "SELECT * FROM menu_choice_groups WHERE menu_id = $1"
+    )
+    .bind(original.id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut group_ids = std::collections::HashMap::new();
+    for group in groups {
+        let (new_id,): (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO menu_choice_groups (
+                menu_id, name, description, course_type, min_choices, max_choices,
+                display_order, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), NOW())
+            RETURNING id
+            "#
+        )
+        .bind(menu.id)
+        .bind(&group.name)
+        .bind(&group.description)
+        .bind(&group.course_type)
+        .bind(group.min_choices)
+        .bind(group.max_choices)
+        .bind(group.display_order)
+        .fetch_one(&mut *tx)
+        .await?;
+        group_ids.insert(group.id, new_id);
+    }
+
+    let items = sqlx::query_as::<_, MenuItem>(
+        "SELECT * FROM menu_items WHERE menu_id = $1 ORDER BY display_order ASC, created_at ASC"
+    )
+    .bind(original.id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut copied_items = Vec::with_capacity(items.len());
+    for item in items {
+        let copied = sqlx::query_as::<_, MenuItem>(
+            r#"
+            INSERT INTO menu_items (
+                menu_id, name, description, course_type, image_url,
+                is_featured, display_order, quantity, choice_group_id, price_adjustment,
+                created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, NOW(), NOW())
+            RETURNING *
+            "#
+        )
+        .bind(menu.id)
+        .bind(&item.name)
+        .bind(&item.description)
+        .bind(&item.course_type)
+        .bind(&item.image_url)
+        .bind(item.is_featured)
+        .bind(item.display_order)
+        .bind(item.quantity)
+        .bind(item.choice_group_id.and_then(|id| group_ids.get(&id).copied()))
+        .bind(item.price_adjustment)
+        .fetch_one(&mut *tx)
+        .await?;
+        copied_items.push(copied);
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO menu_add_ons (
+            menu_id, name, description, price, pricing_unit, is_active,
+            display_order, created_at, updated_at
+        )
+        SELECT $2, name, description, price, pricing_unit, is_active,
+               display_order, NOW(), NOW()
+        FROM menu_add_ons WHERE menu_id = $1
+        "#
+    )
+    .bind(original.id)
+    .bind(menu.id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    tracing::info!(
+        original_menu_id = %original.id,
+        menu_id = %menu.id,
+        item_count = copied_items.len(),
+        "Menu duplicated successfully"
+    );
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "menu": menu,
+        "items": copied_items
+    })))
+}
+
+/// How long browsers and CDNs may reuse public menu responses
+const PUBLIC_MENU_MAX_AGE: u32 = 300;
+
+/// Lists a chef's active menus for diners, items grouped by course.
+///
+/// Public endpoint: inactive menus and menus of inactive chefs are hidden.
+///
+/// # Returns
+/// * `HttpResponse::Ok` with a list of PublicMenu, or `NotModified` when the
+///   client's `If-None-Match` still matches
+/// * `AppError::NotFound` if no active chef has this slug
+pub async fn get_public_chef_menus(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    slug: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let chef: Option<(Uuid,)> = sqlx::query_as(
+        "SELECT id FROM chefs WHERE slug = $1 AND is_active = true"
+    )
+    .bind(slug.as_str())
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    let chef_id = chef
+        .map(|(id,)| id)
+        .ok_or_else(|| AppError::NotFound("Chef profile not found".to_string()))?;
+
+    let menus = sqlx::query_as::<_, Menu>(
+        "SELECT * FROM menus WHERE chef_id = $1 AND is_active = true AND hidden_at IS NULL ORDER BY created_at ASC"
+    )
+    .bind(chef_id)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let menus = load_public_menus(&pool, menus).await?;
+    cached_json(&req, &menus)
+}
+
+/// Returns a single active menu for diners, items grouped by course.
+///
+/// # Returns
+/// * `HttpResponse::Ok` with the PublicMenu, or `NotModified` when the
+///   client's `If-None-Match` still matches
+/// * `AppError::NotFound` if the menu doesn't exist or isn't bookable
+pub async fn get_public_menu(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    menu_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let menu = sqlx::query_as::<_, Menu>(
+        r#"
+        SELECT m.* FROM menus m
+        INNER JOIN chefs c ON c.id = m.chef_id
+        WHERE m.id = $1 AND m.is_active = true AND m.hidden_at IS NULL AND c.is_active = true
+        "#
+    )
+    .bind(*menu_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Menu not found".to_string()))?;
+
+    let menu = load_public_menus(&pool, vec![menu])
+        .await?
+        .pop()
+        .ok_or_else(|| AppError::NotFound("Menu not found".to_string()))?;
+    cached_json(&req, &menu)
+}
+
+/// Loads items, choice groups and active add-ons for `menus` in three
+/// queries and assembles the public view of each.
+pub(crate) async fn load_public_menus(
+    pool: &web::Data<DbPool>,
+    menus: Vec<Menu>,
+) -> Result<Vec<PublicMenu>, AppError> {
+    if menus.is_empty() {
+        return Ok(Vec::new());
+    }
+    let menu_ids: Vec<Uuid> = menus.iter().map(|m| m.id).collect();
+
+    let items = sqlx::query_as::<_, MenuItem>(
+        "SELECT * FROM menu_items WHERE menu_id = ANY($1) ORDER BY display_order ASC, created_at ASC"
+    )
+    .bind(&menu_ids)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let groups = sqlx::query_as::<_, MenuChoiceGroup>(
+        "SELECT * FROM menu_choice_groups WHERE menu_id = ANY($1) ORDER BY display_order ASC, created_at ASC"
+    )
+    .bind(&menu_ids)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let add_ons = sqlx::query_as::<_, MenuAddOn>(
+        "SELECT * FROM menu_add_ons WHERE menu_id = ANY($1) AND is_active = true
+         ORDER BY display_order ASC, created_at ASC"
+    )
+    .bind(&menu_ids)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    Ok(menus
+        .into_iter()
+        .map(|menu| {
+            let id = menu.id;
+            PublicMenu::build(
+                menu,
+                items.iter().filter(|i| i.menu_id == id).cloned().collect(),
+                groups.iter().filter(|g| g.menu_id == id).cloned().collect(),
+                add_ons.iter().filter(|a| a.menu_id == id).cloned().collect(),
+            )
+        })
+        .collect())
+}
+
+/// JSON response with `Cache-Control` and `ETag` headers.
+/// The ETag hashes the body, so removing a menu or an item changes it too.
+/// There is no `Last-Modified`: no timestamp moves when something is deleted.
+fn cached_json<T: Serialize>(
+    req: &HttpRequest,
+    body: &T,
+) -> Result<HttpResponse, AppError> {
+    let json = serde_json::to_vec(body)
+        .map_err(|e| AppError::InternalError(format!("Failed to serialize response: {}", e)))?;
+
+    // SHA-256 rather than std's hasher, whose output may change between
+    // Rust releases and would invalidate every cached copy on upgrade
+    let digest: String = Sha256::digest(&json).iter().map(|byte| format!("{:02x}", byte)).collect();
+    let etag = format!("W/\"{}\"", digest);
+    let cache_control = format!("public, max-age={}", PUBLIC_MENU_MAX_AGE);
+
+    let not_modified = req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"));
+
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response
+        .insert_header((header::CACHE_CONTROL, cache_control))
+        .insert_header((header::ETAG, etag));
+
+    if not_modified {
+        Ok(response.finish())
+    } else {
+        Ok(response.content_type("application/json").body(json))
+    }
+}
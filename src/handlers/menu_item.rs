@@ -2,10 +2,11 @@ use actix_web::{web, HttpResponse};
 use uuid::Uuid;
 
 use crate::db::DbPool;
-use crate::models::{MenuItem, CreateMenuItem, UpdateMenuItem};
+use crate::models::{MenuItem, CreateMenuItem, UpdateMenuItem, ReorderMenuItems, BulkMenuItemChanges};
 use crate::errors::AppError;
-use crate::middleware::auth::extract_user_id;
-use actix_web::HttpRequest;
+use crate::middleware::auth::RequireRole;
+use crate::middleware::roles::{role, Permission};
+use crate::policy::{authorize, Resource};
 
 /// Creates a new menu item for a specific menu.
 /// 
@@ -13,21 +14,21 @@ use actix_web::HttpRequest;
 /// Only the chef who owns the menu or an admin can create menu items.
 /// 
 /// # Arguments
-/// * `req` - HTTP request with bearer token
+/// * `user` - The authenticated chef
 /// * `pool` - Database connection pool
 /// * `menu_id` - UUID of the menu to add item to
 /// * `data` - CreateMenuItem struct with item details
 /// 
 /// # Returns
 /// * `HttpResponse::Created` with the created MenuItem on success
-/// * `AppError::Unauthorized` if user doesn't own the menu
+/// * `AppError::Forbidden` if user doesn't own the menu
 pub async fn create_menu_item(
-    req: HttpRequest,
+    user: RequireRole<role::Chef>,
     pool: web::Data<DbPool>,
     menu_id: web::Path<Uuid>,
     data: web::Json<CreateMenuItem>,
 ) -> Result<HttpResponse, AppError> {
-    let user_id = extract_user_id(&req)?;
+    let user_id = user.id;
     let menu_id = *menu_id;
 
     tracing::debug!(
@@ -37,38 +38,25 @@ pub async fn create_menu_item(
         "Attempting to create menu item"
     );
 
-    // Verify ownership of the menu
-    let _chef: Option<(uuid::Uuid,)> = sqlx::query_as(
-        "SELECT c.id FROM chefs c
-         INNER JOIN menus m ON m.chef_id = c.id
-         WHERE c.user_id = $1 AND m.id = $2"
-    )
-    .bind(user_id)
-    .bind(menu_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
-    
-    if _chef.is_none() {
-        tracing::warn!(
-            user_id = %user_id,
-            menu_id = %menu_id,
-            "User attempted to create menu item for unauthorized menu"
-        );
-        return Err(AppError::Unauthorized("Not authorized".to_string()));
-    }
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(menu_id)).await?;
 
     tracing::debug!(
         menu_id = %menu_id,
         "Menu ownership verified, creating menu item"
     );
 
+    if let Some(group_id) = data.choice_group_id {
+        verify_choice_group_in_menu(&pool, menu_id, group_id).await?;
+    }
+
     let menu_item = sqlx::query_as::<_, MenuItem>(
         r#"
         INSERT INTO menu_items (
             menu_id, name, description, course_type, image_url,
-            is_featured, display_order, quantity, created_at, updated_at
+            is_featured, display_order, quantity, choice_group_id, price_adjustment,
+            created_at, updated_at
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW(), NOW())
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, NOW(), NOW())
         RETURNING *
         "#
     )
@@ -80,6 +68,8 @@ pub async fn create_menu_item(
     .bind(data.is_featured.unwrap_or(false))
     .bind(data.display_order.unwrap_or(0))
     .bind(data.quantity)
+    .bind(data.choice_group_id)
+    .bind(data.price_adjustment.unwrap_or(0.0))
     .fetch_one(pool.get_ref())
     .await?;
 
@@ -95,17 +85,24 @@ pub async fn create_menu_item(
 
 /// Retrieves all menu items for a specific menu.
 /// 
+/// # Authorization
+/// Only the chef who owns the menu or an admin can list its items here;
+/// diners see active menus through the public menu endpoints.
+/// 
 /// # Arguments
+/// * `user` - The authenticated chef
 /// * `pool` - Database connection pool
 /// * `menu_id` - UUID of the menu
 /// 
 /// # Returns
 /// * `HttpResponse::Ok` with array of MenuItems
 pub async fn get_menu_items(
+    user: RequireRole<role::Chef>,
     pool: web::Data<DbPool>,
     menu_id: web::Path<Uuid>,
 ) -> Result<HttpResponse, AppError> {
     let menu_id = *menu_id;
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(menu_id)).await?;
 
     tracing::debug!(
         menu_id = %menu_id,
@@ -134,23 +131,23 @@ pub async fn get_menu_items(
 /// Only the chef who owns the menu or an admin can update menu items.
 /// 
 /// # Arguments
-/// * `req` - HTTP request with bearer token
+/// * `user` - The authenticated chef
 /// * `pool` - Database connection pool
 /// * `path` - Tuple of (menu_id, item_id)
 /// * `data` - UpdateMenuItem struct with fields to update
 /// 
 /// # Returns
 /// * `HttpResponse::Ok` with updated MenuItem on success
-/// * `AppError::Unauthorized` if user doesn't own the menu
+/// * `AppError::Forbidden` if user doesn't own the menu
 /// * `AppError::NotFound` if menu item doesn't exist
 pub async fn update_menu_item(
-    req: HttpRequest,
+    user: RequireRole<role::Chef>,
     pool: web::Data<DbPool>,
     path: web::Path<(Uuid, Uuid)>,
     data: web::Json<UpdateMenuItem>,
 ) -> Result<HttpResponse, AppError> {
     let (menu_id, item_id) = path.into_inner();
-    let user_id = extract_user_id(&req)?;
+    let user_id = user.id;
 
     tracing::debug!(
         user_id = %user_id,
@@ -159,32 +156,18 @@ pub async fn update_menu_item(
         "Attempting to update menu item"
     );
 
-    // Verify ownership of the menu
-    let _chef: Option<(uuid::Uuid,)> = sqlx::query_as(
-        "SELECT c.id FROM chefs c
-         INNER JOIN menus m ON m.chef_id = c.id
-         WHERE c.user_id = $1 AND m.id = $2"
-    )
-    .bind(user_id)
-    .bind(menu_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
-    
-    if _chef.is_none() {
-        tracing::warn!(
-            user_id = %user_id,
-            menu_id = %menu_id,
-            item_id = %item_id,
-            "User attempted to update menu item for unauthorized menu"
-        );
-        return Err(AppError::Unauthorized("Not authorized".to_string()));
-    }
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::MenuItem(item_id)).await?;
 
     tracing::debug!(
         menu_id = %menu_id,
         "Menu ownership verified, updating item"
     );
 
+    data.check().map_err(AppError::ValidationError)?;
+    if let Some(group_id) = data.choice_group_id {
+        verify_choice_group_in_menu(&pool, menu_id, group_id).await?;
+    }
+
     let item = sqlx::query_as::<_, MenuItem>(
         r#"
         UPDATE menu_items
@@ -195,8 +178,10 @@ pub async fn update_menu_item(
             is_featured = COALESCE($5, is_featured),
             display_order = COALESCE($6, display_order),
             quantity = COALESCE($7, quantity),
+            choice_group_id = CASE WHEN $12 THEN NULL ELSE COALESCE($8, choice_group_id) END,
+            price_adjustment = COALESCE($9, price_adjustment),
             updated_at = NOW()
-        WHERE id = $8
+        WHERE id = $10 AND menu_id = $11
         RETURNING *
         "#
     )
@@ -204,10 +189,14 @@ pub async fn update_menu_item(
     .bind(&data.description)
     .bind(&data.course_type)
     .bind(&data.image_url)
-    .bind(&data.is_featured)
-    .bind(&data.display_order)
-    .bind(&data.quantity)
+    .bind(data.is_featured)
+    .bind(data.display_order)
+    .bind(data.quantity)
+    .bind(data.choice_group_id)
+    .bind(data.price_adjustment)
     .bind(item_id)
+    .bind(menu_id)
+    .bind(data.clear_choice_group)
     .fetch_optional(pool.get_ref())
     .await?
     .ok_or_else(|| {
@@ -233,20 +222,21 @@ pub async fn update_menu_item(
 /// Only the chef who owns the menu or an admin can delete menu items.
 /// 
 /// # Arguments
-/// * `req` - HTTP request with bearer token
+/// * `user` - The authenticated chef
 /// * `pool` - Database connection pool
 /// * `path` - Tuple of (menu_id, item_id)
 /// 
 /// # Returns
 /// * `HttpResponse::NoContent` on success
-/// * `AppError::Unauthorized` if user doesn't own the menu
+/// * `AppError::Forbidden` if user doesn't own the menu
+/// * `AppError::NotFound` if the item isn't on this menu
 pub async fn delete_menu_item(
-    req: HttpRequest,
+    user: RequireRole<role::Chef>,
     pool: web::Data<DbPool>,
     path: web::Path<(Uuid, Uuid)>,
 ) -> Result<HttpResponse, AppError> {
     let (menu_id, item_id) = path.into_inner();
-    let user_id = extract_user_id(&req)?;
+    let user_id = user.id;
 
     tracing::debug!(
         user_id = %user_id,
@@ -255,26 +245,7 @@ pub async fn delete_menu_item(
         "Attempting to delete menu item"
     );
 
-    // Verify ownership of the menu
-    let _chef: Option<(uuid::Uuid,)> = sqlx::query_as(
-        "SELECT c.id FROM chefs c
-         INNER JOIN menus m ON m.chef_id = c.id
-         WHERE c.user_id = $1 AND m.id = $2"
-    )
-    .bind(user_id)
-    .bind(menu_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
-    
-    if _chef.is_none() {
-        tracing::warn!(
-            user_id = %user_id,
-            menu_id = %menu_id,
-            item_id = %item_id,
-            "User attempted to delete menu item from unauthorized menu"
-        );
-        return Err(AppError::Unauthorized("Not authorized".to_string()));
-    }
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::MenuItem(item_id)).await?;
 
     tracing::debug!(
         menu_id = %menu_id,
@@ -282,11 +253,16 @@ pub async fn delete_menu_item(
         "Menu ownership verified, deleting item"
     );
 
-    sqlx::query("DELETE FROM menu_items WHERE id = $1")
+    let deleted = sqlx::query("DELETE FROM menu_items WHERE id = $1 AND menu_id = $2")
         .bind(item_id)
+        .bind(menu_id)
         .execute(pool.get_ref())
         .await?;
 
+    if deleted.rows_affected() == 0 {
+        return Err(AppError::NotFound("Menu item not found".to_string()));
+    }
+
     tracing::info!(
         item_id = %item_id,
         "Menu item deleted successfully"
@@ -295,3 +271,231 @@ pub async fn delete_menu_item(
     Ok(HttpResponse::NoContent().finish())
 }
 
+
+/// Sets the display order of all items in a menu in one transaction.
+///
+/// # Authorization
+/// Only the chef who owns the menu or an admin can reorder menu items.
+///
+/// # Arguments
+/// * `user` - The authenticated chef
+/// * `pool` - Database connection pool
+/// * `menu_id` - UUID of the menu
+/// * `data` - Every item ID of the menu, in the desired order
+///
+/// # Returns
+/// * `HttpResponse::Ok` with the reordered MenuItems
+/// * `AppError::ValidationError` if the IDs don't match the menu's items exactly
+/// * `AppError::Forbidden` if user doesn't own the menu
+pub async fn reorder_menu_items(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    menu_id: web::Path<Uuid>,
+    data: web::Json<ReorderMenuItems>,
+) -> Result<HttpResponse, AppError> {
+    let menu_id = *menu_id;
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(menu_id)).await?;
+
+    let mut tx = pool.begin().await?;
+
+    // Lock the menu's items so concurrent reorders apply one after the other
+    let existing: Vec<(Uuid,)> = sqlx::query_as(
+        "SELECT id FROM menu_items WHERE menu_id = $1 FOR UPDATE"
+    )
+    .bind(menu_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let existing: Vec<Uuid> = existing.into_iter().map(|(id,)| id).collect();
+    data.check(&existing).map_err(AppError::ValidationError)?;
+
+    sqlx::query(
+        r#"
+        UPDATE menu_items
+        SET display_order = ordered.position - 1,
+            updated_at = NOW()
+        FROM UNNEST($1::uuid[]) WITH ORDINALITY AS ordered(id, position)
+        WHERE menu_items.id = ordered.id AND menu_items.menu_id = $2
+        "#
+    )
+    .bind(&data.item_ids)
+    .bind(menu_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    tracing::info!(
+        menu_id = %menu_id,
+        item_count = data.item_ids.len(),
+        "Menu items reordered successfully"
+    );
+
+    let items = fetch_menu_items(&pool, menu_id).await?;
+    Ok(HttpResponse::Ok().json(items))
+}
+
+/// Applies bulk creates, updates and deletes to a menu's items atomically.
+///
+/// Deletes run first, then updates, then creates. If any change fails
+/// (unknown item, item from another menu, invalid choice group) nothing is applied.
+///
+/// # Authorization
+/// Only the chef who owns the menu or an admin can edit menu items.
+///
+/// # Arguments
+/// * `user` - The authenticated chef
+/// * `pool` - Database connection pool
+/// * `menu_id` - UUID of the menu
+/// * `data` - BulkMenuItemChanges with `create`, `update` and `delete` lists
+///
+/// # Returns
+/// * `HttpResponse::Ok` with all MenuItems of the menu after the changes
+/// * `AppError::ValidationError` if an item is listed twice or has no name
+/// * `AppError::NotFound` if an updated or deleted item isn't in the menu
+/// * `AppError::Forbidden` if user doesn't own the menu
+pub async fn bulk_update_menu_items(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    menu_id: web::Path<Uuid>,
+    data: web::Json<BulkMenuItemChanges>,
+) -> Result<HttpResponse, AppError> {
+    let menu_id = *menu_id;
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(menu_id)).await?;
+
+    data.check().map_err(AppError::ValidationError)?;
+
+    let group_ids = data.create.iter().filter_map(|item| item.choice_group_id)
+        .chain(data.update.iter().filter_map(|item| item.changes.choice_group_id));
+    for group_id in group_ids {
+        verify_choice_group_in_menu(&pool, menu_id, group_id).await?;
+    }
+
+    let mut tx = pool.begin().await?;
+
+    if !data.delete.is_empty() {
+        let deleted = sqlx::query(
+            "DELETE FROM menu_items WHERE menu_id = $1 AND id = ANY($2)"
+        )
+        .bind(menu_id)
+        .bind(&data.delete)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if deleted != data.delete.len() as u64 {
+            return Err(AppError::NotFound("One or more menu items to delete were not found".to_string()));
+        }
+    }
+
+    for update in &data.update {
+        let changes = &update.changes;
+        let updated = sqlx::query(
+            r#"
+            UPDATE menu_items
+            SET name = COALESCE($1, name),
+                description = COALESCE($2, description),
+                course_type = COALESCE($3, course_type),
+                image_url = COALESCE($4, image_url),
+                is_featured = COALESCE($5, is_featured),
+                display_order = COALESCE($6, display_order),
+                quantity = COALESCE($7, quantity),
+                choice_group_id = CASE WHEN $12 THEN NULL ELSE COALESCE($8, choice_group_id) END,
+                price_adjustment = COALESCE($9, price_adjustment),
+                updated_at = NOW()
+            WHERE id = $10 AND menu_id = $11
+            "#
+        )
+        .bind(&changes.name)
+        .bind(&changes.description)
+        .bind(&changes.course_type)
+        .bind(&changes.image_url)
+        .bind(changes.is_featured)
+        .bind(changes.display_order)
+        .bind(changes.quantity)
+        .bind(changes.choice_group_id)
+        .bind(changes.price_adjustment)
+        .bind(update.id)
+        .bind(menu_id)
+        .bind(changes.clear_choice_group)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if updated == 0 {
+            return Err(AppError::NotFound(format!("Menu item {} not found", update.id)));
+        }
+    }
+
+    for item in &data.create {
+        sqlx::query(
+            r#"
+            INSERT INTO menu_items (
+                menu_id, name, description, course_type, image_url,
+                is_featured, display_order, quantity, choice_group_id, price_adjustment,
+                created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, NOW(), NOW())
+            "#
+        )
+        .bind(menu_id)
+        .bind(item.name.trim())
+        .bind(&item.description)
+        .bind(&item.course_type)
+        .bind(&item.image_url)
+        .bind(item.is_featured.unwrap_or(false))
+        .bind(item.display_order.unwrap_or(0))
+        .bind(item.quantity)
+        .bind(item.choice_group_id)
+        .bind(item.price_adjustment.unwrap_or(0.0))
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(
+        menu_id = %menu_id,
+        created = data.create.len(),
+        updated = data.update.len(),
+        deleted = data.delete.len(),
+        "Bulk menu item changes applied"
+    );
+
+    let items = fetch_menu_items(&pool, menu_id).await?;
+    Ok(HttpResponse::Ok().json(items))
+}
+
+async fn fetch_menu_items(pool: &web::Data<DbPool>, menu_id: Uuid) -> Result<Vec<MenuItem>, AppError> {
+    let items = sqlx::query_as::<_, MenuItem>(
+        "SELECT * FROM menu_items WHERE menu_id = $1 ORDER BY display_order ASC, created_at ASC"
+    )
+    .bind(menu_id)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    Ok(items)
+}
+
+/// Ensures a choice group belongs to the same menu as the item being saved.
+pub(crate) async fn verify_choice_group_in_menu(
+    pool: &web::Data<DbPool>,
+    menu_id: Uuid,
+    group_id: Uuid,
+) -> Result<(), AppError> {
+    let group: Option<(Uuid,)> = sqlx::query_as(
+        "SELECT id FROM menu_choice_groups WHERE id = $1 AND menu_id = $2"
+    )
+    .bind(group_id)
+    .bind(menu_id)
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    if group.is_none() {
+        return Err(AppError::ValidationError(
+            "Choice group does not belong to this menu".to_string(),
+        ));
+    }
+
+    Ok(())
+}
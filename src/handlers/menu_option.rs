@@ -0,0 +1,352 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::models::{
+    AddOnPricing, ChoiceGroupWithItems, CreateMenuAddOn, CreateMenuChoiceGroup, MenuAddOn,
+    MenuChoiceGroup, MenuItem, UpdateMenuAddOn, UpdateMenuChoiceGroup,
+};
+use crate::errors::AppError;
+use crate::middleware::auth::RequireRole;
+use crate::middleware::roles::{role, Permission};
+use crate::policy::{authorize, Resource};
+
+fn validate_choice_bounds(min_choices: i32, max_choices: i32) -> Result<(), AppError> {
+    if min_choices < 0 || max_choices < 1 || max_choices < min_choices {
+        return Err(AppError::ValidationError(
+            "Choice bounds must satisfy 0 <= min_choices <= max_choices and max_choices >= 1".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Creates a choice group ("Choose one main") on a menu.
+///
+/// # Authorization
+/// Only the chef who owns the menu can create choice groups.
+///
+/// # Returns
+/// * `HttpResponse::Created` with the created MenuChoiceGroup
+/// * `AppError::ValidationError` if the choice bounds are inconsistent
+pub async fn create_choice_group(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    menu_id: web::Path<Uuid>,
+    data: web::Json<CreateMenuChoiceGroup>,
+) -> Result<HttpResponse, AppError> {
+    let menu_id = *menu_id;
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(menu_id)).await?;
+
+    if data.name.trim().is_empty() {
+        return Err(AppError::ValidationError("Name is required".to_string()));
+    }
+
+    let min_choices = data.min_choices.unwrap_or(1);
+    let max_choices = data.max_choices.unwrap_or(min_choices.max(1));
+    validate_choice_bounds(min_choices, max_choices)?;
+
+    let group = sqlx::query_as::<_, MenuChoiceGroup>(
+        r#"
+        INSERT INTO menu_choice_groups (
+            menu_id, name, description, course_type, min_choices, max_choices,
+            display_order, created_at, updated_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), NOW())
+        RETURNING *
+        "#
+    )
+    .bind(menu_id)
+    .bind(data.name.trim())
+    .bind(&data.description)
+    .bind(&data.course_type)
+    .bind(min_choices)
+    .bind(max_choices)
+    .bind(data.display_order.unwrap_or(0))
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    tracing::info!(
+        menu_id = %menu_id,
+        group_id = %group.id,
+        "Choice group created successfully"
+    );
+
+    Ok(HttpResponse::Created().json(group))
+}
+
+/// Lists a menu's choice groups, each with the items guests can pick from.
+///
+/// # Authorization
+/// Only the chef who owns the menu can list them; diners see them on the
+/// public menu.
+pub async fn get_choice_groups(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    menu_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let menu_id = *menu_id;
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(menu_id)).await?;
+
+    let groups = sqlx::query_as::<_, MenuChoiceGroup>(
+        "SELECT * FROM menu_choice_groups WHERE menu_id = $1 ORDER BY display_order ASC, created_at ASC"
+    )
+    .bind(menu_id)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let items = sqlx::query_as::<_, MenuItem>(
+        "SELECT * FROM menu_items WHERE menu_id = $1 AND choice_group_id IS NOT NULL
+         ORDER BY display_order ASC, created_at ASC"
+    )
+    .bind(menu_id)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let groups: Vec<ChoiceGroupWithItems> = groups
+        .into_iter()
+        .map(|group| {
+            let items = items
+                .iter()
+                .filter(|item| item.choice_group_id == Some(group.id))
+                .cloned()
+                .collect();
+            ChoiceGroupWithItems { group, items }
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(groups))
+}
+
+/// Updates a choice group.
+///
+/// # Authorization
+/// Only the chef who owns the menu can update its choice groups.
+pub async fn update_choice_group(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    data: web::Json<UpdateMenuChoiceGroup>,
+) -> Result<HttpResponse, AppError> {
+    let (menu_id, group_id) = path.into_inner();
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(menu_id)).await?;
+
+    let existing = sqlx::query_as::<_, MenuChoiceGroup>(
+        "SELECT * FROM menu_choice_groups WHERE id = $1 AND menu_id = $2"
+    )
+    .bind(group_id)
+    .bind(menu_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Choice group not found".to_string()))?;
+
+    validate_choice_bounds(
+        data.min_choices.unwrap_or(existing.min_choices),
+        data.max_choices.unwrap_or(existing.max_choices),
+    )?;
+
+    let group = sqlx::query_as::<_, MenuChoiceGroup>(
+        r#"
+        UPDATE menu_choice_groups
+        SET name = COALESCE($1, name),
+            description = COALESCE($2, description),
+            course_type = COALESCE($3, course_type),
+            min_choices = COALESCE($4, min_choices),
+            max_choices = COALESCE($5, max_choices),
+            display_order = COALESCE($6, display_order),
+            updated_at = NOW()
+        WHERE id = $7 AND menu_id = $8
+        RETURNING *
+        "#
+    )
+    .bind(&data.name)
+    .bind(&data.description)
+    .bind(&data.course_type)
+    .bind(data.min_choices)
+    .bind(data.max_choices)
+    .bind(data.display_order)
+    .bind(group_id)
+    .bind(menu_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Choice group not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(group))
+}
+
+/// Deletes a choice group. Its items stay on the menu as fixed courses.
+///
+/// # Authorization
+/// Only the chef who owns the menu can delete its choice groups.
+pub async fn delete_choice_group(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (menu_id, group_id) = path.into_inner();
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(menu_id)).await?;
+
+    let rows_affected = sqlx::query(
+        "DELETE FROM menu_choice_groups WHERE id = $1 AND menu_id = $2"
+    )
+    .bind(group_id)
+    .bind(menu_id)
+    .execute(pool.get_ref())
+    .await?
+    .rows_affected();
+
+    if rows_affected == 0 {
+        return Err(AppError::NotFound("Choice group not found".to_string()));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Creates a paid add-on (wine pairing, extra course, ...) for a menu.
+///
+/// # Authorization
+/// Only the chef who owns the menu can create add-ons.
+///
+/// # Returns
+/// * `HttpResponse::Created` with the created MenuAddOn
+/// * `AppError::ValidationError` if the price is negative
+pub async fn create_add_on(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    menu_id: web::Path<Uuid>,
+    data: web::Json<CreateMenuAddOn>,
+) -> Result<HttpResponse, AppError> {
+    let menu_id = *menu_id;
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(menu_id)).await?;
+
+    if data.name.trim().is_empty() {
+        return Err(AppError::ValidationError("Name is required".to_string()));
+    }
+    if data.price < 0.0 {
+        return Err(AppError::ValidationError("Price cannot be negative".to_string()));
+    }
+
+    let pricing_unit = data.pricing_unit.unwrap_or(AddOnPricing::PerGuest);
+
+    let add_on = sqlx::query_as::<_, MenuAddOn>(
+        r#"
+        INSERT INTO menu_add_ons (
+            menu_id, name, description, price, pricing_unit, is_active,
+            display_order, created_at, updated_at
+        )
+        VALUES ($1, $2, $3, $4, $5, true, $6, NOW(), NOW())
+        RETURNING *
+        "#
+    )
+    .bind(menu_id)
+    .bind(data.name.trim())
+    .bind(&data.description)
+    .bind(data.price)
+    .bind(pricing_unit.to_string())
+    .bind(data.display_order.unwrap_or(0))
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    tracing::info!(
+        menu_id = %menu_id,
+        add_on_id = %add_on.id,
+        "Menu add-on created successfully"
+    );
+
+    Ok(HttpResponse::Created().json(add_on))
+}
+
+/// Lists the active add-ons of a menu.
+///
+/// # Authorization
+/// Only the chef who owns the menu can list them; diners see them on the
+/// public menu.
+pub async fn get_add_ons(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    menu_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(*menu_id)).await?;
+    let add_ons = sqlx::query_as::<_, MenuAddOn>(
+        "SELECT * FROM menu_add_ons WHERE menu_id = $1 AND is_active = true
+         ORDER BY display_order ASC, created_at ASC"
+    )
+    .bind(*menu_id)
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(add_ons))
+}
+
+/// Updates an add-on. Bookings keep the price they were made with.
+///
+/// # Authorization
+/// Only the chef who owns the menu can update its add-ons.
+pub async fn update_add_on(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    data: web::Json<UpdateMenuAddOn>,
+) -> Result<HttpResponse, AppError> {
+    let (menu_id, add_on_id) = path.into_inner();
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(menu_id)).await?;
+
+    if data.price.is_some_and(|price| price < 0.0) {
+        return Err(AppError::ValidationError("Price cannot be negative".to_string()));
+    }
+
+    let add_on = sqlx::query_as::<_, MenuAddOn>(
+        r#"
+        UPDATE menu_add_ons
+        SET name = COALESCE($1, name),
+            description = COALESCE($2, description),
+            price = COALESCE($3, price),
+            pricing_unit = COALESCE($4, pricing_unit),
+            is_active = COALESCE($5, is_active),
+            display_order = COALESCE($6, display_order),
+            updated_at = NOW()
+        WHERE id = $7 AND menu_id = $8
+        RETURNING *
+        "#
+    )
+    .bind(&data.name)
+    .bind(&data.description)
+    .bind(data.price)
+    .bind(data.pricing_unit.map(|unit| unit.to_string()))
+    .bind(data.is_active)
+    .bind(data.display_order)
+    .bind(add_on_id)
+    .bind(menu_id)
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFound("Add-on not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(add_on))
+}
+
+/// Deletes an add-on. Existing bookings keep their copy of it.
+///
+/// # Authorization
+/// Only the chef who owns the menu can delete its add-ons.
+pub async fn delete_add_on(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (menu_id, add_on_id) = path.into_inner();
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(menu_id)).await?;
+
+    let rows_affected = sqlx::query(
+        "DELETE FROM menu_add_ons WHERE id = $1 AND menu_id = $2"
+    )
+    .bind(add_on_id)
+    .bind(menu_id)
+    .execute(pool.get_ref())
+    .await?
+    .rows_affected();
+
+    if rows_affected == 0 {
+        return Err(AppError::NotFound("Add-on not found".to_string()));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -2,13 +2,47 @@ pub mod auth;
 pub mod chef;
 pub mod menu;
 pub mod menu_item;
+pub mod menu_option;
 pub mod booking;
+pub mod upload;
 pub mod web;
+pub mod chef_page;
+pub mod booking_wizard;
+pub mod chef_dashboard;
+pub mod two_factor;
+pub mod two_factor_page;
+pub mod oidc;
+pub mod admin;
+pub mod moderation;
+pub mod admin_console;
+pub mod chef_application;
+pub mod chef_team;
+pub mod chef_team_page;
+pub mod impersonation;
+pub mod staff;
+pub mod staff_page;
 
 pub use auth::*;
 pub use chef::*;
 pub use menu::*;
 pub use menu_item::*;
+pub use menu_option::*;
 pub use booking::*;
+pub use upload::*;
 pub use web::*;
+pub use chef_page::*;
+pub use booking_wizard::*;
+pub use chef_dashboard::*;
+pub use two_factor::*;
+pub use two_factor_page::*;
+pub use oidc::*;
+pub use admin::*;
+pub use moderation::*;
+pub use admin_console::*;
+pub use chef_application::*;
+pub use chef_team::*;
+pub use chef_team_page::*;
+pub use impersonation::*;
+pub use staff::*;
+pub use staff_page::*;
 
@@ -0,0 +1,394 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::audit::{AuditEvent, RequestMeta};
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::admin::managed_user;
+use crate::middleware::auth::Identity;
+use crate::middleware::roles::Permission;
+use crate::models::{
+    AdminBookingRow, AdminChefRow, AdminReviewRow, Booking, ContentFlag, ContentType, CreateFlag, FlagSummary,
+};
+use crate::policy::require;
+
+/// Rows shown per console page
+pub const CONSOLE_PAGE_SIZE: i64 = 50;
+
+/// Longest report a user can file
+const MAX_REASON_LENGTH: usize = 1000;
+
+/// `%text%` for ILIKE, with the wildcards in `text` escaped
+fn contains_pattern(text: Option<&str>) -> Option<String> {
+    text.map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(|text| format!("%{}%", text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")))
+}
+
+/// Report a chef, menu or review to staff
+pub async fn flag_content(pool: &DbPool, reporter: &Identity, flag: &CreateFlag) -> Result<ContentFlag, AppError> {
+    let reason = flag.reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::ValidationError("Please say what's wrong".to_string()));
+    }
+    if reason.chars().count() > MAX_REASON_LENGTH {
+        return Err(AppError::ValidationError(format!("Reports can be at most {} characters", MAX_REASON_LENGTH)));
+    }
+
+    let table = match flag.content_type {
+        ContentType::Chef => "chefs",
+        ContentType::Menu => "menus",
+        ContentType::Review => "reviews",
+    };
+    let (exists,) = sqlx::query_as::<_, (bool,)>(&format!("SELECT EXISTS (SELECT 1 FROM {} WHERE id = $1)", table))
+        .bind(flag.content_id)
+        .fetch_one(pool)
+        .await?;
+    if !exists {
+        return Err(AppError::NotFound(format!("{} not found", flag.content_type)));
+    }
+
+    // Reporting the same thing again while the first report is open is a no-op
+    let created = sqlx::query_as::<_, ContentFlag>(
+        r#"
+        INSERT INTO content_flags (content_type, content_id, reporter_id, reason)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (content_type, content_id, reporter_id) WHERE status = 'open' DO NOTHING
+        RETURNING *
+        "#
+    )
+    .bind(flag.content_type.to_string())
+    .bind(flag.content_id)
+    .bind(reporter.id)
+    .bind(reason)
+    .fetch_optional(pool)
+    .await?;
+    match created {
+        Some(flag) => Ok(flag),
+        None => Ok(sqlx::query_as::<_, ContentFlag>(
+            r#"
+            SELECT * FROM content_flags
+            WHERE content_type = $1 AND content_id = $2 AND reporter_id = $3 AND status = 'open'
+            "#
+        )
+        .bind(flag.content_type.to_string())
+        .bind(flag.content_id)
+        .bind(reporter.id)
+        .fetch_one(pool)
+        .await?),
+    }
+}
+
+/// Close the open flags on something staff have acted on
+async fn resolve_flags(pool: &DbPool, actor: &Identity, content_type: ContentType, content_id: Uuid) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE content_flags
+        SET status = 'resolved', resolved_by = $3, resolved_at = NOW()
+        WHERE content_type = $1 AND content_id = $2 AND status = 'open'
+        "#
+    )
+    .bind(content_type.to_string())
+    .bind(content_id)
+    .bind(actor.id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn dismiss_flag(pool: &DbPool, actor: &Identity, meta: &RequestMeta, flag_id: Uuid) -> Result<(), AppError> {
+    require(actor, Permission::ManageContent)?;
+    let dismissed = sqlx::query(
+        r#"
+        UPDATE content_flags
+        SET status = 'dismissed', resolved_by = $2, resolved_at = NOW()
+        WHERE id = $1 AND status = 'open'
+        "#
+    )
+    .bind(flag_id)
+    .bind(actor.id)
+    .execute(pool)
+    .await?;
+    if dismissed.rows_affected() == 0 {
+        return Err(AppError::NotFound("Open report not found".to_string()));
+    }
+    AuditEvent::new("flag_dismissed").by(actor).on("flag", flag_id).from(meta).record(pool).await;
+    Ok(())
+}
+
+/// Take a chef off the platform. Their profile, menus and booking pages go
+/// away, and they can't turn the profile back on themselves. Goes through
+/// the same checks as the admin API, so moderators can't deactivate a
+/// profile owned by an admin or moderator.
+pub async fn deactivate_chef(
+    pool: &DbPool,
+    actor: &Identity,
+    meta: &RequestMeta,
+    chef_id: Uuid,
+    reason: Option<&str>,
+) -> Result<(), AppError> {
+    let owner_id = chef_owner(pool, chef_id).await?;
+    managed_user(pool, actor, owner_id).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE chefs
+        SET is_active = false, deactivated_at = COALESCE(deactivated_at, NOW()), deactivation_reason = $2,
+            updated_at = NOW()
+        WHERE id = $1
+        "#
+    )
+    .bind(chef_id)
+    .bind(reason)
+    .execute(pool)
+    .await?;
+    resolve_flags(pool, actor, ContentType::Chef, chef_id).await?;
+
+    AuditEvent::new("chef_deactivated")
+        .by(actor)
+        .on("chef", chef_id)
+        .after(serde_json::json!({ "is_active": false, "reason": reason }))
+        .detail("owner_id", owner_id)
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(())
+}
+
+pub async fn reactivate_chef(pool: &DbPool, actor: &Identity, meta: &RequestMeta, chef_id: Uuid) -> Result<(), AppError> {
+    let owner_id = chef_owner(pool, chef_id).await?;
+    managed_user(pool, actor, owner_id).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE chefs
+        SET is_active = true, deactivated_at = NULL, deactivation_reason = NULL, updated_at = NOW()
+        WHERE id = $1
+        "#
+    )
+    .bind(chef_id)
+    .execute(pool)
+    .await?;
+
+    AuditEvent::new("chef_reactivated")
+        .by(actor)
+        .on("chef", chef_id)
+        .after(serde_json::json!({ "is_active": true }))
+        .detail("owner_id", owner_id)
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(())
+}
+
+async fn chef_owner(pool: &DbPool, chef_id: Uuid) -> Result<Uuid, AppError> {
+    sqlx::query_as::<_, (Uuid,)>("SELECT user_id FROM chefs WHERE id = $1")
+        .bind(chef_id)
+        .fetch_optional(pool)
+        .await?
+        .map(|(user_id,)| user_id)
+        .ok_or_else(|| AppError::NotFound("Chef not found".to_string()))
+}
+
+/// Hide a menu or review from public pages, or show it again. Hiding
+/// closes the open reports on it.
+pub async fn set_content_hidden(
+    pool: &DbPool,
+    actor: &Identity,
+    meta: &RequestMeta,
+    content_type: ContentType,
+    content_id: Uuid,
+    hidden: bool,
+) -> Result<(), AppError> {
+    require(actor, Permission::ManageContent)?;
+    let table = match content_type {
+        ContentType::Menu => "menus",
+        ContentType::Review => "reviews",
+        ContentType::Chef => {
+            return Err(AppError::ValidationError("Chefs are deactivated rather than hidden".to_string()))
+        }
+    };
+
+    let updated = sqlx::query(&format!(
+        "UPDATE {} SET hidden_at = CASE WHEN $2 THEN COALESCE(hidden_at, NOW()) END, updated_at = NOW() WHERE id = $1",
+        table
+    ))
+    .bind(content_id)
+    .bind(hidden)
+    .execute(pool)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("{} not found", content_type)));
+    }
+    if hidden {
+        resolve_flags(pool, actor, content_type, content_id).await?;
+    }
+
+    AuditEvent::new(if hidden { "content_hidden" } else { "content_unhidden" })
+        .by(actor)
+        .on(&content_type.to_string(), content_id)
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(())
+}
+
+/// Mark a paid booking refunded, cancelling it if the event hasn't
+/// happened. Admins only.
+pub async fn refund_booking(
+    pool: &DbPool,
+    actor: &Identity,
+    meta: &RequestMeta,
+    booking_id: Uuid,
+) -> Result<Booking, AppError> {
+    require(actor, Permission::RefundBookings)?;
+
+    let before = sqlx::query_as::<_, (String, String)>("SELECT status, payment_status FROM bookings WHERE id = $1")
+        .bind(booking_id)
+        .fetch_optional(pool)
+        .await?;
+    let booking = sqlx::query_as::<_, Booking>(
+        r#"
+        UPDATE bookings
+        SET payment_status = 'refunded',
+            status = CASE WHEN status IN ('pending', 'confirmed') THEN 'cancelled' ELSE status END,
+            updated_at = NOW()
+        WHERE id = $1 AND payment_status = 'paid'
+        RETURNING *
+        "#
+    )
+    .bind(booking_id)
+    .fetch_optional(pool)
+    .await?;
+    let (booking, (status, payment_status)) = match (booking, before) {
+        (Some(booking), Some(before)) => (booking, before),
+        (_, Some(_)) => return Err(AppError::ValidationError("Only paid bookings can be refunded".to_string())),
+        (_, None) => return Err(AppError::NotFound("Booking not found".to_string())),
+    };
+
+    AuditEvent::new("booking_refunded")
+        .by(actor)
+        .on("booking", booking.id)
+        .before(serde_json::json!({ "status": status, "payment_status": payment_status }))
+        .after(serde_json::json!({ "status": booking.status, "payment_status": booking.payment_status }))
+        .detail("amount", booking.total_price)
+        .detail("customer_id", booking.customer_id)
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(booking)
+}
+
+/// Chef profiles matching a name, location or owner email, newest first.
+/// `active` filters on whether the profile is public.
+pub async fn search_chefs(pool: &DbPool, q: Option<&str>, active: Option<bool>) -> Result<Vec<AdminChefRow>, AppError> {
+    Ok(sqlx::query_as::<_, AdminChefRow>(
+        r#"
+        SELECT c.id, c.user_id, c.chef_name, c.slug, c.location, u.email AS owner_email,
+               COALESCE(c.is_active, false) AS is_active, c.deactivated_at, c.deactivation_reason,
+               c.created_at
+        FROM chefs c
+        INNER JOIN users u ON u.id = c.user_id
+        WHERE ($1::text IS NULL OR c.chef_name ILIKE $1 OR c.location ILIKE $1 OR u.email ILIKE $1)
+          AND ($2::boolean IS NULL OR COALESCE(c.is_active, false) = $2)
+        ORDER BY c.created_at DESC
+        LIMIT $3
+        "#
+    )
+    .bind(contains_pattern(q))
+    .bind(active)
+    .bind(CONSOLE_PAGE_SIZE)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Bookings matching a customer or chef, newest first
+pub async fn search_bookings(
+    pool: &DbPool,
+    q: Option<&str>,
+    status: Option<&str>,
+    payment_status: Option<&str>,
+) -> Result<Vec<AdminBookingRow>, AppError> {
+    Ok(sqlx::query_as::<_, AdminBookingRow>(
+        r#"
+        SELECT b.id, c.chef_name, b.customer_name, b.customer_email, b.event_date, b.number_of_guests,
+               b.total_price::float8 AS total_price, b.status, b.payment_status, b.created_at
+        FROM bookings b
+        INNER JOIN chefs c ON c.id = b.chef_id
+        WHERE ($1::text IS NULL OR b.customer_name ILIKE $1 OR b.customer_email ILIKE $1 OR c.chef_name ILIKE $1)
+          AND ($2::text IS NULL OR b.status = $2)
+          AND ($3::text IS NULL OR b.payment_status = $3)
+        ORDER BY b.created_at DESC
+        LIMIT $4
+        "#
+    )
+    .bind(contains_pattern(q))
+    .bind(status)
+    .bind(payment_status)
+    .bind(CONSOLE_PAGE_SIZE)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Reviews matching a reviewer, chef or comment, newest first
+pub async fn search_reviews(pool: &DbPool, q: Option<&str>, hidden: Option<bool>) -> Result<Vec<AdminReviewRow>, AppError> {
+    Ok(sqlx::query_as::<_, AdminReviewRow>(
+        r#"
+        SELECT r.id, c.chef_name, r.reviewer_name, r.rating, r.comment, r.hidden_at, r.created_at
+        FROM reviews r
+        INNER JOIN chefs c ON c.id = r.chef_id
+        WHERE ($1::text IS NULL OR r.reviewer_name ILIKE $1 OR r.comment ILIKE $1 OR c.chef_name ILIKE $1)
+          AND ($2::boolean IS NULL OR (r.hidden_at IS NOT NULL) = $2)
+        ORDER BY r.created_at DESC
+        LIMIT $3
+        "#
+    )
+    .bind(contains_pattern(q))
+    .bind(hidden)
+    .bind(CONSOLE_PAGE_SIZE)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Reports with the given status, oldest open ones first so nothing waits
+/// too long
+pub async fn list_flags(pool: &DbPool, status: &str) -> Result<Vec<FlagSummary>, AppError> {
+    Ok(sqlx::query_as::<_, FlagSummary>(
+        r#"
+        SELECT f.*,
+               CASE f.content_type
+                   WHEN 'chef' THEN c.chef_name
+                   WHEN 'menu' THEN m.name
+                   ELSE LEFT(r.reviewer_name || ': ' || COALESCE(r.comment, ''), 120)
+               END AS content_label,
+               CASE f.content_type
+                   WHEN 'chef' THEN COALESCE(NOT c.is_active, false)
+                   WHEN 'menu' THEN m.hidden_at IS NOT NULL
+                   ELSE r.hidden_at IS NOT NULL
+               END AS content_hidden,
+               u.email AS reporter_email
+        FROM content_flags f
+        LEFT JOIN chefs c ON f.content_type = 'chef' AND c.id = f.content_id
+        LEFT JOIN menus m ON f.content_type = 'menu' AND m.id = f.content_id
+        LEFT JOIN reviews r ON f.content_type = 'review' AND r.id = f.content_id
+        LEFT JOIN users u ON u.id = f.reporter_id
+        WHERE f.status = $1
+        ORDER BY CASE WHEN f.status = 'open' THEN f.created_at END ASC, f.resolved_at DESC
+        LIMIT $2
+        "#
+    )
+    .bind(status)
+    .bind(CONSOLE_PAGE_SIZE)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Report a chef, menu or review to staff
+pub async fn create_flag(
+    identity: Identity,
+    pool: web::Data<DbPool>,
+    body: web::Json<CreateFlag>,
+) -> Result<HttpResponse, AppError> {
+    let flag = flag_content(&pool, &identity, &body).await?;
+    Ok(HttpResponse::Created().json(flag))
+}
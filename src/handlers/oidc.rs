@@ -0,0 +1,228 @@
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
+
+use crate::audit::{AuditEvent, RequestMeta};
+use crate::cache::RedisClient;
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::auth::{ensure_not_suspended, login_response, revoke_cached_credentials};
+use crate::models::{Role, User};
+use crate::oidc::{AuthorizationRequest, OidcIdentity, OidcProviders};
+
+/// Where the provider sends the browser back to for the web flow
+pub fn default_redirect_uri(config: &Config, provider: &str) -> String {
+    format!("{}/auth/oidc/{}/callback", config.mail.app_url.trim_end_matches('/'), provider)
+}
+
+/// Start a login with `provider`, remembering the PKCE verifier and nonce
+/// until the callback
+pub async fn begin_oidc_login(
+    providers: &OidcProviders,
+    redis: &RedisClient,
+    provider: &str,
+    redirect_uri: &str,
+) -> Result<AuthorizationRequest, AppError> {
+    let request = providers.get(provider)?.authorization_request(redirect_uri).await?;
+    redis.store_oidc_state(&request.state, &request.login).await?;
+    Ok(request)
+}
+
+/// Finish a login from the provider's callback and return the local user
+pub async fn finish_oidc_login(
+    pool: &DbPool,
+    redis: &RedisClient,
+    providers: &OidcProviders,
+    meta: &RequestMeta,
+    provider: &str,
+    code: &str,
+    state: &str,
+) -> Result<User, AppError> {
+    let expired = || AppError::Unauthorized("Your sign-in has expired. Please try again.".to_string());
+    let login = redis.take_oidc_state(state).await?.ok_or_else(expired)?;
+    if login.provider != provider {
+        return Err(expired());
+    }
+
+    let identity = providers.get(provider)?.exchange_code(code, &login).await?;
+    let user = link_or_create_user(pool, redis, meta, provider, &identity).await?;
+    ensure_not_suspended(&user)?;
+    Ok(user)
+}
+
+/// The user a provider identity belongs to: the account already linked to
+/// it, else the account with the same verified email, else a new diner
+async fn link_or_create_user(
+    pool: &DbPool,
+    redis: &RedisClient,
+    meta: &RequestMeta,
+    provider: &str,
+    identity: &OidcIdentity,
+) -> Result<User, AppError> {
+    let linked = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE user_identities i
+        SET last_login_at = NOW()
+        FROM users u
+        WHERE i.provider = $1 AND i.subject = $2 AND u.id = i.user_id
+        RETURNING u.*
+        "#
+    )
+    .bind(provider)
+    .bind(&identity.subject)
+    .fetch_optional(pool)
+    .await?;
+    if let Some(user) = linked {
+        tracing::info!(user_id = %user.id, provider, "OIDC login");
+        return Ok(user);
+    }
+
+    // Only an address the provider has verified may be matched to an account
+    let email = match (&identity.email, identity.email_verified) {
+        (Some(email), true) => email.trim(),
+        _ => {
+            return Err(AppError::Unauthorized(
+                "Your account with this provider has no verified email address".to_string(),
+            ))
+        }
+    };
+
+    let mut tx = pool.begin().await?;
+    let existing = sqlx::query_as::<_, User>("SELECT * FROM users WHERE LOWER(email) = LOWER($1) FOR UPDATE")
+        .bind(email)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    let created = existing.is_none();
+    let user = match existing {
+        Some(user) if user.email_verified => user,
+        Some(user) => {
+            // Nobody proved they own this address, so whoever registered it may
+            // not be the provider's user: take away what they set up before
+            // handing the account over
+            let user = sqlx::query_as::<_, User>(
+                r#"
+                UPDATE users
+                SET password_hash = NULL, totp_secret = NULL, totp_enabled = FALSE, totp_last_step = NULL,
+                    email_verified = TRUE, token_version = token_version + 1, updated_at = NOW()
+                WHERE id = $1
+                RETURNING *
+                "#
+            )
+            .bind(user.id)
+            .fetch_one(&mut *tx)
+            .await?;
+            sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+                .bind(user.id)
+                .execute(&mut *tx)
+                .await?;
+            user
+        }
+        None => {
+            sqlx::query_as::<_, User>(
+                r#"
+                INSERT INTO users (id, email, password_hash, role, email_verified, created_at, updated_at)
+                VALUES (gen_random_uuid(), $1, NULL, $2::varchar, TRUE, NOW(), NOW())
+                RETURNING *
+                "#
+            )
+            .bind(email)
+            .bind(Role::Diner.to_string())
+            .fetch_one(&mut *tx)
+            .await?
+        }
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_identities (user_id, provider, subject, email)
+        VALUES ($1, $2, $3, $4)
+        "#
+    )
+    .bind(user.id)
+    .bind(provider)
+    .bind(&identity.subject)
+    .bind(email)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    revoke_cached_credentials(redis, user.id).await?;
+    if created {
+        AuditEvent::new("oidc_account_created")
+            .by_user(user.id, user.role)
+            .on("user", user.id)
+            .after(serde_json::json!({ "email": user.email, "role": user.role }))
+            .detail("provider", provider)
+            .from(meta)
+            .record(pool)
+            .await;
+    }
+    AuditEvent::new("oidc_account_linked")
+        .by_user(user.id, user.role)
+        .on("user", user.id)
+        .detail("provider", provider)
+        .detail("subject", &identity.subject)
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(user)
+}
+
+/// Providers to offer on the login screen
+pub async fn list_oidc_providers(config: web::Data<Config>) -> HttpResponse {
+    let providers: Vec<_> = config
+        .oidc
+        .providers
+        .iter()
+        .map(|p| serde_json::json!({ "name": p.name, "display_name": p.display_name }))
+        .collect();
+    HttpResponse::Ok().json(serde_json::json!({ "providers": providers }))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct OidcAuthorizeRequest {
+    /// Where the provider should send the user back to; defaults to the web
+    /// callback. Must be registered with the provider.
+    pub redirect_uri: Option<String>,
+}
+
+pub async fn oidc_authorize(
+    path: web::Path<String>,
+    redis: web::Data<RedisClient>,
+    config: web::Data<Config>,
+    providers: web::Data<OidcProviders>,
+    body: Option<web::Json<OidcAuthorizeRequest>>,
+) -> Result<HttpResponse, AppError> {
+    let provider = path.into_inner();
+    let redirect_uri = body
+        .and_then(|body| body.into_inner().redirect_uri)
+        .unwrap_or_else(|| default_redirect_uri(&config, &provider));
+
+    let request = begin_oidc_login(&providers, &redis, &provider, &redirect_uri).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "authorization_url": request.url,
+        "state": request.state
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcTokenRequest {
+    pub code: String,
+    pub state: String,
+}
+
+/// Exchange the code and state from the provider's redirect for tokens, as
+/// `login` does for a password
+pub async fn oidc_token(
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    config: web::Data<Config>,
+    providers: web::Data<OidcProviders>,
+    meta: RequestMeta,
+    body: web::Json<OidcTokenRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user = finish_oidc_login(&pool, &redis, &providers, &meta, &path, &body.code, &body.state).await?;
+    login_response(&pool, &redis, &config, &meta, user, &format!("oidc:{}", path)).await
+}
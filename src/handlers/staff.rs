@@ -0,0 +1,577 @@
+use actix_web::{web, HttpResponse};
+use chrono::{Duration, NaiveDate, Utc};
+use uuid::Uuid;
+
+use crate::audit::{AuditEvent, RequestMeta};
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::booking::{find_booking, load_prep_sheet};
+use crate::middleware::auth::{AuthUser, Identity, RequireRole};
+use crate::middleware::roles::{role, Permission};
+use crate::models::{
+    AssignHelper, AssignedEvent, Booking, BookingAddOn, BookingGuestSelection, CreateHelper, Helper,
+    HelperAvailability, PrepSheet, StaffAssignment, StaffShift, UpdateHelper, User,
+};
+use crate::policy::{acting_chef, authorize, ChefScope, Resource};
+use crate::pricing::{self, BookingQuote};
+
+/// Longest shift a helper can be booked for
+const MAX_SHIFT_HOURS: f64 = 24.0;
+
+/// The chef's roster: active helpers first, then by name
+pub async fn list_helpers(pool: &DbPool, chef_id: Uuid) -> Result<Vec<Helper>, AppError> {
+    let helpers = sqlx::query_as::<_, Helper>(
+        "SELECT * FROM chef_helpers WHERE chef_id = $1 ORDER BY is_active DESC, name ASC"
+    )
+    .bind(chef_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(helpers)
+}
+
+fn helper_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::ValidationError("Please enter the helper's name".to_string()));
+    }
+    Ok(name.to_string())
+}
+
+/// Lowercased, or `None` if left blank
+fn helper_email(email: Option<&str>) -> Result<Option<String>, AppError> {
+    match email.map(str::trim).filter(|email| !email.is_empty()) {
+        Some(email) if !email.contains('@') => {
+            Err(AppError::ValidationError("Please enter a valid email address".to_string()))
+        }
+        email => Ok(email.map(str::to_lowercase)),
+    }
+}
+
+fn hourly_rate(rate: f64) -> Result<f64, AppError> {
+    if !rate.is_finite() || rate < 0.0 {
+        return Err(AppError::ValidationError("The hourly rate can't be negative".to_string()));
+    }
+    Ok(rate)
+}
+
+fn blank_to_none(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+pub async fn add_helper(pool: &DbPool, chef_id: Uuid, data: &CreateHelper) -> Result<Helper, AppError> {
+    let helper = sqlx::query_as::<_, Helper>(
+        r#"
+        INSERT INTO chef_helpers (chef_id, name, email, phone, role, hourly_rate)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#
+    )
+    .bind(chef_id)
+    .bind(helper_name(&data.name)?)
+    .bind(helper_email(data.email.as_deref())?)
+    .bind(blank_to_none(data.phone.as_deref()))
+    .bind(data.role.to_string())
+    .bind(hourly_rate(data.hourly_rate)?)
+    .fetch_one(pool)
+    .await?;
+
+    tracing::info!(helper_id = %helper.id, chef_id = %chef_id, "Helper added to roster");
+    Ok(helper)
+}
+
+/// Applies the given changes. Rate changes only affect later assignments.
+pub async fn update_helper(pool: &DbPool, helper_id: Uuid, data: &UpdateHelper) -> Result<Helper, AppError> {
+    let name = data.name.as_deref().map(helper_name).transpose()?;
+    let rate = data.hourly_rate.map(hourly_rate).transpose()?;
+    // An empty email or phone clears it
+    let email = helper_email(data.email.as_deref())?;
+
+    let helper = sqlx::query_as::<_, Helper>(
+        r#"
+        UPDATE chef_helpers SET
+            name = COALESCE($2, name),
+            email = CASE WHEN $3 THEN $4 ELSE email END,
+            phone = CASE WHEN $5 THEN $6 ELSE phone END,
+            role = COALESCE($7, role),
+            hourly_rate = COALESCE($8, hourly_rate),
+            is_active = COALESCE($9, is_active),
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#
+    )
+    .bind(helper_id)
+    .bind(name)
+    .bind(data.email.is_some())
+    .bind(email)
+    .bind(data.phone.is_some())
+    .bind(blank_to_none(data.phone.as_deref()))
+    .bind(data.role.map(|role| role.to_string()))
+    .bind(rate)
+    .bind(data.is_active)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Helper not found".to_string()))?;
+    Ok(helper)
+}
+
+/// Deletes a helper from the roster. Their past assignments keep their name
+/// and rate; upcoming ones have to be removed first.
+pub async fn delete_helper(pool: &DbPool, helper_id: Uuid) -> Result<Helper, AppError> {
+    let upcoming: Option<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT b.id FROM booking_staff s
+        INNER JOIN bookings b ON b.id = s.booking_id
+        WHERE s.helper_id = $1 AND b.status IN ('pending', 'confirmed') AND b.event_date >= $2
+        LIMIT 1
+        "#
+    )
+    .bind(helper_id)
+    .bind(Utc::now().date_naive())
+    .fetch_optional(pool)
+    .await?;
+    if upcoming.is_some() {
+        return Err(AppError::ValidationError(
+            "This helper is assigned to upcoming events. Remove them from those first, or mark them inactive."
+                .to_string(),
+        ));
+    }
+
+    sqlx::query_as::<_, Helper>("DELETE FROM chef_helpers WHERE id = $1 RETURNING *")
+        .bind(helper_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Helper not found".to_string()))
+}
+
+/// The helpers assigned to a booking, in the order they were added
+pub async fn booking_staff(pool: &DbPool, booking_id: Uuid) -> Result<Vec<StaffAssignment>, AppError> {
+    let staff = sqlx::query_as::<_, StaffAssignment>(
+        "SELECT * FROM booking_staff WHERE booking_id = $1 ORDER BY created_at ASC"
+    )
+    .bind(booking_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(staff)
+}
+
+/// The price breakdown of a booking, including its staff
+pub async fn booking_quote(pool: &DbPool, booking: &Booking) -> Result<BookingQuote, AppError> {
+    let selections = sqlx::query_as::<_, BookingGuestSelection>(
+        "SELECT * FROM booking_guest_selections WHERE booking_id = $1"
+    )
+    .bind(booking.id)
+    .fetch_all(pool)
+    .await?;
+    let add_ons = sqlx::query_as::<_, BookingAddOn>("SELECT * FROM booking_add_ons WHERE booking_id = $1")
+        .bind(booking.id)
+        .fetch_all(pool)
+        .await?;
+    let staff = booking_staff(pool, booking.id).await?;
+    Ok(pricing::booked_quote(booking.total_price, &selections, &add_ons, &staff))
+}
+
+/// The shift a helper works at `booking`
+fn shift(booking: &Booking, hours: f64) -> StaffShift {
+    StaffShift {
+        booking_id: booking.id,
+        event_date: booking.event_date,
+        event_time: booking.event_time,
+        hours,
+    }
+}
+
+/// Shifts `helper_ids` work at other active bookings around the booking's
+/// date, by helper. A day either side catches shifts that cross midnight.
+async fn other_shifts(
+    conn: &mut sqlx::PgConnection,
+    helper_ids: &[Uuid],
+    booking: &Booking,
+) -> Result<Vec<(Uuid, StaffShift)>, AppError> {
+    let rows: Vec<(Uuid, Uuid, NaiveDate, chrono::NaiveTime, f64)> = sqlx::query_as(
+        r#"
+        SELECT s.helper_id, b.id, b.event_date, b.event_time, s.hours
+        FROM booking_staff s
+        INNER JOIN bookings b ON b.id = s.booking_id
+        WHERE s.helper_id = ANY($1)
+        AND b.id <> $2
+        AND b.status IN ('pending', 'confirmed')
+        AND b.event_date BETWEEN $3 AND $4
+        "#
+    )
+    .bind(helper_ids)
+    .bind(booking.id)
+    .bind(booking.event_date - Duration::days(1))
+    .bind(booking.event_date + Duration::days(1))
+    .fetch_all(conn)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(helper_id, booking_id, event_date, event_time, hours)| {
+            (helper_id, StaffShift { booking_id, event_date, event_time, hours })
+        })
+        .collect())
+}
+
+/// The chef's active helpers and whether each is free to work `booking`
+/// for its whole duration
+pub async fn helper_availability(
+    pool: &DbPool,
+    booking: &Booking,
+    assigned: &[StaffAssignment],
+) -> Result<Vec<HelperAvailability>, AppError> {
+    let helpers: Vec<Helper> = list_helpers(pool, booking.chef_id)
+        .await?
+        .into_iter()
+        .filter(|helper| helper.is_active)
+        .collect();
+    let ids: Vec<Uuid> = helpers.iter().map(|helper| helper.id).collect();
+    let shifts = other_shifts(&mut *pool.acquire().await?, &ids, booking).await?;
+    let wanted = shift(booking, booking.duration_hours);
+
+    Ok(helpers
+        .into_iter()
+        .map(|helper| HelperAvailability {
+            assigned: assigned.iter().any(|a| a.helper_id == Some(helper.id)),
+            conflict: shifts
+                .iter()
+                .find(|(helper_id, shift)| *helper_id == helper.id && shift.overlaps(&wanted))
+                .map(|(_, shift)| shift.clone()),
+            helper,
+        })
+        .collect())
+}
+
+/// Staff can change while the booking is active, upcoming and unpaid, as
+/// their cost is part of its price
+pub fn check_staffable(booking: &Booking) -> Result<(), AppError> {
+    if !booking.booking_status().is_active() {
+        return Err(AppError::ValidationError(format!(
+            "Staff can't be changed on a {} booking",
+            booking.status
+        )));
+    }
+    if booking.event_date < Utc::now().date_naive() {
+        return Err(AppError::ValidationError("This event has already taken place".to_string()));
+    }
+    if booking.payment_status != "pending" {
+        return Err(AppError::ValidationError(
+            "This booking has been paid, so its staff can't change".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Assigns a roster helper to `booking` and adds their cost to its price.
+/// Callers check that the actor may manage the booking.
+pub async fn assign_helper(
+    pool: &DbPool,
+    actor: &Identity,
+    meta: &RequestMeta,
+    booking: &Booking,
+    data: &AssignHelper,
+) -> Result<StaffAssignment, AppError> {
+    check_staffable(booking)?;
+
+    // Locking the helper makes concurrent assignments of them wait here, so
+    // each sees the other's shift when checking for clashes
+    let mut tx = pool.begin().await?;
+    let helper = sqlx::query_as::<_, Helper>("SELECT * FROM chef_helpers WHERE id = $1 AND chef_id = $2 FOR UPDATE")
+        .bind(data.helper_id)
+        .bind(booking.chef_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Helper not found".to_string()))?;
+    if !helper.is_active {
+        return Err(AppError::ValidationError(format!("{} is marked inactive", helper.name)));
+    }
+
+    let hours = data.hours.unwrap_or(booking.duration_hours);
+    if !(hours > 0.0 && hours <= MAX_SHIFT_HOURS) {
+        return Err(AppError::ValidationError(format!(
+            "Hours must be more than 0 and at most {}",
+            MAX_SHIFT_HOURS
+        )));
+    }
+
+    let wanted = shift(booking, hours);
+    let clash = other_shifts(&mut tx, &[helper.id], booking)
+        .await?
+        .into_iter()
+        .find(|(_, shift)| shift.overlaps(&wanted));
+    if let Some((_, clash)) = clash {
+        return Err(AppError::ValidationError(format!(
+            "{} is already working an event on {} at {}",
+            helper.name,
+            clash.event_date.format("%b %-d"),
+            clash.event_time.format("%H:%M")
+        )));
+    }
+
+    let cost = pricing::staff_cost(helper.hourly_rate, hours);
+    let assignment = sqlx::query_as::<_, StaffAssignment>(
+        r#"
+        INSERT INTO booking_staff (booking_id, helper_id, name, role, hourly_rate, hours, total_cost)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (booking_id, helper_id) DO NOTHING
+        RETURNING *
+        "#
+    )
+    .bind(booking.id)
+    .bind(helper.id)
+    .bind(&helper.name)
+    .bind(&helper.role)
+    .bind(helper.hourly_rate)
+    .bind(hours)
+    .bind(cost)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::ValidationError(format!("{} is already on this booking", helper.name)))?;
+    let total_price = reprice(&mut tx, booking.id, cost).await?;
+    tx.commit().await?;
+
+    tracing::info!(booking_id = %booking.id, helper_id = %helper.id, "Helper assigned to booking");
+    AuditEvent::new("booking_staff_assigned")
+        .by(actor)
+        .on("booking", booking.id)
+        .before(serde_json::json!({ "total_price": booking.total_price }))
+        .after(serde_json::json!({ "total_price": total_price }))
+        .detail("helper_id", helper.id)
+        .detail("hours", hours)
+        .detail("total_cost", cost)
+        .from(meta)
+        .record(pool)
+        .await;
+
+    Ok(assignment)
+}
+
+/// Takes a helper off `booking` and their cost off its price
+pub async fn unassign_helper(
+    pool: &DbPool,
+    actor: &Identity,
+    meta: &RequestMeta,
+    booking: &Booking,
+    assignment_id: Uuid,
+) -> Result<StaffAssignment, AppError> {
+    check_staffable(booking)?;
+
+    let mut tx = pool.begin().await?;
+    let assignment = sqlx::query_as::<_, StaffAssignment>(
+        "DELETE FROM booking_staff WHERE id = $1 AND booking_id = $2 RETURNING *"
+    )
+    .bind(assignment_id)
+    .bind(booking.id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Staff assignment not found".to_string()))?;
+    let total_price = reprice(&mut tx, booking.id, -assignment.total_cost).await?;
+    tx.commit().await?;
+
+    AuditEvent::new("booking_staff_removed")
+        .by(actor)
+        .on("booking", booking.id)
+        .before(serde_json::json!({ "total_price": booking.total_price }))
+        .after(serde_json::json!({ "total_price": total_price }))
+        .detail("helper_id", assignment.helper_id)
+        .detail("total_cost", assignment.total_cost)
+        .from(meta)
+        .record(pool)
+        .await;
+
+    Ok(assignment)
+}
+
+/// Adds `change` to the booking's price, unless it was paid in the meantime
+async fn reprice(tx: &mut sqlx::PgConnection, booking_id: Uuid, change: f64) -> Result<f64, AppError> {
+    let (total_price,): (f64,) = sqlx::query_as(
+        r#"
+        UPDATE bookings
+        SET total_price = total_price + $2, updated_at = NOW()
+        WHERE id = $1 AND payment_status = 'pending'
+        RETURNING total_price
+        "#
+    )
+    .bind(booking_id)
+    .bind(change)
+    .fetch_optional(tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::ValidationError("The booking was changed in the meantime; please reload and try again".to_string())
+    })?;
+    Ok(total_price)
+}
+
+/// Upcoming events `user` is assigned to, through roster entries with their
+/// email. Only verified addresses count, so nobody sees an event by signing
+/// up with someone else's.
+pub async fn assigned_events(pool: &DbPool, user: &User) -> Result<Vec<AssignedEvent>, AppError> {
+    if !user.email_verified {
+        return Ok(Vec::new());
+    }
+    let events = sqlx::query_as::<_, AssignedEvent>(
+        r#"
+        SELECT b.id AS booking_id, c.chef_name, m.name AS menu_name, b.customer_name,
+               b.event_date, b.event_time, b.duration_hours, b.number_of_guests,
+               b.location_address, b.special_requests, b.status, s.role, s.hours
+        FROM booking_staff s
+        INNER JOIN chef_helpers h ON h.id = s.helper_id
+        INNER JOIN bookings b ON b.id = s.booking_id
+        INNER JOIN chefs c ON c.id = b.chef_id
+        LEFT JOIN menus m ON m.id = b.menu_id
+        WHERE h.email = LOWER($1)
+        AND b.status IN ('pending', 'confirmed')
+        AND b.event_date >= $2
+        ORDER BY b.event_date ASC, b.event_time ASC
+        "#
+    )
+    .bind(user.email.trim())
+    .bind(Utc::now().date_naive())
+    .fetch_all(pool)
+    .await?;
+    Ok(events)
+}
+
+/// One of the user's assigned events, with who else is working it and what
+/// is being served
+pub struct EventDetail {
+    pub event: AssignedEvent,
+    pub staff: Vec<StaffAssignment>,
+    pub prep_sheet: PrepSheet,
+}
+
+pub async fn assigned_event(pool: &web::Data<DbPool>, user: &User, booking_id: Uuid) -> Result<EventDetail, AppError> {
+    let event = assigned_events(pool, user)
+        .await?
+        .into_iter()
+        .find(|event| event.booking_id == booking_id)
+        .ok_or_else(|| AppError::NotFound("Event not found".to_string()))?;
+    let booking = find_booking(pool, booking_id).await?;
+    Ok(EventDetail {
+        event,
+        staff: booking_staff(pool, booking_id).await?,
+        prep_sheet: load_prep_sheet(pool, &booking).await?,
+    })
+}
+
+/// Staff as shown to helpers: names and roles, without rates
+fn staff_names(staff: &[StaffAssignment]) -> Vec<serde_json::Value> {
+    staff
+        .iter()
+        .map(|s| serde_json::json!({ "name": s.name, "role": s.role }))
+        .collect()
+}
+
+/// The roster of the signed-in user's team, or of `?chef_id=` (admins)
+pub async fn get_helpers(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    scope: web::Query<ChefScope>,
+) -> Result<HttpResponse, AppError> {
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnStaff, scope.chef_id).await?;
+    Ok(HttpResponse::Ok().json(list_helpers(&pool, chef_id).await?))
+}
+
+pub async fn create_helper(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    scope: web::Query<ChefScope>,
+    data: web::Json<CreateHelper>,
+) -> Result<HttpResponse, AppError> {
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnStaff, scope.chef_id).await?;
+    Ok(HttpResponse::Created().json(add_helper(&pool, chef_id, &data).await?))
+}
+
+pub async fn update_helper_details(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    helper_id: web::Path<Uuid>,
+    data: web::Json<UpdateHelper>,
+) -> Result<HttpResponse, AppError> {
+    authorize(&pool, &user, Permission::ManageOwnStaff, Resource::Helper(*helper_id)).await?;
+    Ok(HttpResponse::Ok().json(update_helper(&pool, *helper_id, &data).await?))
+}
+
+pub async fn remove_helper(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    helper_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    authorize(&pool, &user, Permission::ManageOwnStaff, Resource::Helper(*helper_id)).await?;
+    delete_helper(&pool, *helper_id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// A booking's staff, the roster's availability for it and its price
+pub async fn get_booking_staff(
+    identity: Identity,
+    pool: web::Data<DbPool>,
+    booking_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    authorize(&pool, &identity, Permission::ManageOwnBookings, Resource::Booking(*booking_id)).await?;
+    let booking = find_booking(&pool, *booking_id).await?;
+    let staff = booking_staff(&pool, booking.id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "helpers": helper_availability(&pool, &booking, &staff).await?,
+        "staff": staff,
+        "quote": booking_quote(&pool, &booking).await?,
+    })))
+}
+
+/// Puts a helper from the roster on a booking. Like the roster itself, this
+/// is for owners, managers and admins.
+pub async fn assign_booking_staff(
+    user: RequireRole<role::Chef>,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    booking_id: web::Path<Uuid>,
+    data: web::Json<AssignHelper>,
+) -> Result<HttpResponse, AppError> {
+    authorize(&pool, &user, Permission::ManageOwnStaff, Resource::Booking(*booking_id)).await?;
+    let booking = find_booking(&pool, *booking_id).await?;
+    let assignment = assign_helper(&pool, &user, &meta, &booking, &data).await?;
+    let booking = find_booking(&pool, booking.id).await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "assignment": assignment,
+        "quote": booking_quote(&pool, &booking).await?,
+    })))
+}
+
+pub async fn remove_booking_staff(
+    user: RequireRole<role::Chef>,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (booking_id, assignment_id) = path.into_inner();
+    authorize(&pool, &user, Permission::ManageOwnStaff, Resource::Booking(booking_id)).await?;
+    let booking = find_booking(&pool, booking_id).await?;
+    unassign_helper(&pool, &user, &meta, &booking, assignment_id).await?;
+    let booking = find_booking(&pool, booking.id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "quote": booking_quote(&pool, &booking).await?,
+    })))
+}
+
+/// Upcoming events the signed-in user is on the staff of
+pub async fn get_my_events(user: AuthUser, pool: web::Data<DbPool>) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(assigned_events(&pool, &user).await?))
+}
+
+/// One of the signed-in user's events, read-only
+pub async fn get_my_event(
+    user: AuthUser,
+    pool: web::Data<DbPool>,
+    booking_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let detail = assigned_event(&pool, &user, *booking_id).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "event": detail.event,
+        "staff": staff_names(&detail.staff),
+        "prep_sheet": detail.prep_sheet,
+    })))
+}
@@ -0,0 +1,257 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use askama::Template;
+use uuid::Uuid;
+
+use crate::audit::RequestMeta;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::chef_dashboard::current_chef;
+use crate::handlers::staff::{
+    add_helper, assign_helper, assigned_event, booking_quote, booking_staff, check_staffable, delete_helper,
+    helper_availability, list_helpers, unassign_helper, update_helper,
+};
+use crate::middleware::auth::{AuthUser, RequireRole};
+use crate::middleware::flash::{redirect_with_flash, FlashMessage};
+use crate::middleware::roles::{role, Permission};
+use crate::models::form::FormData;
+use crate::models::{AssignHelper, BookingSummary, Chef, CreateHelper, HelperRole, Role, UpdateHelper, UserResponse};
+use crate::policy::membership;
+use crate::templates::{AssignedEventTemplate, BookingStaffTemplate, ChefStaffTemplate, PageContext};
+
+const STAFF_PAGE: &str = "/chef-dashboard/staff";
+
+fn render(template: impl Template) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok()
+        .content_type("text/html")
+        .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
+}
+
+/// Flash the outcome of a staff action and go back to `path`
+fn finish(req: &HttpRequest, path: &str, result: Result<String, AppError>) -> Result<HttpResponse, AppError> {
+    match result {
+        Ok(message) => Ok(redirect_with_flash(req, path, FlashMessage::success(message))),
+        Err(AppError::NotFound(message) | AppError::ValidationError(message)) => {
+            Ok(redirect_with_flash(req, path, FlashMessage::error(message)))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether `helper_id` is on the chef's roster
+async fn is_on_roster(pool: &DbPool, chef: &Chef, helper_id: Uuid) -> Result<bool, AppError> {
+    let found: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM chef_helpers WHERE id = $1 AND chef_id = $2")
+        .bind(helper_id)
+        .bind(chef.id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(found.is_some())
+}
+
+async fn team_booking(pool: &DbPool, chef: &Chef, booking_id: Uuid) -> Result<Option<BookingSummary>, AppError> {
+    let booking = sqlx::query_as::<_, BookingSummary>(
+        r#"
+        SELECT b.*, c.chef_name, c.slug AS chef_slug, m.name AS menu_name
+        FROM bookings b
+        JOIN chefs c ON c.id = b.chef_id
+        LEFT JOIN menus m ON m.id = b.menu_id
+        WHERE b.id = $1 AND b.chef_id = $2
+        "#
+    )
+    .bind(booking_id)
+    .bind(chef.id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(booking)
+}
+
+/// The team's roster of helpers
+pub async fn chef_staff_page(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, AppError> {
+    let (user, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnStaff).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+
+    render(ChefStaffTemplate {
+        ctx: PageContext::new(&req, Some(user)),
+        helpers: list_helpers(&pool, chef.id).await?,
+        chef,
+        roles: HelperRole::ALL,
+    })
+}
+
+pub async fn handle_add_helper(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    form: web::Form<FormData>,
+) -> Result<HttpResponse, AppError> {
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnStaff).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let result = match CreateHelper::from_form(&form) {
+        Ok(data) => add_helper(&pool, chef.id, &data)
+            .await
+            .map(|helper| format!("{} added to your roster", helper.name)),
+        Err(message) => Err(AppError::ValidationError(message)),
+    };
+    finish(&req, STAFF_PAGE, result)
+}
+
+pub async fn handle_update_helper(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    helper_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+) -> Result<HttpResponse, AppError> {
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnStaff).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let result = match UpdateHelper::from_form(&form) {
+        Ok(_) if !is_on_roster(&pool, &chef, *helper_id).await? => {
+            Err(AppError::NotFound("Helper not found".to_string()))
+        }
+        Ok(data) => update_helper(&pool, *helper_id, &data)
+            .await
+            .map(|helper| format!("{} updated", helper.name)),
+        Err(message) => Err(AppError::ValidationError(message)),
+    };
+    finish(&req, STAFF_PAGE, result)
+}
+
+pub async fn handle_delete_helper(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    helper_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnStaff).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let result = if is_on_roster(&pool, &chef, *helper_id).await? {
+        delete_helper(&pool, *helper_id)
+            .await
+            .map(|helper| format!("{} deleted from your roster", helper.name))
+    } else {
+        Err(AppError::NotFound("Helper not found".to_string()))
+    };
+    finish(&req, STAFF_PAGE, result)
+}
+
+/// Who is working a booking, who else could, and what it costs
+pub async fn booking_staff_page(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    booking_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let (user, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnBookings).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let booking = match team_booking(&pool, &chef, *booking_id).await? {
+        Some(booking) => booking,
+        None => return Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::error("Booking not found"))),
+    };
+
+    // Team staff see who is working, but only those who keep the roster
+    // change it
+    let manages_staff = user.role == Role::Admin
+        || membership(&pool, user.id).await?.is_some_and(|m| m.role.allows(Permission::ManageOwnStaff));
+
+    let staff = booking_staff(&pool, booking.booking.id).await?;
+    render(BookingStaffTemplate {
+        ctx: PageContext::new(&req, Some(user)),
+        helpers: helper_availability(&pool, &booking.booking, &staff).await?,
+        quote: booking_quote(&pool, &booking.booking).await?,
+        can_change: manages_staff && check_staffable(&booking.booking).is_ok(),
+        staff,
+        booking,
+    })
+}
+
+pub async fn handle_assign_staff(
+    identity: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    booking_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+) -> Result<HttpResponse, AppError> {
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnStaff).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let booking = match team_booking(&pool, &chef, *booking_id).await? {
+        Some(booking) => booking.booking,
+        None => return Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::error("Booking not found"))),
+    };
+    let meta = RequestMeta::from_request(&req);
+
+    let result = match AssignHelper::from_form(&form) {
+        Ok(data) => assign_helper(&pool, &identity, &meta, &booking, &data)
+            .await
+            .map(|assignment| format!("{} assigned for {} hours", assignment.name, assignment.hours)),
+        Err(message) => Err(AppError::ValidationError(message)),
+    };
+    finish(&req, &format!("/chef-dashboard/bookings/{}/staff", booking.id), result)
+}
+
+pub async fn handle_remove_staff(
+    identity: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (booking_id, assignment_id) = path.into_inner();
+    let (_, chef) = match current_chef(&req, &pool, user, Permission::ManageOwnStaff).await? {
+        Ok(found) => found,
+        Err(redirect) => return Ok(redirect),
+    };
+    let booking = match team_booking(&pool, &chef, booking_id).await? {
+        Some(booking) => booking.booking,
+        None => return Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::error("Booking not found"))),
+    };
+
+    let result = unassign_helper(&pool, &identity, &meta, &booking, assignment_id)
+        .await
+        .map(|assignment| format!("{} removed from this event", assignment.name));
+    finish(&req, &format!("/chef-dashboard/bookings/{}/staff", booking.id), result)
+}
+
+/// A helper's read-only view of an event they're assigned to
+pub async fn assigned_event_page(
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    booking_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let detail = match assigned_event(&pool, &user, *booking_id).await {
+        Ok(detail) => detail,
+        Err(AppError::NotFound(message)) => {
+            return Ok(redirect_with_flash(&req, "/dashboard", FlashMessage::error(message)))
+        }
+        Err(e) => return Err(e),
+    };
+
+    render(AssignedEventTemplate {
+        ctx: PageContext::new(&req, Some(UserResponse::from(user))),
+        event: detail.event,
+        staff: detail.staff,
+        prep_sheet: detail.prep_sheet,
+    })
+}
@@ -0,0 +1,409 @@
+use actix_web::{web, HttpResponse};
+use bcrypt::verify;
+use qrcode::render::svg;
+use qrcode::QrCode;
+use serde::Deserialize;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use crate::audit::{AuditEvent, RequestMeta};
+use crate::cache::RedisClient;
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::middleware::auth::{AuthUser, NotImpersonating};
+use crate::models::User;
+use crate::utils::{generate_secret_token, hash_token};
+
+/// Seconds each code is valid for, the authenticator app default
+const TOTP_STEP_SECONDS: u64 = 30;
+/// Codes from one step either side are accepted, for clock drift
+const TOTP_SKEW_STEPS: u64 = 1;
+/// Recovery codes issued at a time; issuing new ones replaces the old
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Wrong codes allowed per login before the password has to be entered again
+const MAX_CHALLENGE_ATTEMPTS: u32 = 5;
+
+/// What an authenticator app needs to start generating codes
+#[derive(Debug, Clone)]
+pub struct TwoFactorSetup {
+    /// Base32 secret, for entering by hand
+    pub secret: String,
+    /// `otpauth://` URI, normally scanned as a QR code
+    pub provisioning_uri: String,
+}
+
+impl TwoFactorSetup {
+    /// The provisioning URI as an SVG QR code
+    pub fn qr_code_svg(&self) -> Result<String, AppError> {
+        let code = QrCode::new(self.provisioning_uri.as_bytes())
+            .map_err(|e| AppError::InternalError(format!("Failed to build QR code: {}", e)))?;
+        Ok(code.render::<svg::Color>().min_dimensions(200, 200).build())
+    }
+}
+
+fn totp(config: &Config, user: &User, secret: &str) -> Result<TOTP, AppError> {
+    let bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::InternalError(format!("Invalid TOTP secret: {:?}", e)))?;
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        TOTP_SKEW_STEPS as u8,
+        TOTP_STEP_SECONDS,
+        bytes,
+        Some(config.two_factor.issuer.clone()),
+        user.email.clone(),
+    )
+    .map_err(|e| AppError::InternalError(format!("Failed to set up TOTP: {:?}", e)))
+}
+
+/// Provisioning details for the secret stored on `user` during enrollment
+pub fn two_factor_setup(config: &Config, user: &User) -> Result<TwoFactorSetup, AppError> {
+    let secret = user
+        .totp_secret
+        .as_deref()
+        .ok_or_else(|| AppError::ValidationError("Two-factor setup hasn't been started".to_string()))?;
+    Ok(TwoFactorSetup {
+        secret: secret.to_string(),
+        provisioning_uri: totp(config, user, secret)?.get_url(),
+    })
+}
+
+/// Give `user` a new TOTP secret to add to their authenticator app. It only
+/// protects logins once confirmed with [`enable_two_factor`].
+pub async fn begin_two_factor_setup(pool: &DbPool, config: &Config, user: &User) -> Result<TwoFactorSetup, AppError> {
+    if user.totp_enabled {
+        return Err(AppError::ValidationError("Two-factor authentication is already enabled".to_string()));
+    }
+
+    let secret = match Secret::generate_secret().to_encoded() {
+        Secret::Encoded(secret) => secret,
+        Secret::Raw(_) => unreachable!("to_encoded always returns an encoded secret"),
+    };
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET totp_secret = $2, totp_last_step = NULL, updated_at = NOW() WHERE id = $1 RETURNING *"
+    )
+    .bind(user.id)
+    .bind(&secret)
+    .fetch_one(pool)
+    .await?;
+
+    two_factor_setup(config, &user)
+}
+
+/// Turn on 2FA once the user has shown their app produces valid codes.
+/// Returns the recovery codes, which are only ever shown this once.
+pub async fn enable_two_factor(
+    pool: &DbPool,
+    redis: &RedisClient,
+    config: &Config,
+    user: &User,
+    meta: &RequestMeta,
+    code: &str,
+) -> Result<Vec<String>, AppError> {
+    if user.totp_enabled {
+        return Err(AppError::ValidationError("Two-factor authentication is already enabled".to_string()));
+    }
+    if !verify_totp_code(pool, config, user, code).await? {
+        return Err(AppError::ValidationError(
+            "That code didn't match. Check your authenticator app and try again.".to_string(),
+        ));
+    }
+
+    sqlx::query("UPDATE users SET totp_enabled = TRUE, updated_at = NOW() WHERE id = $1")
+        .bind(user.id)
+        .execute(pool)
+        .await?;
+    let codes = replace_recovery_codes(pool, user.id).await?;
+    forget_auth_state(redis, user.id).await;
+
+    AuditEvent::new("two_factor_enabled")
+        .by_user(user.id, user.role)
+        .on("user", user.id)
+        .before(serde_json::json!({ "two_factor_enabled": false }))
+        .after(serde_json::json!({ "two_factor_enabled": true }))
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(codes)
+}
+
+/// Turn off 2FA, after checking the password and a current code. Not
+/// allowed for roles that must use it.
+pub async fn disable_two_factor(
+    pool: &DbPool,
+    redis: &RedisClient,
+    config: &Config,
+    user: &User,
+    meta: &RequestMeta,
+    password: &str,
+    code: &str,
+) -> Result<(), AppError> {
+    if !user.totp_enabled {
+        return Err(AppError::ValidationError("Two-factor authentication isn't enabled".to_string()));
+    }
+    if config.two_factor.requires(user.role) {
+        return Err(AppError::Forbidden(
+            "Two-factor authentication is required for your account and can't be turned off".to_string(),
+        ));
+    }
+    // Accounts from an OIDC provider may not have a password to check
+    if let Some(hash) = &user.password_hash {
+        if !verify(password, hash)? {
+            return Err(AppError::ValidationError("Password is incorrect".to_string()));
+        }
+    }
+    if !verify_second_factor(pool, config, user, meta, code).await? {
+        return Err(AppError::ValidationError("Invalid authentication code".to_string()));
+    }
+
+    sqlx::query(
+        "UPDATE users SET totp_enabled = FALSE, totp_secret = NULL, totp_last_step = NULL, updated_at = NOW() WHERE id = $1"
+    )
+    .bind(user.id)
+    .execute(pool)
+    .await?;
+    sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+        .bind(user.id)
+        .execute(pool)
+        .await?;
+    forget_auth_state(redis, user.id).await;
+
+    AuditEvent::new("two_factor_disabled")
+        .by_user(user.id, user.role)
+        .on("user", user.id)
+        .before(serde_json::json!({ "two_factor_enabled": true }))
+        .after(serde_json::json!({ "two_factor_enabled": false }))
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(())
+}
+
+/// Replace the user's recovery codes, after checking a current code
+pub async fn regenerate_recovery_codes(
+    pool: &DbPool,
+    config: &Config,
+    user: &User,
+    meta: &RequestMeta,
+    code: &str,
+) -> Result<Vec<String>, AppError> {
+    if !user.totp_enabled {
+        return Err(AppError::ValidationError("Two-factor authentication isn't enabled".to_string()));
+    }
+    if !verify_second_factor(pool, config, user, meta, code).await? {
+        return Err(AppError::ValidationError("Invalid authentication code".to_string()));
+    }
+    let codes = replace_recovery_codes(pool, user.id).await?;
+    AuditEvent::new("recovery_codes_regenerated")
+        .by_user(user.id, user.role)
+        .on("user", user.id)
+        .from(meta)
+        .record(pool)
+        .await;
+    Ok(codes)
+}
+
+/// Second login step: check `code` for the user behind a challenge issued
+/// after their password was accepted. Too many wrong codes cancel the
+/// challenge, so the password has to be entered again.
+pub async fn complete_two_factor_login(
+    pool: &DbPool,
+    redis: &RedisClient,
+    config: &Config,
+    meta: &RequestMeta,
+    challenge: &str,
+    code: &str,
+) -> Result<User, AppError> {
+    let expired = || AppError::Unauthorized("Your login has expired. Please sign in again.".to_string());
+    let user_id = redis.get_two_factor_challenge(challenge).await?.ok_or_else(expired)?;
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(expired)?;
+
+    if verify_second_factor(pool, config, &user, meta, code).await? {
+        redis.clear_two_factor_challenge(challenge).await?;
+        return Ok(user);
+    }
+
+    let attempts = redis.record_two_factor_attempt(challenge).await?;
+    tracing::warn!(user_id = %user.id, attempts, "Invalid two-factor code");
+    if attempts >= MAX_CHALLENGE_ATTEMPTS {
+        redis.clear_two_factor_challenge(challenge).await?;
+        AuditEvent::new("two_factor_challenge_failed")
+            .by_user(user.id, user.role)
+            .on("user", user.id)
+            .detail("attempts", attempts)
+            .from(meta)
+            .record(pool)
+            .await;
+        return Err(expired());
+    }
+    Err(AppError::Unauthorized("Invalid authentication code".to_string()))
+}
+
+/// Accepts a current TOTP code or an unused recovery code
+pub async fn verify_second_factor(
+    pool: &DbPool,
+    config: &Config,
+    user: &User,
+    meta: &RequestMeta,
+    code: &str,
+) -> Result<bool, AppError> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace() && *c != '-').collect();
+    if code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()) {
+        return verify_totp_code(pool, config, user, &code).await;
+    }
+    use_recovery_code(pool, user, meta, &code).await
+}
+
+/// Checks a TOTP code against the user's secret. The matching time step is
+/// recorded, and a step at or before the last one used is refused, so each
+/// code works once.
+async fn verify_totp_code(pool: &DbPool, config: &Config, user: &User, code: &str) -> Result<bool, AppError> {
+    let Some(secret) = user.totp_secret.as_deref() else {
+        return Ok(false);
+    };
+    let totp = totp(config, user, secret)?;
+    let code = code.trim();
+    let current_step = chrono::Utc::now().timestamp() as u64 / TOTP_STEP_SECONDS;
+
+    let matched = (current_step.saturating_sub(TOTP_SKEW_STEPS)..=current_step + TOTP_SKEW_STEPS)
+        .find(|step| totp.generate(step * TOTP_STEP_SECONDS) == code);
+    let Some(step) = matched else {
+        return Ok(false);
+    };
+
+    let result = sqlx::query(
+        "UPDATE users SET totp_last_step = $2 WHERE id = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)"
+    )
+    .bind(user.id)
+    .bind(step as i64)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+async fn use_recovery_code(pool: &DbPool, user: &User, meta: &RequestMeta, code: &str) -> Result<bool, AppError> {
+    let used = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE user_recovery_codes
+        SET used_at = NOW()
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        RETURNING id
+        "#
+    )
+    .bind(user.id)
+    .bind(hash_token(&code.to_lowercase()))
+    .fetch_optional(pool)
+    .await?;
+
+    if used.is_some() {
+        AuditEvent::new("recovery_code_used")
+            .by_user(user.id, user.role)
+            .on("user", user.id)
+            .from(meta)
+            .record(pool)
+            .await;
+    }
+    Ok(used.is_some())
+}
+
+/// New recovery codes as shown to the user, e.g. `3f9a1-c07be`
+async fn replace_recovery_codes(pool: &DbPool, user_id: Uuid) -> Result<Vec<String>, AppError> {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let token = generate_secret_token();
+            format!("{}-{}", &token[..5], &token[5..10])
+        })
+        .collect();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    for code in &codes {
+        sqlx::query("INSERT INTO user_recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(hash_token(&code.replace('-', "")))
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(codes)
+}
+
+/// The cached auth state carries whether 2FA is on, which `RequireRole` checks
+async fn forget_auth_state(redis: &RedisClient, user_id: Uuid) {
+    if let Err(e) = redis.invalidate_user_auth(user_id).await {
+        tracing::warn!(user_id = %user_id, error = ?e, "Failed to invalidate cached auth state");
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisableTwoFactorRequest {
+    pub password: String,
+    pub code: String,
+}
+
+/// Start enrollment: returns the secret and provisioning URI for the app
+pub async fn setup_two_factor(
+    _: NotImpersonating,
+    user: AuthUser,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, AppError> {
+    let setup = begin_two_factor_setup(&pool, &config, &user).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "secret": setup.secret,
+        "provisioning_uri": setup.provisioning_uri,
+    })))
+}
+
+/// Finish enrollment with a code from the app
+pub async fn confirm_two_factor(
+    _: NotImpersonating,
+    user: AuthUser,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    config: web::Data<Config>,
+    body: web::Json<TwoFactorCodeRequest>,
+) -> Result<HttpResponse, AppError> {
+    let recovery_codes = enable_two_factor(&pool, &redis, &config, &user, &meta, &body.code).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "recovery_codes": recovery_codes })))
+}
+
+pub async fn turn_off_two_factor(
+    _: NotImpersonating,
+    user: AuthUser,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    config: web::Data<Config>,
+    body: web::Json<DisableTwoFactorRequest>,
+) -> Result<HttpResponse, AppError> {
+    disable_two_factor(&pool, &redis, &config, &user, &meta, &body.password, &body.code).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn new_recovery_codes(
+    _: NotImpersonating,
+    user: AuthUser,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    body: web::Json<TwoFactorCodeRequest>,
+) -> Result<HttpResponse, AppError> {
+    let recovery_codes = regenerate_recovery_codes(&pool, &config, &user, &meta, &body.code).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "recovery_codes": recovery_codes })))
+}
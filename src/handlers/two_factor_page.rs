@@ -0,0 +1,158 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use askama::Template;
+
+use crate::audit::RequestMeta;
+use crate::cache::RedisClient;
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::handlers::two_factor::{
+    begin_two_factor_setup, disable_two_factor, enable_two_factor, regenerate_recovery_codes, two_factor_setup,
+    TwoFactorSetup,
+};
+use crate::middleware::auth::{AuthUser, NotImpersonating};
+use crate::middleware::flash::{redirect_with_flash, FlashMessage};
+use crate::models::{User, UserResponse};
+use crate::templates::{PageContext, TwoFactorSetupView, TwoFactorTemplate};
+
+type Form = web::Form<std::collections::HashMap<String, String>>;
+
+/// What the settings page shows besides the current status
+#[derive(Default)]
+struct PageState {
+    setup: Option<TwoFactorSetup>,
+    recovery_codes: Vec<String>,
+    error: Option<String>,
+}
+
+fn render(req: &HttpRequest, config: &Config, user: &User, state: PageState) -> Result<HttpResponse, AppError> {
+    let setup = match state.setup {
+        Some(setup) => Some(TwoFactorSetupView {
+            qr_code_svg: setup.qr_code_svg()?,
+            secret: setup.secret,
+        }),
+        None => None,
+    };
+    let template = TwoFactorTemplate {
+        ctx: PageContext::new(req, Some(UserResponse::from(user.clone()))),
+        enabled: user.totp_enabled,
+        required: config.two_factor.requires(user.role),
+        has_password: user.password_hash.is_some(),
+        setup,
+        recovery_codes: state.recovery_codes,
+        error: state.error,
+    };
+    Ok(HttpResponse::Ok()
+        .content_type("text/html")
+        .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
+}
+
+fn form_field<'a>(form: &'a Form, name: &str) -> &'a str {
+    form.get(name).map(String::as_str).unwrap_or_default()
+}
+
+/// Two-factor settings. Takes any signed-in user, so accounts that must
+/// enable 2FA can reach it before `RequireRole` lets them in elsewhere.
+pub async fn two_factor_page(
+    user: AuthUser,
+    req: HttpRequest,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, AppError> {
+    render(&req, &config, &user, PageState::default())
+}
+
+/// Generate a secret and show the QR code for it
+pub async fn handle_two_factor_setup(
+    _: NotImpersonating,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, AppError> {
+    match begin_two_factor_setup(&pool, &config, &user).await {
+        Ok(setup) => render(&req, &config, &user, PageState { setup: Some(setup), ..Default::default() }),
+        Err(AppError::ValidationError(message)) => {
+            Ok(redirect_with_flash(&req, "/dashboard/two-factor", FlashMessage::error(message)))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Confirm the first code and show the recovery codes
+pub async fn handle_enable_two_factor(
+    _: NotImpersonating,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    config: web::Data<Config>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    match enable_two_factor(&pool, &redis, &config, &user, &meta, form_field(&form, "code")).await {
+        Ok(recovery_codes) => {
+            let mut user = user.0;
+            user.totp_enabled = true;
+            render(&req, &config, &user, PageState { recovery_codes, ..Default::default() })
+        }
+        Err(AppError::ValidationError(message)) if user.totp_secret.is_some() => {
+            let setup = two_factor_setup(&config, &user)?;
+            render(&req, &config, &user, PageState { setup: Some(setup), error: Some(message), ..Default::default() })
+        }
+        Err(AppError::ValidationError(message)) => {
+            Ok(redirect_with_flash(&req, "/dashboard/two-factor", FlashMessage::error(message)))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn handle_disable_two_factor(
+    _: NotImpersonating,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    config: web::Data<Config>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    let meta = RequestMeta::from_request(&req);
+    let result = disable_two_factor(
+        &pool,
+        &redis,
+        &config,
+        &user,
+        &meta,
+        form_field(&form, "password"),
+        form_field(&form, "code"),
+    )
+    .await;
+    match result {
+        Ok(()) => Ok(redirect_with_flash(
+            &req,
+            "/dashboard/two-factor",
+            FlashMessage::success("Two-factor authentication is turned off"),
+        )),
+        Err(AppError::ValidationError(message)) | Err(AppError::Forbidden(message)) => {
+            Ok(redirect_with_flash(&req, "/dashboard/two-factor", FlashMessage::error(message)))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn handle_regenerate_recovery_codes(
+    _: NotImpersonating,
+    user: AuthUser,
+    req: HttpRequest,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    form: Form,
+) -> Result<HttpResponse, AppError> {
+    match regenerate_recovery_codes(&pool, &config, &user, &meta, form_field(&form, "code")).await {
+        Ok(recovery_codes) => render(&req, &config, &user, PageState { recovery_codes, ..Default::default() }),
+        Err(AppError::ValidationError(message)) => {
+            Ok(redirect_with_flash(&req, "/dashboard/two-factor", FlashMessage::error(message)))
+        }
+        Err(e) => Err(e),
+    }
+}
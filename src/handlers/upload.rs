@@ -0,0 +1,314 @@
+use actix_multipart::Multipart;
+use actix_web::{web, HttpResponse};
+use futures_util::StreamExt;
+use uuid::Uuid;
+
+use crate::audit::{AuditEvent, RequestMeta};
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::images::{process_image, ImageKind, ImageVariant};
+use crate::middleware::auth::RequireRole;
+use crate::middleware::roles::{role, Permission};
+use crate::policy::{acting_chef, authorize, ChefScope, Resource};
+use crate::models::{UploadedImage, UploadedImageVariant};
+use crate::storage::BlobStore;
+
+/// Multipart field names accepted for the image
+const FILE_FIELDS: [&str; 2] = ["file", "image"];
+
+/// Reads the image field of a multipart upload, refusing bodies over `max_bytes`
+/// without buffering the rest of the stream.
+async fn read_image_field(mut payload: Multipart, max_bytes: usize) -> Result<Vec<u8>, AppError> {
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(|e| AppError::ValidationError(format!("Invalid multipart body: {}", e)))?;
+
+        let name = field.content_disposition().get_name().unwrap_or_default().to_string();
+        if !FILE_FIELDS.contains(&name.as_str()) {
+            // Drain fields we don't use
+            while let Some(chunk) = field.next().await {
+                chunk.map_err(|e| AppError::ValidationError(format!("Invalid multipart body: {}", e)))?;
+            }
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| AppError::ValidationError(format!("Invalid multipart body: {}", e)))?;
+            if bytes.len() + chunk.len() > max_bytes {
+                return Err(AppError::ValidationError(format!(
+                    "Image is too large; the limit is {} bytes",
+                    max_bytes
+                )));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        return Ok(bytes);
+    }
+
+    Err(AppError::ValidationError("Missing 'file' field in upload".to_string()))
+}
+
+/// Validates and resizes an upload off the async runtime, then stores every
+/// variant under `prefix/{upload_id}/`.
+async fn process_and_store(
+    blob_store: &web::Data<dyn BlobStore>,
+    config: &web::Data<Config>,
+    payload: Multipart,
+    kind: ImageKind,
+    prefix: String,
+) -> Result<UploadedImage, AppError> {
+    let max_bytes = config.storage.max_upload_bytes;
+    let bytes = read_image_field(payload, max_bytes).await?;
+
+    let variants: Vec<ImageVariant> = web::block(move || process_image(&bytes, kind, max_bytes))
+        .await
+        .map_err(|e| AppError::InternalError(format!("Image processing failed: {}", e)))??;
+
+    let folder = format!("{}/{}", prefix, Uuid::new_v4());
+    let stored = store_variants(blob_store.get_ref(), &folder, variants).await?;
+
+    // Variants are rendered smallest first, so the last non-WebP one is the largest
+    let url = stored
+        .iter()
+        .rev()
+        .find(|v| v.content_type != "image/webp")
+        .map(|v| v.url.clone())
+        .ok_or_else(|| AppError::InternalError("No image variants were produced".to_string()))?;
+
+    Ok(UploadedImage { url, variants: stored })
+}
+
+/// Stores each variant as `{folder}/{width}.{extension}`. If one can't be
+/// stored, the variants already written are deleted again.
+pub async fn store_variants(
+    blob_store: &dyn BlobStore,
+    folder: &str,
+    variants: Vec<ImageVariant>,
+) -> Result<Vec<UploadedImageVariant>, AppError> {
+    let mut keys = Vec::with_capacity(variants.len());
+    let mut stored = Vec::with_capacity(variants.len());
+    for variant in variants {
+        let key = format!("{}/{}.{}", folder, variant.width, variant.extension);
+        let url = match blob_store.put(&key, variant.data, variant.content_type).await {
+            Ok(url) => url,
+            Err(e) => {
+                remove_files(blob_store, &keys).await;
+                return Err(e);
+            }
+        };
+        keys.push(key);
+        stored.push(UploadedImageVariant {
+            url,
+            width: variant.width,
+            height: variant.height,
+            content_type: variant.content_type.to_string(),
+        });
+    }
+    Ok(stored)
+}
+
+async fn remove_files(blob_store: &dyn BlobStore, keys: &[String]) {
+    for key in keys {
+        if let Err(e) = blob_store.delete(key).await {
+            tracing::warn!(key = %key, error = ?e, "Failed to remove image file");
+        }
+    }
+}
+
+/// Keys of every variant stored with an image, given the key of its largest
+/// non-WebP variant (`{folder}/{width}.{extension}`). Smaller variants were
+/// rendered at each target width, capped at the largest one's width.
+pub fn variant_keys(kind: ImageKind, largest_key: &str) -> Option<Vec<String>> {
+    let (folder, file) = largest_key.rsplit_once('/')?;
+    let (width, extension) = file.split_once('.')?;
+    let largest: u32 = width.parse().ok()?;
+
+    let mut keys = Vec::new();
+    for &target in kind.widths() {
+        for extension in [extension, "webp"] {
+            let key = format!("{}/{}.{}", folder, target.min(largest), extension);
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    Some(keys)
+}
+
+/// Deletes the stored files of an image that has just been replaced.
+///
+/// Image URLs can also be typed into the profile form and are copied when a
+/// menu is duplicated, so only files under `prefix` are touched, and nothing
+/// is deleted while a chef profile or menu item still shows the image.
+/// Failures are logged; the new image is already in place.
+async fn remove_replaced_image(
+    pool: &DbPool,
+    blob_store: &dyn BlobStore,
+    kind: ImageKind,
+    prefix: &str,
+    previous: Option<String>,
+) {
+    let Some(previous) = previous else { return };
+    let Some(key) = blob_store.key_of(&previous) else { return };
+    if !key.starts_with(&format!("{}/", prefix)) {
+        return;
+    }
+
+    let in_use: Result<(bool,), _> = sqlx::query_as(
+        "SELECT EXISTS (
+            SELECT 1 FROM chefs WHERE profile_image_url = $1 OR cover_image_url = $1
+            UNION ALL
+            SELECT 1 FROM menu_items WHERE image_url = $1
+        )"
+    )
+    .bind(&previous)
+    .fetch_one(pool)
+    .await;
+    match in_use {
+        Ok((false,)) => {}
+        Ok((true,)) => return,
+        Err(e) => {
+            tracing::warn!(url = %previous, error = ?e, "Failed to check whether a replaced image is in use");
+            return;
+        }
+    }
+
+    remove_files(blob_store, &variant_keys(kind, &key).unwrap_or_default()).await;
+}
+
+/// Uploads the authenticated chef's profile photo.
+///
+/// Expects a multipart body with the image in a `file` field. The photo is
+/// cropped square, resized and stored; the largest variant becomes the
+/// chef's `profile_image_url`. Admins pass `?chef_id=` to upload for a chef.
+///
+/// # Returns
+/// * `HttpResponse::Ok` with the UploadedImage
+/// * `AppError::ValidationError` if the file is missing, too large or not a supported image
+pub async fn upload_chef_profile_image(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    blob_store: web::Data<dyn BlobStore>,
+    scope: web::Query<ChefScope>,
+    meta: RequestMeta,
+    payload: Multipart,
+) -> Result<HttpResponse, AppError> {
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnChefProfile, scope.chef_id).await?;
+    let audit = AuditEvent::new("chef_profile_updated").by(&user).from(&meta);
+    upload_chef_image(pool, config, blob_store, chef_id, audit, payload, ImageKind::ChefProfile).await
+}
+
+/// Uploads the authenticated chef's cover image; the largest variant becomes
+/// the chef's `cover_image_url`.
+pub async fn upload_chef_cover_image(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    blob_store: web::Data<dyn BlobStore>,
+    scope: web::Query<ChefScope>,
+    meta: RequestMeta,
+    payload: Multipart,
+) -> Result<HttpResponse, AppError> {
+    let chef_id = acting_chef(&pool, &user, Permission::ManageOwnChefProfile, scope.chef_id).await?;
+    let audit = AuditEvent::new("chef_profile_updated").by(&user).from(&meta);
+    upload_chef_image(pool, config, blob_store, chef_id, audit, payload, ImageKind::ChefCover).await
+}
+
+/// Stores a chef image and records `audit`, the event for the change,
+/// once the profile points at it
+async fn upload_chef_image(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    blob_store: web::Data<dyn BlobStore>,
+    chef_id: Uuid,
+    audit: AuditEvent,
+    payload: Multipart,
+    kind: ImageKind,
+) -> Result<HttpResponse, AppError> {
+
+    let (folder, column) = match kind {
+        ImageKind::ChefCover => ("cover", "cover_image_url"),
+        _ => ("profile", "profile_image_url"),
+    };
+
+    let prefix = format!("chefs/{}/{}", chef_id, folder);
+    let image = process_and_store(&blob_store, &config, payload, kind, prefix.clone()).await?;
+
+    let (previous,) = sqlx::query_as::<_, (Option<String>,)>(&format!("SELECT {} FROM chefs WHERE id = $1", column))
+        .bind(chef_id)
+        .fetch_one(pool.get_ref())
+        .await?;
+    sqlx::query(&format!(
+        "UPDATE chefs SET {} = $1, updated_at = NOW() WHERE id = $2",
+        column
+    ))
+    .bind(&image.url)
+    .bind(chef_id)
+    .execute(pool.get_ref())
+    .await?;
+    audit
+        .on("chef", chef_id)
+        .before(serde_json::json!({ column: previous }))
+        .after(serde_json::json!({ column: image.url }))
+        .record(&pool)
+        .await;
+    remove_replaced_image(&pool, blob_store.get_ref(), kind, &prefix, previous).await;
+
+    tracing::info!(
+        chef_id = %chef_id,
+        kind = folder,
+        variants = image.variants.len(),
+        "Chef image uploaded successfully"
+    );
+
+    Ok(HttpResponse::Ok().json(image))
+}
+
+/// Uploads a photo for a menu item; the largest variant becomes the item's
+/// `image_url`.
+///
+/// # Authorization
+/// Only the chef who owns the menu can upload item images.
+pub async fn upload_menu_item_image(
+    user: RequireRole<role::Chef>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    blob_store: web::Data<dyn BlobStore>,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: Multipart,
+) -> Result<HttpResponse, AppError> {
+    let (menu_id, item_id) = path.into_inner();
+    authorize(&pool, &user, Permission::ManageOwnMenus, Resource::Menu(menu_id)).await?;
+
+    let existing: Option<(Option<String>,)> =
+        sqlx::query_as("SELECT image_url FROM menu_items WHERE id = $1 AND menu_id = $2")
+            .bind(item_id)
+            .bind(menu_id)
+            .fetch_optional(pool.get_ref())
+            .await?;
+    let Some((previous,)) = existing else {
+        return Err(AppError::NotFound("Menu item not found".to_string()));
+    };
+
+    let prefix = format!("menus/{}/items/{}", menu_id, item_id);
+    let image = process_and_store(&blob_store, &config, payload, ImageKind::MenuItem, prefix.clone()).await?;
+
+    sqlx::query("UPDATE menu_items SET image_url = $1, updated_at = NOW() WHERE id = $2 AND menu_id = $3")
+        .bind(&image.url)
+        .bind(item_id)
+        .bind(menu_id)
+        .execute(pool.get_ref())
+        .await?;
+    remove_replaced_image(&pool, blob_store.get_ref(), ImageKind::MenuItem, &prefix, previous).await;
+
+    tracing::info!(
+        menu_id = %menu_id,
+        item_id = %item_id,
+        variants = image.variants.len(),
+        "Menu item image uploaded successfully"
+    );
+
+    Ok(HttpResponse::Ok().json(image))
+}
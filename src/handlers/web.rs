@@ -4,25 +4,46 @@ use crate::db::DbPool;
 use crate::config::Config;
 use crate::cache::RedisClient;
 use crate::cache::session::SessionData;
-use crate::templates::{HomeTemplate, LoginTemplate, DashboardTemplate, ChefDashboardTemplate};
-use crate::models::{UserResponse, User};
-use crate::middleware::auth::extract_user_id_from_session;
+use crate::cache::TWO_FACTOR_CHALLENGE_TTL_SECONDS;
+use crate::templates::{
+    ChefDashboardTemplate, DashboardTemplate, ForgotPasswordTemplate, HomeTemplate, LoginTemplate, OidcProviderLink,
+    PageContext, ResetPasswordTemplate, TwoFactorLoginTemplate,
+};
+use crate::handlers::two_factor::complete_two_factor_login;
+use crate::handlers::oidc::{begin_oidc_login, default_redirect_uri, finish_oidc_login};
+use crate::oidc::OidcProviders;
+use crate::audit::{AuditEvent, RequestMeta};
+use crate::handlers::auth::{
+    authenticate, record_login, record_registration, reset_password, send_password_reset, send_verification_email,
+    verify_email,
+};
+use crate::handlers::booking::{change_booking_status, find_booking};
+use crate::handlers::impersonation::end_impersonation_session;
+use crate::handlers::staff::assigned_events;
+use crate::mail::Mailer;
+use crate::models::{BookingCalendar, BookingStatus, BookingSummary, BookingTab, UserResponse, User};
+use crate::middleware::auth::{AuthUser, Identity, RequireRole};
+use crate::middleware::roles::{role, Permission};
+use crate::policy::{authorize, membership, Resource};
+use crate::middleware::csrf::generate_csrf_token;
+use crate::middleware::flash::{redirect_with_flash, FlashMessage};
 use crate::errors::AppError;
 use actix_web::cookie::{Cookie, SameSite, time::Duration as CookieDuration};
-use bcrypt::verify;
 use uuid::Uuid;
+use chrono::Utc;
+
+/// Cookie holding the pending two-factor challenge between the login steps
+const TWO_FACTOR_COOKIE: &str = "two_factor_challenge";
+/// Cookie holding the `state` of an OIDC login until the provider's callback
+const OIDC_STATE_COOKIE: &str = "oidc_state";
 use slug::slugify;
 
 /// Serve home page
 pub async fn home(
     req: HttpRequest,
-    pool: web::Data<DbPool>,
-    redis: web::Data<RedisClient>,
+    user: Option<AuthUser>,
 ) -> Result<HttpResponse, AppError> {
-    // Try to get user from session if available
-    let user = get_user_from_request(&req, &pool, &redis).await.ok();
-    
-    let template = HomeTemplate { user };
+    let template = HomeTemplate { ctx: PageContext::new(&req, user.map(UserResponse::from)) };
     Ok(HttpResponse::Ok()
         .content_type("text/html")
         .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
@@ -31,18 +52,23 @@ pub async fn home(
 /// Serve login page
 pub async fn login_page(
     req: HttpRequest,
-    pool: web::Data<DbPool>,
-    redis: web::Data<RedisClient>,
+    user: Option<AuthUser>,
+    config: web::Data<Config>,
     query: web::Query<std::collections::HashMap<String, String>>,
 ) -> Result<HttpResponse, AppError> {
     let is_register = query.get("register").map(|s| s == "true").unwrap_or(false);
-    let user = get_user_from_request(&req, &pool, &redis).await.ok();
-    
+
     let template = LoginTemplate {
-        user,
+        ctx: PageContext::new(&req, user.map(UserResponse::from)),
         is_register,
         error: None,
         loading: false,
+        oidc_providers: config
+            .oidc
+            .providers
+            .iter()
+            .map(|p| OidcProviderLink { name: p.name.clone(), display_name: p.display_name.clone() })
+            .collect(),
     };
     Ok(HttpResponse::Ok()
         .content_type("text/html")
@@ -51,95 +77,110 @@ pub async fn login_page(
 
 /// Serve dashboard page
 pub async fn dashboard_page(
+    user: AuthUser,
     req: HttpRequest,
     pool: web::Data<DbPool>,
-    redis: web::Data<RedisClient>,
 ) -> Result<HttpResponse, AppError> {
-    // Log all cookies for debugging
-    tracing::info!("Dashboard request - all cookies: {:?}", req.cookies());
-    tracing::info!("Dashboard request - session_id cookie: {:?}", req.cookie("session_id"));
-    
-    let user_id = match extract_user_id_from_session(&req, &redis).await {
-        Ok(id) => {
-            tracing::info!("Successfully extracted user_id: {}", id);
-            id
-        }
-        Err(e) => {
-            tracing::warn!("Failed to extract user from session: {:?}", e);
-            tracing::warn!("Redirecting to login page");
-            // Redirect to login instead of returning 401
-            return Ok(HttpResponse::SeeOther()
-                .append_header(("Location", "/login"))
-                .finish());
-        }
-    };
-    
-    let user = sqlx::query_as::<_, crate::models::User>(
-        "SELECT * FROM users WHERE id = $1"
+    let user_id = user.id;
+    let bookings = sqlx::query_as::<_, BookingSummary>(
+        r#"
+        SELECT b.*, c.chef_name, c.slug AS chef_slug, m.name AS menu_name
+        FROM bookings b
+        JOIN chefs c ON c.id = b.chef_id
+        LEFT JOIN menus m ON m.id = b.menu_id
+        WHERE b.customer_id = $1
+        ORDER BY b.event_date, b.event_time
+        "#
     )
     .bind(user_id)
-    .fetch_optional(pool.get_ref())
-    .await?
-    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    // Upcoming requests and confirmed events first, everything else is history
+    let today = Utc::now().date_naive();
+    let (upcoming_bookings, mut past_bookings): (Vec<_>, Vec<_>) = bookings
+        .into_iter()
+        .partition(|b| b.booking.booking_status().is_active() && b.booking.event_date >= today);
+    past_bookings.reverse();
+    let assigned_events = assigned_events(&pool, &user).await?;
 
     let template = DashboardTemplate {
-        user: Some(UserResponse::from(user)),
+        ctx: PageContext::new(&req, Some(UserResponse::from(user))),
+        upcoming_bookings,
+        past_bookings,
+        assigned_events,
+        today,
     };
     Ok(HttpResponse::Ok()
         .content_type("text/html")
         .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
 }
 
-/// Serve chef dashboard page
-pub async fn chef_dashboard_page(
+/// Handle a diner cancelling one of their own bookings from the dashboard
+pub async fn handle_cancel_diner_booking(
+    user: Identity,
     req: HttpRequest,
+    meta: RequestMeta,
     pool: web::Data<DbPool>,
-    redis: web::Data<RedisClient>,
+    booking_id: web::Path<Uuid>,
 ) -> Result<HttpResponse, AppError> {
-    // Log all cookies for debugging
-    tracing::info!("Chef dashboard request - all cookies: {:?}", req.cookies());
-    tracing::info!("Chef dashboard request - session_id cookie: {:?}", req.cookie("session_id"));
-    
-    // Extract user ID from session, redirect to login if not authenticated
-    let user_id = match extract_user_id_from_session(&req, &redis).await {
-        Ok(id) => {
-            tracing::info!("Chef dashboard - Successfully extracted user_id: {}", id);
-            id
-        }
-        Err(e) => {
-            tracing::warn!("Chef dashboard - Failed to extract user from session: {:?}", e);
-            tracing::warn!("Redirecting to login page");
-            return Ok(HttpResponse::SeeOther()
-                .append_header(("Location", "/login"))
-                .finish());
-        }
-    };
-    
-    // Get user and check role
-    let user = sqlx::query_as::<_, crate::models::User>(
-        "SELECT * FROM users WHERE id = $1"
-    )
-    .bind(user_id)
-    .fetch_optional(pool.get_ref())
-    .await?
-    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
-    
-    // Check if user has chef or admin role
-    if user.role != crate::models::Role::Chef && user.role != crate::models::Role::Admin {
-        tracing::warn!("Chef dashboard - Access denied for user {} with role: {:?}", user_id, user.role);
-        return Ok(HttpResponse::SeeOther()
-            .append_header(("Location", "/login"))
-            .finish());
-    }
+    let user_id = user.id;
 
-    // Get chef profile if exists
-    let chef = sqlx::query_as::<_, crate::models::Chef>(
-        "SELECT * FROM chefs WHERE user_id = $1"
+    let booking = sqlx::query_as::<_, BookingSummary>(
+        r#"
+        SELECT b.*, c.chef_name, c.slug AS chef_slug, m.name AS menu_name
+        FROM bookings b
+        JOIN chefs c ON c.id = b.chef_id
+        LEFT JOIN menus m ON m.id = b.menu_id
+        WHERE b.id = $1 AND b.customer_id = $2
+        "#
     )
+    .bind(*booking_id)
     .bind(user_id)
     .fetch_optional(pool.get_ref())
     .await?;
 
+    let booking = match booking {
+        Some(booking) => booking,
+        None => return Ok(redirect_with_flash(&req, "/dashboard", FlashMessage::error("Booking not found"))),
+    };
+
+    if !booking.can_cancel(Utc::now().date_naive()) {
+        return Ok(redirect_with_flash(
+            &req,
+            "/dashboard",
+            FlashMessage::error("This booking can no longer be cancelled online. Please contact the chef."),
+        ));
+    }
+
+    match change_booking_status(&pool, &user, &meta, &booking.booking, BookingStatus::Cancelled).await {
+        Ok(_) => Ok(redirect_with_flash(
+            &req,
+            "/dashboard",
+            FlashMessage::success(format!("Your booking with {} has been cancelled", booking.chef_name)),
+        )),
+        Err(AppError::ValidationError(message)) => Ok(redirect_with_flash(&req, "/dashboard", FlashMessage::error(message))),
+        Err(e) => Err(e),
+    }
+}
+
+/// Serve chef dashboard page
+pub async fn chef_dashboard_page(
+    _chef: RequireRole<role::Chef>,
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, AppError> {
+    // Get the profile of the user's team, if they're on one
+    let member = membership(&pool, user.id).await?;
+    let chef = match member {
+        Some(member) => sqlx::query_as::<_, crate::models::Chef>("SELECT * FROM chefs WHERE id = $1")
+            .bind(member.chef_id)
+            .fetch_optional(pool.get_ref())
+            .await?,
+        None => None,
+    };
+
     // Get menus if chef exists
     let menus = if let Some(ref chef) = chef {
         sqlx::query_as::<_, crate::models::Menu>(
@@ -167,99 +208,290 @@ pub async fn chef_dashboard_page(
         });
     }
 
-    // Get error/success messages from query params
-    let query = web::Query::<std::collections::HashMap<String, String>>::from_query(req.query_string()).ok();
-    let error = query.as_ref().and_then(|q| q.get("error")).map(|s| {
-        urlencoding::decode(s).map(|decoded| decoded.into_owned()).unwrap_or_else(|_| s.clone())
-    });
-    let success = query.as_ref().and_then(|q| q.get("success")).map(|s| {
-        urlencoding::decode(s).map(|decoded| decoded.into_owned()).unwrap_or_else(|_| s.clone())
-    });
+    // Booking inbox and month calendar
+    let params = query_params(&req);
+    let today = Utc::now().date_naive();
+    let booking_tab = BookingTab::from_query(params.get("tab").map(String::as_str));
+    let all_bookings = match chef {
+        Some(ref chef) => sqlx::query_as::<_, BookingSummary>(
+            r#"
+            SELECT b.*, c.chef_name, c.slug AS chef_slug, m.name AS menu_name
+            FROM bookings b
+            JOIN chefs c ON c.id = b.chef_id
+            LEFT JOIN menus m ON m.id = b.menu_id
+            WHERE b.chef_id = $1
+            "#
+        )
+        .bind(chef.id)
+        .fetch_all(pool.get_ref())
+        .await?,
+        None => Vec::new(),
+    };
+    let month = params
+        .get("month")
+        .and_then(|m| BookingCalendar::parse_month(m))
+        .unwrap_or(today);
+    let calendar = chef
+        .as_ref()
+        .map(|_| BookingCalendar::build(month, &all_bookings, today));
+    let pending_count = all_bookings
+        .iter()
+        .filter(|b| BookingTab::Pending.contains(b, today))
+        .count();
 
     let template = ChefDashboardTemplate {
-        user: Some(UserResponse::from(user)),
+        ctx: PageContext::new(&req, Some(UserResponse::from(user))),
         chef,
         menus_with_items,
-        error,
-        success,
+        booking_tab,
+        bookings: booking_tab.select(&all_bookings, today),
+        pending_count,
+        calendar,
+        today,
+        team_role: member.map(|member| member.role),
     };
     Ok(HttpResponse::Ok()
         .content_type("text/html")
         .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
 }
 
-/// Helper to get user from request (from session)
-async fn get_user_from_request(
-    req: &HttpRequest,
-    pool: &web::Data<DbPool>,
-    redis: &web::Data<RedisClient>,
-) -> Result<UserResponse, AppError> {
-    // Try to get session ID from cookie
-    if let Some(cookie) = req.cookie("session_id") {
-        let session_id = cookie.value();
-        
-        // Get session data from Redis
-        if let Some(session_data) = redis.get_session_data(session_id).await? {
-            // Fetch user from database
-            let user = sqlx::query_as::<_, User>(
-                "SELECT * FROM users WHERE id = $1"
-            )
-            .bind(session_data.user_id)
-            .fetch_optional(pool.get_ref())
-            .await?
-            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
-            
-            Ok(UserResponse::from(user))
-        } else {
-            Err(AppError::Unauthorized("Invalid or expired session".to_string()))
+/// Handle confirm/decline/complete actions from the chef booking inbox.
+/// Status changes go through the same transition rules as the bookings API.
+pub async fn handle_chef_booking_action(
+    user: RequireRole<role::Chef>,
+    req: HttpRequest,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    path: web::Path<(Uuid, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (booking_id, action) = path.into_inner();
+
+    let (next, tab) = match action.as_str() {
+        "confirm" => (BookingStatus::Confirmed, BookingTab::Pending),
+        "decline" => (BookingStatus::Declined, BookingTab::Pending),
+        "complete" => (BookingStatus::Completed, BookingTab::Past),
+        _ => return Err(AppError::NotFound("Unknown booking action".to_string())),
+    };
+    let return_path = format!("/chef-dashboard?tab={}", tab.slug());
+
+    // The same ownership rules as the bookings API
+    let booking = match authorize(&pool, &user, Permission::ManageOwnBookings, Resource::Booking(booking_id)).await {
+        Ok(_) => find_booking(&pool, booking_id).await?,
+        Err(AppError::NotFound(_) | AppError::Forbidden(_)) => {
+            return Ok(redirect_with_flash(&req, &return_path, FlashMessage::error("Booking not found")))
         }
-    } else {
-        Err(AppError::Unauthorized("Not authenticated".to_string()))
+        Err(e) => return Err(e),
+    };
+
+    match change_booking_status(&pool, &user, &meta, &booking, next).await {
+        Ok(updated) => {
+            let message = format!(
+                "Booking for {} on {} is now {}",
+                updated.customer_name,
+                updated.event_date.format("%b %-d"),
+                updated.status
+            );
+            Ok(redirect_with_flash(&req, &return_path, FlashMessage::success(message)))
+        }
+        Err(AppError::ValidationError(message)) => Ok(redirect_with_flash(&req, &return_path, FlashMessage::error(message))),
+        Err(e) => Err(e),
     }
 }
 
+/// Query string parameters, decoded
+pub(crate) fn query_params(req: &HttpRequest) -> std::collections::HashMap<String, String> {
+    web::Query::<std::collections::HashMap<String, String>>::from_query(req.query_string())
+        .map(|q| q.into_inner())
+        .unwrap_or_default()
+}
+
 /// Handle login form submission
 pub async fn handle_login(
     req: HttpRequest,
+    meta: RequestMeta,
     pool: web::Data<DbPool>,
     redis: web::Data<RedisClient>,
     form: web::Form<std::collections::HashMap<String, String>>,
 ) -> Result<HttpResponse, AppError> {
-    tracing::info!("Login attempt received");
-    
-    let email = form.get("email")
-        .ok_or_else(|| AppError::ValidationError("Email is required".to_string()))?;
-    let password = form.get("password")
-        .ok_or_else(|| AppError::ValidationError("Password is required".to_string()))?;
-
-    tracing::info!("Attempting login for email: {}", email);
+    let (email, password) = match (form.get("email"), form.get("password")) {
+        (Some(email), Some(password)) if !email.is_empty() && !password.is_empty() => (email, password),
+        _ => return Ok(redirect_with_flash(&req, "/login", FlashMessage::error("Email and password are required"))),
+    };
 
-    // Find user
-    let user = match sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE email = $1"
-    )
-    .bind(email)
-    .fetch_optional(pool.get_ref())
-    .await? {
-        Some(u) => {
-            tracing::info!("User found: {}", u.id);
-            u
+    let user = match authenticate(&pool, &redis, &meta, email, password).await {
+        Ok(user) => user,
+        Err(AppError::Unauthorized(_)) => {
+            return Ok(redirect_with_flash(&req, "/login", FlashMessage::error("Invalid email or password")));
         }
-        None => {
-            tracing::warn!("User not found for email: {}", email);
-            return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+        Err(AppError::TooManyRequests(message)) | Err(AppError::Forbidden(message)) => {
+            return Ok(redirect_with_flash(&req, "/login", FlashMessage::error(message)));
         }
+        Err(e) => return Err(e),
     };
 
-    // Verify password
-    tracing::debug!("Verifying password");
-    if !verify(password, &user.password_hash)? {
-        tracing::warn!("Password verification failed for user: {}", user.id);
-        return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+    complete_login(&pool, &redis, &meta, &user, "password").await
+}
+
+/// Sign in a user whose first factor checked out: straight into a session,
+/// or on to the code step if they have 2FA on. `method` is recorded with the
+/// login, as in [`login_response`](crate::handlers::auth::login_response).
+async fn complete_login(
+    pool: &DbPool,
+    redis: &RedisClient,
+    meta: &RequestMeta,
+    user: &User,
+    method: &str,
+) -> Result<HttpResponse, AppError> {
+    if user.totp_enabled {
+        let challenge = redis.create_two_factor_challenge(user.id).await?;
+        let cookie = Cookie::build(TWO_FACTOR_COOKIE, challenge)
+            .path("/login")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(CookieDuration::seconds(TWO_FACTOR_CHALLENGE_TTL_SECONDS as i64))
+            .finish();
+        return Ok(HttpResponse::SeeOther()
+            .cookie(cookie)
+            .append_header(("Location", "/login/two-factor"))
+            .finish());
     }
-    
-    tracing::info!("Password verified successfully for user: {}", user.id);
 
+    let response = start_session(redis, user).await?;
+    record_login(pool, user, method, meta).await;
+    Ok(response)
+}
+
+/// Send the browser to an OIDC provider to sign in
+pub async fn oidc_login(
+    req: HttpRequest,
+    path: web::Path<String>,
+    redis: web::Data<RedisClient>,
+    config: web::Data<Config>,
+    providers: web::Data<OidcProviders>,
+) -> Result<HttpResponse, AppError> {
+    let provider = path.into_inner();
+    let redirect_uri = default_redirect_uri(&config, &provider);
+    let request = match begin_oidc_login(&providers, &redis, &provider, &redirect_uri).await {
+        Ok(request) => request,
+        Err(AppError::NotFound(message)) => return Ok(redirect_with_flash(&req, "/login", FlashMessage::error(message))),
+        Err(e) => return Err(e),
+    };
+
+    // Ties the callback to this browser, so a login started elsewhere can't
+    // be finished here
+    let cookie = Cookie::build(OIDC_STATE_COOKIE, request.state)
+        .path("/auth/oidc")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(CookieDuration::minutes(10))
+        .finish();
+    Ok(HttpResponse::SeeOther()
+        .cookie(cookie)
+        .append_header(("Location", request.url))
+        .finish())
+}
+
+/// Where the provider sends the browser back to after signing in
+pub async fn oidc_callback(
+    req: HttpRequest,
+    path: web::Path<String>,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    providers: web::Data<OidcProviders>,
+    meta: RequestMeta,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<HttpResponse, AppError> {
+    let failed = |message: &str| Ok(redirect_with_flash(&req, "/login", FlashMessage::error(message)));
+    if let Some(error) = query.get("error") {
+        tracing::info!(provider = %path, error = %error, "OIDC login cancelled or refused");
+        return failed("Sign-in was cancelled");
+    }
+
+    let state = query.get("state").map(String::as_str).unwrap_or_default();
+    let code = query.get("code").map(String::as_str).unwrap_or_default();
+    let expected = req.cookie(OIDC_STATE_COOKIE).map(|c| c.value().to_string());
+    if state.is_empty() || code.is_empty() || expected.as_deref() != Some(state) {
+        return failed("Your sign-in has expired. Please try again.");
+    }
+
+    let user = match finish_oidc_login(&pool, &redis, &providers, &meta, &path, code, state).await {
+        Ok(user) => user,
+        Err(AppError::Unauthorized(message)) | Err(AppError::Forbidden(message)) | Err(AppError::NotFound(message)) => {
+            return failed(&message)
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut response = complete_login(&pool, &redis, &meta, &user, &format!("oidc:{}", path)).await?;
+    let mut expired = Cookie::build(OIDC_STATE_COOKIE, "").path("/auth/oidc").finish();
+    expired.make_removal();
+    response.add_cookie(&expired).map_err(|e| AppError::InternalError(e.to_string()))?;
+    Ok(response)
+}
+
+/// Second login step for accounts with two-factor authentication
+pub async fn two_factor_login_page(req: HttpRequest) -> Result<HttpResponse, AppError> {
+    if req.cookie(TWO_FACTOR_COOKIE).is_none() {
+        return Ok(redirect_with_flash(&req, "/login", FlashMessage::error("Your login has expired. Please sign in again.")));
+    }
+    render_two_factor_login(&req, None)
+}
+
+pub async fn handle_two_factor_login(
+    req: HttpRequest,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    config: web::Data<Config>,
+    form: web::Form<std::collections::HashMap<String, String>>,
+) -> Result<HttpResponse, AppError> {
+    let challenge = req.cookie(TWO_FACTOR_COOKIE).map(|c| c.value().to_string()).unwrap_or_default();
+    let code = form.get("code").map(String::as_str).unwrap_or_default();
+
+    match complete_two_factor_login(&pool, &redis, &config, &meta, &challenge, code).await {
+        Ok(user) => {
+            let mut response = start_session(&redis, &user).await?;
+            record_login(&pool, &user, "two_factor", &meta).await;
+            let mut expired = Cookie::build(TWO_FACTOR_COOKIE, "").path("/login").finish();
+            expired.make_removal();
+            response.add_cookie(&expired).map_err(|e| AppError::InternalError(e.to_string()))?;
+            Ok(response)
+        }
+        Err(AppError::Unauthorized(message)) => {
+            // Wrong code: try again, unless the challenge is used up
+            if redis.get_two_factor_challenge(&challenge).await?.is_some() {
+                render_two_factor_login(&req, Some(message))
+            } else {
+                Ok(redirect_with_flash(&req, "/login", FlashMessage::error(message)))
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn render_two_factor_login(req: &HttpRequest, error: Option<String>) -> Result<HttpResponse, AppError> {
+    let template = TwoFactorLoginTemplate { ctx: PageContext::new(req, None), error };
+    Ok(HttpResponse::Ok()
+        .content_type("text/html")
+        .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
+}
+
+/// Create a web session for `user` and send them to their dashboard
+/// How long a web session lasts
+pub(crate) const SESSION_TTL_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+/// The `session_id` cookie; a `max_age` of 0 removes it
+pub(crate) fn session_cookie(session_id: &str, max_age: i64) -> Cookie<'static> {
+    Cookie::build("session_id", session_id.to_string())
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(CookieDuration::seconds(max_age))
+        .finish()
+}
+
+/// Store a new session for `user` and return its id
+pub(crate) async fn create_user_session(redis: &RedisClient, user: &User) -> Result<String, AppError> {
     // Generate session ID
     let session_id = Uuid::new_v4().to_string();
     
@@ -268,42 +500,35 @@ pub async fn handle_login(
         user_id: user.id,
         email: user.email.clone(),
         role: user.role.to_string(),
+        csrf_token: generate_csrf_token(),
+        token_version: user.token_version,
+        impersonation: None,
+        admin_session_id: None,
     };
     
-    // Store session in Redis (30 days expiration)
-    let session_ttl = 30 * 24 * 60 * 60; // 30 days in seconds
-    tracing::info!("Creating session for user {}: {}", user.id, session_id);
-    redis.create_session(&session_id, &session_data, session_ttl).await?;
-    tracing::info!("Session created successfully");
+    // Store session in Redis
+    redis.create_session(&session_id, &session_data, SESSION_TTL_SECONDS as usize).await?;
+    Ok(session_id)
+}
+
+async fn start_session(redis: &RedisClient, user: &User) -> Result<HttpResponse, AppError> {
+    let session_id = create_user_session(redis, user).await?;
 
-    // Create redirect response with session cookie
     // Use 303 See Other to ensure cookie is sent with redirect
-    let cookie = Cookie::build("session_id", session_id.clone())
-        .path("/")
-        .http_only(true)
-        .same_site(SameSite::Lax)
-        .max_age(CookieDuration::seconds(session_ttl as i64))
-        .finish();
-    
-    tracing::info!("Setting session cookie: {} in response", session_id);
-    tracing::info!("Cookie value: {}, Cookie string: {}", cookie.value(), cookie.to_string());
-    
-    // Build response with cookie
     let mut response = HttpResponse::SeeOther();
-    response.cookie(cookie);
+    response.cookie(session_cookie(&session_id, SESSION_TTL_SECONDS));
     response.append_header(("Location", "/dashboard"));
     
-    tracing::info!("Response built, cookie should be set");
-    
     Ok(response.finish())
 }
 
 /// Handle register form submission
 pub async fn handle_register(
-    _req: HttpRequest,
+    meta: RequestMeta,
     pool: web::Data<DbPool>,
     redis: web::Data<RedisClient>,
-    _config: web::Data<Config>,
+    mailer: web::Data<dyn Mailer>,
+    config: web::Data<Config>,
     form: web::Form<std::collections::HashMap<String, String>>,
 ) -> Result<HttpResponse, AppError> {
     let email = form.get("email")
@@ -325,7 +550,7 @@ pub async fn handle_register(
     let existing_user = sqlx::query_as::<_, User>(
         "SELECT * FROM users WHERE email = $1"
     )
-    .bind(&email)
+    .bind(email)
     .fetch_optional(pool.get_ref())
     .await?;
 
@@ -345,58 +570,36 @@ pub async fn handle_register(
         RETURNING *
         "#
     )
-    .bind(&email)
+    .bind(email)
     .bind(&password_hash)
     .bind(&role_str)
     .fetch_one(pool.get_ref())
     .await?;
+    record_registration(&pool, &user, &meta).await;
 
-    // Generate session ID
-    let session_id = Uuid::new_v4().to_string();
-    
-    // Create session data
-    let session_data = SessionData {
-        user_id: user.id,
-        email: user.email.clone(),
-        role: user.role.to_string(),
-    };
-    
-    // Store session in Redis (30 days expiration)
-    let session_ttl = 30 * 24 * 60 * 60; // 30 days in seconds
-    tracing::info!("Creating session for user {}: {}", user.id, session_id);
-    redis.create_session(&session_id, &session_data, session_ttl).await?;
-    tracing::info!("Session created successfully");
+    if let Err(e) = send_verification_email(&pool, &redis, mailer.get_ref(), &config, &user).await {
+        tracing::error!(user_id = %user.id, error = ?e, "Failed to send verification email");
+    }
 
-    // Create redirect response with session cookie
-    // Use 303 See Other to ensure cookie is sent with redirect
-    let cookie = Cookie::build("session_id", session_id.clone())
-        .path("/")
-        .http_only(true)
-        .same_site(SameSite::Lax)
-        .max_age(CookieDuration::seconds(session_ttl as i64))
-        .finish();
-    
-    tracing::info!("Setting session cookie: {} in response", session_id);
-    
-    // Build response with cookie
-    let mut response = HttpResponse::SeeOther();
-    response.cookie(cookie);
-    response.append_header(("Location", "/dashboard"));
-    
-    // Log to verify cookie is set
-    tracing::info!("Response built, cookie should be set");
-    
-    Ok(response.finish())
+    start_session(&redis, &user).await
 }
 
 /// Handle logout
 pub async fn handle_logout(
     req: HttpRequest,
+    pool: web::Data<DbPool>,
     redis: web::Data<RedisClient>,
+    meta: RequestMeta,
 ) -> Result<HttpResponse, AppError> {
     // Get session ID from cookie
     if let Some(cookie) = req.cookie("session_id") {
         let session_id = cookie.value();
+        // Logging out of an impersonation returns the admin to their own session
+        if let Some(session) = redis.get_session_data(session_id).await? {
+            if session.impersonation.is_some() {
+                return end_impersonation_session(&req, &pool, &redis, &meta, session_id, &session).await;
+            }
+        }
         // Delete session from Redis
         redis.destroy_session(session_id).await?;
     }
@@ -404,69 +607,155 @@ pub async fn handle_logout(
     // Create redirect response that removes the session cookie
     let mut response = HttpResponse::SeeOther();
     response.append_header(("Location", "/"));
-    
-    // Remove session cookie by setting it with max_age 0
-    response.cookie(
-        Cookie::build("session_id", "")
-            .path("/")
-            .http_only(true)
-            .same_site(SameSite::Lax)
-            .max_age(CookieDuration::seconds(0))
-            .finish(),
-    );
+    response.cookie(session_cookie("", 0));
     
     Ok(response.finish())
 }
 
+/// Serve the forgot password form
+pub async fn forgot_password_page(req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let template = ForgotPasswordTemplate { ctx: PageContext::new(&req, None) };
+    Ok(HttpResponse::Ok()
+        .content_type("text/html")
+        .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
+}
+
+/// Email a reset link. The reply doesn't say whether the account exists.
+pub async fn handle_forgot_password(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    mailer: web::Data<dyn Mailer>,
+    config: web::Data<Config>,
+    form: web::Form<std::collections::HashMap<String, String>>,
+) -> Result<HttpResponse, AppError> {
+    let email = match form.get("email").map(|e| e.trim()) {
+        Some(email) if !email.is_empty() => email,
+        _ => return Ok(redirect_with_flash(&req, "/forgot-password", FlashMessage::error("Email is required"))),
+    };
+
+    send_password_reset(&pool, &redis, mailer.get_ref(), &config, email).await?;
+    Ok(redirect_with_flash(
+        &req,
+        "/login",
+        FlashMessage::info("If an account exists for that email, we've sent a link to reset your password."),
+    ))
+}
+
+/// Serve the new password form for an emailed reset link
+pub async fn reset_password_page(req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let token = match query_params(&req).remove("token") {
+        Some(token) if !token.is_empty() => token,
+        _ => {
+            return Ok(redirect_with_flash(
+                &req,
+                "/forgot-password",
+                FlashMessage::error("That reset link is incomplete. Please request a new one."),
+            ))
+        }
+    };
+    render_reset_password(&req, token, None)
+}
+
+/// Set the new password, then send the user to log in with it
+pub async fn handle_reset_password(
+    req: HttpRequest,
+    meta: RequestMeta,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    form: web::Form<std::collections::HashMap<String, String>>,
+) -> Result<HttpResponse, AppError> {
+    let field = |name: &str| form.get(name).cloned().unwrap_or_default();
+    let token = field("token");
+    let password = field("password");
+
+    if password != field("confirm_password") {
+        return render_reset_password(&req, token, Some("Passwords don't match".to_string()));
+    }
+
+    match reset_password(&pool, &redis, &meta, &token, &password).await {
+        Ok(_) => Ok(redirect_with_flash(
+            &req,
+            "/login",
+            FlashMessage::success("Your password has been reset. Please log in with your new password."),
+        )),
+        Err(AppError::ValidationError(message)) => render_reset_password(&req, token, Some(message)),
+        Err(e) => Err(e),
+    }
+}
+
+fn render_reset_password(req: &HttpRequest, token: String, error: Option<String>) -> Result<HttpResponse, AppError> {
+    let template = ResetPasswordTemplate {
+        ctx: PageContext::new(req, None),
+        token,
+        error,
+    };
+    Ok(HttpResponse::Ok()
+        .content_type("text/html")
+        .body(template.render().map_err(|e| AppError::InternalError(e.to_string()))?))
+}
+
+/// Handle the link from a verification email
+pub async fn handle_verify_email(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, AppError> {
+    let token = query_params(&req).remove("token").unwrap_or_default();
+    match verify_email(&pool, &token).await {
+        Ok(_) => Ok(redirect_with_flash(&req, "/dashboard", FlashMessage::success("Thanks, your email address is confirmed"))),
+        Err(AppError::ValidationError(message)) => Ok(redirect_with_flash(&req, "/dashboard", FlashMessage::error(message))),
+        Err(e) => Err(e),
+    }
+}
+
+/// Send a fresh verification email from the dashboard banner
+pub async fn handle_resend_verification(
+    user: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    redis: web::Data<RedisClient>,
+    mailer: web::Data<dyn Mailer>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, AppError> {
+    if user.email_verified {
+        return Ok(redirect_with_flash(&req, "/dashboard", FlashMessage::info("Your email address is already confirmed")));
+    }
+    match send_verification_email(&pool, &redis, mailer.get_ref(), &config, &user).await {
+        Ok(()) => {}
+        Err(AppError::TooManyRequests(message)) => {
+            return Ok(redirect_with_flash(&req, "/dashboard", FlashMessage::error(message)))
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(redirect_with_flash(
+        &req,
+        "/dashboard",
+        FlashMessage::success(format!("We've sent a new confirmation link to {}", user.email)),
+    ))
+}
+
 /// Handle create chef profile form submission
 pub async fn handle_create_chef(
+    user: RequireRole<role::Chef>,
     req: HttpRequest,
+    meta: RequestMeta,
     pool: web::Data<DbPool>,
-    redis: web::Data<RedisClient>,
     form: web::Form<std::collections::HashMap<String, String>>,
 ) -> Result<HttpResponse, AppError> {
     tracing::info!("Create chef profile form submission received");
     
-    // Extract user ID from session
-    let user_id = extract_user_id_from_session(&req, &redis).await?;
-    
-    // Get user and check role
-    let user = sqlx::query_as::<_, crate::models::User>(
-        "SELECT * FROM users WHERE id = $1"
-    )
-    .bind(user_id)
-    .fetch_optional(pool.get_ref())
-    .await?
-    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
-    
-    // Check if user has chef or admin role
-    if user.role != crate::models::Role::Chef && user.role != crate::models::Role::Admin {
-        return Err(AppError::Unauthorized("Chef or admin role required".to_string()));
-    }
-    
-    // Check if chef profile already exists
-    let existing = sqlx::query_as::<_, crate::models::Chef>(
-        "SELECT * FROM chefs WHERE user_id = $1"
-    )
-    .bind(user_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
+    let user_id = user.id;
     
-    if existing.is_some() {
-        let error_msg = urlencoding::encode("Chef profile already exists");
-        return Ok(HttpResponse::SeeOther()
-            .append_header(("Location", format!("/chef-dashboard?error={}", error_msg)))
-            .finish());
+    // Members of a team, owners included, work under that team's profile
+    if membership(&pool, user_id).await?.is_some() {
+        return Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::error("Chef profile already exists")));
     }
     
     // Extract form data
     let chef_name = match form.get("chef_name") {
         Some(name) if !name.trim().is_empty() => name.trim(),
         _ => {
-            let error_msg = urlencoding::encode("Chef name is required");
-            return Ok(HttpResponse::SeeOther()
-                .append_header(("Location", format!("/chef-dashboard?error={}", error_msg)))
-                .finish());
+            return Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::error("Chef name is required")));
         }
     };
     let business_name = form.get("business_name").and_then(|s| {
@@ -507,74 +796,54 @@ pub async fn handle_create_chef(
     .bind(&bio)
     .bind(&cuisine_types)
     .bind(&location)
-    .bind(&hourly_rate)
+    .bind(hourly_rate)
     .bind(2) // default minimum_hours
     .bind(&slug)
     .fetch_one(pool.get_ref())
     .await {
-        Ok(_chef) => {
+        Ok(chef) => {
+            AuditEvent::new("chef_profile_created")
+                .by(&user)
+                .on("chef", chef.id)
+                .after(serde_json::to_value(&chef).unwrap_or_default())
+                .from(&meta)
+                .record(&pool)
+                .await;
             tracing::info!("Chef profile created successfully for user: {}", user_id);
-            let success_msg = urlencoding::encode("Chef profile created successfully!");
-            Ok(HttpResponse::SeeOther()
-                .append_header(("Location", format!("/chef-dashboard?success={}", success_msg)))
-                .finish())
+            Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::success("Chef profile created successfully!")))
         }
         Err(e) => {
             tracing::error!("Failed to create chef profile: {:?}", e);
-            let error_msg = urlencoding::encode("Failed to create chef profile. Please try again.");
-            Ok(HttpResponse::SeeOther()
-                .append_header(("Location", format!("/chef-dashboard?error={}", error_msg)))
-                .finish())
+            Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::error("Failed to create chef profile. Please try again.")))
         }
     }
 }
 
 /// Handle create menu form submission
 pub async fn handle_create_menu(
+    user: RequireRole<role::Chef>,
     req: HttpRequest,
     pool: web::Data<DbPool>,
-    redis: web::Data<RedisClient>,
     form: web::Form<std::collections::HashMap<String, String>>,
 ) -> Result<HttpResponse, AppError> {
     tracing::info!("Create menu form submission received");
     
-    // Extract user ID from session
-    let user_id = extract_user_id_from_session(&req, &redis).await?;
-    
-    // Get user and check role
-    let user = sqlx::query_as::<_, crate::models::User>(
-        "SELECT * FROM users WHERE id = $1"
-    )
-    .bind(user_id)
-    .fetch_optional(pool.get_ref())
-    .await?
-    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
-    
-    // Check if user has chef or admin role
-    if user.role != crate::models::Role::Chef && user.role != crate::models::Role::Admin {
-        return Err(AppError::Unauthorized("Chef or admin role required".to_string()));
-    }
+    let user_id = user.id;
     
-    // Get chef profile
-    let chef: Option<(uuid::Uuid,)> = sqlx::query_as(
-        "SELECT id FROM chefs WHERE user_id = $1"
-    )
-    .bind(user_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
-    
-    let chef_id = chef
-        .map(|(id,)| id)
+    // Get the team's chef profile
+    let member = membership(&pool, user_id)
+        .await?
         .ok_or_else(|| AppError::Unauthorized("Chef profile not found. Please create a chef profile first.".to_string()))?;
+    if !member.role.allows(Permission::ManageOwnMenus) {
+        return Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::error("Your team role doesn't allow editing menus")));
+    }
+    let chef_id = member.chef_id;
     
     // Extract form data
     let name = match form.get("name") {
         Some(n) if !n.trim().is_empty() => n.trim(),
         _ => {
-            let error_msg = urlencoding::encode("Menu name is required");
-            return Ok(HttpResponse::SeeOther()
-                .append_header(("Location", format!("/chef-dashboard?error={}", error_msg)))
-                .finish());
+            return Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::error("Menu name is required")));
         }
     };
     let description = form.get("description").and_then(|s| {
@@ -607,55 +876,34 @@ pub async fn handle_create_menu(
     .bind(chef_id)
     .bind(name)
     .bind(&description)
-    .bind(&price_per_person)
+    .bind(price_per_person)
     .bind(minimum_guests)
     .bind(&cuisine_type)
-    .bind(&duration_hours)
+    .bind(duration_hours)
     .bind(true) // is_active
     .fetch_one(pool.get_ref())
     .await {
         Ok(_menu) => {
             tracing::info!("Menu created successfully for chef: {}", chef_id);
-            let success_msg = urlencoding::encode("Menu created successfully!");
-            Ok(HttpResponse::SeeOther()
-                .append_header(("Location", format!("/chef-dashboard?success={}", success_msg)))
-                .finish())
+            Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::success("Menu created successfully!")))
         }
         Err(e) => {
             tracing::error!("Failed to create menu: {:?}", e);
-            let error_msg = urlencoding::encode("Failed to create menu. Please try again.");
-            Ok(HttpResponse::SeeOther()
-                .append_header(("Location", format!("/chef-dashboard?error={}", error_msg)))
-                .finish())
+            Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::error("Failed to create menu. Please try again.")))
         }
     }
 }
 
 /// Handle create menu item form submission
 pub async fn handle_create_menu_item(
+    user: RequireRole<role::Chef>,
     req: HttpRequest,
     pool: web::Data<DbPool>,
-    redis: web::Data<RedisClient>,
     form: web::Form<std::collections::HashMap<String, String>>,
 ) -> Result<HttpResponse, AppError> {
     tracing::info!("Create menu item form submission received");
     
-    // Extract user ID from session
-    let user_id = extract_user_id_from_session(&req, &redis).await?;
-    
-    // Get user and check role
-    let user = sqlx::query_as::<_, crate::models::User>(
-        "SELECT * FROM users WHERE id = $1"
-    )
-    .bind(user_id)
-    .fetch_optional(pool.get_ref())
-    .await?
-    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
-    
-    // Check if user has chef or admin role
-    if user.role != crate::models::Role::Chef && user.role != crate::models::Role::Admin {
-        return Err(AppError::Unauthorized("Chef or admin role required".to_string()));
-    }
+    let user_id = user.id;
     
     // Extract menu_id from form
     let menu_id_str = form.get("menu_id")
@@ -663,32 +911,26 @@ pub async fn handle_create_menu_item(
     let menu_id = menu_id_str.parse::<uuid::Uuid>()
         .map_err(|_| AppError::ValidationError("Invalid menu ID".to_string()))?;
     
-    // Verify ownership
-    let _chef: Option<(uuid::Uuid,)> = sqlx::query_as(
-        "SELECT c.id FROM chefs c
-         INNER JOIN menus m ON m.chef_id = c.id
-         WHERE c.user_id = $1 AND m.id = $2"
-    )
-    .bind(user_id)
-    .bind(menu_id)
-    .fetch_optional(pool.get_ref())
-    .await?;
+    // Verify the menu is the team's and the user may edit it
+    let chef_id = membership(&pool, user_id)
+        .await?
+        .filter(|member| member.role.allows(Permission::ManageOwnMenus))
+        .map(|member| member.chef_id);
+    let _menu: Option<(uuid::Uuid,)> = sqlx::query_as("SELECT id FROM menus WHERE chef_id = $1 AND id = $2")
+        .bind(chef_id)
+        .bind(menu_id)
+        .fetch_optional(pool.get_ref())
+        .await?;
     
-    if _chef.is_none() {
-        let error_msg = urlencoding::encode("Not authorized to add items to this menu");
-        return Ok(HttpResponse::SeeOther()
-            .append_header(("Location", format!("/chef-dashboard?error={}", error_msg)))
-            .finish());
+    if _menu.is_none() {
+        return Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::error("Not authorized to add items to this menu")));
     }
     
     // Extract form data
     let name = match form.get("name") {
         Some(n) if !n.trim().is_empty() => n.trim(),
         _ => {
-            let error_msg = urlencoding::encode("Item name is required");
-            return Ok(HttpResponse::SeeOther()
-                .append_header(("Location", format!("/chef-dashboard?error={}", error_msg)))
-                .finish());
+            return Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::error("Item name is required")));
         }
     };
     let description = form.get("description").and_then(|s| {
@@ -719,24 +961,18 @@ pub async fn handle_create_menu_item(
     .bind(name)
     .bind(&description)
     .bind(&course_type)
-    .bind(&quantity)
+    .bind(quantity)
     .bind(is_featured)
     .bind(0) // display_order
     .fetch_one(pool.get_ref())
     .await {
         Ok(_item) => {
             tracing::info!("Menu item created successfully for menu: {}", menu_id);
-            let success_msg = urlencoding::encode("Menu item added successfully!");
-            Ok(HttpResponse::SeeOther()
-                .append_header(("Location", format!("/chef-dashboard?success={}", success_msg)))
-                .finish())
+            Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::success("Menu item added successfully!")))
         }
         Err(e) => {
             tracing::error!("Failed to create menu item: {:?}", e);
-            let error_msg = urlencoding::encode("Failed to create menu item. Please try again.");
-            Ok(HttpResponse::SeeOther()
-                .append_header(("Location", format!("/chef-dashboard?error={}", error_msg)))
-                .finish())
+            Ok(redirect_with_flash(&req, "/chef-dashboard", FlashMessage::error("Failed to create menu item. Please try again.")))
         }
     }
 }
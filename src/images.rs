@@ -0,0 +1,172 @@
+// Image processing for uploads
+// Validates the upload, strips metadata by re-encoding, and renders resized
+// variants in the original family (JPEG/PNG) plus WebP
+
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+
+use crate::errors::AppError;
+
+/// Largest width or height accepted before decoding, to refuse decompression bombs
+const MAX_DIMENSION: u32 = 8000;
+const JPEG_QUALITY: u8 = 85;
+
+/// What an uploaded image is used for; decides the rendered sizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageKind {
+    ChefProfile,
+    ChefCover,
+    MenuItem,
+}
+
+impl ImageKind {
+    /// Target widths, smallest first
+    pub fn widths(&self) -> &'static [u32] {
+        match self {
+            ImageKind::ChefProfile => &[128, 256, 512],
+            ImageKind::ChefCover => &[800, 1600],
+            ImageKind::MenuItem => &[400, 800],
+        }
+    }
+
+    /// Profile photos are cropped to a square; everything else keeps its aspect ratio
+    pub fn is_square(&self) -> bool {
+        matches!(self, ImageKind::ChefProfile)
+    }
+}
+
+/// One encoded rendition of an upload
+#[derive(Debug, Clone)]
+pub struct ImageVariant {
+    pub width: u32,
+    pub height: u32,
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    pub data: Vec<u8>,
+}
+
+/// Content types accepted for upload, detected from the file's magic bytes
+pub fn detect_format(bytes: &[u8]) -> Result<ImageFormat, AppError> {
+    match image::guess_format(bytes) {
+        Ok(format @ (ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP | ImageFormat::Gif)) => Ok(format),
+        _ => Err(AppError::ValidationError(
+            "Unsupported image type; upload a JPEG, PNG, WebP or GIF".to_string(),
+        )),
+    }
+}
+
+/// Validate an upload and render every variant for `kind`.
+///
+/// Re-encoding from decoded pixels drops EXIF and other metadata; the EXIF
+/// orientation is applied first so photos from phones stay upright.
+/// Images are never upscaled, so a small upload yields variants no larger
+/// than the original.
+pub fn process_image(bytes: &[u8], kind: ImageKind, max_bytes: usize) -> Result<Vec<ImageVariant>, AppError> {
+    if bytes.is_empty() {
+        return Err(AppError::ValidationError("Uploaded file is empty".to_string()));
+    }
+    if bytes.len() > max_bytes {
+        return Err(AppError::ValidationError(format!(
+            "Image is too large; the limit is {} bytes",
+            max_bytes
+        )));
+    }
+
+    let format = detect_format(bytes)?;
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+    let mut decoder = reader
+        .into_decoder()
+        .map_err(|e| AppError::ValidationError(format!("Could not read image: {}", e)))?;
+    let orientation = decoder
+        .orientation()
+        .map_err(|e| AppError::ValidationError(format!("Could not read image: {}", e)))?;
+    let mut original = DynamicImage::from_decoder(decoder)
+        .map_err(|e| AppError::ValidationError(format!("Could not read image: {}", e)))?;
+    original.apply_orientation(orientation);
+
+    let mut variants = Vec::with_capacity(kind.widths().len() * 2);
+    let mut rendered: Vec<(u32, u32)> = Vec::new();
+
+    for &width in kind.widths() {
+        let resized = resize(&original, width, kind.is_square());
+        let dimensions = (resized.width(), resized.height());
+        // Small originals collapse several target widths to the same size
+        if rendered.contains(&dimensions) {
+            continue;
+        }
+        rendered.push(dimensions);
+
+        variants.push(encode_primary(&resized)?);
+        variants.push(encode_webp(&resized)?);
+    }
+
+    Ok(variants)
+}
+
+fn resize(image: &DynamicImage, width: u32, square: bool) -> DynamicImage {
+    if square {
+        let side = width.min(image.width()).min(image.height());
+        image.resize_to_fill(side, side, FilterType::Lanczos3)
+    } else if image.width() > width {
+        image.resize(width, u32::MAX, FilterType::Lanczos3)
+    } else {
+        image.clone()
+    }
+}
+
+/// JPEG for opaque images, PNG when transparency has to survive
+fn encode_primary(image: &DynamicImage) -> Result<ImageVariant, AppError> {
+    let mut data = Vec::new();
+
+    let (content_type, extension) = if image.color().has_alpha() {
+        image
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .map_err(|e| AppError::InternalError(format!("Failed to encode PNG: {}", e)))?;
+        ("image/png", "png")
+    } else {
+        let rgb = image.to_rgb8();
+        JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY)
+            .encode_image(&rgb)
+            .map_err(|e| AppError::InternalError(format!("Failed to encode JPEG: {}", e)))?;
+        ("image/jpeg", "jpg")
+    };
+
+    Ok(ImageVariant {
+        width: image.width(),
+        height: image.height(),
+        content_type,
+        extension,
+        data,
+    })
+}
+
+fn encode_webp(image: &DynamicImage) -> Result<ImageVariant, AppError> {
+    let mut data = Vec::new();
+    // The WebP encoder only takes 8-bit RGB(A)
+    let image = if image.color().has_alpha() {
+        DynamicImage::ImageRgba8(image.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    };
+    image
+        .write_with_encoder(WebPEncoder::new_lossless(&mut data))
+        .map_err(|e| AppError::InternalError(format!("Failed to encode WebP: {}", e)))?;
+
+    Ok(ImageVariant {
+        width: image.width(),
+        height: image.height(),
+        content_type: "image/webp",
+        extension: "webp",
+        data,
+    })
+}
@@ -1,6 +1,7 @@
 // Library crate for privatechefspace-backend
 // This allows the code to be used in both the binary (main.rs) and tests
 
+pub mod audit;
 pub mod config;
 pub mod errors;
 pub mod utils;
@@ -11,11 +12,17 @@ pub mod handlers;
 pub mod middleware;
 pub mod routes;
 pub mod templates;
+pub mod pricing;
+pub mod storage;
+pub mod images;
+pub mod mail;
+pub mod oidc;
+pub mod policy;
 
 pub use config::Config;
 pub use errors::AppError;
 pub use db::create_pool;
 pub use cache::RedisClient;
-pub use routes::{configure_auth, configure_api, configure_web};
+pub use routes::{configure_admin, configure_auth, configure_api, configure_web};
 pub use middleware::cors::configure_cors;
 
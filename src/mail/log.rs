@@ -0,0 +1,16 @@
+use async_trait::async_trait;
+
+use crate::errors::AppError;
+use super::{Email, Mailer};
+
+/// Writes emails to the debug log instead of sending them, for development
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        tracing::info!(subject = %email.subject, "Email not sent: mail backend is \"log\"");
+        tracing::debug!(to = %email.to, body = %email.body, "Logged email");
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+// Outgoing email
+// Handlers depend on the Mailer trait; the backend is picked from MailConfig
+
+pub mod log;
+pub mod smtp;
+
+pub use self::log::LogMailer;
+pub use smtp::SmtpMailer;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::config::MailConfig;
+use crate::errors::AppError;
+
+/// A plain-text email
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), AppError>;
+}
+
+/// Build the mailer selected by `MAIL_BACKEND`
+pub fn create_mailer(config: &MailConfig) -> Result<Arc<dyn Mailer>, AppError> {
+    match config.backend.as_str() {
+        "log" => Ok(Arc::new(LogMailer)),
+        "smtp" => Ok(Arc::new(SmtpMailer::new(config)?)),
+        other => Err(AppError::InternalError(format!("Unknown mail backend: {}", other))),
+    }
+}
@@ -2,9 +2,8 @@
 use privatechefspace_backend::*;
 
 use actix_web::{web, App, HttpServer};
-use tracing_subscriber;
 
-use routes::{configure_auth, configure_api, configure_web};
+use routes::{configure_admin, configure_auth, configure_api, configure_web};
 use middleware::cors::configure_cors;
 use actix_files::Files;
 
@@ -29,6 +28,14 @@ async fn main() -> std::io::Result<()> {
         .await
         .expect("Failed to create Redis client");
 
+    // Create blob store for uploaded images
+    let blob_store = storage::create_blob_store(&config.storage)
+        .expect("Failed to create blob store");
+
+    // Create mailer for account emails
+    let mailer = mail::create_mailer(&config.mail)
+        .expect("Failed to create mailer");
+
     tracing::info!(
         "Starting server on {}:{}",
         config.server.host,
@@ -41,12 +48,24 @@ async fn main() -> std::io::Result<()> {
     let pool_data = web::Data::new(pool);
     let config_data = web::Data::new(config);
     let redis_data = web::Data::new(redis_client);
+    let blob_store_data: web::Data<dyn storage::BlobStore> = web::Data::from(blob_store);
+    let mailer_data: web::Data<dyn mail::Mailer> = web::Data::from(mailer);
+    // Shared so provider discovery documents are fetched once, not per worker
+    let oidc_data = web::Data::new(oidc::OidcProviders::new(&config_data.oidc));
+    // Local uploads are served by this process; S3 objects are served by the bucket
+    let serve_local_uploads = config_data.storage.backend == "local"
+        && config_data.storage.public_url.starts_with('/');
+    let uploads_url = config_data.storage.public_url.clone();
+    let uploads_dir = config_data.storage.local_path.clone();
     
     HttpServer::new(move || {
         App::new()
             .app_data(pool_data.clone())
             .app_data(config_data.clone())
             .app_data(redis_data.clone())
+            .app_data(blob_store_data.clone())
+            .app_data(mailer_data.clone())
+            .app_data(oidc_data.clone())
             .wrap(configure_cors())
             // Serve static files - must be before other routes
             .service(
@@ -54,10 +73,20 @@ async fn main() -> std::io::Result<()> {
                     .show_files_listing()
                     .prefer_utf8(true)
             )
+            .configure(|cfg| {
+                if serve_local_uploads {
+                    std::fs::create_dir_all(&uploads_dir).expect("Failed to create uploads directory");
+                    cfg.service(
+                        Files::new(&uploads_url, &uploads_dir)
+                            .path_filter(|path, _| !path.starts_with(storage::PRIVATE_PREFIX)),
+                    );
+                }
+            })
             // Configure routes
             .configure(|cfg| {
                 configure_web(cfg, pool_data.clone(), config_data.clone(), redis_data.clone());
                 configure_auth(cfg, pool_data.clone(), config_data.clone());
+                configure_admin(cfg, pool_data.clone(), config_data.clone());
                 configure_api(cfg, pool_data.clone(), config_data.clone());
             })
     })
@@ -1,82 +1,336 @@
-use actix_web::{dev::ServiceRequest, Error, HttpMessage};
-use actix_web_httpauth::extractors::bearer::BearerAuth;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::http::header::{ACCEPT, AUTHORIZATION};
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, HttpResponse};
+use futures_util::future::LocalBoxFuture;
 use uuid::Uuid;
 
+use crate::cache::{RedisClient, UserAuthState};
 use crate::config::Config;
-use crate::utils::verify_token;
+use crate::db::DbPool;
 use crate::errors::AppError;
-use crate::cache::RedisClient;
-
-pub struct AuthenticatedUser {
-    pub user_id: Uuid,
-}
-
-/// Validator that checks Bearer token (for API routes)
-pub async fn validator(
-    req: ServiceRequest,
-    credentials: BearerAuth,
-) -> Result<ServiceRequest, (Error, ServiceRequest)> {
-    let config = match req.app_data::<actix_web::web::Data<Config>>() {
-        Some(cfg) => cfg,
-        None => {
-            let error = actix_web::error::ErrorInternalServerError("Configuration not found");
-            return Err((error, req));
+use crate::middleware::csrf::{tokens_match, CsrfToken, CSRF_HEADER};
+use crate::middleware::flash::{redirect_with_flash, FlashMessage};
+use crate::middleware::roles::RoleSet;
+use crate::models::{Impersonation, Role, User, UserResponse};
+use crate::utils::{verify_token, TokenKind};
+
+/// Who is making the request: user id and current role.
+///
+/// Accepts either a bearer token (API clients) or the `session_id` cookie
+/// (web pages). A bearer header that fails to verify is rejected rather than
+/// falling back to the cookie. Outside routes wrapped in `CsrfProtection`, a
+/// state-changing request signed in by cookie must send the session's CSRF
+/// token in the `X-CSRF-Token` header. The token or session must carry the user's
+/// current `token_version`, which is checked against the role/version cached
+/// in Redis, so resolving an identity normally needs no database query.
+///
+/// When authentication fails, API requests get a 401 JSON error and browser
+/// requests are redirected to the login page.
+///
+/// Users whose role must have two-factor authentication (see
+/// `TwoFactorConfig`) are refused with a 403 until they've enabled it, and
+/// browsers are sent to the setup page. Only routes carrying
+/// [`TwoFactorExempt`] let them through.
+///
+/// While an admin is impersonating the user, `id` and `role` are the user's
+/// and `impersonation` says who is really acting.
+#[derive(Debug, Clone, Copy)]
+pub struct Identity {
+    pub id: Uuid,
+    pub role: Role,
+    pub two_factor_enabled: bool,
+    pub impersonation: Option<Impersonation>,
+}
+
+impl Identity {
+    /// The admin acting as this user, if any
+    pub fn impersonator(&self) -> Option<Uuid> {
+        self.impersonation.map(|impersonation| impersonation.admin_id)
+    }
+
+    /// Fails with `Forbidden` while an admin is impersonating the user. For
+    /// actions that only the user themselves may take.
+    pub fn forbid_impersonation(&self) -> Result<(), AppError> {
+        match self.impersonation {
+            Some(_) => Err(AppError::Forbidden(NOT_WHILE_IMPERSONATING.to_string())),
+            None => Ok(()),
         }
-    };
+    }
+}
+
+impl FromRequest for Identity {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
-    let token = credentials.token();
-    
-    match verify_token(token, &config.jwt.secret) {
-        Ok(claims) => {
-            let user_id = match Uuid::parse_str(&claims.sub) {
-                Ok(id) => id,
-                Err(_) => {
-                    let error = actix_web::error::ErrorUnauthorized("Invalid user ID in token");
-                    return Err((error, req));
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            let cached = req.extensions().get::<Identity>().copied();
+            let identity = match cached {
+                Some(identity) => identity,
+                None => {
+                    let identity = resolve_identity(&req).await.map_err(|e| reject(&req, e))?;
+                    req.extensions_mut().insert(identity);
+                    identity
                 }
             };
+            require_two_factor(&req, &identity).map_err(|e| reject(&req, e))?;
+            Ok(identity)
+        })
+    }
+}
 
-            req.extensions_mut().insert(AuthenticatedUser { user_id });
-            Ok(req)
-        }
-        Err(_) => {
-            let error = actix_web::error::ErrorUnauthorized("Invalid or expired token");
-            Err((error, req))
-        }
+/// Route data for the routes a user who still has to enable two-factor
+/// authentication may use: setting it up and signing out
+#[derive(Debug, Clone, Copy)]
+pub struct TwoFactorExempt;
+
+/// The signed-in user, loaded from the database once per request. Use it
+/// as a handler argument, or `Option<AuthUser>` on pages that also serve
+/// visitors. Handlers that only need the id or role should take
+/// [`Identity`] or [`RequireRole`] instead.
+#[derive(Debug, Clone)]
+pub struct AuthUser(pub User);
+
+impl Deref for AuthUser {
+    type Target = User;
+
+    fn deref(&self) -> &User {
+        &self.0
     }
 }
 
-/// Extract user ID from session cookie (for web routes)
-pub async fn extract_user_id_from_session(
-    req: &actix_web::HttpRequest,
-    redis: &actix_web::web::Data<RedisClient>,
-) -> Result<Uuid, AppError> {
-    // Get session ID from cookie
-    tracing::debug!("Extracting session - all cookies: {:?}", req.cookies());
-    let session_id = match req.cookie("session_id") {
-        Some(cookie) => {
-            let id = cookie.value().to_string();
-            tracing::info!("Found session_id cookie: {}", id);
-            id
-        }
-        None => {
-            tracing::warn!("No session_id cookie found in request");
-            tracing::warn!("Available cookies: {:?}", req.cookies());
-            return Err(AppError::Unauthorized("No session found. Please log in.".to_string()));
-        }
-    };
+impl From<AuthUser> for UserResponse {
+    fn from(user: AuthUser) -> Self {
+        UserResponse::from(user.0)
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let identity = Identity::from_request(&req, payload);
+        Box::pin(async move {
+            if let Some(user) = req.extensions().get::<AuthUser>() {
+                return Ok(user.clone());
+            }
+            let identity = identity.await?;
+            let user = load_user(&req, identity.id).await.map_err(|e| reject(&req, e))?;
+            req.extensions_mut().insert(user.clone());
+            Ok(user)
+        })
+    }
+}
+
+/// Refuses requests made while an admin is impersonating the user. Take it
+/// as a handler argument for actions support must never take on a user's
+/// behalf, such as changing security settings or money movements.
+#[derive(Debug, Clone, Copy)]
+pub struct NotImpersonating;
+
+impl FromRequest for NotImpersonating {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let identity = Identity::from_request(&req, payload);
+        Box::pin(async move {
+            let identity = identity.await?;
+            if let Some(admin_id) = identity.impersonator() {
+                tracing::warn!(
+                    target: "audit",
+                    user_id = %identity.id,
+                    admin_id = %admin_id,
+                    path = req.path(),
+                    "Blocked a sensitive action during impersonation"
+                );
+            }
+            identity.forbid_impersonation().map_err(|e| reject(&req, e))?;
+            Ok(NotImpersonating)
+        })
+    }
+}
+
+/// An [`Identity`] whose role is in `R`, e.g. `RequireRole<role::Chef>`.
+/// Other signed-in users get a 403 (or are sent back to their dashboard).
+#[derive(Debug, Clone, Copy)]
+pub struct RequireRole<R> {
+    pub identity: Identity,
+    roles: PhantomData<R>,
+}
+
+impl<R> Deref for RequireRole<R> {
+    type Target = Identity;
+
+    fn deref(&self) -> &Identity {
+        &self.identity
+    }
+}
+
+impl<R: RoleSet> FromRequest for RequireRole<R> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
 
-    // Get session data from Redis
-    tracing::debug!("Looking up session in Redis: {}", session_id);
-    match redis.get_session_data(&session_id).await {
-        Ok(Some(session_data)) => {
-            tracing::debug!("Session found for user_id: {}", session_data.user_id);
-            Ok(session_data.user_id)
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let identity = Identity::from_request(&req, payload);
+        Box::pin(async move {
+            let identity = identity.await?;
+            if !R::ROLES.contains(&identity.role) {
+                tracing::warn!(user_id = %identity.id, role = %identity.role, path = req.path(), "Role check failed");
+                return Err(reject(
+                    &req,
+                    AppError::Forbidden(format!("Access denied. Required roles: {:?}", R::ROLES)),
+                ));
+            }
+            Ok(RequireRole { identity, roles: PhantomData })
+        })
+    }
+}
+
+pub(crate) const ACCOUNT_SUSPENDED: &str = "This account has been suspended.";
+
+pub(crate) const NOT_WHILE_IMPERSONATING: &str = "That isn't available while impersonating a user.";
+
+const CSRF_TOKEN_REQUIRED: &str = "Requests signed in with a session cookie must send the X-CSRF-Token header.";
+
+const TWO_FACTOR_REQUIRED: &str = "Two-factor authentication is required for your account. Set it up to continue.";
+
+/// Refuses users whose role must have 2FA until they've enabled it, except
+/// on routes marked [`TwoFactorExempt`]
+fn require_two_factor(req: &HttpRequest, identity: &Identity) -> Result<(), AppError> {
+    if identity.two_factor_enabled || req.app_data::<TwoFactorExempt>().is_some() {
+        return Ok(());
+    }
+    let config = req
+        .app_data::<web::Data<Config>>()
+        .ok_or_else(|| AppError::InternalError("Configuration not found".to_string()))?;
+    if config.two_factor.requires(identity.role) {
+        tracing::warn!(user_id = %identity.id, role = %identity.role, path = req.path(), "Two-factor authentication not enabled");
+        return Err(AppError::Forbidden(TWO_FACTOR_REQUIRED.to_string()));
+    }
+    Ok(())
+}
+
+async fn load_user(req: &HttpRequest, user_id: Uuid) -> Result<AuthUser, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(db_pool(req)?.get_ref())
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("User no longer exists".to_string()))?;
+    Ok(AuthUser(user))
+}
+
+fn db_pool(req: &HttpRequest) -> Result<&web::Data<DbPool>, AppError> {
+    req.app_data::<web::Data<DbPool>>()
+        .ok_or_else(|| AppError::InternalError("Database pool not configured".to_string()))
+}
+
+/// Checks the presented credentials against the user's current state. A
+/// token or session issued before the last role change (or other version
+/// bump) is no longer accepted.
+async fn resolve_identity(req: &HttpRequest) -> Result<Identity, AppError> {
+    let (user_id, role, token_version, impersonation) = presented_credentials(req).await?;
+    let current = current_auth_state(req, user_id).await?;
+
+    if current.token_version != token_version || current.role != role {
+        tracing::debug!(user_id = %user_id, "Rejected credentials issued before the user's last token version bump");
+        return Err(AppError::Unauthorized("Your access has changed. Please log in again.".to_string()));
+    }
+    if current.suspended {
+        return Err(AppError::Unauthorized(ACCOUNT_SUSPENDED.to_string()));
+    }
+    if let Some(impersonation) = impersonation {
+        check_impersonation(req, user_id, &impersonation).await?;
+    }
+    Ok(Identity {
+        id: user_id,
+        role: current.role,
+        two_factor_enabled: current.two_factor_enabled,
+        impersonation,
+    })
+}
+
+/// An impersonation lasts until it expires or the admin who started it is
+/// signed out, suspended or no longer an admin
+async fn check_impersonation(req: &HttpRequest, user_id: Uuid, impersonation: &Impersonation) -> Result<(), AppError> {
+    let admin = current_auth_state(req, impersonation.admin_id).await?;
+    if impersonation.is_expired()
+        || admin.role != Role::Admin
+        || admin.suspended
+        || admin.token_version != impersonation.admin_token_version
+    {
+        tracing::info!(user_id = %user_id, admin_id = %impersonation.admin_id, "Impersonation no longer valid");
+        return Err(AppError::Unauthorized("The impersonation session has ended.".to_string()));
+    }
+    tracing::info!(
+        target: "audit",
+        user_id = %user_id,
+        admin_id = %impersonation.admin_id,
+        method = %req.method(),
+        path = req.path(),
+        "Impersonated request"
+    );
+    Ok(())
+}
+
+/// User id, role, token version and any impersonation from the bearer token
+/// if one is sent, otherwise from the session
+async fn presented_credentials(req: &HttpRequest) -> Result<(Uuid, Role, i32, Option<Impersonation>), AppError> {
+    if let Some(header) = req.headers().get(AUTHORIZATION) {
+        let token = header
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| AppError::Unauthorized("Malformed authorization header".to_string()))?;
+        let config = req
+            .app_data::<web::Data<Config>>()
+            .ok_or_else(|| AppError::InternalError("Configuration not found".to_string()))?;
+        let claims = verify_token(token.trim(), &config.jwt.secret)
+            .map_err(|_| AppError::Unauthorized("Invalid or expired token".to_string()))?;
+        if claims.typ != TokenKind::Access {
+            return Err(AppError::Unauthorized("Refresh tokens can't be used to authenticate requests".to_string()));
         }
-        Ok(None) => {
-            tracing::warn!("Session not found in Redis: {}", session_id);
-            Err(AppError::Unauthorized("Invalid or expired session. Please log in again.".to_string()))
+        let user_id = Uuid::parse_str(&claims.sub)
+            .map_err(|_| AppError::Unauthorized("Invalid user ID in token".to_string()))?;
+        return Ok((user_id, claims.role, claims.ver, claims.imp));
+    }
+
+    let session_id = req
+        .cookie("session_id")
+        .ok_or_else(|| AppError::Unauthorized("Not authenticated".to_string()))?;
+    // `CsrfProtection` has already checked the token on web routes. The API
+    // isn't behind it, and a cookie alone would let other sites act for the user.
+    let csrf_checked = req.method().is_safe() || req.extensions().get::<CsrfToken>().is_some();
+    let csrf_header = req.headers().get(CSRF_HEADER).and_then(|v| v.to_str().ok()).map(str::to_string);
+    if !csrf_checked && csrf_header.is_none() {
+        tracing::warn!(method = %req.method(), path = req.path(), "Rejected cookie-authenticated request without a CSRF token");
+        return Err(AppError::Forbidden(CSRF_TOKEN_REQUIRED.to_string()));
+    }
+    let redis = req
+        .app_data::<web::Data<RedisClient>>()
+        .ok_or_else(|| AppError::Unauthorized("Not authenticated".to_string()))?;
+    match redis.get_session_data(session_id.value()).await {
+        Ok(Some(session)) => {
+            if !csrf_checked && !csrf_header.is_some_and(|token| tokens_match(&token, &session.csrf_token)) {
+                tracing::warn!(method = %req.method(), path = req.path(), "Rejected cookie-authenticated request with an invalid CSRF token");
+                return Err(AppError::Forbidden(CSRF_TOKEN_REQUIRED.to_string()));
+            }
+            let role = session
+                .role
+                .parse()
+                .map_err(|_| AppError::Unauthorized("Invalid session. Please log in again.".to_string()))?;
+            Ok((session.user_id, role, session.token_version, session.impersonation))
         }
+        Ok(None) => Err(AppError::Unauthorized("Invalid or expired session. Please log in again.".to_string())),
         Err(e) => {
             tracing::error!("Redis error when looking up session: {:?}", e);
             Err(AppError::Unauthorized("Session lookup failed".to_string()))
@@ -84,10 +338,66 @@ pub async fn extract_user_id_from_session(
     }
 }
 
-pub fn extract_user_id(req: &actix_web::HttpRequest) -> Result<Uuid, AppError> {
-    req.extensions()
-        .get::<AuthenticatedUser>()
-        .map(|user| user.user_id)
-        .ok_or_else(|| AppError::Unauthorized("User not authenticated".to_string()))
+/// Role and token version from the Redis cache, falling back to (and
+/// refilling from) the database
+async fn current_auth_state(req: &HttpRequest, user_id: Uuid) -> Result<UserAuthState, AppError> {
+    let redis = req.app_data::<web::Data<RedisClient>>();
+    if let Some(redis) = redis {
+        match redis.get_user_auth(user_id).await {
+            Ok(Some(state)) => return Ok(state),
+            Ok(None) => {}
+            Err(e) => tracing::warn!(error = ?e, "User auth cache unavailable, reading from database"),
+        }
+    }
+
+    let (role, token_version, two_factor_enabled, suspended) = sqlx::query_as::<_, (Role, i32, bool, bool)>(
+        "SELECT role, token_version, totp_enabled, suspended_at IS NOT NULL FROM users WHERE id = $1"
+    )
+    .bind(user_id)
+    .fetch_optional(db_pool(req)?.get_ref())
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("User no longer exists".to_string()))?;
+    let state = UserAuthState { role, token_version, two_factor_enabled, suspended };
+
+    if let Some(redis) = redis {
+        if let Err(e) = redis.cache_user_auth(user_id, &state).await {
+            tracing::warn!(error = ?e, "Failed to cache user auth state");
+        }
+    }
+    Ok(state)
 }
 
+/// Browsers are sent somewhere useful; API clients get the JSON error
+fn reject(req: &HttpRequest, error: AppError) -> actix_web::Error {
+    let wants_html = req
+        .headers()
+        .get(ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    if !wants_html {
+        return error.into();
+    }
+
+    let response = match error {
+        AppError::Unauthorized(_) => {
+            HttpResponse::SeeOther().append_header(("Location", "/login")).finish()
+        }
+        AppError::Forbidden(message) if message == TWO_FACTOR_REQUIRED => redirect_with_flash(
+            req,
+            "/dashboard/two-factor",
+            FlashMessage::info(message),
+        ),
+        AppError::Forbidden(message) if message == NOT_WHILE_IMPERSONATING => redirect_with_flash(
+            req,
+            "/dashboard",
+            FlashMessage::error(message),
+        ),
+        AppError::Forbidden(_) => redirect_with_flash(
+            req,
+            "/dashboard",
+            FlashMessage::error("You don't have access to that page"),
+        ),
+        other => return other.into(),
+    };
+    InternalError::from_response(format!("Access denied to {}", req.path()), response).into()
+}
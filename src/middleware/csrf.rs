@@ -0,0 +1,197 @@
+use std::rc::Rc;
+
+use actix_web::body::EitherBody;
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::web::{self, Bytes, BytesMut};
+use actix_web::{Error, HttpMessage, HttpRequest, HttpResponse};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use futures_util::StreamExt;
+use uuid::Uuid;
+
+use crate::cache::RedisClient;
+
+/// Form field carrying the token in HTML forms
+pub const CSRF_FIELD: &str = "csrf_token";
+/// Header carrying the token for scripted requests
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+/// Cookie holding the token for visitors without a session (login/register forms)
+const CSRF_COOKIE: &str = "csrf_token";
+/// Largest form body buffered while looking for the token
+const MAX_FORM_BYTES: usize = 256 * 1024;
+
+/// The CSRF token for the current request, set by [`CsrfProtection`]
+#[derive(Debug, Clone)]
+pub struct CsrfToken(pub String);
+
+/// A new random token
+pub fn generate_csrf_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Token to render into forms. Empty on routes without [`CsrfProtection`].
+pub fn csrf_token(req: &HttpRequest) -> String {
+    req.extensions()
+        .get::<CsrfToken>()
+        .map(|token| token.0.clone())
+        .unwrap_or_default()
+}
+
+/// Rejects state-changing requests that don't echo the CSRF token.
+///
+/// Logged-in visitors get a per-session token stored in their `SessionData`;
+/// visitors without a session get one in a `csrf_token` cookie, so the login
+/// and register forms are covered too. The token is made available to
+/// handlers through [`csrf_token`]. On POST/PUT/PATCH/DELETE it must be sent
+/// as the `csrf_token` form field or the `X-CSRF-Token` header.
+pub struct CsrfProtection;
+
+impl<S, B> Transform<S, ServiceRequest> for CsrfProtection
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = CsrfMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CsrfMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct CsrfMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for CsrfMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            let (token, new_cookie) = resolve_token(&req).await;
+            req.extensions_mut().insert(CsrfToken(token.clone()));
+
+            if !req.method().is_safe() {
+                let submitted = match req.headers().get(CSRF_HEADER).and_then(|v| v.to_str().ok()) {
+                    Some(header) => Some(header.to_string()),
+                    None => submitted_form_token(&mut req).await?,
+                };
+
+                if !submitted.is_some_and(|submitted| tokens_match(&submitted, &token)) {
+                    tracing::warn!(
+                        method = %req.method(),
+                        path = req.path(),
+                        "Rejected request with missing or invalid CSRF token"
+                    );
+                    let response = HttpResponse::Forbidden()
+                        .content_type("text/html")
+                        .body("<p>This form has expired. Please go back, reload the page and try again.</p>");
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+            }
+
+            let mut res = service.call(req).await?.map_into_left_body();
+            if let Some(cookie) = new_cookie {
+                res.response_mut().add_cookie(&cookie)?;
+            }
+            Ok(res)
+        })
+    }
+}
+
+/// The session's token, or the anonymous cookie token. Returns a cookie to
+/// set when a new anonymous token had to be issued.
+async fn resolve_token(req: &ServiceRequest) -> (String, Option<Cookie<'static>>) {
+    if let (Some(session_cookie), Some(redis)) = (req.cookie("session_id"), req.app_data::<web::Data<RedisClient>>()) {
+        let session_id = session_cookie.value();
+        match redis.get_session_data(session_id).await {
+            Ok(Some(mut session)) => {
+                if session.csrf_token.is_empty() {
+                    session.csrf_token = generate_csrf_token();
+                    if let Err(e) = redis.update_session_data(session_id, &session).await {
+                        tracing::error!(error = ?e, "Failed to store CSRF token in session");
+                    }
+                }
+                return (session.csrf_token, None);
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!(error = ?e, "Session lookup failed while checking CSRF token"),
+        }
+    }
+
+    if let Some(cookie) = req.cookie(CSRF_COOKIE).filter(|c| !c.value().is_empty()) {
+        return (cookie.value().to_string(), None);
+    }
+
+    let token = generate_csrf_token();
+    let cookie = Cookie::build(CSRF_COOKIE, token.clone())
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .finish();
+    (token, Some(cookie))
+}
+
+/// Reads the token from a urlencoded form body, putting the body back for
+/// the handler. Other content types must use the header.
+async fn submitted_form_token(req: &mut ServiceRequest) -> Result<Option<String>, Error> {
+    let is_form = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/x-www-form-urlencoded"));
+    if !is_form {
+        return Ok(None);
+    }
+
+    let mut payload = req.take_payload();
+    let mut body = BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if body.len() + chunk.len() > MAX_FORM_BYTES {
+            return Err(actix_web::error::ErrorPayloadTooLarge("Form is too large"));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let body: Bytes = body.freeze();
+
+    let token = std::str::from_utf8(&body)
+        .ok()
+        .and_then(|text| web::Query::<Vec<(String, String)>>::from_query(text).ok())
+        .and_then(|fields| {
+            fields
+                .into_inner()
+                .into_iter()
+                .find(|(name, _)| name == CSRF_FIELD)
+                .map(|(_, value)| value)
+        });
+
+    req.set_payload(Payload::from(body));
+    Ok(token)
+}
+
+/// Compares without returning early so timing doesn't reveal the token
+pub(crate) fn tokens_match(submitted: &str, expected: &str) -> bool {
+    if expected.is_empty() || submitted.len() != expected.len() {
+        return false;
+    }
+    submitted
+        .bytes()
+        .zip(expected.bytes())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
@@ -0,0 +1,208 @@
+use std::rc::Rc;
+
+use actix_web::cookie::{time::Duration as CookieDuration, Cookie, SameSite};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{Error, HttpMessage, HttpRequest, HttpResponse};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// Cookie carrying pending messages to the next page
+const FLASH_COOKIE: &str = "flash";
+/// Messages waiting in the cookie are dropped after this long
+const FLASH_TTL_SECONDS: i64 = 300;
+/// Oldest messages are dropped beyond this so the cookie stays small
+const MAX_MESSAGES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlashLevel {
+    Success,
+    Info,
+    Error,
+}
+
+/// A one-shot message shown on the page after a redirect
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlashMessage {
+    pub level: FlashLevel,
+    pub text: String,
+}
+
+impl FlashMessage {
+    pub fn success(text: impl Into<String>) -> Self {
+        FlashMessage { level: FlashLevel::Success, text: text.into() }
+    }
+
+    pub fn info(text: impl Into<String>) -> Self {
+        FlashMessage { level: FlashLevel::Info, text: text.into() }
+    }
+
+    pub fn error(text: impl Into<String>) -> Self {
+        FlashMessage { level: FlashLevel::Error, text: text.into() }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.level {
+            FlashLevel::Success => "Success",
+            FlashLevel::Info => "Note",
+            FlashLevel::Error => "Error",
+        }
+    }
+
+    /// Tailwind classes for the message box
+    pub fn css_class(&self) -> &'static str {
+        match self.level {
+            FlashLevel::Success => "bg-green-50 border-green-200 text-green-800",
+            FlashLevel::Info => "bg-blue-50 border-blue-200 text-blue-800",
+            FlashLevel::Error => "bg-red-50 border-red-200 text-red-800",
+        }
+    }
+}
+
+/// Per-request flash state, set by [`FlashMessages`]
+struct FlashState {
+    key: Rc<[u8]>,
+    incoming: Vec<FlashMessage>,
+}
+
+/// Carries flash messages across redirects in a signed cookie.
+///
+/// The cookie is HMAC-signed with the app secret, so messages can only come
+/// from our own handlers; a crafted or tampered cookie is ignored. Handlers
+/// set messages with [`redirect_with_flash`] and pages read them with
+/// [`flash_messages`]. Once a page has been served the cookie is cleared.
+#[derive(Clone)]
+pub struct FlashMessages {
+    key: Rc<[u8]>,
+}
+
+impl FlashMessages {
+    pub fn new(secret: &str) -> Self {
+        FlashMessages { key: Rc::from(secret.as_bytes()) }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for FlashMessages
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = FlashMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(FlashMiddleware { service: Rc::new(service), key: self.key.clone() }))
+    }
+}
+
+pub struct FlashMiddleware<S> {
+    service: Rc<S>,
+    key: Rc<[u8]>,
+}
+
+impl<S, B> Service<ServiceRequest> for FlashMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let key = self.key.clone();
+
+        Box::pin(async move {
+            let had_cookie = req.cookie(FLASH_COOKIE).is_some();
+            let incoming = req
+                .cookie(FLASH_COOKIE)
+                .and_then(|cookie| decode(&key, cookie.value()))
+                .unwrap_or_default();
+            req.extensions_mut().insert(FlashState { key, incoming });
+
+            let mut res = service.call(req).await?;
+
+            // Anything but a redirect shows the messages, so they're used up.
+            // Redirects keep them for the page they lead to.
+            let sets_flash = res.response().cookies().any(|c| c.name() == FLASH_COOKIE);
+            if had_cookie && !sets_flash && !res.status().is_redirection() {
+                res.response_mut().add_cookie(&flash_cookie(String::new(), 0))?;
+            }
+            Ok(res)
+        })
+    }
+}
+
+/// Messages carried over from the previous request, for rendering
+pub fn flash_messages(req: &HttpRequest) -> Vec<FlashMessage> {
+    req.extensions()
+        .get::<FlashState>()
+        .map(|state| state.incoming.clone())
+        .unwrap_or_default()
+}
+
+/// 303 redirect that shows `message` on the page it leads to
+pub fn redirect_with_flash(req: &HttpRequest, path: &str, message: FlashMessage) -> HttpResponse {
+    let mut response = HttpResponse::SeeOther();
+    response.append_header(("Location", path));
+
+    match req.extensions().get::<FlashState>() {
+        Some(state) => {
+            let mut messages = state.incoming.clone();
+            messages.push(message);
+            let skip = messages.len().saturating_sub(MAX_MESSAGES);
+            response.cookie(flash_cookie(encode(&state.key, &messages[skip..]), FLASH_TTL_SECONDS));
+        }
+        None => tracing::warn!(path = req.path(), "Flash message dropped: route is not wrapped in FlashMessages"),
+    }
+    response.finish()
+}
+
+fn flash_cookie(value: String, max_age: i64) -> Cookie<'static> {
+    Cookie::build(FLASH_COOKIE, value)
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(CookieDuration::seconds(max_age))
+        .finish()
+}
+
+fn mac(key: &[u8]) -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length")
+}
+
+/// `<hex signature>.<url-encoded JSON>`
+fn encode(key: &[u8], messages: &[FlashMessage]) -> String {
+    let json = serde_json::to_string(messages).unwrap_or_else(|_| "[]".to_string());
+    let mut mac = mac(key);
+    mac.update(json.as_bytes());
+    let signature: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}.{}", signature, urlencoding::encode(&json))
+}
+
+fn decode(key: &[u8], value: &str) -> Option<Vec<FlashMessage>> {
+    let (signature, payload) = value.split_once('.')?;
+    let json = urlencoding::decode(payload).ok()?;
+    let signature = (0..signature.len())
+        .step_by(2)
+        .map(|i| signature.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect::<Option<Vec<u8>>>()?;
+
+    let mut mac = mac(key);
+    mac.update(json.as_bytes());
+    mac.verify_slice(&signature).ok()?;
+    serde_json::from_str(&json).ok()
+}
@@ -1,8 +1,14 @@
 pub mod auth;
 pub mod cors;
+pub mod csrf;
+pub mod flash;
+pub mod rate_limit;
 pub mod roles;
 
 pub use auth::*;
 pub use cors::*;
+pub use csrf::*;
+pub use flash::*;
+pub use rate_limit::*;
 pub use roles::*;
 
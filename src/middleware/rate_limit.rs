@@ -0,0 +1,125 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{ACCEPT, RETRY_AFTER};
+use actix_web::{web, Error, HttpResponse};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+
+use crate::cache::RedisClient;
+
+/// Limits state-changing requests per client IP over a sliding window.
+///
+/// Wrap any resource or scope with it; GET and other safe requests pass
+/// through uncounted. Limiters with the same name share their counts, so the
+/// API and web versions of a form draw from one budget. Requests are let
+/// through if Redis is unavailable.
+#[derive(Clone)]
+pub struct RateLimit {
+    name: &'static str,
+    max_requests: u32,
+    window: Duration,
+}
+
+impl RateLimit {
+    pub fn new(name: &'static str, max_requests: u32, window: Duration) -> Self {
+        RateLimit { name, max_requests, window }
+    }
+
+    /// Login attempts, on top of the per-account lockout in `handlers::auth`
+    pub fn login() -> Self {
+        RateLimit::new("login", 10, Duration::from_secs(60))
+    }
+
+    pub fn registration() -> Self {
+        RateLimit::new("register", 5, Duration::from_secs(60 * 60))
+    }
+
+    pub fn booking() -> Self {
+        RateLimit::new("booking", 20, Duration::from_secs(60 * 60))
+    }
+
+    /// Password reset and verification emails, on top of the per-address
+    /// limit in `handlers::auth`
+    pub fn account_email() -> Self {
+        RateLimit::new("account_email", 10, Duration::from_secs(60 * 60))
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware { service: Rc::new(service), limit: self.clone() }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limit: RateLimit,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let limit = self.limit.clone();
+
+        Box::pin(async move {
+            let redis = req.app_data::<web::Data<RedisClient>>().cloned();
+            if let (false, Some(redis)) = (req.method().is_safe(), redis) {
+                // The socket address: forwarded-for headers can be set by anyone
+                let client = req.connection_info().peer_addr().unwrap_or("unknown").to_string();
+                let key = format!("rate:{}:{}", limit.name, client);
+
+                match redis.check_rate_limit(&key, limit.max_requests, limit.window).await {
+                    Ok(None) => {}
+                    Ok(Some(retry_after)) => {
+                        tracing::warn!(limiter = limit.name, client = %client, path = req.path(), "Rate limit exceeded");
+                        let response = too_many_requests(&req, retry_after);
+                        return Ok(req.into_response(response).map_into_right_body());
+                    }
+                    Err(e) => tracing::warn!(limiter = limit.name, error = ?e, "Rate limiter unavailable, allowing request"),
+                }
+            }
+
+            Ok(service.call(req).await?.map_into_left_body())
+        })
+    }
+}
+
+fn too_many_requests(req: &ServiceRequest, retry_after: Duration) -> HttpResponse {
+    let seconds = retry_after.as_secs().max(1);
+    let message = "Too many requests. Please wait a moment and try again.";
+    let mut response = HttpResponse::TooManyRequests();
+    response.insert_header((RETRY_AFTER, seconds.to_string()));
+
+    let wants_html = req
+        .headers()
+        .get(ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    if wants_html {
+        response.content_type("text/html").body(format!("<p>{}</p>", message))
+    } else {
+        response.json(serde_json::json!({ "error": message }))
+    }
+}
@@ -1,92 +1,94 @@
-use actix_web::HttpRequest;
-use crate::db::DbPool;
-use crate::models::{User, Role};
-use crate::errors::AppError;
-use crate::middleware::auth::extract_user_id;
+use crate::models::Role;
 
-/// Extract the authenticated user's role from the database
-pub async fn extract_user_role(
-    req: &HttpRequest,
-    pool: &actix_web::web::Data<DbPool>,
-) -> Result<Role, AppError> {
-    let user_id = extract_user_id(req)?;
-    
-    let user = sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE id = $1"
-    )
-    .bind(user_id)
-    .fetch_optional(pool.get_ref())
-    .await?
-    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
-
-    Ok(user.role)
+/// The roles a [`RequireRole`](crate::middleware::auth::RequireRole) guard lets through
+pub trait RoleSet {
+    const ROLES: &'static [Role];
 }
 
-/// Check if user has one of the required roles
-pub async fn require_roles(
-    req: &HttpRequest,
-    pool: &actix_web::web::Data<DbPool>,
-    allowed_roles: &[Role],
-) -> Result<(), AppError> {
-    let user_role = extract_user_role(req, pool).await?;
-    
-    if allowed_roles.contains(&user_role) {
-        Ok(())
-    } else {
-        Err(AppError::Unauthorized(
-            format!("Access denied. Required roles: {:?}", allowed_roles)
-        ))
+/// Role sets for `RequireRole`
+pub mod role {
+    use super::RoleSet;
+    use crate::models::Role;
+
+    /// Chefs, and admins who can act on any chef's behalf
+    pub struct Chef;
+
+    impl RoleSet for Chef {
+        const ROLES: &'static [Role] = &[Role::Chef, Role::Admin];
     }
-}
 
-/// Check if user is admin
-pub async fn require_admin(
-    req: &HttpRequest,
-    pool: &actix_web::web::Data<DbPool>,
-) -> Result<(), AppError> {
-    require_roles(req, pool, &[Role::Admin]).await
-}
+    pub struct Admin;
+
+    impl RoleSet for Admin {
+        const ROLES: &'static [Role] = &[Role::Admin];
+    }
+
+    /// Admins and moderators
+    pub struct Staff;
 
-/// Check if user is admin or moderator
-pub async fn require_admin_or_mod(
-    req: &HttpRequest,
-    pool: &actix_web::web::Data<DbPool>,
-) -> Result<(), AppError> {
-    require_roles(req, pool, &[Role::Admin, Role::Mod]).await
+    impl RoleSet for Staff {
+        const ROLES: &'static [Role] = &[Role::Admin, Role::Mod];
+    }
 }
 
-/// Check if user is chef
-pub async fn require_chef(
-    req: &HttpRequest,
-    pool: &actix_web::web::Data<DbPool>,
-) -> Result<(), AppError> {
-    require_roles(req, pool, &[Role::Chef]).await
+/// Something a role may be allowed to do. The `ManageOwn*` and
+/// `ViewOwnBookings` permissions only cover resources the user owns; see
+/// [`policy`](crate::policy) for how ownership is checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    /// Hide menus and reviews, dismiss flags, deactivate chefs
+    ManageContent,
+    /// Search, suspend and change the role of users below staff
+    ManageUsers,
+    ViewReports,
+    ReviewChefApplications,
+    RefundBookings,
+    ManageOwnChefProfile,
+    ManageOwnMenus,
+    ManageOwnBookings,
+    /// Change the payment status of a chef's bookings
+    ManageOwnPayments,
+    /// Invite, remove and change the roles of the members of a chef's team
+    ManageOwnTeam,
+    /// Keep the roster of helpers a chef hires for events
+    ManageOwnStaff,
+    ViewChefs,
+    CreateBooking,
+    ViewOwnBookings,
 }
 
-/// Check if user is chef or admin
-pub async fn require_chef_or_admin(
-    req: &HttpRequest,
-    pool: &actix_web::web::Data<DbPool>,
-) -> Result<(), AppError> {
-    require_roles(req, pool, &[Role::Chef, Role::Admin]).await
+impl Permission {
+    /// Whether the permission is limited to the holder's own resources
+    pub fn is_ownership_scoped(self) -> bool {
+        matches!(
+            self,
+            Permission::ManageOwnChefProfile
+                | Permission::ManageOwnMenus
+                | Permission::ManageOwnBookings
+                | Permission::ManageOwnPayments
+                | Permission::ManageOwnTeam
+                | Permission::ManageOwnStaff
+                | Permission::ViewOwnBookings
+        )
+    }
 }
 
-/// Helper function to check if a role has permission for an action
-pub fn has_permission(role: Role, action: &str) -> bool {
+/// Whether `role` has `permission` at all. Admins have every permission.
+pub fn has_permission(role: Role, permission: Permission) -> bool {
+    use Permission::*;
+
     match role {
-        Role::Admin => true, // Admins can do everything
-        Role::Mod => {
-            // Moderators can manage content and users (except admins)
-            matches!(action, "manage_content" | "manage_users" | "view_reports")
-        }
-        Role::Chef => {
-            // Chefs can manage their own content
-            matches!(action, "manage_own_chef_profile" | "manage_own_menus" | "manage_own_bookings")
-        }
-        Role::Diner => {
-            // Diners can view and book
-            matches!(action, "view_chefs" | "create_booking" | "view_own_bookings")
-        }
+        Role::Admin => true,
+        Role::Mod => matches!(permission, ManageContent | ManageUsers | ViewReports | ReviewChefApplications),
+        Role::Chef => matches!(
+            permission,
+            ManageOwnChefProfile
+                | ManageOwnMenus
+                | ManageOwnBookings
+                | ManageOwnPayments
+                | ManageOwnTeam
+                | ManageOwnStaff
+        ),
+        Role::Diner => matches!(permission, ViewChefs | CreateBooking | ViewOwnBookings),
     }
 }
-
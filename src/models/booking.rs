@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use super::{AddOnSelection, GuestSelection};
+
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct Booking {
     pub id: Uuid,
@@ -37,6 +39,106 @@ pub struct CreateBooking {
     pub number_of_guests: i32,
     pub location_address: String,
     pub special_requests: Option<String>,
+    #[serde(default)]
+    pub guest_selections: Vec<GuestSelection>,
+    #[serde(default)]
+    pub add_ons: Vec<AddOnSelection>,
+}
+
+/// Lifecycle of a booking. Stored as lowercase text in `bookings.status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookingStatus {
+    Pending,
+    Confirmed,
+    Declined,
+    Cancelled,
+    Completed,
+}
+
+impl BookingStatus {
+    /// Allowed moves: pending -> confirmed/declined/cancelled,
+    /// confirmed -> completed/cancelled. Declined, cancelled and completed are final.
+    pub fn can_transition_to(self, next: BookingStatus) -> bool {
+        use BookingStatus::*;
+        matches!(
+            (self, next),
+            (Pending, Confirmed) | (Pending, Declined) | (Pending, Cancelled)
+                | (Confirmed, Completed) | (Confirmed, Cancelled)
+        )
+    }
+
+    /// Pending and confirmed bookings hold the chef's time slot
+    pub fn is_active(self) -> bool {
+        matches!(self, BookingStatus::Pending | BookingStatus::Confirmed)
+    }
+}
+
+impl std::fmt::Display for BookingStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BookingStatus::Pending => write!(f, "pending"),
+            BookingStatus::Confirmed => write!(f, "confirmed"),
+            BookingStatus::Declined => write!(f, "declined"),
+            BookingStatus::Cancelled => write!(f, "cancelled"),
+            BookingStatus::Completed => write!(f, "completed"),
+        }
+    }
+}
+
+impl std::str::FromStr for BookingStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pending" => Ok(BookingStatus::Pending),
+            "confirmed" => Ok(BookingStatus::Confirmed),
+            "declined" => Ok(BookingStatus::Declined),
+            "cancelled" => Ok(BookingStatus::Cancelled),
+            "completed" => Ok(BookingStatus::Completed),
+            _ => Err(format!("Invalid booking status: {}", s)),
+        }
+    }
+}
+
+impl Booking {
+    /// Parsed status; unknown values are treated as pending
+    pub fn booking_status(&self) -> BookingStatus {
+        self.status.parse().unwrap_or(BookingStatus::Pending)
+    }
+
+    /// Whether the booking may move to `next` on `today`. Events can only be
+    /// marked completed from their day onwards.
+    pub fn can_become(&self, next: BookingStatus, today: NaiveDate) -> bool {
+        self.booking_status().can_transition_to(next) && (next != BookingStatus::Completed || self.event_date <= today)
+    }
+}
+
+/// A booking with the chef and menu names, for dashboards
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct BookingSummary {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub booking: Booking,
+    pub chef_name: String,
+    pub chef_slug: Option<String>,
+    pub menu_name: Option<String>,
+}
+
+impl BookingSummary {
+    /// Diners can cancel active bookings until the day of the event
+    pub fn can_cancel(&self, today: NaiveDate) -> bool {
+        self.booking.booking_status().is_active() && self.booking.event_date > today
+    }
+
+    /// Chefs confirm or decline requests that are still pending
+    pub fn awaits_reply(&self) -> bool {
+        self.booking.booking_status() == BookingStatus::Pending
+    }
+
+    /// Confirmed events can be marked completed from their day onwards
+    pub fn can_complete(&self, today: NaiveDate) -> bool {
+        self.booking.can_become(BookingStatus::Completed, today)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,224 @@
+use chrono::{NaiveDate, NaiveTime};
+use uuid::Uuid;
+
+use super::{AddOnSelection, CreateBooking};
+
+/// Longest event the booking wizard accepts
+const MAX_DURATION_HOURS: f64 = 12.0;
+
+/// Steps of the web booking wizard, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WizardStep {
+    Date,
+    Menu,
+    Guests,
+    Details,
+    Review,
+}
+
+impl WizardStep {
+    pub const ALL: [WizardStep; 5] = [
+        WizardStep::Date,
+        WizardStep::Menu,
+        WizardStep::Guests,
+        WizardStep::Details,
+        WizardStep::Review,
+    ];
+
+    pub fn number(self) -> usize {
+        WizardStep::ALL.iter().position(|s| *s == self).unwrap_or(0) + 1
+    }
+
+    pub fn from_number(number: usize) -> Option<Self> {
+        number.checked_sub(1).and_then(|i| WizardStep::ALL.get(i).copied())
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            WizardStep::Date => "Date",
+            WizardStep::Menu => "Menu",
+            WizardStep::Guests => "Guests",
+            WizardStep::Details => "Details",
+            WizardStep::Review => "Review",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        WizardStep::from_number(self.number() + 1).unwrap_or(WizardStep::Review)
+    }
+
+    pub fn previous(self) -> Self {
+        WizardStep::from_number(self.number() - 1).unwrap_or(WizardStep::Date)
+    }
+}
+
+/// Answers collected so far by the booking wizard.
+///
+/// The wizard is stateless on the server: every step posts all earlier
+/// answers back as hidden fields, and each step is re-validated on submit.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookingDraft {
+    pub slot: Option<String>, // "YYYY-MM-DD HH:MM"
+    pub menu_id: Option<Uuid>,
+    pub add_on_ids: Vec<Uuid>,
+    pub number_of_guests: Option<i32>,
+    pub duration_hours: Option<f64>,
+    pub dietary_notes: Option<String>,
+    pub customer_name: Option<String>,
+    pub customer_phone: Option<String>,
+    pub location_address: Option<String>,
+    pub special_requests: Option<String>,
+}
+
+impl BookingDraft {
+    /// Read a draft from urlencoded form pairs. Unparseable values are
+    /// dropped and reported when their step is validated.
+    pub fn from_form(fields: &[(String, String)]) -> Self {
+        let mut draft = BookingDraft::default();
+        for (name, value) in fields {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match name.as_str() {
+                "slot" => draft.slot = Some(value.to_string()),
+                "menu_id" => draft.menu_id = value.parse().ok(),
+                "add_on" => {
+                    if let Ok(id) = value.parse() {
+                        if !draft.add_on_ids.contains(&id) {
+                            draft.add_on_ids.push(id);
+                        }
+                    }
+                }
+                "number_of_guests" => draft.number_of_guests = value.parse().ok(),
+                "duration_hours" => draft.duration_hours = value.parse().ok(),
+                "dietary_notes" => draft.dietary_notes = Some(value.to_string()),
+                "customer_name" => draft.customer_name = Some(value.to_string()),
+                "customer_phone" => draft.customer_phone = Some(value.to_string()),
+                "location_address" => draft.location_address = Some(value.to_string()),
+                "special_requests" => draft.special_requests = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        draft
+    }
+
+    /// Fields to carry as hidden inputs on `step`, i.e. every answer not edited there
+    pub fn hidden_fields(&self, step: WizardStep) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+        let mut push = |name: &'static str, value: Option<String>| {
+            if let Some(value) = value {
+                fields.push((name, value));
+            }
+        };
+
+        if step != WizardStep::Date {
+            push("slot", self.slot.clone());
+        }
+        if step != WizardStep::Menu {
+            push("menu_id", self.menu_id.map(|id| id.to_string()));
+            for id in &self.add_on_ids {
+                push("add_on", Some(id.to_string()));
+            }
+        }
+        if step != WizardStep::Guests {
+            push("number_of_guests", self.number_of_guests.map(|n| n.to_string()));
+            push("duration_hours", self.duration_hours.map(|h| h.to_string()));
+            push("dietary_notes", self.dietary_notes.clone());
+        }
+        if step != WizardStep::Details {
+            push("customer_name", self.customer_name.clone());
+            push("customer_phone", self.customer_phone.clone());
+            push("location_address", self.location_address.clone());
+            push("special_requests", self.special_requests.clone());
+        }
+        fields
+    }
+
+    pub fn event_slot(&self) -> Option<(NaiveDate, NaiveTime)> {
+        let (date, time) = self.slot.as_deref()?.split_once(' ')?;
+        Some((
+            NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?,
+            NaiveTime::parse_from_str(time, "%H:%M").ok()?,
+        ))
+    }
+
+    pub fn is_slot(&self, date: &NaiveDate, time: &str) -> bool {
+        self.slot.as_deref() == Some(format!("{} {}", date, time).as_str())
+    }
+
+    pub fn is_menu(&self, menu_id: &Uuid) -> bool {
+        self.menu_id.as_ref() == Some(menu_id)
+    }
+
+    pub fn has_add_on(&self, add_on_id: &Uuid) -> bool {
+        self.add_on_ids.contains(add_on_id)
+    }
+
+    /// Checks the answers a step asks for. Checks that need the database
+    /// (open slots, menu ownership, minimum guests) are done by the handler.
+    pub fn validate_step(&self, step: WizardStep) -> Result<(), String> {
+        match step {
+            WizardStep::Date => {
+                self.event_slot().ok_or("Please pick a date and time")?;
+            }
+            WizardStep::Menu => {
+                if self.menu_id.is_none() && !self.add_on_ids.is_empty() {
+                    return Err("Add-ons need a menu".to_string());
+                }
+            }
+            WizardStep::Guests => {
+                let guests = self.number_of_guests.ok_or("Number of guests is required")?;
+                if guests < 1 {
+                    return Err("At least one guest is required".to_string());
+                }
+                let hours = self.duration_hours.ok_or("Event length is required")?;
+                if hours <= 0.0 || hours > MAX_DURATION_HOURS {
+                    return Err(format!("Event length must be between 0 and {} hours", MAX_DURATION_HOURS));
+                }
+            }
+            WizardStep::Details => {
+                self.customer_name.as_ref().ok_or("Your name is required")?;
+                self.location_address.as_ref().ok_or("The event address is required")?;
+            }
+            WizardStep::Review => {
+                for step in &WizardStep::ALL[..WizardStep::ALL.len() - 1] {
+                    self.validate_step(*step)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The booking request this draft describes. Dietary notes are passed
+    /// to the chef with the special requests.
+    pub fn to_create_booking(&self, customer_email: &str) -> Result<CreateBooking, String> {
+        self.validate_step(WizardStep::Review)?;
+        let (event_date, event_time) = self.event_slot().ok_or("Please pick a date and time")?;
+
+        let special_requests = match (&self.dietary_notes, &self.special_requests) {
+            (Some(dietary), Some(requests)) => Some(format!("Dietary: {}\n{}", dietary, requests)),
+            (Some(dietary), None) => Some(format!("Dietary: {}", dietary)),
+            (None, requests) => requests.clone(),
+        };
+
+        Ok(CreateBooking {
+            menu_id: self.menu_id,
+            customer_name: self.customer_name.clone().unwrap_or_default(),
+            customer_email: customer_email.to_string(),
+            customer_phone: self.customer_phone.clone(),
+            event_date,
+            event_time,
+            duration_hours: self.duration_hours.unwrap_or_default(),
+            number_of_guests: self.number_of_guests.unwrap_or_default(),
+            location_address: self.location_address.clone().unwrap_or_default(),
+            special_requests,
+            guest_selections: Vec::new(),
+            add_ons: self
+                .add_on_ids
+                .iter()
+                .map(|id| AddOnSelection { add_on_id: *id, quantity: None })
+                .collect(),
+        })
+    }
+}
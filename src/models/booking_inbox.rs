@@ -0,0 +1,165 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime};
+use uuid::Uuid;
+
+use super::{BookingStatus, BookingSummary};
+
+/// Tabs of the chef dashboard booking inbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BookingTab {
+    #[default]
+    Pending,
+    Upcoming,
+    Past,
+}
+
+impl BookingTab {
+    pub const ALL: [BookingTab; 3] = [BookingTab::Pending, BookingTab::Upcoming, BookingTab::Past];
+
+    /// Parse the `?tab=` query value; anything unknown shows the pending tab
+    pub fn from_query(value: Option<&str>) -> Self {
+        match value {
+            Some("upcoming") => BookingTab::Upcoming,
+            Some("past") => BookingTab::Past,
+            _ => BookingTab::Pending,
+        }
+    }
+
+    pub fn slug(self) -> &'static str {
+        match self {
+            BookingTab::Pending => "pending",
+            BookingTab::Upcoming => "upcoming",
+            BookingTab::Past => "past",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            BookingTab::Pending => "Pending",
+            BookingTab::Upcoming => "Upcoming",
+            BookingTab::Past => "Past",
+        }
+    }
+
+    /// Pending requests wait for the chef whatever their date; confirmed
+    /// events are upcoming until their day has passed; the rest is history.
+    pub fn contains(self, booking: &BookingSummary, today: NaiveDate) -> bool {
+        let status = booking.booking.booking_status();
+        let upcoming = status == BookingStatus::Confirmed && booking.booking.event_date >= today;
+        match self {
+            BookingTab::Pending => status == BookingStatus::Pending,
+            BookingTab::Upcoming => upcoming,
+            BookingTab::Past => status != BookingStatus::Pending && !upcoming,
+        }
+    }
+
+    /// The bookings shown on this tab: soonest first, except the past tab
+    /// which shows the most recent first
+    pub fn select(self, bookings: &[BookingSummary], today: NaiveDate) -> Vec<BookingSummary> {
+        let mut selected: Vec<BookingSummary> = bookings
+            .iter()
+            .filter(|b| self.contains(b, today))
+            .cloned()
+            .collect();
+        selected.sort_by_key(|b| (b.booking.event_date, b.booking.event_time));
+        if self == BookingTab::Past {
+            selected.reverse();
+        }
+        selected
+    }
+}
+
+/// A booking as shown in a calendar cell
+#[derive(Debug, Clone)]
+pub struct CalendarEntry {
+    pub booking_id: Uuid,
+    pub time: NaiveTime,
+    pub customer_name: String,
+    pub number_of_guests: i32,
+    pub status: BookingStatus,
+}
+
+#[derive(Debug, Clone)]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    pub in_month: bool,
+    pub is_today: bool,
+    pub entries: Vec<CalendarEntry>,
+}
+
+/// Month view of a chef's bookings, in Monday-first weeks. Declined and
+/// cancelled bookings are left out since they don't take up the day.
+#[derive(Debug, Clone)]
+pub struct BookingCalendar {
+    pub month: NaiveDate, // first day of the month
+    pub weeks: Vec<Vec<CalendarDay>>,
+}
+
+impl BookingCalendar {
+    pub fn build(month: NaiveDate, bookings: &[BookingSummary], today: NaiveDate) -> Self {
+        let month = month.with_day(1).unwrap_or(month);
+        let start = month - Duration::days(month.weekday().num_days_from_monday() as i64);
+
+        let mut weeks = Vec::new();
+        let mut date = start;
+        while weeks.is_empty() || date.month() == month.month() {
+            let mut week = Vec::with_capacity(7);
+            for _ in 0..7 {
+                let mut entries: Vec<CalendarEntry> = bookings
+                    .iter()
+                    .filter(|b| b.booking.event_date == date)
+                    .filter(|b| {
+                        !matches!(
+                            b.booking.booking_status(),
+                            BookingStatus::Declined | BookingStatus::Cancelled
+                        )
+                    })
+                    .map(|b| CalendarEntry {
+                        booking_id: b.booking.id,
+                        time: b.booking.event_time,
+                        customer_name: b.booking.customer_name.clone(),
+                        number_of_guests: b.booking.number_of_guests,
+                        status: b.booking.booking_status(),
+                    })
+                    .collect();
+                entries.sort_by_key(|e| e.time);
+
+                week.push(CalendarDay {
+                    date,
+                    in_month: date.month() == month.month(),
+                    is_today: date == today,
+                    entries,
+                });
+                date += Duration::days(1);
+            }
+            weeks.push(week);
+        }
+
+        BookingCalendar { month, weeks }
+    }
+
+    /// Parse a `?month=YYYY-MM` query value
+    pub fn parse_month(value: &str) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d").ok()
+    }
+
+    pub fn title(&self) -> String {
+        self.month.format("%B %Y").to_string()
+    }
+
+    /// `YYYY-MM` of the previous month, for navigation links
+    pub fn previous_month(&self) -> String {
+        (self.month - Duration::days(1)).format("%Y-%m").to_string()
+    }
+
+    /// `YYYY-MM` of the next month, for navigation links
+    pub fn next_month(&self) -> String {
+        (self.month + Duration::days(31)).format("%Y-%m").to_string()
+    }
+
+    /// First and last day shown, including days from neighbouring months
+    pub fn range(&self) -> (NaiveDate, NaiveDate) {
+        let first = self.weeks.first().and_then(|w| w.first()).map(|d| d.date).unwrap_or(self.month);
+        let last = self.weeks.last().and_then(|w| w.last()).map(|d| d.date).unwrap_or(self.month);
+        (first, last)
+    }
+}
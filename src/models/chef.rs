@@ -3,6 +3,9 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use super::form::{self, FormData};
+use super::MenuItem;
+
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct Chef {
     pub id: Uuid,
@@ -61,6 +64,47 @@ pub struct UpdateChef {
     pub is_active: Option<bool>,
 }
 
+impl UpdateChef {
+    /// Read the dashboard profile form. The form always carries every field,
+    /// so blank optional inputs come back as `None` and clear the column.
+    pub fn from_form(form: &FormData) -> Result<Self, String> {
+        let hourly_rate = form::number::<f64>(form, "hourly_rate", "Hourly rate")?;
+        if hourly_rate.is_some_and(|rate| rate < 0.0) {
+            return Err("Hourly rate can't be negative".to_string());
+        }
+        let minimum_hours = form::number::<i32>(form, "minimum_hours", "Minimum hours")?
+            .ok_or("Minimum hours is required")?;
+        if minimum_hours < 1 {
+            return Err("Minimum hours must be at least 1".to_string());
+        }
+        let travel_radius = form::number::<i32>(form, "travel_radius", "Travel radius")?;
+        if travel_radius.is_some_and(|radius| radius < 0) {
+            return Err("Travel radius can't be negative".to_string());
+        }
+        let email = form::text(form, "email");
+        if email.as_deref().is_some_and(|e| !e.contains('@')) {
+            return Err("Contact email is not a valid email address".to_string());
+        }
+
+        Ok(UpdateChef {
+            business_name: form::text(form, "business_name"),
+            chef_name: Some(form::required(form, "chef_name", "Chef name")?),
+            bio: form::text(form, "bio"),
+            cuisine_types: form::list(form, "cuisine_types"),
+            location: form::text(form, "location"),
+            phone: form::text(form, "phone"),
+            email,
+            website: form::text(form, "website"),
+            profile_image_url: form::text(form, "profile_image_url"),
+            cover_image_url: form::text(form, "cover_image_url"),
+            hourly_rate,
+            minimum_hours: Some(minimum_hours),
+            travel_radius,
+            is_active: Some(form::checkbox(form, "is_active")),
+        })
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct ChefPublicProfile {
     pub id: Uuid,
@@ -84,6 +128,29 @@ pub struct MenuItemPublic {
     pub description: Option<String>,
     pub course_type: Option<String>,
     pub image_url: Option<String>,
+    pub choice_group_id: Option<Uuid>,
+    pub price_adjustment: Option<f64>,
+}
+
+impl MenuItemPublic {
+    /// Per-guest surcharge worth showing next to the dish
+    pub fn supplement(&self) -> Option<f64> {
+        self.price_adjustment.filter(|amount| *amount > 0.0)
+    }
+}
+
+impl From<MenuItem> for MenuItemPublic {
+    fn from(item: MenuItem) -> Self {
+        MenuItemPublic {
+            id: item.id,
+            name: item.name,
+            description: item.description,
+            course_type: item.course_type,
+            image_url: item.image_url,
+            choice_group_id: item.choice_group_id,
+            price_adjustment: item.price_adjustment,
+        }
+    }
 }
 
 impl From<Chef> for ChefPublicProfile {
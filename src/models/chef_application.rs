@@ -0,0 +1,102 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// The credentials a chef application must include. Stored as snake_case
+/// text in `chef_application_documents.kind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    FoodSafetyCertificate,
+    Insurance,
+}
+
+impl DocumentKind {
+    pub const ALL: [DocumentKind; 2] = [DocumentKind::FoodSafetyCertificate, DocumentKind::Insurance];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DocumentKind::FoodSafetyCertificate => "Food safety certificate",
+            DocumentKind::Insurance => "Insurance",
+        }
+    }
+}
+
+impl std::fmt::Display for DocumentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentKind::FoodSafetyCertificate => write!(f, "food_safety_certificate"),
+            DocumentKind::Insurance => write!(f, "insurance"),
+        }
+    }
+}
+
+impl std::str::FromStr for DocumentKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "food_safety_certificate" => Ok(DocumentKind::FoodSafetyCertificate),
+            "insurance" => Ok(DocumentKind::Insurance),
+            _ => Err(format!("Invalid document kind: {}", s)),
+        }
+    }
+}
+
+/// A diner's request to become a chef
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct ChefApplication {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// The applicant's cooking experience, in their own words
+    pub experience: String,
+    /// pending, approved or rejected
+    pub status: String,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub rejection_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ChefApplication {
+    pub fn is_pending(&self) -> bool {
+        self.status == "pending"
+    }
+}
+
+/// A credential uploaded with an application. The file itself is private;
+/// staff download it through the app.
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct ChefApplicationDocument {
+    pub id: Uuid,
+    pub application_id: Uuid,
+    pub kind: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i32,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ChefApplicationDocument {
+    pub fn kind_label(&self) -> &str {
+        self.kind.parse::<DocumentKind>().map(DocumentKind::label).unwrap_or(&self.kind)
+    }
+}
+
+/// An application with its documents, as returned by the API
+#[derive(Debug, Serialize, Clone)]
+pub struct ChefApplicationDetail {
+    #[serde(flatten)]
+    pub application: ChefApplication,
+    pub applicant_email: String,
+    pub documents: Vec<ChefApplicationDocument>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RejectApplication {
+    pub reason: String,
+}
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::middleware::roles::Permission;
+
+/// A user's role on a chef's team. Stored as lowercase text in
+/// `chef_members.role` and `chef_invitations.role`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TeamRole {
+    /// Created the chef profile. Manages the team as well as everything else.
+    Owner,
+    /// Edits the profile, menus and staff roster and handles bookings and
+    /// their payments
+    Manager,
+    /// Handles bookings, but not their payments
+    Staff,
+}
+
+impl TeamRole {
+    /// The roles an owner can invite people to or give them
+    pub const INVITABLE: [TeamRole; 2] = [TeamRole::Manager, TeamRole::Staff];
+
+    /// Whether a member with this role may use `permission` on the team's
+    /// resources. Only chef-scoped permissions are ever granted.
+    pub fn allows(self, permission: Permission) -> bool {
+        use Permission::*;
+
+        match self {
+            TeamRole::Owner => matches!(
+                permission,
+                ManageOwnChefProfile
+                    | ManageOwnMenus
+                    | ManageOwnBookings
+                    | ManageOwnPayments
+                    | ManageOwnTeam
+                    | ManageOwnStaff
+            ),
+            TeamRole::Manager => matches!(
+                permission,
+                ManageOwnChefProfile | ManageOwnMenus | ManageOwnBookings | ManageOwnPayments | ManageOwnStaff
+            ),
+            TeamRole::Staff => matches!(permission, ManageOwnBookings),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TeamRole::Owner => "Owner",
+            TeamRole::Manager => "Manager",
+            TeamRole::Staff => "Staff",
+        }
+    }
+}
+
+impl std::fmt::Display for TeamRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TeamRole::Owner => write!(f, "owner"),
+            TeamRole::Manager => write!(f, "manager"),
+            TeamRole::Staff => write!(f, "staff"),
+        }
+    }
+}
+
+impl std::str::FromStr for TeamRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "owner" => Ok(TeamRole::Owner),
+            "manager" => Ok(TeamRole::Manager),
+            "staff" => Ok(TeamRole::Staff),
+            _ => Err(format!("Invalid team role: {}", s)),
+        }
+    }
+}
+
+/// A member of a chef's team, with their email for display
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct TeamMember {
+    pub chef_id: Uuid,
+    pub user_id: Uuid,
+    pub email: String,
+    /// owner, manager or staff
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TeamMember {
+    pub fn team_role(&self) -> Option<TeamRole> {
+        self.role.parse().ok()
+    }
+
+    pub fn role_label(&self) -> &str {
+        self.team_role().map(TeamRole::label).unwrap_or(&self.role)
+    }
+
+    pub fn is_owner(&self) -> bool {
+        self.team_role() == Some(TeamRole::Owner)
+    }
+}
+
+/// An emailed invitation to join a chef's team
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct ChefInvitation {
+    pub id: Uuid,
+    pub chef_id: Uuid,
+    pub email: String,
+    /// manager or staff
+    pub role: String,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub invited_by: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub accepted_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ChefInvitation {
+    pub fn team_role(&self) -> Option<TeamRole> {
+        self.role.parse().ok()
+    }
+
+    pub fn role_label(&self) -> &str {
+        self.team_role().map(TeamRole::label).unwrap_or(&self.role)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    pub fn expires_at_display(&self) -> String {
+        self.expires_at.format("%b %-d, %Y").to_string()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InviteTeamMember {
+    pub email: String,
+    pub role: TeamRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeTeamRole {
+    pub role: TeamRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptInvitation {
+    pub token: String,
+}
@@ -0,0 +1,39 @@
+//! Helpers for reading urlencoded web forms into the update models.
+//! Blank inputs read as `None` so an emptied field clears the column.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+pub(crate) type FormData = HashMap<String, String>;
+
+/// Trimmed text, `None` when missing or blank
+pub(crate) fn text(form: &FormData, key: &str) -> Option<String> {
+    form.get(key)
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// Required trimmed text
+pub(crate) fn required(form: &FormData, key: &str, label: &str) -> Result<String, String> {
+    text(form, key).ok_or_else(|| format!("{} is required", label))
+}
+
+/// A number, `None` when blank, an error when it doesn't parse
+pub(crate) fn number<T: FromStr>(form: &FormData, key: &str, label: &str) -> Result<Option<T>, String> {
+    text(form, key)
+        .map(|s| s.parse::<T>().map_err(|_| format!("{} must be a number", label)))
+        .transpose()
+}
+
+/// HTML checkboxes are only submitted when ticked
+pub(crate) fn checkbox(form: &FormData, key: &str) -> bool {
+    form.get(key).map(|s| s == "on" || s == "true").unwrap_or(false)
+}
+
+/// Comma-separated list, `None` when empty
+pub(crate) fn list(form: &FormData, key: &str) -> Option<Vec<String>> {
+    text(form, key)
+        .map(|s| s.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect::<Vec<_>>())
+        .filter(|v| !v.is_empty())
+}
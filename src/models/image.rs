@@ -0,0 +1,18 @@
+use serde::Serialize;
+
+/// A stored rendition of an uploaded image
+#[derive(Debug, Serialize, Clone)]
+pub struct UploadedImageVariant {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+    pub content_type: String,
+}
+
+/// Response for image uploads. `url` is the largest JPEG/PNG variant and is
+/// the value written back to the profile, cover or menu item.
+#[derive(Debug, Serialize, Clone)]
+pub struct UploadedImage {
+    pub url: String,
+    pub variants: Vec<UploadedImageVariant>,
+}
@@ -3,6 +3,9 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use super::form::{self, FormData};
+use super::{MenuAddOn, MenuChoiceGroup, MenuItem, MenuItemPublic};
+
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct Menu {
     pub id: Uuid,
@@ -42,3 +45,110 @@ pub struct UpdateMenu {
     pub is_active: Option<bool>,
 }
 
+impl UpdateMenu {
+    /// Read the dashboard menu form; blank optional inputs clear the column
+    pub fn from_form(form: &FormData) -> Result<Self, String> {
+        let price_per_person = form::number::<f64>(form, "price_per_person", "Price per person")?;
+        if price_per_person.is_some_and(|price| price < 0.0) {
+            return Err("Price per person can't be negative".to_string());
+        }
+        let minimum_guests = form::number::<i32>(form, "minimum_guests", "Minimum guests")?
+            .ok_or("Minimum guests is required")?;
+        if minimum_guests < 1 {
+            return Err("Minimum guests must be at least 1".to_string());
+        }
+        let duration_hours = form::number::<f64>(form, "duration_hours", "Duration")?;
+        if duration_hours.is_some_and(|hours| hours <= 0.0) {
+            return Err("Duration must be greater than zero".to_string());
+        }
+
+        Ok(UpdateMenu {
+            name: Some(form::required(form, "name", "Menu name")?),
+            description: form::text(form, "description"),
+            price_per_person,
+            minimum_guests: Some(minimum_guests),
+            cuisine_type: form::text(form, "cuisine_type"),
+            dietary_options: form::list(form, "dietary_options"),
+            duration_hours,
+            is_active: Some(form::checkbox(form, "is_active")),
+        })
+    }
+}
+
+
+#[derive(Debug, Deserialize)]
+pub struct DuplicateMenu {
+    pub name: Option<String>, // Defaults to "<original name> (copy)"
+}
+
+/// Items sharing a course, in the order the chef arranged them
+#[derive(Debug, Serialize)]
+pub struct MenuCourse {
+    pub course_type: Option<String>,
+    pub items: Vec<MenuItemPublic>,
+}
+
+/// An active menu as diners see it
+#[derive(Debug, Serialize)]
+pub struct PublicMenu {
+    pub id: Uuid,
+    pub chef_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub price_per_person: Option<f64>,
+    pub minimum_guests: i32,
+    pub cuisine_type: Option<String>,
+    pub dietary_options: Option<Vec<String>>,
+    pub duration_hours: Option<f64>,
+    pub courses: Vec<MenuCourse>,
+    pub choice_groups: Vec<MenuChoiceGroup>,
+    pub add_ons: Vec<MenuAddOn>,
+    pub updated_at: DateTime<Utc>, // Latest change to the menu or anything on it
+}
+
+impl PublicMenu {
+    /// Group `items` (already in display order) into courses. Courses appear
+    /// in the order of their first item; only active add-ons are kept.
+    pub fn build(
+        menu: Menu,
+        items: Vec<MenuItem>,
+        choice_groups: Vec<MenuChoiceGroup>,
+        add_ons: Vec<MenuAddOn>,
+    ) -> Self {
+        let add_ons: Vec<MenuAddOn> = add_ons.into_iter().filter(|a| a.is_active).collect();
+
+        let updated_at = items
+            .iter()
+            .map(|i| i.updated_at)
+            .chain(choice_groups.iter().map(|g| g.updated_at))
+            .chain(add_ons.iter().map(|a| a.updated_at))
+            .fold(menu.updated_at, |latest, t| latest.max(t));
+
+        let mut courses: Vec<MenuCourse> = Vec::new();
+        for item in items {
+            match courses.iter_mut().find(|c| c.course_type == item.course_type) {
+                Some(course) => course.items.push(item.into()),
+                None => courses.push(MenuCourse {
+                    course_type: item.course_type.clone(),
+                    items: vec![item.into()],
+                }),
+            }
+        }
+
+        PublicMenu {
+            id: menu.id,
+            chef_id: menu.chef_id,
+            name: menu.name,
+            description: menu.description,
+            price_per_person: menu.price_per_person,
+            minimum_guests: menu.minimum_guests,
+            cuisine_type: menu.cuisine_type,
+            dietary_options: menu.dietary_options,
+            duration_hours: menu.duration_hours,
+            courses,
+            choice_groups,
+            add_ons,
+            updated_at,
+        }
+    }
+}
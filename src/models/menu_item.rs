@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use super::form::{self, FormData};
+
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct MenuItem {
     pub id: Uuid,
@@ -14,6 +16,8 @@ pub struct MenuItem {
     pub is_featured: bool,
     pub display_order: i32,
     pub quantity: Option<i32>, // Number of plates/servings for this item
+    pub choice_group_id: Option<Uuid>, // Set when guests pick this item from a choice group
+    pub price_adjustment: Option<f64>, // Per-guest supplement when picked
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -27,6 +31,8 @@ pub struct CreateMenuItem {
     pub is_featured: Option<bool>,
     pub display_order: Option<i32>,
     pub quantity: Option<i32>, // Number of plates/servings
+    pub choice_group_id: Option<Uuid>,
+    pub price_adjustment: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -38,5 +44,110 @@ pub struct UpdateMenuItem {
     pub is_featured: Option<bool>,
     pub display_order: Option<i32>,
     pub quantity: Option<i32>, // Number of plates/servings
+    pub choice_group_id: Option<Uuid>,
+    pub price_adjustment: Option<f64>,
+    /// Take the item out of its choice group, making it a fixed course again.
+    /// A missing or null `choice_group_id` leaves the group unchanged.
+    #[serde(default)]
+    pub clear_choice_group: bool,
+}
+
+impl UpdateMenuItem {
+    /// Rejects asking for a new choice group and no group at the same time
+    pub fn check(&self) -> Result<(), String> {
+        if self.clear_choice_group && self.choice_group_id.is_some() {
+            return Err("Set either choice_group_id or clear_choice_group, not both".to_string());
+        }
+        Ok(())
+    }
+
+    /// Read the dashboard dish form; blank optional inputs clear the column
+    pub fn from_form(form: &FormData) -> Result<Self, String> {
+        let quantity = form::number::<i32>(form, "quantity", "Plates")?;
+        if quantity.is_some_and(|q| q < 1) {
+            return Err("Plates must be at least 1".to_string());
+        }
+        let choice_group_id = form::text(form, "choice_group_id")
+            .map(|id| id.parse::<Uuid>().map_err(|_| "Invalid choice group".to_string()))
+            .transpose()?;
+
+        Ok(UpdateMenuItem {
+            name: Some(form::required(form, "name", "Dish name")?),
+            description: form::text(form, "description"),
+            course_type: form::text(form, "course_type"),
+            image_url: form::text(form, "image_url"),
+            is_featured: Some(form::checkbox(form, "is_featured")),
+            display_order: Some(form::number::<i32>(form, "display_order", "Display order")?.unwrap_or(0)),
+            quantity,
+            choice_group_id,
+            price_adjustment: form::number::<f64>(form, "price_adjustment", "Supplement")?,
+            clear_choice_group: false,
+        })
+    }
 }
 
+
+/// New display order for a menu's items, first to last
+#[derive(Debug, Deserialize)]
+pub struct ReorderMenuItems {
+    pub item_ids: Vec<Uuid>,
+}
+
+impl ReorderMenuItems {
+    /// Checks the new order lists each of the menu's `existing` items once
+    pub fn check(&self, existing: &[Uuid]) -> Result<(), String> {
+        let mut requested = self.item_ids.clone();
+        requested.sort();
+        requested.dedup();
+        if requested.len() != self.item_ids.len() {
+            return Err("Item IDs must not repeat".to_string());
+        }
+        let mut existing = existing.to_vec();
+        existing.sort();
+        if existing != requested {
+            return Err("Item IDs must list every item of the menu exactly once".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkUpdateMenuItem {
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub changes: UpdateMenuItem,
+}
+
+/// Creates, updates and deletes applied to a menu's items in one transaction
+#[derive(Debug, Deserialize)]
+pub struct BulkMenuItemChanges {
+    #[serde(default)]
+    pub create: Vec<CreateMenuItem>,
+    #[serde(default)]
+    pub update: Vec<BulkUpdateMenuItem>,
+    #[serde(default)]
+    pub delete: Vec<Uuid>,
+}
+
+impl BulkMenuItemChanges {
+    /// Rejects changes that don't make sense together: new items without a
+    /// name, an item deleted or updated twice, one both updated and deleted,
+    /// or an update that both sets and clears the choice group
+    pub fn check(&self) -> Result<(), String> {
+        if self.create.iter().any(|item| item.name.trim().is_empty()) {
+            return Err("Item name is required".to_string());
+        }
+        let mut deleted = std::collections::HashSet::new();
+        if !self.delete.iter().all(|id| deleted.insert(*id)) {
+            return Err("Items to delete must not repeat".to_string());
+        }
+        let mut updated = std::collections::HashSet::new();
+        if !self.update.iter().all(|update| updated.insert(update.id)) {
+            return Err("Each item can only be updated once".to_string());
+        }
+        if updated.iter().any(|id| deleted.contains(id)) {
+            return Err("An item can't be both updated and deleted".to_string());
+        }
+        self.update.iter().try_for_each(|update| update.changes.check())
+    }
+}
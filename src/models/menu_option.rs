@@ -0,0 +1,271 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::MenuItem;
+
+/// A set of menu items guests choose from, e.g. "Choose one main: beef / fish / vegetarian"
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct MenuChoiceGroup {
+    pub id: Uuid,
+    pub menu_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub course_type: Option<String>,
+    pub min_choices: i32,
+    pub max_choices: i32,
+    pub display_order: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateMenuChoiceGroup {
+    pub name: String,
+    pub description: Option<String>,
+    pub course_type: Option<String>,
+    pub min_choices: Option<i32>,
+    pub max_choices: Option<i32>,
+    pub display_order: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMenuChoiceGroup {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub course_type: Option<String>,
+    pub min_choices: Option<i32>,
+    pub max_choices: Option<i32>,
+    pub display_order: Option<i32>,
+}
+
+/// How an add-on's price is applied to a booking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddOnPricing {
+    PerGuest,
+    PerBooking,
+}
+
+impl std::fmt::Display for AddOnPricing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddOnPricing::PerGuest => write!(f, "per_guest"),
+            AddOnPricing::PerBooking => write!(f, "per_booking"),
+        }
+    }
+}
+
+impl std::str::FromStr for AddOnPricing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "per_guest" => Ok(AddOnPricing::PerGuest),
+            "per_booking" => Ok(AddOnPricing::PerBooking),
+            _ => Err(format!("Invalid pricing unit: {}", s)),
+        }
+    }
+}
+
+/// Optional paid extra offered with a menu (wine pairing, extra course, ...)
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct MenuAddOn {
+    pub id: Uuid,
+    pub menu_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub price: f64,
+    pub pricing_unit: String, // per_guest, per_booking
+    pub is_active: bool,
+    pub display_order: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MenuAddOn {
+    /// Parsed pricing unit; unknown values are treated as per-guest
+    pub fn pricing(&self) -> AddOnPricing {
+        self.pricing_unit.parse().unwrap_or(AddOnPricing::PerGuest)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateMenuAddOn {
+    pub name: String,
+    pub description: Option<String>,
+    pub price: f64,
+    pub pricing_unit: Option<AddOnPricing>,
+    pub display_order: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMenuAddOn {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub price: Option<f64>,
+    pub pricing_unit: Option<AddOnPricing>,
+    pub is_active: Option<bool>,
+    pub display_order: Option<i32>,
+}
+
+/// One guest's pick of an item from a choice group, as submitted with a booking
+#[derive(Debug, Deserialize, Clone)]
+pub struct GuestSelection {
+    pub guest_number: i32, // 1-based
+    pub guest_name: Option<String>,
+    pub menu_item_id: Uuid,
+    pub dietary_notes: Option<String>,
+}
+
+/// An add-on requested with a booking. Quantity defaults to the guest count
+/// for per-guest add-ons and to 1 for per-booking add-ons.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AddOnSelection {
+    pub add_on_id: Uuid,
+    pub quantity: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct BookingGuestSelection {
+    pub id: Uuid,
+    pub booking_id: Uuid,
+    pub guest_number: i32,
+    pub guest_name: Option<String>,
+    pub choice_group_id: Option<Uuid>,
+    pub menu_item_id: Option<Uuid>,
+    pub item_name: String,
+    pub price_adjustment: f64,
+    pub dietary_notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct BookingAddOn {
+    pub id: Uuid,
+    pub booking_id: Uuid,
+    pub add_on_id: Option<Uuid>,
+    pub name: String,
+    pub quantity: i32,
+    pub unit_price: f64,
+    pub total_price: f64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Choice group with the items guests can pick from (for API responses)
+#[derive(Debug, Serialize)]
+pub struct ChoiceGroupWithItems {
+    #[serde(flatten)]
+    pub group: MenuChoiceGroup,
+    pub items: Vec<MenuItem>,
+}
+
+/// What the chef needs to prepare for a booking
+#[derive(Debug, Serialize)]
+pub struct PrepSheet {
+    pub booking_id: Uuid,
+    pub number_of_guests: i32,
+    pub dishes: Vec<PrepSheetLine>,
+    pub add_ons: Vec<BookingAddOn>,
+    pub dietary_notes: Vec<GuestNote>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PrepSheetLine {
+    pub menu_item_id: Option<Uuid>,
+    pub name: String,
+    pub course_type: Option<String>,
+    pub servings: i32,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct GuestNote {
+    pub guest_number: i32,
+    pub guest_name: Option<String>,
+    pub notes: String,
+}
+
+impl PrepSheet {
+    /// Build the prep sheet for a booking.
+    ///
+    /// Items outside any choice group are served to every guest; items in a
+    /// choice group are counted from the guests' selections. Selections whose
+    /// item has since been removed from the menu are still listed by name.
+    pub fn build(
+        booking_id: Uuid,
+        number_of_guests: i32,
+        items: &[MenuItem],
+        selections: &[BookingGuestSelection],
+        add_ons: Vec<BookingAddOn>,
+    ) -> Self {
+        let mut dishes: Vec<PrepSheetLine> = items
+            .iter()
+            .map(|item| {
+                let servings = if item.choice_group_id.is_some() {
+                    selections
+                        .iter()
+                        .filter(|s| s.menu_item_id == Some(item.id))
+                        .count() as i32
+                } else {
+                    number_of_guests
+                };
+                PrepSheetLine {
+                    menu_item_id: Some(item.id),
+                    name: item.name.clone(),
+                    course_type: item.course_type.clone(),
+                    servings,
+                }
+            })
+            .collect();
+
+        for selection in selections {
+            let on_menu = selection
+                .menu_item_id
+                .map(|id| items.iter().any(|item| item.id == id))
+                .unwrap_or(false);
+            if on_menu {
+                continue;
+            }
+            match dishes
+                .iter_mut()
+                .find(|line| line.menu_item_id.is_none() && line.name == selection.item_name)
+            {
+                Some(line) => line.servings += 1,
+                None => dishes.push(PrepSheetLine {
+                    menu_item_id: None,
+                    name: selection.item_name.clone(),
+                    course_type: None,
+                    servings: 1,
+                }),
+            }
+        }
+
+        let mut dietary_notes: Vec<GuestNote> = Vec::new();
+        for selection in selections {
+            let notes = match selection.dietary_notes.as_deref().map(str::trim) {
+                Some(notes) if !notes.is_empty() => notes,
+                _ => continue,
+            };
+            let already_noted = dietary_notes
+                .iter()
+                .any(|n| n.guest_number == selection.guest_number && n.notes == notes);
+            if !already_noted {
+                dietary_notes.push(GuestNote {
+                    guest_number: selection.guest_number,
+                    guest_name: selection.guest_name.clone(),
+                    notes: notes.to_string(),
+                });
+            }
+        }
+        dietary_notes.sort_by_key(|n| n.guest_number);
+
+        PrepSheet {
+            booking_id,
+            number_of_guests,
+            dishes,
+            add_ons,
+            dietary_notes,
+        }
+    }
+}
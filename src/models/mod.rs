@@ -2,11 +2,30 @@ pub mod user;
 pub mod chef;
 pub mod menu;
 pub mod menu_item;
+pub mod menu_option;
 pub mod booking;
+pub mod booking_draft;
+pub mod booking_inbox;
+pub mod image;
+pub mod review;
+pub mod moderation;
+pub mod chef_application;
+pub mod chef_team;
+pub mod staff;
+pub(crate) mod form;
 
 pub use user::*;
 pub use chef::*;
 pub use menu::*;
 pub use menu_item::*;
+pub use menu_option::*;
 pub use booking::*;
+pub use booking_draft::*;
+pub use booking_inbox::*;
+pub use image::*;
+pub use review::*;
+pub use moderation::*;
+pub use chef_application::*;
+pub use chef_team::*;
+pub use staff::*;
 
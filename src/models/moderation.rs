@@ -0,0 +1,128 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// What a content flag points at. Stored as lowercase text in
+/// `content_flags.content_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentType {
+    Chef,
+    Menu,
+    Review,
+}
+
+impl ContentType {
+    /// Menus and reviews are hidden; chefs are deactivated instead
+    pub fn can_hide(self) -> bool {
+        matches!(self, ContentType::Menu | ContentType::Review)
+    }
+}
+
+impl std::fmt::Display for ContentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentType::Chef => write!(f, "chef"),
+            ContentType::Menu => write!(f, "menu"),
+            ContentType::Review => write!(f, "review"),
+        }
+    }
+}
+
+impl std::str::FromStr for ContentType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "chef" => Ok(ContentType::Chef),
+            "menu" => Ok(ContentType::Menu),
+            "review" => Ok(ContentType::Review),
+            _ => Err(format!("Invalid content type: {}", s)),
+        }
+    }
+}
+
+/// A user's report about a chef, menu or review, for staff to act on
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct ContentFlag {
+    pub id: Uuid,
+    pub content_type: String,
+    pub content_id: Uuid,
+    pub reporter_id: Option<Uuid>,
+    pub reason: String,
+    /// open, resolved or dismissed
+    pub status: String,
+    pub resolved_by: Option<Uuid>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateFlag {
+    pub content_type: ContentType,
+    pub content_id: Uuid,
+    pub reason: String,
+}
+
+/// A flag with a short description of what it points at, for the console
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct FlagSummary {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub flag: ContentFlag,
+    /// Chef name, menu name or review excerpt
+    pub content_label: Option<String>,
+    /// Whether the content is already hidden or deactivated
+    pub content_hidden: bool,
+    pub reporter_email: Option<String>,
+}
+
+/// A chef profile as staff see it in the console
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct AdminChefRow {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub chef_name: String,
+    pub slug: Option<String>,
+    pub location: Option<String>,
+    pub owner_email: String,
+    pub is_active: bool,
+    pub deactivated_at: Option<DateTime<Utc>>,
+    pub deactivation_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A booking as staff see it in the console
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct AdminBookingRow {
+    pub id: Uuid,
+    pub chef_name: String,
+    pub customer_name: String,
+    pub customer_email: String,
+    pub event_date: NaiveDate,
+    pub number_of_guests: i32,
+    pub total_price: f64,
+    pub status: String,
+    pub payment_status: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AdminBookingRow {
+    /// Only paid bookings can be refunded
+    pub fn can_refund(&self) -> bool {
+        self.payment_status == "paid"
+    }
+}
+
+/// A review as staff see it in the console
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct AdminReviewRow {
+    pub id: Uuid,
+    pub chef_name: String,
+    pub reviewer_name: String,
+    pub rating: i32,
+    pub comment: Option<String>,
+    pub hidden_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
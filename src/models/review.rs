@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Review {
+    pub id: Uuid,
+    pub chef_id: Uuid,
+    pub booking_id: Option<Uuid>,
+    pub customer_id: Option<Uuid>,
+    pub reviewer_name: String,
+    pub rating: i32, // 1-5
+    pub comment: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Average rating and count for a chef
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct RatingSummary {
+    pub average: Option<f64>,
+    pub count: usize,
+}
@@ -0,0 +1,233 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::form::{self, FormData};
+
+/// What a helper does at an event. Stored as lowercase text in
+/// `chef_helpers.role` and `booking_staff.role`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HelperRole {
+    SousChef,
+    Server,
+    Bartender,
+}
+
+impl HelperRole {
+    pub const ALL: [HelperRole; 3] = [HelperRole::SousChef, HelperRole::Server, HelperRole::Bartender];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HelperRole::SousChef => "Sous chef",
+            HelperRole::Server => "Server",
+            HelperRole::Bartender => "Bartender",
+        }
+    }
+}
+
+impl std::fmt::Display for HelperRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HelperRole::SousChef => write!(f, "sous_chef"),
+            HelperRole::Server => write!(f, "server"),
+            HelperRole::Bartender => write!(f, "bartender"),
+        }
+    }
+}
+
+impl std::str::FromStr for HelperRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sous_chef" => Ok(HelperRole::SousChef),
+            "server" => Ok(HelperRole::Server),
+            "bartender" => Ok(HelperRole::Bartender),
+            _ => Err(format!("Invalid helper role: {}", s)),
+        }
+    }
+}
+
+/// Label for a stored role, falling back to the raw value
+fn role_label(role: &str) -> &str {
+    role.parse::<HelperRole>().map(HelperRole::label).unwrap_or(role)
+}
+
+/// Someone on a chef's roster of event staff. Helpers don't need an
+/// account; those with one see their events if its email matches.
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Helper {
+    pub id: Uuid,
+    pub chef_id: Uuid,
+    pub name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    /// sous_chef, server or bartender
+    pub role: String,
+    pub hourly_rate: f64,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Helper {
+    pub fn role_label(&self) -> &str {
+        role_label(&self.role)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateHelper {
+    pub name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub role: HelperRole,
+    pub hourly_rate: f64,
+}
+
+fn form_role(form: &FormData) -> Result<HelperRole, String> {
+    form::text(form, "role").and_then(|role| role.parse().ok()).ok_or_else(|| "Please choose a role".to_string())
+}
+
+impl CreateHelper {
+    /// Read the dashboard's add-a-helper form
+    pub fn from_form(form: &FormData) -> Result<Self, String> {
+        Ok(CreateHelper {
+            name: form::required(form, "name", "Name")?,
+            email: form::text(form, "email"),
+            phone: form::text(form, "phone"),
+            role: form_role(form)?,
+            hourly_rate: form::number(form, "hourly_rate", "Hourly rate")?.ok_or("Hourly rate is required")?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct UpdateHelper {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub role: Option<HelperRole>,
+    pub hourly_rate: Option<f64>,
+    pub is_active: Option<bool>,
+}
+
+impl UpdateHelper {
+    /// Read a row of the dashboard roster, which edits the role, rate and
+    /// whether the helper is active
+    pub fn from_form(form: &FormData) -> Result<Self, String> {
+        Ok(UpdateHelper {
+            role: Some(form_role(form)?),
+            hourly_rate: Some(form::number(form, "hourly_rate", "Hourly rate")?.ok_or("Hourly rate is required")?),
+            is_active: Some(form::checkbox(form, "is_active")),
+            ..Default::default()
+        })
+    }
+}
+
+/// A helper assigned to a booking, with the rate they were booked at
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct StaffAssignment {
+    pub id: Uuid,
+    pub booking_id: Uuid,
+    /// `None` once the helper has been deleted from the roster
+    pub helper_id: Option<Uuid>,
+    pub name: String,
+    pub role: String,
+    pub hourly_rate: f64,
+    pub hours: f64,
+    pub total_cost: f64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl StaffAssignment {
+    pub fn role_label(&self) -> &str {
+        role_label(&self.role)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignHelper {
+    pub helper_id: Uuid,
+    /// Defaults to the booking's duration
+    pub hours: Option<f64>,
+}
+
+impl AssignHelper {
+    pub fn from_form(form: &FormData) -> Result<Self, String> {
+        Ok(AssignHelper {
+            helper_id: form::text(form, "helper_id")
+                .and_then(|id| id.parse().ok())
+                .ok_or("Please choose a helper")?,
+            hours: form::number(form, "hours", "Hours")?,
+        })
+    }
+}
+
+/// When a helper works: from the event's start for `hours`
+#[derive(Debug, Serialize, FromRow, Clone, PartialEq)]
+pub struct StaffShift {
+    pub booking_id: Uuid,
+    pub event_date: NaiveDate,
+    pub event_time: NaiveTime,
+    pub hours: f64,
+}
+
+impl StaffShift {
+    pub fn start(&self) -> NaiveDateTime {
+        self.event_date.and_time(self.event_time)
+    }
+
+    pub fn end(&self) -> NaiveDateTime {
+        self.start() + Duration::minutes((self.hours * 60.0).round() as i64)
+    }
+
+    /// Whether the two shifts share any time. Back-to-back shifts don't.
+    pub fn overlaps(&self, other: &StaffShift) -> bool {
+        self.start() < other.end() && other.start() < self.end()
+    }
+}
+
+/// A roster helper as seen when staffing a booking
+#[derive(Debug, Serialize, Clone)]
+pub struct HelperAvailability {
+    #[serde(flatten)]
+    pub helper: Helper,
+    pub assigned: bool,
+    /// Another active booking the helper works at the same time
+    pub conflict: Option<StaffShift>,
+}
+
+impl HelperAvailability {
+    pub fn is_available(&self) -> bool {
+        !self.assigned && self.conflict.is_none()
+    }
+}
+
+/// An event a helper is assigned to, as they see it. Leaves out the
+/// diner's contact details and the price.
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct AssignedEvent {
+    pub booking_id: Uuid,
+    pub chef_name: String,
+    pub menu_name: Option<String>,
+    pub customer_name: String,
+    pub event_date: NaiveDate,
+    pub event_time: NaiveTime,
+    pub duration_hours: f64,
+    pub number_of_guests: i32,
+    pub location_address: String,
+    pub special_requests: Option<String>,
+    pub status: String,
+    /// The helper's role and hours at this event
+    pub role: String,
+    pub hours: f64,
+}
+
+impl AssignedEvent {
+    pub fn role_label(&self) -> &str {
+        role_label(&self.role)
+    }
+}
@@ -4,11 +4,12 @@ use sqlx::FromRow;
 use uuid::Uuid;
 
 /// User roles in the system
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Role {
     Admin,
     Mod,
     Chef,
+    #[default]
     Diner,
 }
 
@@ -73,12 +74,6 @@ impl<'q> sqlx::Encode<'q, sqlx::Postgres> for Role {
     }
 }
 
-impl Default for Role {
-    fn default() -> Self {
-        Role::Diner
-    }
-}
-
 impl std::fmt::Display for Role {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -108,8 +103,23 @@ impl std::str::FromStr for Role {
 pub struct User {
     pub id: Uuid,
     pub email: String,
-    pub password_hash: String,
+    /// None for accounts that only log in through an OIDC provider
+    pub password_hash: Option<String>,
     pub role: Role,
+    /// Bumped to revoke every token and session issued to the user
+    pub token_version: i32,
+    pub email_verified: bool,
+    /// Base32 TOTP secret, set from enrollment onwards
+    #[serde(skip_serializing)]
+    pub totp_secret: Option<String>,
+    /// Whether logins need a second factor
+    pub totp_enabled: bool,
+    /// Last TOTP time step accepted, so codes can't be replayed
+    #[serde(skip_serializing)]
+    pub totp_last_step: Option<i64>,
+    /// Set while an admin has suspended the account
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspension_reason: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -118,6 +128,7 @@ pub struct User {
 pub struct CreateUser {
     pub email: String,
     pub password: String,
+    /// Only `diner` is accepted; see `registration_role`
     #[serde(default)]
     pub role: Option<Role>,
 }
@@ -127,6 +138,8 @@ pub struct UserResponse {
     pub id: Uuid,
     pub email: String,
     pub role: Role,
+    pub email_verified: bool,
+    pub two_factor_enabled: bool,
     pub created_at: DateTime<Utc>,
 }
 
@@ -136,8 +149,64 @@ impl From<User> for UserResponse {
             id: user.id,
             email: user.email,
             role: user.role,
+            email_verified: user.email_verified,
+            two_factor_enabled: user.totp_enabled,
             created_at: user.created_at,
         }
     }
 }
 
+
+/// What an emailed single-use token (stored hashed in `user_tokens`) is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPurpose {
+    EmailVerification,
+    PasswordReset,
+}
+
+impl TokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenPurpose::EmailVerification => "email_verification",
+            TokenPurpose::PasswordReset => "password_reset",
+        }
+    }
+
+    /// How long an emailed link stays valid
+    pub fn lifetime(&self) -> chrono::Duration {
+        match self {
+            TokenPurpose::EmailVerification => chrono::Duration::days(3),
+            TokenPurpose::PasswordReset => chrono::Duration::hours(1),
+        }
+    }
+}
+
+/// An admin signed in as another user for support. Carried by the session
+/// or access token it was started with, and by every `Identity` resolved
+/// from them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Impersonation {
+    /// The admin doing the impersonating
+    pub admin_id: Uuid,
+    /// The admin's `token_version` when it started; signing the admin out
+    /// everywhere ends it too
+    pub admin_token_version: i32,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Impersonation {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    /// When it ends, for the banner shown while it lasts
+    pub fn expires_at_display(&self) -> String {
+        self.expires_at.format("%H:%M UTC").to_string()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartImpersonation {
+    /// Why support needs to see the account, e.g. a ticket reference
+    pub reason: String,
+}
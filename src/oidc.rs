@@ -0,0 +1,161 @@
+// OpenID Connect login providers
+// Each provider is configured generically by issuer URL and client
+// credentials; endpoints and signing keys come from its discovery document,
+// which is fetched on first use and cached.
+
+use std::time::{Duration, Instant};
+
+use openidconnect::core::{CoreAuthenticationFlow, CoreClient, CoreProviderMetadata};
+use openidconnect::reqwest::async_http_client;
+use openidconnect::{
+    AuthorizationCode, ClientId, ClientSecret, CsrfToken, IssuerUrl, Nonce, PkceCodeChallenge, PkceCodeVerifier,
+    RedirectUrl, Scope, TokenResponse,
+};
+use tokio::sync::RwLock;
+
+use crate::cache::OidcLoginState;
+use crate::config::{OidcConfig, OidcProviderConfig};
+use crate::errors::AppError;
+
+/// Discovery documents are refetched after this, picking up rotated keys
+const METADATA_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The configured providers, shared by every worker
+pub struct OidcProviders {
+    providers: Vec<OidcProvider>,
+}
+
+impl OidcProviders {
+    pub fn new(config: &OidcConfig) -> Self {
+        OidcProviders {
+            providers: config.providers.iter().cloned().map(OidcProvider::new).collect(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<&OidcProvider, AppError> {
+        self.providers
+            .iter()
+            .find(|provider| provider.config.name == name)
+            .ok_or_else(|| AppError::NotFound(format!("Unknown login provider: {}", name)))
+    }
+}
+
+/// The start of a login: send the user to `url`, and keep `login` under
+/// `state` for the callback
+#[derive(Debug, Clone)]
+pub struct AuthorizationRequest {
+    pub url: String,
+    pub state: String,
+    pub login: OidcLoginState,
+}
+
+/// Who the provider says the user is, from a verified ID token
+#[derive(Debug, Clone)]
+pub struct OidcIdentity {
+    /// The provider's stable id for the account
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+}
+
+pub struct OidcProvider {
+    config: OidcProviderConfig,
+    metadata: RwLock<Option<(Instant, CoreProviderMetadata)>>,
+}
+
+impl OidcProvider {
+    pub fn new(config: OidcProviderConfig) -> Self {
+        OidcProvider { config, metadata: RwLock::new(None) }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    pub fn display_name(&self) -> &str {
+        &self.config.display_name
+    }
+
+    /// Authorization code request with PKCE and a nonce, redirecting back to
+    /// `redirect_uri` (which must be registered with the provider)
+    pub async fn authorization_request(&self, redirect_uri: &str) -> Result<AuthorizationRequest, AppError> {
+        let client = self.client(redirect_uri).await?;
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+
+        let mut request = client
+            .authorize_url(CoreAuthenticationFlow::AuthorizationCode, CsrfToken::new_random, Nonce::new_random)
+            .set_pkce_challenge(pkce_challenge);
+        for scope in self.config.scopes.iter().filter(|scope| scope.as_str() != "openid") {
+            request = request.add_scope(Scope::new(scope.clone()));
+        }
+        let (url, state, nonce) = request.url();
+
+        Ok(AuthorizationRequest {
+            url: url.to_string(),
+            state: state.secret().clone(),
+            login: OidcLoginState {
+                provider: self.config.name.clone(),
+                pkce_verifier: pkce_verifier.secret().clone(),
+                nonce: nonce.secret().clone(),
+                redirect_uri: redirect_uri.to_string(),
+            },
+        })
+    }
+
+    /// Redeem the code from the callback and verify the ID token that comes
+    /// back: signature, issuer, audience, expiry and nonce
+    pub async fn exchange_code(&self, code: &str, login: &OidcLoginState) -> Result<OidcIdentity, AppError> {
+        let client = self.client(&login.redirect_uri).await?;
+        let failed = |e: &dyn std::fmt::Debug| {
+            tracing::warn!(provider = %self.config.name, error = ?e, "OIDC login failed");
+            AppError::Unauthorized(format!("Signing in with {} failed. Please try again.", self.config.display_name))
+        };
+
+        let response = client
+            .exchange_code(AuthorizationCode::new(code.to_string()))
+            .set_pkce_verifier(PkceCodeVerifier::new(login.pkce_verifier.clone()))
+            .request_async(async_http_client)
+            .await
+            .map_err(|e| failed(&e))?;
+        let id_token = response.id_token().ok_or_else(|| failed(&"Token response has no ID token"))?;
+        let claims = id_token
+            .claims(&client.id_token_verifier(), &Nonce::new(login.nonce.clone()))
+            .map_err(|e| failed(&e))?;
+
+        Ok(OidcIdentity {
+            subject: claims.subject().as_str().to_string(),
+            email: claims.email().map(|email| email.as_str().to_string()),
+            email_verified: claims.email_verified().unwrap_or(false),
+        })
+    }
+
+    async fn client(&self, redirect_uri: &str) -> Result<CoreClient, AppError> {
+        let redirect_uri = RedirectUrl::new(redirect_uri.to_string())
+            .map_err(|e| AppError::ValidationError(format!("Invalid redirect URI: {}", e)))?;
+        Ok(CoreClient::from_provider_metadata(
+            self.metadata().await?,
+            ClientId::new(self.config.client_id.clone()),
+            self.config.client_secret.clone().map(ClientSecret::new),
+        )
+        .set_redirect_uri(redirect_uri))
+    }
+
+    async fn metadata(&self) -> Result<CoreProviderMetadata, AppError> {
+        if let Some((fetched_at, metadata)) = self.metadata.read().await.as_ref() {
+            if fetched_at.elapsed() < METADATA_TTL {
+                return Ok(metadata.clone());
+            }
+        }
+
+        let issuer = IssuerUrl::new(self.config.issuer_url.clone())
+            .map_err(|e| AppError::InternalError(format!("Invalid issuer URL for {}: {}", self.config.name, e)))?;
+        let metadata = CoreProviderMetadata::discover_async(issuer, async_http_client)
+            .await
+            .map_err(|e| {
+                tracing::error!(provider = %self.config.name, error = ?e, "OIDC discovery failed");
+                AppError::InternalError(format!("Couldn't reach {}", self.config.display_name))
+            })?;
+        *self.metadata.write().await = Some((Instant::now(), metadata.clone()));
+        Ok(metadata)
+    }
+}
@@ -0,0 +1,199 @@
+// Authorization policy
+// Decides whether an actor may take an action on a resource. The actor's
+// role says which permissions they have at all; permissions scoped to the
+// holder's own resources also need the resource to be theirs, which for a
+// chef's resources means being on the chef's team in a role that allows
+// it. Admins may act on any resource.
+
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::db::DbPool;
+use crate::errors::AppError;
+use crate::middleware::auth::Identity;
+use crate::middleware::roles::{has_permission, Permission};
+use crate::models::{Role, TeamRole};
+
+/// Something a permission is checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    Chef(Uuid),
+    Menu(Uuid),
+    MenuItem(Uuid),
+    Booking(Uuid),
+    Helper(Uuid),
+}
+
+impl Resource {
+    fn name(self) -> &'static str {
+        match self {
+            Resource::Chef(_) => "Chef profile",
+            Resource::Menu(_) => "Menu",
+            Resource::MenuItem(_) => "Menu item",
+            Resource::Booking(_) => "Booking",
+            Resource::Helper(_) => "Helper",
+        }
+    }
+}
+
+/// Who a resource belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Owner {
+    /// The chef the resource belongs to
+    pub chef_id: Uuid,
+    /// The users on that chef's team, and their roles
+    pub team: Vec<(Uuid, TeamRole)>,
+    /// The diner who made the booking, for bookings by signed-in diners
+    pub customer_id: Option<Uuid>,
+}
+
+impl Owner {
+    /// Whether `user_id` owns the resource for the purposes of `permission`:
+    /// diners own their bookings, and the chef's team everything under the
+    /// profile that their team role allows
+    pub fn is(&self, user_id: Uuid, permission: Permission) -> bool {
+        match permission {
+            Permission::ViewOwnBookings => self.customer_id == Some(user_id),
+            _ => self.team_role(user_id).is_some_and(|role| role.allows(permission)),
+        }
+    }
+
+    /// `user_id`'s role on the chef's team, if they're on it
+    pub fn team_role(&self, user_id: Uuid) -> Option<TeamRole> {
+        self.team.iter().find(|(id, _)| *id == user_id).map(|(_, role)| *role)
+    }
+}
+
+/// The team a user belongs to, and their role on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Membership {
+    pub chef_id: Uuid,
+    pub role: TeamRole,
+}
+
+/// The chef team `user_id` is on, if any. Users are on at most one.
+pub async fn membership(pool: &DbPool, user_id: Uuid) -> Result<Option<Membership>, AppError> {
+    let row: Option<(Uuid, String)> = sqlx::query_as("SELECT chef_id, role FROM chef_members WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.and_then(|(chef_id, role)| Some(Membership { chef_id, role: role.parse().ok()? })))
+}
+
+/// Whether `actor` may use `permission` on a resource belonging to `owner`
+pub fn allows(actor: &Identity, permission: Permission, owner: &Owner) -> bool {
+    if actor.role == Role::Admin {
+        return true;
+    }
+    has_permission(actor.role, permission) && (!permission.is_ownership_scoped() || owner.is(actor.id, permission))
+}
+
+/// Fails with `Forbidden` unless the actor's role has `permission`. For
+/// permissions that don't concern a particular resource.
+pub fn require(actor: &Identity, permission: Permission) -> Result<(), AppError> {
+    if has_permission(actor.role, permission) {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden("You don't have permission to do that".to_string()))
+    }
+}
+
+/// Checks that `actor` may use `permission` on `resource` and returns its
+/// owner.
+///
+/// # Returns
+/// * `AppError::NotFound` if the resource doesn't exist
+/// * `AppError::Forbidden` if it isn't the actor's and they aren't an admin
+pub async fn authorize(
+    pool: &DbPool,
+    actor: &Identity,
+    permission: Permission,
+    resource: Resource,
+) -> Result<Owner, AppError> {
+    let owner = find_owner(pool, resource)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("{} not found", resource.name())))?;
+
+    if !allows(actor, permission, &owner) {
+        tracing::warn!(
+            user_id = %actor.id,
+            role = %actor.role,
+            permission = ?permission,
+            resource = ?resource,
+            "Permission denied"
+        );
+        return Err(AppError::Forbidden("You don't have permission to do that".to_string()));
+    }
+
+    Ok(owner)
+}
+
+/// Optional `?chef_id=` on endpoints that otherwise act on the signed-in
+/// user's own chef profile, so admins can act for any chef
+#[derive(Debug, Default, Deserialize)]
+pub struct ChefScope {
+    pub chef_id: Option<Uuid>,
+}
+
+/// The chef a request acts for: the one named by `chef_id`, after checking
+/// `permission` on it, or else the team the actor is on
+///
+/// # Returns
+/// * `AppError::NotFound` if there is no such chef, or the actor isn't on a team
+/// * `AppError::Forbidden` if the actor may not act for the chef
+pub async fn acting_chef(
+    pool: &DbPool,
+    actor: &Identity,
+    permission: Permission,
+    chef_id: Option<Uuid>,
+) -> Result<Uuid, AppError> {
+    if let Some(chef_id) = chef_id {
+        return authorize(pool, actor, permission, Resource::Chef(chef_id)).await.map(|owner| owner.chef_id);
+    }
+
+    require(actor, permission)?;
+    let member = membership(pool, actor.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Chef profile not found".to_string()))?;
+    if actor.role != Role::Admin && !member.role.allows(permission) {
+        tracing::warn!(
+            user_id = %actor.id,
+            team_role = %member.role,
+            permission = ?permission,
+            chef_id = %member.chef_id,
+            "Permission denied by team role"
+        );
+        return Err(AppError::Forbidden("You don't have permission to do that".to_string()));
+    }
+    Ok(member.chef_id)
+}
+
+async fn find_owner(pool: &DbPool, resource: Resource) -> Result<Option<Owner>, AppError> {
+    let (query, id) = match resource {
+        Resource::Chef(id) => ("SELECT c.id, NULL::uuid FROM chefs c WHERE c.id = $1", id),
+        Resource::Menu(id) => ("SELECT m.chef_id, NULL::uuid FROM menus m WHERE m.id = $1", id),
+        Resource::MenuItem(id) => (
+            "SELECT m.chef_id, NULL::uuid FROM menu_items mi
+             INNER JOIN menus m ON m.id = mi.menu_id
+             WHERE mi.id = $1",
+            id,
+        ),
+        Resource::Booking(id) => ("SELECT b.chef_id, b.customer_id FROM bookings b WHERE b.id = $1", id),
+        Resource::Helper(id) => ("SELECT h.chef_id, NULL::uuid FROM chef_helpers h WHERE h.id = $1", id),
+    };
+
+    let found: Option<(Uuid, Option<Uuid>)> = sqlx::query_as(query).bind(id).fetch_optional(pool).await?;
+    let Some((chef_id, customer_id)) = found else {
+        return Ok(None);
+    };
+
+    let team: Vec<(Uuid, String)> = sqlx::query_as("SELECT user_id, role FROM chef_members WHERE chef_id = $1")
+        .bind(chef_id)
+        .fetch_all(pool)
+        .await?;
+    let team = team
+        .into_iter()
+        .filter_map(|(user_id, role)| Some((user_id, role.parse().ok()?)))
+        .collect();
+    Ok(Some(Owner { chef_id, team, customer_id }))
+}
@@ -0,0 +1,225 @@
+// Booking price calculation
+// Kept free of database access so quotes can be computed and tested from plain data
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+use crate::models::{
+    AddOnPricing, AddOnSelection, BookingAddOn, BookingGuestSelection, GuestSelection, MenuAddOn, MenuChoiceGroup,
+    MenuItem, StaffAssignment,
+};
+
+/// Hourly rate used when a chef hasn't set one
+pub const DEFAULT_HOURLY_RATE: f64 = 100.0;
+
+/// Most of a per-booking add-on (bottles, extra courses) one booking can order.
+/// Per-guest add-ons are capped at the guest count instead.
+pub const MAX_PER_BOOKING_QUANTITY: i32 = 50;
+
+/// Price breakdown for a booking
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct BookingQuote {
+    pub base_price: f64,
+    pub selections_total: f64,
+    pub add_ons_total: f64,
+    /// Helpers the chef assigned to the event
+    pub staff_total: f64,
+    pub total_price: f64,
+}
+
+/// An add-on resolved against the menu with its quantity and line total
+#[derive(Debug, Clone)]
+pub struct PricedAddOn<'a> {
+    pub add_on: &'a MenuAddOn,
+    pub quantity: i32,
+    pub total_price: f64,
+}
+
+fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+/// Chef time: hourly rate x billable hours (at least the chef's minimum) x guests
+pub fn base_price(
+    hourly_rate: Option<f64>,
+    minimum_hours: i32,
+    duration_hours: f64,
+    number_of_guests: i32,
+) -> f64 {
+    let duration = minimum_hours.max(duration_hours as i32);
+    let hourly_rate = hourly_rate.unwrap_or(DEFAULT_HOURLY_RATE);
+    round_cents(hourly_rate * duration as f64 * number_of_guests as f64)
+}
+
+/// Check per-guest selections against the menu's choice groups.
+///
+/// `items` must be the items of the booked menu. Selections are optional as a
+/// whole, but once any are given every guest must satisfy every group's
+/// min/max bounds.
+pub fn validate_guest_selections(
+    groups: &[MenuChoiceGroup],
+    items: &[MenuItem],
+    selections: &[GuestSelection],
+    number_of_guests: i32,
+) -> Result<(), AppError> {
+    if selections.is_empty() {
+        return Ok(());
+    }
+
+    // (guest_number, choice_group_id) -> number of picks
+    let mut picks: HashMap<(i32, Uuid), i32> = HashMap::new();
+    let mut seen: Vec<(i32, Uuid)> = Vec::with_capacity(selections.len());
+
+    for selection in selections {
+        if selection.guest_number < 1 || selection.guest_number > number_of_guests {
+            return Err(AppError::ValidationError(format!(
+                "Guest number {} is outside 1..{}",
+                selection.guest_number, number_of_guests
+            )));
+        }
+
+        let group_id = items
+            .iter()
+            .find(|item| item.id == selection.menu_item_id)
+            .and_then(|item| item.choice_group_id)
+            .ok_or_else(|| {
+                AppError::ValidationError(format!(
+                    "Menu item {} is not a choice on this menu",
+                    selection.menu_item_id
+                ))
+            })?;
+
+        if seen.contains(&(selection.guest_number, selection.menu_item_id)) {
+            return Err(AppError::ValidationError(format!(
+                "Guest {} selected the same item more than once",
+                selection.guest_number
+            )));
+        }
+        seen.push((selection.guest_number, selection.menu_item_id));
+
+        *picks.entry((selection.guest_number, group_id)).or_insert(0) += 1;
+    }
+
+    for guest_number in 1..=number_of_guests {
+        for group in groups {
+            let count = picks.get(&(guest_number, group.id)).copied().unwrap_or(0);
+            if count < group.min_choices || count > group.max_choices {
+                return Err(AppError::ValidationError(format!(
+                    "Guest {} must choose between {} and {} from '{}'",
+                    guest_number, group.min_choices, group.max_choices, group.name
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Match requested add-ons to the menu's active add-ons and price each line
+pub fn resolve_add_ons<'a>(
+    available: &'a [MenuAddOn],
+    requested: &[AddOnSelection],
+    number_of_guests: i32,
+) -> Result<Vec<PricedAddOn<'a>>, AppError> {
+    let mut priced = Vec::with_capacity(requested.len());
+
+    for selection in requested {
+        let add_on = available
+            .iter()
+            .find(|a| a.id == selection.add_on_id && a.is_active)
+            .ok_or_else(|| {
+                AppError::ValidationError(format!(
+                    "Add-on {} is not available for this menu",
+                    selection.add_on_id
+                ))
+            })?;
+
+        if priced.iter().any(|p: &PricedAddOn| p.add_on.id == add_on.id) {
+            return Err(AppError::ValidationError(format!(
+                "Add-on '{}' was requested more than once",
+                add_on.name
+            )));
+        }
+
+        let (default_quantity, max_quantity) = match add_on.pricing() {
+            AddOnPricing::PerGuest => (number_of_guests, number_of_guests),
+            AddOnPricing::PerBooking => (1, MAX_PER_BOOKING_QUANTITY),
+        };
+        let quantity = selection.quantity.unwrap_or(default_quantity);
+        if quantity < 1 {
+            return Err(AppError::ValidationError(format!(
+                "Quantity for add-on '{}' must be at least 1",
+                add_on.name
+            )));
+        }
+        if quantity > max_quantity {
+            return Err(AppError::ValidationError(format!(
+                "Quantity for add-on '{}' can't be more than {}",
+                add_on.name, max_quantity
+            )));
+        }
+
+        priced.push(PricedAddOn {
+            add_on,
+            quantity,
+            total_price: round_cents(add_on.price * quantity as f64),
+        });
+    }
+
+    Ok(priced)
+}
+
+/// Combine the base price with choice supplements and add-ons
+pub fn quote_booking(
+    base_price: f64,
+    items: &[MenuItem],
+    selections: &[GuestSelection],
+    add_ons: &[PricedAddOn],
+) -> BookingQuote {
+    let selections_total: f64 = selections
+        .iter()
+        .filter_map(|s| items.iter().find(|item| item.id == s.menu_item_id))
+        .map(|item| item.price_adjustment.unwrap_or(0.0))
+        .sum();
+    let add_ons_total: f64 = add_ons.iter().map(|a| a.total_price).sum();
+
+    let selections_total = round_cents(selections_total);
+    let add_ons_total = round_cents(add_ons_total);
+
+    BookingQuote {
+        base_price,
+        selections_total,
+        add_ons_total,
+        staff_total: 0.0,
+        total_price: round_cents(base_price + selections_total + add_ons_total),
+    }
+}
+
+/// What a helper costs for an event: hourly rate x hours
+pub fn staff_cost(hourly_rate: f64, hours: f64) -> f64 {
+    round_cents(hourly_rate * hours)
+}
+
+/// The breakdown of a stored booking's price. Supplements, add-ons and
+/// staff are read from what was booked; the base price is the rest.
+pub fn booked_quote(
+    total_price: f64,
+    selections: &[BookingGuestSelection],
+    add_ons: &[BookingAddOn],
+    staff: &[StaffAssignment],
+) -> BookingQuote {
+    let selections_total = round_cents(selections.iter().map(|s| s.price_adjustment).sum());
+    let add_ons_total = round_cents(add_ons.iter().map(|a| a.total_price).sum());
+    let staff_total = round_cents(staff.iter().map(|s| s.total_cost).sum());
+
+    BookingQuote {
+        base_price: round_cents(total_price - selections_total - add_ons_total - staff_total),
+        selections_total,
+        add_ons_total,
+        staff_total,
+        total_price,
+    }
+}
@@ -0,0 +1,32 @@
+use actix_web::web;
+use crate::handlers::{admin, chef_application, impersonation};
+use crate::config::Config;
+use crate::db::DbPool;
+
+/// User management, chef application review and the audit trail for staff.
+/// Every handler takes `RequireRole<role::Staff>` or stricter; what
+/// moderators may do beyond that is checked per action. Registered ahead of `configure_api`, whose `/api`
+/// scope would otherwise swallow it.
+pub fn configure_admin(cfg: &mut web::ServiceConfig, pool: web::Data<DbPool>, config: web::Data<Config>) {
+    cfg.service(
+        web::scope("/api/admin")
+            .app_data(pool.clone())
+            .app_data(config.clone())
+            .route("/users", web::get().to(admin::list_users))
+            .route("/users/{user_id}", web::get().to(admin::get_user))
+            .route("/users/{user_id}/role", web::put().to(admin::update_user_role))
+            .route("/users/{user_id}/suspend", web::post().to(admin::suspend))
+            .route("/users/{user_id}/suspend", web::delete().to(admin::unsuspend))
+            .route("/users/{user_id}/logout", web::post().to(admin::force_logout))
+            .route("/users/{user_id}/impersonate", web::post().to(impersonation::impersonate_user))
+            .route("/audit-events", web::get().to(admin::list_audit_events))
+            .route("/chef-applications", web::get().to(chef_application::list_chef_applications))
+            .route("/chef-applications/{application_id}", web::get().to(chef_application::get_chef_application))
+            .route("/chef-applications/{application_id}/approve", web::post().to(chef_application::approve_chef_application))
+            .route("/chef-applications/{application_id}/reject", web::post().to(chef_application::reject_chef_application))
+            .route(
+                "/chef-applications/{application_id}/documents/{document_id}",
+                web::get().to(chef_application::get_chef_application_document),
+            )
+    );
+}
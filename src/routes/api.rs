@@ -1,208 +1,110 @@
-use actix_web::{web, HttpRequest};
-use actix_web_httpauth::middleware::HttpAuthentication;
-use uuid::Uuid;
+use actix_web::web;
 
-use crate::handlers::{auth, chef, menu, menu_item, booking};
-use crate::middleware::auth::{validator, extract_user_id};
-use crate::middleware::roles::require_chef_or_admin;
+use crate::handlers::{
+    auth, chef, chef_application, chef_team, menu, menu_item, menu_option, booking, moderation, staff, upload,
+};
 use crate::config::Config;
 use crate::db::DbPool;
-use crate::errors::AppError;
+use crate::middleware::rate_limit::RateLimit;
 
 pub fn configure_api(cfg: &mut web::ServiceConfig, pool: web::Data<DbPool>, config: web::Data<Config>) {
-    let auth_middleware = HttpAuthentication::bearer(validator);
-
+    // Protected handlers authenticate through their `AuthUser`/`RequireRole`
+    // arguments, so public and private routes can share a scope.
+    // Scopes don't fall through to later siblings once their prefix matches,
+    // so nested paths like /menus/{menu_id}/items are registered before /menus.
     cfg.service(
         web::scope("/api")
             .app_data(pool.clone())
             .app_data(config.clone())
-            // Public routes
+            // Chef-scoped routes: availability and booking creation are public
             .service(
-                web::scope("/chefs")
-                    .route("/{slug}", web::get().to(chef::get_public_chef_profile))
+                web::resource("/chefs/{chef_id}/availability")
+                    .route(web::get().to(booking::get_chef_availability))
             )
             .service(
-                web::scope("/chefs/{chef_id}")
-                    .route("/availability", web::get().to(booking::get_chef_availability))
-                    .route("/bookings", web::post().to(booking::create_booking))
+                web::resource("/chefs/{chef_id}/bookings")
+                    .wrap(RateLimit::booking())
+                    .route(web::post().to(booking::create_booking))
+                    .route(web::get().to(booking::get_chef_bookings))
             )
-            // Protected routes
             .service(
-                web::scope("/users")
-                    .wrap(auth_middleware.clone())
-                    .route("/me", web::get().to(get_me_wrapper))
+                web::scope("/chefs")
+                    .route("", web::post().to(chef::create_chef_profile))
+                    .route("/profile", web::get().to(chef::get_chef_profile))
+                    .route("/profile", web::put().to(chef::update_chef_profile))
+                    .route("/profile/image", web::post().to(upload::upload_chef_profile_image))
+                    .route("/profile/cover-image", web::post().to(upload::upload_chef_cover_image))
+                    // Team members and invitations, before /{slug}
+                    .route("/team", web::get().to(chef_team::get_team))
+                    .route("/team/invitations", web::post().to(chef_team::invite_team_member))
+                    .route("/team/invitations/{invitation_id}", web::delete().to(chef_team::revoke_team_invitation))
+                    .route("/team/members/{user_id}", web::put().to(chef_team::change_team_member_role))
+                    .route("/team/members/{user_id}", web::delete().to(chef_team::remove_team_member))
+                    .route("/team/join", web::post().to(chef_team::accept_team_invitation))
+                    .route("/team/leave", web::post().to(chef_team::leave_team))
+                    // Roster of event staff
+                    .route("/helpers", web::get().to(staff::get_helpers))
+                    .route("/helpers", web::post().to(staff::create_helper))
+                    .route("/helpers/{helper_id}", web::put().to(staff::update_helper_details))
+                    .route("/helpers/{helper_id}", web::delete().to(staff::remove_helper))
+                    // Public profile and menus
+                    .route("/{slug}", web::get().to(chef::get_public_chef_profile))
+                    .route("/{slug}/menus", web::get().to(menu::get_public_chef_menus))
             )
+            .route("/users/me", web::get().to(auth::get_me))
+            // Events the user is on the staff of
+            .route("/users/me/events", web::get().to(staff::get_my_events))
+            .route("/users/me/events/{booking_id}", web::get().to(staff::get_my_event))
+            // Diners apply to become chefs; staff review under /api/admin
+            .route("/chef-applications", web::post().to(chef_application::apply_to_be_chef))
+            .route("/chef-applications/me", web::get().to(chef_application::get_my_application))
+            // Any signed-in user can report a chef, menu or review
+            .route("/flags", web::post().to(moderation::create_flag))
             .service(
-                web::scope("/chefs")
-                    .wrap(auth_middleware.clone())
-                    .route("", web::post().to(create_chef_wrapper))
-                    .route("/profile", web::get().to(get_chef_profile_wrapper))
-                    .route("/profile", web::put().to(update_chef_profile_wrapper))
+                web::scope("/menus/{menu_id}/items")
+                    .route("", web::get().to(menu_item::get_menu_items))
+                    .route("", web::post().to(menu_item::create_menu_item))
+                    // Fixed segments before /{item_id}
+                    .route("/order", web::put().to(menu_item::reorder_menu_items))
+                    .route("/bulk", web::post().to(menu_item::bulk_update_menu_items))
+                    .route("/{item_id}", web::put().to(menu_item::update_menu_item))
+                    .route("/{item_id}", web::delete().to(menu_item::delete_menu_item))
+                    .route("/{item_id}/image", web::post().to(upload::upload_menu_item_image))
             )
             .service(
-                web::scope("/menus")
-                    .wrap(auth_middleware.clone())
-                    .route("", web::get().to(get_menus_wrapper))
-                    .route("", web::post().to(create_menu_wrapper))
-                    .route("/{menu_id}", web::put().to(update_menu_wrapper))
-                    .route("/{menu_id}", web::delete().to(delete_menu_wrapper))
+                web::scope("/menus/{menu_id}/choice-groups")
+                    .route("", web::get().to(menu_option::get_choice_groups))
+                    .route("", web::post().to(menu_option::create_choice_group))
+                    .route("/{group_id}", web::put().to(menu_option::update_choice_group))
+                    .route("/{group_id}", web::delete().to(menu_option::delete_choice_group))
             )
             .service(
-                web::scope("/menus/{menu_id}/items")
-                    .wrap(auth_middleware.clone())
-                    .route("", web::get().to(get_menu_items_wrapper))
-                    .route("", web::post().to(create_menu_item_wrapper))
-                    .route("/{item_id}", web::put().to(update_menu_item_wrapper))
-                    .route("/{item_id}", web::delete().to(delete_menu_item_wrapper))
+                web::scope("/menus/{menu_id}/add-ons")
+                    .route("", web::get().to(menu_option::get_add_ons))
+                    .route("", web::post().to(menu_option::create_add_on))
+                    .route("/{add_on_id}", web::put().to(menu_option::update_add_on))
+                    .route("/{add_on_id}", web::delete().to(menu_option::delete_add_on))
+            )
+            // Reading a single menu is public; changing it is not
+            .service(
+                web::resource("/menus/{menu_id}")
+                    .route(web::get().to(menu::get_public_menu))
+                    .route(web::put().to(menu::update_menu))
+                    .route(web::delete().to(menu::delete_menu))
             )
             .service(
-                web::scope("/chefs/{chef_id}/bookings")
-                    .wrap(auth_middleware.clone())
-                    .route("", web::get().to(get_chef_bookings_wrapper))
+                web::scope("/menus")
+                    .route("", web::get().to(menu::get_chef_menus))
+                    .route("", web::post().to(menu::create_menu))
+                    .route("/{menu_id}/duplicate", web::post().to(menu::duplicate_menu))
             )
             .service(
                 web::scope("/bookings/{booking_id}")
-                    .wrap(auth_middleware.clone())
-                    .route("", web::put().to(update_booking_wrapper))
+                    .route("", web::put().to(booking::update_booking))
+                    .route("/prep-sheet", web::get().to(booking::get_booking_prep_sheet))
+                    .route("/staff", web::get().to(staff::get_booking_staff))
+                    .route("/staff", web::post().to(staff::assign_booking_staff))
+                    .route("/staff/{assignment_id}", web::delete().to(staff::remove_booking_staff))
             )
     );
 }
-
-async fn get_me_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    let user_id = extract_user_id(&req)?;
-    auth::get_me(pool, web::Path::from(user_id)).await
-}
-
-async fn create_chef_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-    data: web::Json<crate::models::CreateChef>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    // Check if user has chef or admin role
-    require_chef_or_admin(&req, &pool).await?;
-    let user_id = extract_user_id(&req)?;
-    chef::create_chef_profile(pool, web::Path::from(user_id), data).await
-}
-
-async fn get_chef_profile_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    // Check if user has chef or admin role
-    require_chef_or_admin(&req, &pool).await?;
-    let user_id = extract_user_id(&req)?;
-    chef::get_chef_profile(pool, web::Path::from(user_id)).await
-}
-
-async fn update_chef_profile_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-    data: web::Json<crate::models::UpdateChef>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    // Check if user has chef or admin role
-    require_chef_or_admin(&req, &pool).await?;
-    let user_id = extract_user_id(&req)?;
-    chef::update_chef_profile(pool, web::Path::from(user_id), data).await
-}
-
-async fn get_menus_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    // Check if user has chef or admin role
-    require_chef_or_admin(&req, &pool).await?;
-    menu::get_chef_menus(req, pool).await
-}
-
-async fn create_menu_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-    data: web::Json<crate::models::CreateMenu>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    // Check if user has chef or admin role
-    require_chef_or_admin(&req, &pool).await?;
-    menu::create_menu(req, pool, data).await
-}
-
-async fn update_menu_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-    path: web::Path<Uuid>,
-    data: web::Json<crate::models::UpdateMenu>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    // Check if user has chef or admin role
-    require_chef_or_admin(&req, &pool).await?;
-    menu::update_menu(req, pool, path, data).await
-}
-
-async fn delete_menu_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-    path: web::Path<Uuid>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    // Check if user has chef or admin role
-    require_chef_or_admin(&req, &pool).await?;
-    menu::delete_menu(req, pool, path).await
-}
-
-async fn get_menu_items_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-    path: web::Path<Uuid>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    // Check if user has chef or admin role
-    require_chef_or_admin(&req, &pool).await?;
-    menu_item::get_menu_items(pool, path).await
-}
-
-async fn create_menu_item_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-    path: web::Path<Uuid>,
-    data: web::Json<crate::models::CreateMenuItem>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    // Check if user has chef or admin role
-    require_chef_or_admin(&req, &pool).await?;
-    menu_item::create_menu_item(req, pool, path, data).await
-}
-
-async fn update_menu_item_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-    path: web::Path<(Uuid, Uuid)>,
-    data: web::Json<crate::models::UpdateMenuItem>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    // Check if user has chef or admin role
-    require_chef_or_admin(&req, &pool).await?;
-    menu_item::update_menu_item(req, pool, path, data).await
-}
-
-async fn delete_menu_item_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-    path: web::Path<(Uuid, Uuid)>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    // Check if user has chef or admin role
-    require_chef_or_admin(&req, &pool).await?;
-    menu_item::delete_menu_item(req, pool, path).await
-}
-
-async fn get_chef_bookings_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-    path: web::Path<Uuid>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    booking::get_chef_bookings(req, pool, path).await
-}
-
-async fn update_booking_wrapper(
-    req: HttpRequest,
-    pool: web::Data<DbPool>,
-    path: web::Path<Uuid>,
-    data: web::Json<crate::models::UpdateBooking>,
-) -> Result<actix_web::HttpResponse, AppError> {
-    booking::update_booking(req, pool, path, data).await
-}
@@ -1,5 +1,7 @@
 use actix_web::web;
-use crate::handlers::auth;
+use crate::handlers::{auth, oidc, two_factor};
+use crate::middleware::auth::TwoFactorExempt;
+use crate::middleware::rate_limit::RateLimit;
 use crate::config::Config;
 use crate::db::DbPool;
 
@@ -8,8 +10,63 @@ pub fn configure_auth(cfg: &mut web::ServiceConfig, pool: web::Data<DbPool>, con
         web::scope("/api/auth")
             .app_data(pool.clone())
             .app_data(config.clone())
-            .route("/register", web::post().to(auth::register))
-            .route("/login", web::post().to(auth::login))
+            .service(
+                web::resource("/register")
+                    .wrap(RateLimit::registration())
+                    .route(web::post().to(auth::register))
+            )
+            .service(
+                web::resource("/login")
+                    .wrap(RateLimit::login())
+                    .route(web::post().to(auth::login))
+            )
+            .service(
+                web::resource("/login/two-factor")
+                    .wrap(RateLimit::login())
+                    .route(web::post().to(auth::login_two_factor))
+            )
+            .route("/oidc/providers", web::get().to(oidc::list_oidc_providers))
+            .service(
+                web::resource("/oidc/{provider}/authorize")
+                    .wrap(RateLimit::login())
+                    .route(web::post().to(oidc::oidc_authorize))
+            )
+            .service(
+                web::resource("/oidc/{provider}/token")
+                    .wrap(RateLimit::login())
+                    .route(web::post().to(oidc::oidc_token))
+            )
+            .route("/refresh", web::post().to(auth::refresh))
+            .route("/logout", web::post().to(auth::logout))
+            .service(
+                web::resource("/logout-all")
+                    .app_data(TwoFactorExempt)
+                    .route(web::post().to(auth::logout_all))
+            )
+            .service(
+                web::resource("/password-reset")
+                    .wrap(RateLimit::account_email())
+                    .route(web::post().to(auth::request_password_reset))
+            )
+            .route("/password-reset/confirm", web::post().to(auth::confirm_password_reset))
+            .route("/verify-email", web::post().to(auth::confirm_email))
+            .service(
+                web::resource("/verify-email/resend")
+                    .wrap(RateLimit::account_email())
+                    .route(web::post().to(auth::resend_verification_email))
+            )
+            .service(
+                web::resource("/two-factor/setup")
+                    .app_data(TwoFactorExempt)
+                    .route(web::post().to(two_factor::setup_two_factor))
+            )
+            .service(
+                web::resource("/two-factor/enable")
+                    .app_data(TwoFactorExempt)
+                    .route(web::post().to(two_factor::confirm_two_factor))
+            )
+            .route("/two-factor/disable", web::post().to(two_factor::turn_off_two_factor))
+            .route("/two-factor/recovery-codes", web::post().to(two_factor::new_recovery_codes))
     );
 }
 
@@ -1,7 +1,9 @@
+pub mod admin;
 pub mod auth;
 pub mod api;
 pub mod web;
 
+pub use admin::*;
 pub use auth::*;
 pub use api::*;
 pub use web::*;
@@ -1,27 +1,238 @@
 use actix_web::web;
 use crate::handlers::web as web_handlers;
+use crate::handlers::{
+    admin_console, booking_wizard, chef_dashboard, chef_page, chef_team_page, impersonation, staff_page, two_factor_page,
+};
+use crate::middleware::auth::TwoFactorExempt;
+use crate::middleware::csrf::CsrfProtection;
+use crate::middleware::flash::FlashMessages;
+use crate::middleware::rate_limit::RateLimit;
 use crate::config::Config;
 use crate::db::DbPool;
 use crate::cache::RedisClient;
 
+/// Web routes use session cookies, so every one of them goes through
+/// `CsrfProtection`, and `FlashMessages` carries messages across their
+/// redirects. They're grouped in resources and scopes to share the
+/// middleware. The API is configured separately; cookie-authenticated API
+/// requests are checked for the token by the `Identity` extractor instead.
 pub fn configure_web(
     cfg: &mut web::ServiceConfig, 
     _pool: web::Data<DbPool>, 
-    _config: web::Data<Config>,
+    config: web::Data<Config>,
     _redis: web::Data<RedisClient>,
 ) {
+    let flash = FlashMessages::new(&config.jwt.secret);
+
     cfg
         // Public web routes
-        .route("/", web::get().to(web_handlers::home))
-        .route("/login", web::get().to(web_handlers::login_page))
-        .route("/login", web::post().to(web_handlers::handle_login))
-        .route("/register", web::post().to(web_handlers::handle_register))
-        .route("/logout", web::get().to(web_handlers::handle_logout))
+        .service(
+            web::resource("/")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route(web::get().to(web_handlers::home))
+        )
+        .service(
+            web::resource("/login")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .wrap(RateLimit::login())
+                .route(web::get().to(web_handlers::login_page))
+                .route(web::post().to(web_handlers::handle_login))
+        )
+        .service(
+            web::resource("/login/two-factor")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .wrap(RateLimit::login())
+                .route(web::get().to(web_handlers::two_factor_login_page))
+                .route(web::post().to(web_handlers::handle_two_factor_login))
+        )
+        .service(
+            web::resource("/auth/oidc/{provider}")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route(web::get().to(web_handlers::oidc_login))
+        )
+        .service(
+            web::resource("/auth/oidc/{provider}/callback")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route(web::get().to(web_handlers::oidc_callback))
+        )
+        .service(
+            web::resource("/register")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .wrap(RateLimit::registration())
+                .route(web::post().to(web_handlers::handle_register))
+        )
+        .service(
+            web::resource("/forgot-password")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .wrap(RateLimit::account_email())
+                .route(web::get().to(web_handlers::forgot_password_page))
+                .route(web::post().to(web_handlers::handle_forgot_password))
+        )
+        .service(
+            web::resource("/reset-password")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route(web::get().to(web_handlers::reset_password_page))
+                .route(web::post().to(web_handlers::handle_reset_password))
+        )
+        .service(
+            web::resource("/verify-email")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route(web::get().to(web_handlers::handle_verify_email))
+        )
+        .service(
+            web::resource("/team/join")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route(web::get().to(chef_team_page::team_join_page))
+                .route(web::post().to(chef_team_page::handle_team_join))
+        )
+        .service(
+            web::resource("/logout")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route(web::get().to(web_handlers::handle_logout))
+        )
+        // Booking submissions are rate limited; registered ahead of the
+        // scopes that would otherwise match them
+        .service(
+            web::resource("/chef/{slug}/book")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .wrap(RateLimit::booking())
+                .route(web::post().to(chef_page::handle_chef_booking))
+        )
+        .service(
+            web::resource("/book/{slug}/confirm")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .wrap(RateLimit::booking())
+                .route(web::post().to(booking_wizard::handle_booking_wizard_submit))
+        )
+        .service(
+            web::scope("/chef")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route("/{slug}", web::get().to(chef_page::chef_profile_page))
+                .route("/{slug}/menus/{menu_id}", web::get().to(chef_page::chef_menu_page))
+        )
         // Protected web routes (session check is done in handlers)
-        .route("/dashboard", web::get().to(web_handlers::dashboard_page))
-        .route("/chef-dashboard", web::get().to(web_handlers::chef_dashboard_page))
-        .route("/chef-dashboard/create-chef", web::post().to(web_handlers::handle_create_chef))
-        .route("/chef-dashboard/create-menu", web::post().to(web_handlers::handle_create_menu))
-        .route("/chef-dashboard/create-menu-item", web::post().to(web_handlers::handle_create_menu_item));
+        .service(
+            web::scope("/book")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route("/{slug}", web::get().to(booking_wizard::booking_wizard_page))
+                .route("/{slug}", web::post().to(booking_wizard::handle_booking_wizard))
+        )
+        .service(
+            web::scope("/dashboard")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route("", web::get().to(web_handlers::dashboard_page))
+                .service(
+                    web::resource("/verify-email")
+                        .wrap(RateLimit::account_email())
+                        .route(web::post().to(web_handlers::handle_resend_verification))
+                )
+                .service(
+                    web::resource("/two-factor")
+                        .app_data(TwoFactorExempt)
+                        .route(web::get().to(two_factor_page::two_factor_page))
+                )
+                .service(
+                    web::resource("/two-factor/setup")
+                        .app_data(TwoFactorExempt)
+                        .route(web::post().to(two_factor_page::handle_two_factor_setup))
+                )
+                .service(
+                    web::resource("/two-factor/enable")
+                        .app_data(TwoFactorExempt)
+                        .route(web::post().to(two_factor_page::handle_enable_two_factor))
+                )
+                .route("/two-factor/disable", web::post().to(two_factor_page::handle_disable_two_factor))
+                .route("/two-factor/recovery-codes", web::post().to(two_factor_page::handle_regenerate_recovery_codes))
+                .route("/bookings/{booking_id}/cancel", web::post().to(web_handlers::handle_cancel_diner_booking))
+                .route("/stop-impersonating", web::post().to(impersonation::handle_stop_impersonating))
+        )
+        // Events a helper is on the staff of, read-only
+        .service(
+            web::scope("/events")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route("/{booking_id}", web::get().to(staff_page::assigned_event_page))
+        )
+        .service(
+            web::scope("/chef-dashboard")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route("", web::get().to(web_handlers::chef_dashboard_page))
+                .route("/create-chef", web::post().to(web_handlers::handle_create_chef))
+                .route("/create-menu", web::post().to(web_handlers::handle_create_menu))
+                .route("/create-menu-item", web::post().to(web_handlers::handle_create_menu_item))
+                // Staffing before /bookings/{booking_id}/{action}
+                .route("/bookings/{booking_id}/staff", web::get().to(staff_page::booking_staff_page))
+                .route("/bookings/{booking_id}/staff/assign", web::post().to(staff_page::handle_assign_staff))
+                .route(
+                    "/bookings/{booking_id}/staff/{assignment_id}/remove",
+                    web::post().to(staff_page::handle_remove_staff),
+                )
+                .route("/bookings/{booking_id}/{action}", web::post().to(web_handlers::handle_chef_booking_action))
+                .route("/profile/edit", web::get().to(chef_dashboard::edit_chef_profile_page))
+                .route("/profile/edit", web::post().to(chef_dashboard::handle_update_chef_profile))
+                .route("/menus/{menu_id}/edit", web::get().to(chef_dashboard::edit_menu_page))
+                .route("/menus/{menu_id}/edit", web::post().to(chef_dashboard::handle_update_menu))
+                .route("/menus/{menu_id}/delete", web::get().to(chef_dashboard::delete_menu_page))
+                .route("/menus/{menu_id}/delete", web::post().to(chef_dashboard::handle_delete_menu))
+                .route("/menus/{menu_id}/items/{item_id}/edit", web::get().to(chef_dashboard::edit_menu_item_page))
+                .route("/menus/{menu_id}/items/{item_id}/edit", web::post().to(chef_dashboard::handle_update_menu_item))
+                .route("/menus/{menu_id}/items/{item_id}/delete", web::get().to(chef_dashboard::delete_menu_item_page))
+                .route("/menus/{menu_id}/items/{item_id}/delete", web::post().to(chef_dashboard::handle_delete_menu_item))
+                .route("/team", web::get().to(chef_team_page::chef_team_page))
+                .route("/team/invite", web::post().to(chef_team_page::handle_invite_team_member))
+                .route("/team/invitations/{invitation_id}/revoke", web::post().to(chef_team_page::handle_revoke_team_invitation))
+                .route("/team/members/{user_id}/role", web::post().to(chef_team_page::handle_change_team_role))
+                .route("/team/members/{user_id}/remove", web::post().to(chef_team_page::handle_remove_team_member))
+                .route("/team/leave", web::post().to(chef_team_page::handle_leave_team))
+                .route("/staff", web::get().to(staff_page::chef_staff_page))
+                .route("/staff", web::post().to(staff_page::handle_add_helper))
+                .route("/staff/{helper_id}/edit", web::post().to(staff_page::handle_update_helper))
+                .route("/staff/{helper_id}/delete", web::post().to(staff_page::handle_delete_helper))
+        )
+        // Staff console; the handlers take `RequireRole<role::Staff>`
+        .service(
+            web::scope("/admin")
+                .wrap(CsrfProtection)
+                .wrap(flash.clone())
+                .route("", web::get().to(admin_console::admin_home))
+                .route("/users", web::get().to(admin_console::admin_users_page))
+                .route("/users/{user_id}/role", web::post().to(admin_console::handle_admin_role_change))
+                .route("/users/{user_id}/suspend", web::post().to(admin_console::handle_admin_suspend))
+                .route("/users/{user_id}/unsuspend", web::post().to(admin_console::handle_admin_unsuspend))
+                .route("/users/{user_id}/logout", web::post().to(admin_console::handle_admin_force_logout))
+                .route("/users/{user_id}/impersonate", web::post().to(admin_console::handle_admin_impersonate))
+                .route("/applications", web::get().to(admin_console::admin_applications_page))
+                .route("/applications/{application_id}/approve", web::post().to(admin_console::handle_admin_approve_application))
+                .route("/applications/{application_id}/reject", web::post().to(admin_console::handle_admin_reject_application))
+                .route(
+                    "/applications/{application_id}/documents/{document_id}",
+                    web::get().to(admin_console::admin_application_document),
+                )
+                .route("/chefs", web::get().to(admin_console::admin_chefs_page))
+                .route("/chefs/{chef_id}/deactivate", web::post().to(admin_console::handle_admin_deactivate_chef))
+                .route("/chefs/{chef_id}/reactivate", web::post().to(admin_console::handle_admin_reactivate_chef))
+                .route("/bookings", web::get().to(admin_console::admin_bookings_page))
+                .route("/bookings/{booking_id}/refund", web::post().to(admin_console::handle_admin_refund))
+                .route("/reviews", web::get().to(admin_console::admin_reviews_page))
+                .route("/content/{content_type}/{content_id}/{action}", web::post().to(admin_console::handle_admin_content_visibility))
+                .route("/flags", web::get().to(admin_console::admin_flags_page))
+                .route("/flags/{flag_id}/dismiss", web::post().to(admin_console::handle_admin_dismiss_flag))
+        );
 }
-
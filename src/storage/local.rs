@@ -0,0 +1,70 @@
+use std::path::{Component, Path, PathBuf};
+
+use async_trait::async_trait;
+
+use crate::errors::AppError;
+use super::{key_from_url, public_url, BlobStore};
+
+/// Stores files on the local filesystem; main.rs serves the directory under
+/// the public URL prefix
+pub struct LocalBlobStore {
+    root: PathBuf,
+    base_url: String,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>, base_url: &str) -> Self {
+        Self {
+            root: root.into(),
+            base_url: base_url.to_string(),
+        }
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf, AppError> {
+        // Keys are generated by us, but never let one escape the upload directory
+        let relative = Path::new(key);
+        if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(AppError::ValidationError(format!("Invalid storage key: {}", key)));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, key: &str, data: Vec<u8>, _content_type: &str) -> Result<String, AppError> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| AppError::InternalError(format!("Failed to create upload directory: {}", e)))?;
+        }
+        tokio::fs::write(&path, data)
+            .await
+            .map_err(|e| AppError::InternalError(format!("Failed to write upload: {}", e)))?;
+
+        Ok(public_url(&self.base_url, key))
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, AppError> {
+        let path = self.path_for(key)?;
+        match tokio::fs::read(&path).await {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(AppError::NotFound("File not found".to_string())),
+            Err(e) => Err(AppError::InternalError(format!("Failed to read upload: {}", e))),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        let path = self.path_for(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(AppError::InternalError(format!("Failed to delete upload: {}", e))),
+        }
+    }
+
+    fn key_of(&self, url: &str) -> Option<String> {
+        key_from_url(&self.base_url, url)
+    }
+}
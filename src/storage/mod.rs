@@ -0,0 +1,57 @@
+// Blob storage for uploaded files
+// Handlers depend on the BlobStore trait; the backend is picked from StorageConfig
+
+pub mod local;
+pub mod s3;
+
+pub use local::LocalBlobStore;
+pub use s3::S3BlobStore;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::config::StorageConfig;
+use crate::errors::AppError;
+
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// Store `data` under `key` and return the public URL it is served from
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<String, AppError>;
+
+    /// Read back the object stored under `key`, for files that are served
+    /// through the app rather than from their public URL
+    async fn get(&self, key: &str) -> Result<Vec<u8>, AppError>;
+
+    /// Remove the object stored under `key`. Missing objects are not an error.
+    async fn delete(&self, key: &str) -> Result<(), AppError>;
+
+    /// The key behind a URL returned by `put`, or None for URLs this store
+    /// doesn't serve
+    fn key_of(&self, url: &str) -> Option<String>;
+}
+
+/// Keys under this prefix hold private files, such as chef credentials.
+/// The local upload directory never serves them; S3 buckets should deny
+/// public reads on the prefix.
+pub const PRIVATE_PREFIX: &str = "private";
+
+/// Build the blob store selected by `STORAGE_BACKEND`
+pub fn create_blob_store(config: &StorageConfig) -> Result<Arc<dyn BlobStore>, AppError> {
+    match config.backend.as_str() {
+        "local" => Ok(Arc::new(LocalBlobStore::new(&config.local_path, &config.public_url))),
+        "s3" => Ok(Arc::new(S3BlobStore::new(config)?)),
+        other => Err(AppError::InternalError(format!("Unknown storage backend: {}", other))),
+    }
+}
+
+/// Join a public base URL and an object key
+pub(crate) fn public_url(base: &str, key: &str) -> String {
+    format!("{}/{}", base.trim_end_matches('/'), key.trim_start_matches('/'))
+}
+
+/// Split an object key back out of a URL built by `public_url`
+pub(crate) fn key_from_url(base: &str, url: &str) -> Option<String> {
+    let key = url.strip_prefix(base.trim_end_matches('/'))?.strip_prefix('/')?;
+    (!key.is_empty()).then(|| key.to_string())
+}
@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use s3::creds::Credentials;
+use s3::error::S3Error;
+use s3::{Bucket, Region};
+
+use crate::config::StorageConfig;
+use crate::errors::AppError;
+use super::{key_from_url, public_url, BlobStore};
+
+/// Stores files in an S3 bucket (or an S3-compatible service when
+/// `S3_ENDPOINT` is set)
+pub struct S3BlobStore {
+    bucket: Box<Bucket>,
+    base_url: String,
+}
+
+impl S3BlobStore {
+    pub fn new(config: &StorageConfig) -> Result<Self, AppError> {
+        let bucket_name = config
+            .s3_bucket
+            .as_deref()
+            .ok_or_else(|| AppError::InternalError("S3_BUCKET must be set for the s3 storage backend".to_string()))?;
+
+        let region = match &config.s3_endpoint {
+            Some(endpoint) => Region::Custom {
+                region: config.s3_region.clone(),
+                endpoint: endpoint.clone(),
+            },
+            None => config
+                .s3_region
+                .parse()
+                .map_err(|e| AppError::InternalError(format!("Invalid S3 region: {}", e)))?,
+        };
+
+        let credentials = Credentials::new(
+            config.s3_access_key.as_deref(),
+            config.s3_secret_key.as_deref(),
+            None,
+            None,
+            None,
+        )
+        .map_err(|e| AppError::InternalError(format!("Invalid S3 credentials: {}", e)))?;
+
+        let mut bucket = Bucket::new(bucket_name, region, credentials)
+            .map_err(|e| AppError::InternalError(format!("Failed to configure S3 bucket: {}", e)))?;
+        if config.s3_endpoint.is_some() {
+            // Most S3-compatible services don't support virtual-hosted buckets
+            bucket = bucket.with_path_style();
+        }
+
+        // Without an explicit public URL, serve objects straight from the bucket
+        let base_url = if config.public_url.starts_with("http") {
+            config.public_url.clone()
+        } else {
+            bucket.url()
+        };
+
+        Ok(Self { bucket, base_url })
+    }
+}
+
+#[async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<String, AppError> {
+        self.bucket
+            .put_object_with_content_type(key, &data, content_type)
+            .await
+            .map_err(|e| AppError::InternalError(format!("Failed to upload to S3: {}", e)))?;
+
+        Ok(public_url(&self.base_url, key))
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, AppError> {
+        match self.bucket.get_object(key).await {
+            Ok(response) => Ok(response.to_vec()),
+            Err(S3Error::HttpFailWithBody(404, _)) => Err(AppError::NotFound("File not found".to_string())),
+            Err(e) => Err(AppError::InternalError(format!("Failed to read from S3: {}", e))),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        self.bucket
+            .delete_object(key)
+            .await
+            .map_err(|e| AppError::InternalError(format!("Failed to delete from S3: {}", e)))?;
+        Ok(())
+    }
+
+    fn key_of(&self, url: &str) -> Option<String> {
+        key_from_url(&self.base_url, url)
+    }
+}
@@ -1,28 +1,132 @@
+use actix_web::{HttpMessage, HttpRequest};
 use askama::Template;
-use crate::models::{UserResponse, Menu, MenuItem};
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::handlers::admin::AdminUserResponse;
+use crate::models::{
+    AdminBookingRow, AdminChefRow, AdminReviewRow, AssignedEvent, BookingAvailability, BookingCalendar,
+    BookingDraft, BookingSummary, BookingTab, ChefApplicationDetail, ChefPublicProfile, FlagSummary, Helper,
+    HelperAvailability, HelperRole, Menu, MenuChoiceGroup, Impersonation, MenuItem, PrepSheet, PublicMenu,
+    RatingSummary, Review, Role, StaffAssignment, TeamRole, UserResponse, WizardStep,
+};
+use crate::middleware::auth::Identity;
+use crate::middleware::roles::Permission;
+use crate::middleware::csrf::csrf_token;
+use crate::middleware::flash::{flash_messages, FlashMessage};
+use crate::pricing::BookingQuote;
+
+/// Values every page template needs: the visitor, the CSRF token for forms
+/// and any flash messages from the previous request. `base.html` renders
+/// the navigation and messages from it.
+pub struct PageContext {
+    pub user: Option<UserResponse>,
+    pub csrf_token: String,
+    pub flash: Vec<FlashMessage>,
+    /// Set while an admin is signed in as `user`, for the banner
+    pub impersonation: Option<Impersonation>,
+}
+
+impl PageContext {
+    pub fn new(req: &HttpRequest, user: Option<UserResponse>) -> Self {
+        let impersonation = user
+            .as_ref()
+            .and_then(|_| req.extensions().get::<Identity>().and_then(|identity| identity.impersonation));
+        PageContext {
+            user,
+            csrf_token: csrf_token(req),
+            flash: flash_messages(req),
+            impersonation,
+        }
+    }
+}
 
 // Home page template
 #[derive(Template)]
 #[template(path = "home.html")]
 pub struct HomeTemplate {
-    pub user: Option<UserResponse>,
+    pub ctx: PageContext,
 }
 
 // Login page template
 #[derive(Template)]
 #[template(path = "login.html")]
 pub struct LoginTemplate {
-    pub user: Option<UserResponse>,
+    pub ctx: PageContext,
     pub is_register: bool,
     pub error: Option<String>,
     pub loading: bool,
+    /// "Continue with ..." buttons for the configured OIDC providers
+    pub oidc_providers: Vec<OidcProviderLink>,
+}
+
+pub struct OidcProviderLink {
+    pub name: String,
+    pub display_name: String,
+}
+
+// Forgot password page: asks for the email to send a reset link to
+#[derive(Template)]
+#[template(path = "forgot_password.html")]
+pub struct ForgotPasswordTemplate {
+    pub ctx: PageContext,
+}
+
+// Reset password page, opened from the emailed link
+#[derive(Template)]
+#[template(path = "reset_password.html")]
+pub struct ResetPasswordTemplate {
+    pub ctx: PageContext,
+    pub token: String,
+    pub error: Option<String>,
+}
+
+// Second login step for accounts with two-factor authentication
+#[derive(Template)]
+#[template(path = "login_two_factor.html")]
+pub struct TwoFactorLoginTemplate {
+    pub ctx: PageContext,
+    pub error: Option<String>,
+}
+
+// Two-factor settings: enrollment, recovery codes and turning it off
+#[derive(Template)]
+#[template(path = "two_factor.html")]
+pub struct TwoFactorTemplate {
+    pub ctx: PageContext,
+    pub enabled: bool,
+    /// The user's role must have 2FA, so it can't be turned off
+    pub required: bool,
+    /// Accounts created through an OIDC provider may have no password to confirm
+    pub has_password: bool,
+    /// Secret and QR code while enrolling
+    pub setup: Option<TwoFactorSetupView>,
+    /// Freshly issued recovery codes, shown once
+    pub recovery_codes: Vec<String>,
+    pub error: Option<String>,
+}
+
+pub struct TwoFactorSetupView {
+    pub secret: String,
+    pub qr_code_svg: String,
 }
 
 // Dashboard template
 #[derive(Template)]
 #[template(path = "dashboard.html")]
 pub struct DashboardTemplate {
-    pub user: Option<UserResponse>,
+    pub ctx: PageContext,
+    pub upcoming_bookings: Vec<BookingSummary>,
+    pub past_bookings: Vec<BookingSummary>,
+    /// Upcoming events the user is on a chef's staff for
+    pub assigned_events: Vec<AssignedEvent>,
+    pub today: NaiveDate,
+}
+
+impl DashboardTemplate {
+    pub fn can_cancel(&self, booking: &BookingSummary) -> bool {
+        booking.can_cancel(self.today)
+    }
 }
 
 // Helper struct for menu with items (for template rendering)
@@ -36,9 +140,350 @@ pub struct MenuWithItems {
 #[derive(Template)]
 #[template(path = "chef_dashboard.html")]
 pub struct ChefDashboardTemplate {
-    pub user: Option<UserResponse>,
+    pub ctx: PageContext,
     pub chef: Option<crate::models::Chef>,
     pub menus_with_items: Vec<MenuWithItems>,
+    pub booking_tab: BookingTab,
+    pub bookings: Vec<BookingSummary>,
+    pub pending_count: usize,
+    pub calendar: Option<BookingCalendar>,
+    pub today: NaiveDate,
+    /// The user's role on the chef's team; `None` until they have a profile
+    pub team_role: Option<TeamRole>,
+}
+
+impl ChefDashboardTemplate {
+    fn team_allows(&self, permission: Permission) -> bool {
+        self.team_role.is_some_and(|role| role.allows(permission))
+    }
+
+    pub fn can_edit_profile(&self) -> bool {
+        self.team_allows(Permission::ManageOwnChefProfile)
+    }
+
+    pub fn can_edit_menus(&self) -> bool {
+        self.team_allows(Permission::ManageOwnMenus)
+    }
+
+    pub fn can_manage_team(&self) -> bool {
+        self.team_allows(Permission::ManageOwnTeam)
+    }
+
+    pub fn can_manage_staff(&self) -> bool {
+        self.team_allows(Permission::ManageOwnStaff)
+    }
+
+    /// Helpers can be put on active bookings that haven't happened yet
+    pub fn can_staff(&self, booking: &BookingSummary) -> bool {
+        booking.booking.booking_status().is_active() && booking.booking.event_date >= self.today
+    }
+
+    /// Owners can't leave their own team
+    pub fn can_leave_team(&self) -> bool {
+        self.team_role.is_some_and(|role| role != TeamRole::Owner)
+    }
+
+    pub fn tabs(&self) -> [BookingTab; 3] {
+        BookingTab::ALL
+    }
+
+    pub fn is_tab(&self, tab: &BookingTab) -> bool {
+        self.booking_tab == *tab
+    }
+
+    pub fn can_complete(&self, booking: &BookingSummary) -> bool {
+        booking.can_complete(self.today)
+    }
+}
+
+// Chef dashboard edit forms
+#[derive(Template)]
+#[template(path = "chef_profile_edit.html")]
+pub struct ChefProfileEditTemplate {
+    pub ctx: PageContext,
+    pub chef: crate::models::Chef,
+}
+
+#[derive(Template)]
+#[template(path = "menu_edit.html")]
+pub struct MenuEditTemplate {
+    pub ctx: PageContext,
+    pub menu: Menu,
+}
+
+#[derive(Template)]
+#[template(path = "menu_item_edit.html")]
+pub struct MenuItemEditTemplate {
+    pub ctx: PageContext,
+    pub menu: Menu,
+    pub item: MenuItem,
+    pub choice_groups: Vec<MenuChoiceGroup>,
+}
+
+impl MenuItemEditTemplate {
+    pub fn in_group(&self, group_id: &Uuid) -> bool {
+        self.item.choice_group_id.as_ref() == Some(group_id)
+    }
+}
+
+// Chef team management
+#[derive(Template)]
+#[template(path = "chef_team.html")]
+pub struct ChefTeamTemplate {
+    pub ctx: PageContext,
+    pub chef: crate::models::Chef,
+    pub members: Vec<crate::models::TeamMember>,
+    pub invitations: Vec<crate::models::ChefInvitation>,
+    pub roles: [TeamRole; 2],
+    pub invitation_days: i64,
+}
+
+/// An invitation as shown to the person it was sent to
+pub struct TeamInvitationView {
+    pub chef_name: String,
+    pub email: String,
+    pub role_label: String,
+}
+
+// Landing page for the link in an invitation email
+#[derive(Template)]
+#[template(path = "team_join.html")]
+pub struct TeamJoinTemplate {
+    pub ctx: PageContext,
+    pub token: String,
+    pub invitation: Option<TeamInvitationView>,
+}
+
+// Roster of helpers for events
+#[derive(Template)]
+#[template(path = "chef_staff.html")]
+pub struct ChefStaffTemplate {
+    pub ctx: PageContext,
+    pub chef: crate::models::Chef,
+    pub helpers: Vec<Helper>,
+    pub roles: [HelperRole; 3],
+}
+
+// Assigning helpers to a booking
+#[derive(Template)]
+#[template(path = "booking_staff.html")]
+pub struct BookingStaffTemplate {
+    pub ctx: PageContext,
+    pub booking: BookingSummary,
+    pub staff: Vec<StaffAssignment>,
+    pub helpers: Vec<HelperAvailability>,
+    pub quote: BookingQuote,
+    /// Whether the user may change the staff and the booking still allows
+    /// it; see `check_staffable`
+    pub can_change: bool,
+}
+
+// A helper's read-only view of an event they're working
+#[derive(Template)]
+#[template(path = "assigned_event.html")]
+pub struct AssignedEventTemplate {
+    pub ctx: PageContext,
+    pub event: AssignedEvent,
+    pub staff: Vec<StaffAssignment>,
+    pub prep_sheet: PrepSheet,
+}
+
+// Confirmation step before deleting something from the dashboard
+#[derive(Template)]
+#[template(path = "confirm_delete.html")]
+pub struct ConfirmDeleteTemplate {
+    pub ctx: PageContext,
+    pub title: String,
+    pub message: String,
+    pub action: String,
+    pub cancel_path: String,
+}
+
+// SEO and Open Graph metadata for public pages
+pub struct PageMeta {
+    pub title: String,
+    pub description: String,
+    pub url: String,
+    pub image: Option<String>,
+    pub og_type: &'static str,
+}
+
+// Booking form shared by the public chef and menu pages
+pub struct BookingForm {
+    pub action: String,
+    pub return_path: String,
+    pub menus: Vec<(Uuid, String)>,
+    pub selected_menu_id: Option<Uuid>,
+    pub number_of_guests: i32,
+    pub duration_hours: f64,
+    pub customer_email: String,
+}
+
+impl BookingForm {
+    pub fn is_selected(&self, menu_id: &Uuid) -> bool {
+        self.selected_menu_id.as_ref() == Some(menu_id)
+    }
+}
+
+// Public chef profile page
+#[derive(Template)]
+#[template(path = "chef_profile.html")]
+pub struct ChefProfileTemplate {
+    pub ctx: PageContext,
+    pub meta: PageMeta,
+    pub chef: ChefPublicProfile,
+    pub menus: Vec<PublicMenu>,
+    pub reviews: Vec<Review>,
+    pub rating: RatingSummary,
+    pub availability: Vec<BookingAvailability>,
+    pub booking_form: BookingForm,
+}
+
+// Public menu page
+#[derive(Template)]
+#[template(path = "chef_menu.html")]
+pub struct ChefMenuTemplate {
+    pub ctx: PageContext,
+    pub meta: PageMeta,
+    pub chef: ChefPublicProfile,
+    pub menu: PublicMenu,
+    pub availability: Vec<BookingAvailability>,
+    pub booking_form: BookingForm,
+}
+
+// Multi-step booking wizard
+#[derive(Template)]
+#[template(path = "booking_wizard.html")]
+pub struct BookingWizardTemplate {
+    pub ctx: PageContext,
+    pub chef: ChefPublicProfile,
+    pub step: WizardStep,
+    pub draft: BookingDraft,
+    pub hidden_fields: Vec<(&'static str, String)>,
+    pub availability: Vec<BookingAvailability>,
+    pub menus: Vec<PublicMenu>,
+    pub selected_menu: Option<PublicMenu>,
+    pub quote: Option<BookingQuote>,
     pub error: Option<String>,
-    pub success: Option<String>,
+}
+
+impl BookingWizardTemplate {
+    pub fn steps(&self) -> &'static [WizardStep] {
+        &WizardStep::ALL
+    }
+
+    pub fn is_step(&self, number: usize) -> bool {
+        self.step.number() == number
+    }
+}
+
+/// Filter values from an admin console page's query string
+#[derive(Debug, Default)]
+pub struct AdminQuery(pub std::collections::HashMap<String, String>);
+
+impl AdminQuery {
+    /// The value of `key`, or "" when it isn't set
+    pub fn get(&self, key: &str) -> &str {
+        self.0.get(key).map(String::as_str).unwrap_or_default()
+    }
+
+    /// The value of `key` if it's set and non-empty
+    pub fn filter(&self, key: &str) -> Option<&str> {
+        Some(self.get(key)).filter(|value| !value.is_empty())
+    }
+
+    /// Whether `key` is set to `value`, for marking the selected option
+    pub fn is(&self, key: &str, value: &str) -> bool {
+        self.get(key) == value
+    }
+}
+
+/// A user in the console, with whether the viewer may act on them
+pub struct AdminUserRow {
+    pub user: AdminUserResponse,
+    pub manageable: bool,
+    /// The viewer is an admin who may sign in as this user
+    pub impersonatable: bool,
+}
+
+impl AdminUserRow {
+    pub fn account(&self) -> &UserResponse {
+        &self.user.user
+    }
+
+    pub fn has_role(&self, role: &Role) -> bool {
+        self.user.user.role == *role
+    }
+}
+
+// Admin console: users
+#[derive(Template)]
+#[template(path = "admin_users.html")]
+pub struct AdminUsersTemplate {
+    pub ctx: PageContext,
+    pub section: &'static str,
+    pub query: AdminQuery,
+    pub users: Vec<AdminUserRow>,
+    pub total: i64,
+    /// Roles the viewer may hand out
+    pub assignable_roles: Vec<Role>,
+}
+
+impl AdminUsersTemplate {
+    /// Whether more users match than are shown
+    pub fn is_truncated(&self) -> bool {
+        self.total > self.users.len() as i64
+    }
+}
+
+// Admin console: chef profiles
+#[derive(Template)]
+#[template(path = "admin_chefs.html")]
+pub struct AdminChefsTemplate {
+    pub ctx: PageContext,
+    pub section: &'static str,
+    pub query: AdminQuery,
+    pub chefs: Vec<AdminChefRow>,
+}
+
+// Admin console: bookings
+#[derive(Template)]
+#[template(path = "admin_bookings.html")]
+pub struct AdminBookingsTemplate {
+    pub ctx: PageContext,
+    pub section: &'static str,
+    pub query: AdminQuery,
+    pub bookings: Vec<AdminBookingRow>,
+    /// Refunds are for admins only
+    pub can_refund: bool,
+}
+
+// Admin console: reviews
+#[derive(Template)]
+#[template(path = "admin_reviews.html")]
+pub struct AdminReviewsTemplate {
+    pub ctx: PageContext,
+    pub section: &'static str,
+    pub query: AdminQuery,
+    pub reviews: Vec<AdminReviewRow>,
+}
+
+// Admin console: reported content
+#[derive(Template)]
+#[template(path = "admin_flags.html")]
+pub struct AdminFlagsTemplate {
+    pub ctx: PageContext,
+    pub section: &'static str,
+    pub query: AdminQuery,
+    pub flags: Vec<FlagSummary>,
+}
+
+// Admin console: chef applications
+#[derive(Template)]
+#[template(path = "admin_applications.html")]
+pub struct AdminApplicationsTemplate {
+    pub ctx: PageContext,
+    pub section: &'static str,
+    pub query: AdminQuery,
+    pub applications: Vec<ChefApplicationDetail>,
 }
@@ -1,43 +1,92 @@
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
 
 use crate::config::JwtConfig;
+use crate::models::{Impersonation, Role, User};
+
+/// What a token may be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    /// Sent as a bearer token on API requests
+    Access,
+    /// Only accepted by `/api/auth/refresh` and `/api/auth/logout`
+    Refresh,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
+    /// Role when the token was issued
+    pub role: Role,
+    /// The user's `token_version` when the token was issued. The token stops
+    /// being accepted once the version is bumped (e.g. on a role change).
+    pub ver: i32,
+    pub typ: TokenKind,
+    /// Unique token id
+    pub jti: String,
+    /// Refresh token family: every token rotated from the same login
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fam: Option<String>,
+    /// Set on access tokens an admin uses to act as the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imp: Option<Impersonation>,
     pub exp: i64,
     pub iat: i64,
 }
 
 impl Claims {
-    pub fn new(user_id: String, expiration_seconds: i64) -> Self {
+    fn new(user: &User, typ: TokenKind, fam: Option<Uuid>, expiration_seconds: i64) -> Self {
         let now = Utc::now();
         Claims {
-            sub: user_id,
+            sub: user.id.to_string(),
+            role: user.role,
+            ver: user.token_version,
+            typ,
+            jti: Uuid::new_v4().to_string(),
+            fam: fam.map(|id| id.to_string()),
+            imp: None,
             exp: (now + Duration::seconds(expiration_seconds)).timestamp(),
             iat: now.timestamp(),
         }
     }
-}
 
-pub fn create_token(user_id: String, config: &JwtConfig) -> Result<String, jsonwebtoken::errors::Error> {
-    let claims = Claims::new(user_id, config.expiration);
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(config.secret.as_ref()),
-    )
+    pub fn access(user: &User, config: &JwtConfig) -> Self {
+        Claims::new(user, TokenKind::Access, None, config.expiration)
+    }
+
+    pub fn refresh(user: &User, family: Uuid, config: &JwtConfig) -> Self {
+        Claims::new(user, TokenKind::Refresh, Some(family), config.refresh_expiration)
+    }
+
+    /// An access token for an admin acting as `user`, valid until the
+    /// impersonation ends. There's no refresh token to go with it.
+    pub fn impersonation(user: &User, impersonation: Impersonation) -> Self {
+        let mut claims = Claims::new(user, TokenKind::Access, None, 0);
+        claims.exp = impersonation.expires_at.timestamp();
+        claims.imp = Some(impersonation);
+        claims
+    }
+
+    /// The refresh token family, if this is a well-formed refresh token
+    pub fn family(&self) -> Option<Uuid> {
+        self.fam.as_deref().and_then(|id| Uuid::parse_str(id).ok())
+    }
+
+    pub fn encode(&self, secret: &str) -> Result<String, jsonwebtoken::errors::Error> {
+        encode(
+            &Header::default(),
+            self,
+            &EncodingKey::from_secret(secret.as_ref()),
+        )
+    }
 }
 
-pub fn create_refresh_token(user_id: String, config: &JwtConfig) -> Result<String, jsonwebtoken::errors::Error> {
-    let claims = Claims::new(user_id, config.refresh_expiration);
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(config.secret.as_ref()),
-    )
+pub fn create_token(user: &User, config: &JwtConfig) -> Result<String, jsonwebtoken::errors::Error> {
+    Claims::access(user, config).encode(&config.secret)
 }
 
 pub fn verify_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
@@ -49,3 +98,16 @@ pub fn verify_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::e
     .map(|data| data.claims)
 }
 
+/// A random token for emailed links
+pub fn generate_secret_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// SHA-256 hex digest stored in place of an emailed token, so a database
+/// leak doesn't expose usable links
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
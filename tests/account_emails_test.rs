@@ -0,0 +1,118 @@
+// Tests for email verification and password reset: token hashing, the
+// mailer backends, the pages, and the checks made before the database is
+// reached (the pool is lazy and Redis is unreachable).
+
+use actix_web::{http::header, http::StatusCode, test};
+use askama::Template;
+use chrono::Utc;
+use privatechefspace_backend::mail::{create_mailer, Email};
+use privatechefspace_backend::models::{Role, TokenPurpose, UserResponse};
+use privatechefspace_backend::templates::{DashboardTemplate, ForgotPasswordTemplate, ResetPasswordTemplate};
+use privatechefspace_backend::utils::{generate_secret_token, hash_token};
+use uuid::Uuid;
+
+mod common;
+use common::{call, page_context, test_config};
+
+fn diner(email_verified: bool) -> UserResponse {
+    UserResponse {
+        id: Uuid::new_v4(),
+        email: "alex@example.com".to_string(),
+        role: Role::Diner,
+        email_verified,
+        two_factor_enabled: false,
+        created_at: Utc::now(),
+    }
+}
+#[actix_web::test]
+async fn test_tokens_are_stored_as_sha256_hex() {
+    let token = generate_secret_token();
+    let hashed = hash_token(&token);
+    assert_eq!(hashed.len(), 64);
+    assert!(hashed.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_ne!(hashed, token);
+    assert_eq!(hashed, hash_token(&token));
+    assert_ne!(generate_secret_token(), token);
+}
+
+#[actix_web::test]
+async fn test_token_purposes() {
+    assert_eq!(TokenPurpose::EmailVerification.as_str(), "email_verification");
+    assert_eq!(TokenPurpose::PasswordReset.as_str(), "password_reset");
+    assert!(TokenPurpose::PasswordReset.lifetime() < TokenPurpose::EmailVerification.lifetime());
+}
+
+#[actix_web::test]
+async fn test_mailer_backends() {
+    let mut config = test_config().mail;
+    let mailer = create_mailer(&config).unwrap();
+    let email = Email {
+        to: "alex@example.com".to_string(),
+        subject: "Hello".to_string(),
+        body: "Hi there".to_string(),
+    };
+    assert!(mailer.send(email).await.is_ok());
+
+    config.backend = "smtp".to_string();
+    assert!(create_mailer(&config).is_err(), "smtp needs SMTP_URL");
+    config.backend = "pigeon".to_string();
+    assert!(create_mailer(&config).is_err());
+}
+
+#[actix_web::test]
+async fn test_forgot_password_page_renders_form() {
+    let html = ForgotPasswordTemplate { ctx: page_context(None, vec![]) }.render().unwrap();
+    assert!(html.contains("action=\"/forgot-password\""));
+    assert!(html.contains("name=\"csrf_token\" value=\"test-token\""));
+}
+
+#[actix_web::test]
+async fn test_reset_password_page_carries_token_and_error() {
+    let html = ResetPasswordTemplate {
+        ctx: page_context(None, vec![]),
+        token: "abc123".to_string(),
+        error: Some("Passwords don't match".to_string()),
+    }
+    .render()
+    .unwrap();
+    assert!(html.contains("name=\"token\" value=\"abc123\""));
+    assert!(html.contains("Passwords don&#x27;t match"));
+}
+
+#[actix_web::test]
+async fn test_dashboard_shows_verification_banner_until_verified() {
+    let render = |user| {
+        DashboardTemplate {
+            ctx: page_context(Some(user), vec![]),
+            upcoming_bookings: vec![],
+            past_bookings: vec![],
+            assigned_events: vec![],
+            today: Utc::now().date_naive(),
+        }
+        .render()
+        .unwrap()
+    };
+    assert!(render(diner(false)).contains("action=\"/dashboard/verify-email\""));
+    assert!(!render(diner(true)).contains("action=\"/dashboard/verify-email\""));
+}
+
+#[actix_web::test]
+async fn test_resend_requires_auth() {
+    let res = call(test::TestRequest::post().uri("/api/auth/verify-email/resend"), None).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_reset_rejects_short_password_before_using_token() {
+    let req = test::TestRequest::post()
+        .uri("/api/auth/password-reset/confirm")
+        .set_json(serde_json::json!({ "token": "whatever", "password": "short" }));
+    assert_eq!(call(req, None).await.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_reset_page_without_token_goes_to_forgot_password() {
+    let res = call(test::TestRequest::get().uri("/reset-password"), None).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/forgot-password");
+}
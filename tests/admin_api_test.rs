@@ -0,0 +1,91 @@
+// Tests for the admin user-management API: who may act on whom, and the
+// checks made before the database is reached. The signed-in staff member is
+// seeded on the request, as in auth_extractor_test.
+
+use actix_web::{http::StatusCode, test};
+use privatechefspace_backend::handlers::admin::{can_assign_role, can_manage_user};
+use privatechefspace_backend::models::Role;
+use uuid::Uuid;
+
+mod common;
+use common::{call, signed_in};
+
+#[actix_web::test]
+async fn test_admins_manage_everyone() {
+    for target in [Role::Admin, Role::Mod, Role::Chef, Role::Diner] {
+        assert!(can_manage_user(Role::Admin, target), "{}", target);
+        assert!(can_assign_role(Role::Admin, target), "{}", target);
+    }
+}
+
+#[actix_web::test]
+async fn test_mods_manage_only_chefs_and_diners() {
+    assert!(can_manage_user(Role::Mod, Role::Chef));
+    assert!(can_manage_user(Role::Mod, Role::Diner));
+    assert!(!can_manage_user(Role::Mod, Role::Admin));
+    assert!(!can_manage_user(Role::Mod, Role::Mod));
+
+    assert!(can_assign_role(Role::Mod, Role::Chef));
+    assert!(!can_assign_role(Role::Mod, Role::Admin));
+    assert!(!can_assign_role(Role::Mod, Role::Mod));
+}
+
+#[actix_web::test]
+async fn test_other_roles_manage_nobody() {
+    for actor in [Role::Chef, Role::Diner] {
+        assert!(!can_manage_user(actor, Role::Diner), "{}", actor);
+    }
+}
+
+#[actix_web::test]
+async fn test_requires_authentication() {
+    let res = call(test::TestRequest::get().uri("/api/admin/users"), None).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_rejects_chefs_and_diners() {
+    for role in [Role::Chef, Role::Diner] {
+        let res = call(test::TestRequest::get().uri("/api/admin/users"), Some(signed_in(role))).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", role);
+    }
+}
+
+#[actix_web::test]
+async fn test_mods_cant_hand_out_admin() {
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/admin/users/{}/role", Uuid::new_v4()))
+        .set_json(serde_json::json!({ "role": "admin" }));
+    let res = call(req, Some(signed_in(Role::Mod))).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_staff_cant_act_on_themselves() {
+    let admin = signed_in(Role::Admin);
+    for (method, path) in [
+        ("POST", "suspend"),
+        ("DELETE", "suspend"),
+        ("POST", "logout"),
+    ] {
+        let req = test::TestRequest::default()
+            .method(method.parse().unwrap())
+            .uri(&format!("/api/admin/users/{}/{}", admin.id, path));
+        let res = call(req, Some(admin)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{} {}", method, path);
+    }
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/admin/users/{}/role", admin.id))
+        .set_json(serde_json::json!({ "role": "diner" }));
+    assert_eq!(call(req, Some(admin)).await.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_unknown_role_is_rejected() {
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/admin/users/{}/role", Uuid::new_v4()))
+        .set_json(serde_json::json!({ "role": "superuser" }));
+    let res = call(req, Some(signed_in(Role::Admin))).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
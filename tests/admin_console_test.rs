@@ -0,0 +1,206 @@
+// Tests for the staff console and moderation: the pages, who may reach
+// them, and the permission checks made before the database is reached (the
+// pool is lazy and Redis is unreachable).
+
+use std::collections::HashMap;
+
+use actix_web::{http::header, http::StatusCode, test};
+use askama::Template;
+use chrono::{NaiveDate, Utc};
+use privatechefspace_backend::audit::RequestMeta;
+use privatechefspace_backend::errors::AppError;
+use privatechefspace_backend::handlers::admin::{managed_user, AdminUserResponse};
+use privatechefspace_backend::handlers::moderation::{flag_content, refund_booking, set_content_hidden};
+use privatechefspace_backend::models::{
+    AdminBookingRow, ContentFlag, ContentType, CreateFlag, FlagSummary, Role, User,
+};
+use privatechefspace_backend::templates::{
+    AdminBookingsTemplate, AdminFlagsTemplate, AdminQuery, AdminUserRow, AdminUsersTemplate,
+};
+use uuid::Uuid;
+
+mod common;
+use common::{call, lazy_pool, page_context, signed_in};
+
+fn query(pairs: &[(&str, &str)]) -> AdminQuery {
+    AdminQuery(pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>())
+}
+
+fn user(email: &str, role: Role) -> User {
+    User {
+        id: Uuid::new_v4(),
+        email: email.to_string(),
+        password_hash: Some("hash".to_string()),
+        role,
+        token_version: 0,
+        email_verified: true,
+        totp_secret: None,
+        totp_enabled: false,
+        totp_last_step: None,
+        suspended_at: None,
+        suspension_reason: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+fn booking(payment_status: &str) -> AdminBookingRow {
+    AdminBookingRow {
+        id: Uuid::new_v4(),
+        chef_name: "Camille".to_string(),
+        customer_name: "Dana".to_string(),
+        customer_email: "dana@example.com".to_string(),
+        event_date: NaiveDate::from_ymd_opt(2030, 6, 1).unwrap(),
+        number_of_guests: 6,
+        total_price: 480.0,
+        status: "confirmed".to_string(),
+        payment_status: payment_status.to_string(),
+        created_at: Utc::now(),
+    }
+}
+
+fn flag(content_type: ContentType) -> FlagSummary {
+    FlagSummary {
+        flag: ContentFlag {
+            id: Uuid::new_v4(),
+            content_type: content_type.to_string(),
+            content_id: Uuid::new_v4(),
+            reporter_id: Some(Uuid::new_v4()),
+            reason: "Spam".to_string(),
+            status: "open".to_string(),
+            resolved_by: None,
+            resolved_at: None,
+            created_at: Utc::now(),
+        },
+        content_label: Some("Something".to_string()),
+        content_hidden: false,
+        reporter_email: Some("reporter@example.com".to_string()),
+    }
+}
+
+#[actix_web::test]
+async fn test_console_requires_staff() {
+    let res = call(test::TestRequest::get().uri("/admin/users"), None).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    for role in [Role::Chef, Role::Diner] {
+        for page in ["users", "chefs", "bookings", "reviews", "flags"] {
+            let res = call(test::TestRequest::get().uri(&format!("/admin/{}", page)), Some(signed_in(role))).await;
+            assert_eq!(res.status(), StatusCode::FORBIDDEN, "{} {}", role, page);
+        }
+    }
+}
+
+#[actix_web::test]
+async fn test_console_home_redirects_to_users() {
+    let res = call(test::TestRequest::get().uri("/admin"), Some(signed_in(Role::Admin))).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/admin/users");
+}
+
+#[actix_web::test]
+async fn test_staff_cant_act_on_themselves() {
+    let admin = signed_in(Role::Admin);
+    let err = managed_user(&lazy_pool(), &admin, admin.id).await.unwrap_err();
+    assert!(matches!(err, AppError::Forbidden(_)), "{:?}", err);
+}
+
+#[actix_web::test]
+async fn test_only_admins_refund() {
+    let err = refund_booking(&lazy_pool(), &signed_in(Role::Mod), &RequestMeta::default(), Uuid::new_v4()).await.unwrap_err();
+    assert!(matches!(err, AppError::Forbidden(_)), "{:?}", err);
+}
+
+#[actix_web::test]
+async fn test_hiding_needs_staff_and_hideable_content() {
+    let meta = RequestMeta::default();
+    let err = set_content_hidden(&lazy_pool(), &signed_in(Role::Chef), &meta, ContentType::Review, Uuid::new_v4(), true)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Forbidden(_)), "{:?}", err);
+
+    let err = set_content_hidden(&lazy_pool(), &signed_in(Role::Mod), &meta, ContentType::Chef, Uuid::new_v4(), true)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::ValidationError(_)), "{:?}", err);
+}
+
+#[actix_web::test]
+async fn test_reports_need_a_reason() {
+    let report = CreateFlag { content_type: ContentType::Menu, content_id: Uuid::new_v4(), reason: "  ".to_string() };
+    let err = flag_content(&lazy_pool(), &signed_in(Role::Diner), &report).await.unwrap_err();
+    assert!(matches!(err, AppError::ValidationError(_)), "{:?}", err);
+}
+
+#[actix_web::test]
+async fn test_content_types() {
+    assert_eq!("Review".parse::<ContentType>().unwrap(), ContentType::Review);
+    assert!("user".parse::<ContentType>().is_err());
+    assert!(ContentType::Menu.can_hide());
+    assert!(!ContentType::Chef.can_hide());
+}
+
+#[actix_web::test]
+async fn test_users_page_offers_actions_only_where_allowed() {
+    let diner = user("diner@example.com", Role::Diner);
+    let other_admin = user("boss@example.com", Role::Admin);
+    let html = AdminUsersTemplate {
+        ctx: page_context(None, vec![]),
+        section: "users",
+        query: query(&[("role", "diner")]),
+        users: vec![
+            AdminUserRow { manageable: true, impersonatable: true, user: AdminUserResponse::from(diner.clone()) },
+            AdminUserRow { manageable: false, impersonatable: false, user: AdminUserResponse::from(other_admin.clone()) },
+        ],
+        total: 2,
+        assignable_roles: vec![Role::Chef, Role::Diner],
+    }
+    .render()
+    .unwrap();
+
+    assert!(html.contains(&format!("/admin/users/{}/suspend", diner.id)));
+    assert!(!html.contains(&format!("/admin/users/{}/suspend", other_admin.id)));
+    assert!(html.contains("<option value=\"diner\" selected>"));
+    assert!(!html.contains("<option value=\"mod\" selected>"));
+    assert!(html.contains("<input type=\"hidden\" name=\"csrf_token\" value=\"test-token\">"));
+}
+
+#[actix_web::test]
+async fn test_refund_button_needs_permission_and_payment() {
+    let paid = booking("paid");
+    let unpaid = booking("pending");
+    let render = |can_refund| {
+        AdminBookingsTemplate {
+            ctx: page_context(None, vec![]),
+            section: "bookings",
+            query: query(&[]),
+            bookings: vec![paid.clone(), unpaid.clone()],
+            can_refund,
+        }
+        .render()
+        .unwrap()
+    };
+
+    let html = render(true);
+    assert!(html.contains(&format!("/admin/bookings/{}/refund", paid.id)));
+    assert!(!html.contains(&format!("/admin/bookings/{}/refund", unpaid.id)));
+    assert!(!render(false).contains("/refund"));
+}
+
+#[actix_web::test]
+async fn test_flags_page_offers_the_right_action() {
+    let chef = flag(ContentType::Chef);
+    let review = flag(ContentType::Review);
+    let html = AdminFlagsTemplate {
+        ctx: page_context(None, vec![]),
+        section: "flags",
+        query: query(&[("status", "open")]),
+        flags: vec![chef.clone(), review.clone()],
+    }
+    .render()
+    .unwrap();
+
+    assert!(html.contains(&format!("/admin/chefs/{}/deactivate", chef.flag.content_id)));
+    assert!(html.contains(&format!("/admin/content/review/{}/hide", review.flag.content_id)));
+    assert!(html.contains(&format!("/admin/flags/{}/dismiss", review.flag.id)));
+}
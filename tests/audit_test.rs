@@ -0,0 +1,102 @@
+// Tests for the audit trail: how events are built, the request metadata
+// kept with them, and who may read them. Recording needs the database, so
+// the endpoint tests stop at the checks made before it is reached.
+
+use actix_web::{http::header, http::StatusCode, test, FromRequest};
+use privatechefspace_backend::audit::{AuditEvent, AuditQuery, RequestMeta};
+use privatechefspace_backend::errors::AppError;
+use privatechefspace_backend::models::Role;
+use serde_json::json;
+use uuid::Uuid;
+
+mod common;
+use common::{call, signed_in};
+
+#[actix_web::test]
+async fn test_event_records_actor_target_and_request() {
+    let admin = signed_in(Role::Admin);
+    let user_id = Uuid::new_v4();
+    let meta = RequestMeta { ip: Some("203.0.113.7".to_string()), user_agent: Some("Firefox".to_string()) };
+
+    let event = AuditEvent::new("user_role_changed")
+        .by(&admin)
+        .on("user", user_id)
+        .before(json!({ "role": Role::Diner }))
+        .after(json!({ "role": Role::Chef }))
+        .detail("reason", "approved application")
+        .from(&meta);
+
+    assert_eq!(event.action, "user_role_changed");
+    assert_eq!(event.actor_id, Some(admin.id));
+    assert_eq!(event.actor_role, Some(Role::Admin));
+    assert_eq!(event.target_type.as_deref(), Some("user"));
+    assert_eq!(event.target_id, Some(user_id));
+    assert_eq!(event.before, Some(json!({ "role": "diner" })));
+    assert_eq!(event.after, Some(json!({ "role": "chef" })));
+    assert_eq!(event.metadata["ip"], "203.0.113.7");
+    assert_eq!(event.metadata["user_agent"], "Firefox");
+    assert_eq!(event.metadata["reason"], "approved application");
+}
+
+#[actix_web::test]
+async fn test_changes_keep_only_changed_fields() {
+    let before = json!({ "chef_name": "Camille", "bio": "Bistro", "hourly_rate": 60.0, "updated_at": "then" });
+    let after = json!({ "chef_name": "Camille", "bio": "Bistronomy", "hourly_rate": 75.0, "updated_at": "now" });
+
+    let event = AuditEvent::new("chef_profile_updated").changes(&before, &after);
+
+    assert_eq!(event.before, Some(json!({ "bio": "Bistro", "hourly_rate": 60.0 })));
+    assert_eq!(event.after, Some(json!({ "bio": "Bistronomy", "hourly_rate": 75.0 })));
+}
+
+#[actix_web::test]
+async fn test_request_meta_reads_peer_and_user_agent() {
+    let long_agent = "a".repeat(2000);
+    let req = test::TestRequest::default()
+        .peer_addr("198.51.100.4:5000".parse().unwrap())
+        .insert_header((header::USER_AGENT, long_agent.as_str()))
+        .to_http_request();
+
+    let meta = RequestMeta::extract(&req).await.unwrap();
+    assert_eq!(meta.ip.as_deref(), Some("198.51.100.4"));
+    assert_eq!(meta.user_agent.map(|agent| agent.len()), Some(512));
+
+    let meta = RequestMeta::extract(&test::TestRequest::default().to_http_request()).await.unwrap();
+    assert_eq!(meta.user_agent, None);
+}
+
+#[actix_web::test]
+async fn test_query_range_must_be_ordered() {
+    let query = AuditQuery {
+        since: Some("2030-01-02T00:00:00Z".parse().unwrap()),
+        until: Some("2030-01-01T00:00:00Z".parse().unwrap()),
+        ..Default::default()
+    };
+    assert!(matches!(query.validate(), Err(AppError::ValidationError(_))));
+    assert!(AuditQuery::default().validate().is_ok());
+}
+
+#[actix_web::test]
+async fn test_audit_log_is_admin_only() {
+    let res = call(test::TestRequest::get().uri("/api/admin/audit-events"), None).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    for role in [Role::Mod, Role::Chef, Role::Diner] {
+        let res = call(test::TestRequest::get().uri("/api/admin/audit-events"), Some(signed_in(role))).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", role);
+    }
+}
+
+#[actix_web::test]
+async fn test_audit_log_rejects_bad_filters() {
+    let admin = Some(signed_in(Role::Admin));
+    let res = call(
+        test::TestRequest::get().uri("/api/admin/audit-events?since=2030-01-02T00:00:00Z&until=2030-01-01T00:00:00Z"),
+        admin,
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = call(test::TestRequest::get().uri("/api/admin/audit-events?actor_id=not-a-uuid"), admin).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
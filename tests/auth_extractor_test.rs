@@ -0,0 +1,185 @@
+// Tests for the AuthUser extractor and RequireRole guards.
+// Requests without credentials are rejected before the database is touched;
+// role checks are exercised by seeding the per-request identity and user,
+// the same place the extractors store what they have resolved.
+
+use actix_web::{http::header, http::StatusCode, test, web, App, HttpMessage, HttpResponse};
+use actix_web::dev::Service;
+use chrono::Utc;
+use privatechefspace_backend::middleware::auth::{AuthUser, Identity, RequireRole};
+use privatechefspace_backend::middleware::roles::role;
+use privatechefspace_backend::models::{Role, User};
+use privatechefspace_backend::utils::{create_token, verify_token};
+use uuid::Uuid;
+
+mod common;
+use common::{lazy_pool, test_config};
+
+fn user_with_role(role: Role) -> User {
+    // Admins must have 2FA, so they get it unless a test takes it away
+    User {
+        id: Uuid::new_v4(),
+        email: "someone@example.com".to_string(),
+        password_hash: None,
+        role,
+        token_version: 0,
+        email_verified: false,
+        totp_secret: None,
+        totp_enabled: role == Role::Admin,
+        totp_last_step: None,
+        suspended_at: None,
+        suspension_reason: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+async fn me(user: AuthUser) -> HttpResponse {
+    HttpResponse::Ok().body(user.email.clone())
+}
+
+async fn maybe_me(user: Option<AuthUser>) -> HttpResponse {
+    HttpResponse::Ok().body(user.map(|u| u.email.clone()).unwrap_or_else(|| "visitor".to_string()))
+}
+
+async fn chef_only(user: RequireRole<role::Chef>) -> HttpResponse {
+    HttpResponse::Ok().body(user.role.to_string())
+}
+
+async fn admin_only(user: RequireRole<role::Admin>) -> HttpResponse {
+    HttpResponse::Ok().body(user.role.to_string())
+}
+
+/// Sends `req` to a small app using the extractors. With `signed_in`, that
+/// user is already cached on the request.
+async fn call(req: test::TestRequest, signed_in: Option<User>) -> actix_web::dev::ServiceResponse {
+    let pool = lazy_pool();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool))
+            .app_data(web::Data::new(test_config()))
+            .wrap_fn(move |req, srv| {
+                if let Some(user) = signed_in.clone() {
+                    req.extensions_mut().insert(Identity {
+                        id: user.id,
+                        role: user.role,
+                        two_factor_enabled: user.totp_enabled,
+                        impersonation: None,
+                    });
+                    req.extensions_mut().insert(AuthUser(user));
+                }
+                srv.call(req)
+            })
+            .route("/me", web::get().to(me))
+            .route("/maybe-me", web::get().to(maybe_me))
+            .route("/chef", web::get().to(chef_only))
+            .route("/admin", web::get().to(admin_only)),
+    )
+    .await;
+
+    match test::try_call_service(&app, req.to_request()).await {
+        Ok(res) => res,
+        Err(err) => panic!("unexpected service error: {}", err),
+    }
+}
+
+#[actix_web::test]
+async fn test_missing_credentials_is_401_json() {
+    let res = call(test::TestRequest::get().uri("/me"), None).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert!(body["error"].as_str().unwrap().contains("Not authenticated"));
+}
+
+#[actix_web::test]
+async fn test_missing_credentials_redirects_browsers_to_login() {
+    let req = test::TestRequest::get()
+        .uri("/me")
+        .insert_header((header::ACCEPT, "text/html,application/xhtml+xml"));
+    let res = call(req, None).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/login");
+}
+
+#[actix_web::test]
+async fn test_invalid_bearer_token_is_rejected() {
+    let req = test::TestRequest::get()
+        .uri("/me")
+        .insert_header((header::AUTHORIZATION, "Bearer not-a-jwt"));
+    assert_eq!(call(req, None).await.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::get()
+        .uri("/me")
+        .insert_header((header::AUTHORIZATION, "Basic dXNlcjpwYXNz"));
+    assert_eq!(call(req, None).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_optional_user_allows_visitors() {
+    let res = call(test::TestRequest::get().uri("/maybe-me"), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, "visitor");
+
+    let res = call(test::TestRequest::get().uri("/maybe-me"), Some(user_with_role(Role::Diner))).await;
+    assert_eq!(test::read_body(res).await, "someone@example.com");
+}
+
+#[actix_web::test]
+async fn test_require_role_allows_listed_roles() {
+    for role in [Role::Chef, Role::Admin] {
+        let res = call(test::TestRequest::get().uri("/chef"), Some(user_with_role(role))).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", role);
+    }
+    let res = call(test::TestRequest::get().uri("/admin"), Some(user_with_role(Role::Admin))).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_require_role_rejects_other_roles() {
+    for role in [Role::Diner, Role::Mod] {
+        let res = call(test::TestRequest::get().uri("/chef"), Some(user_with_role(role))).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", role);
+    }
+    let res = call(test::TestRequest::get().uri("/admin"), Some(user_with_role(Role::Chef))).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_require_role_sends_browsers_back_to_dashboard() {
+    let req = test::TestRequest::get()
+        .uri("/chef")
+        .insert_header((header::ACCEPT, "text/html"));
+    let res = call(req, Some(user_with_role(Role::Diner))).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/dashboard");
+}
+
+#[actix_web::test]
+async fn test_require_role_rejects_admins_without_two_factor() {
+    let mut admin = user_with_role(Role::Admin);
+    admin.totp_enabled = false;
+    let res = call(test::TestRequest::get().uri("/admin"), Some(admin.clone())).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert!(body["error"].as_str().unwrap().contains("Two-factor authentication is required"));
+
+    let req = test::TestRequest::get()
+        .uri("/chef")
+        .insert_header((header::ACCEPT, "text/html"));
+    let res = call(req, Some(admin)).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/dashboard/two-factor");
+}
+
+#[actix_web::test]
+async fn test_access_token_carries_role_and_version() {
+    let mut user = user_with_role(Role::Chef);
+    user.token_version = 3;
+    let config = test_config();
+
+    let token = create_token(&user, &config.jwt).unwrap();
+    let claims = verify_token(&token, &config.jwt.secret).unwrap();
+    assert_eq!(claims.sub, user.id.to_string());
+    assert_eq!(claims.role, Role::Chef);
+    assert_eq!(claims.ver, 3);
+}
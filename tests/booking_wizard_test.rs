@@ -0,0 +1,235 @@
+// Tests for the diner booking wizard and booking dashboard
+// Covers the stateless wizard draft, step navigation, status transitions and dashboard rendering
+
+use askama::Template;
+use chrono::{NaiveDate, NaiveTime, Utc};
+use privatechefspace_backend::models::{
+    Booking, BookingDraft, BookingStatus, BookingSummary, Role, UserResponse, WizardStep,
+};
+use privatechefspace_backend::middleware::flash::FlashMessage;
+use privatechefspace_backend::templates::DashboardTemplate;
+use uuid::Uuid;
+
+mod common;
+use common::page_context;
+
+fn fields(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+fn complete_draft() -> BookingDraft {
+    BookingDraft::from_form(&fields(&[
+        ("slot", "2030-06-01 18:00"),
+        ("number_of_guests", "6"),
+        ("duration_hours", "4"),
+        ("dietary_notes", "One vegetarian"),
+        ("customer_name", "Alex Diner"),
+        ("location_address", "12 Harbour Street"),
+        ("special_requests", "Birthday dinner"),
+    ]))
+}
+
+fn summary(status: &str, event_date: NaiveDate) -> BookingSummary {
+    BookingSummary {
+        booking: Booking {
+            id: Uuid::new_v4(),
+            chef_id: Uuid::new_v4(),
+            customer_id: Some(Uuid::new_v4()),
+            menu_id: None,
+            customer_name: "Alex Diner".to_string(),
+            customer_email: "alex@example.com".to_string(),
+            customer_phone: None,
+            event_date,
+            event_time: NaiveTime::from_hms_opt(19, 0, 0).unwrap(),
+            duration_hours: 3.0,
+            number_of_guests: 4,
+            location_address: "12 Harbour Street".to_string(),
+            special_requests: None,
+            total_price: 255.0,
+            status: status.to_string(),
+            payment_status: "pending".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        },
+        chef_name: "Camille <Rose>".to_string(),
+        chef_slug: Some("camille-rose".to_string()),
+        menu_name: Some("Tasting Menu".to_string()),
+    }
+}
+
+#[test]
+fn test_wizard_step_navigation() {
+    assert_eq!(WizardStep::Date.number(), 1);
+    assert_eq!(WizardStep::Review.number(), 5);
+    assert_eq!(WizardStep::from_number(3), Some(WizardStep::Guests));
+    assert_eq!(WizardStep::from_number(0), None);
+    assert_eq!(WizardStep::from_number(6), None);
+    assert_eq!(WizardStep::Date.next(), WizardStep::Menu);
+    assert_eq!(WizardStep::Review.next(), WizardStep::Review);
+    assert_eq!(WizardStep::Menu.previous(), WizardStep::Date);
+    assert_eq!(WizardStep::Date.previous(), WizardStep::Date);
+}
+
+#[test]
+fn test_draft_from_form_collects_add_ons_and_skips_blanks() {
+    let add_on = Uuid::new_v4();
+    let draft = BookingDraft::from_form(&fields(&[
+        ("menu_id", ""),
+        ("add_on", &add_on.to_string()),
+        ("add_on", &add_on.to_string()),
+        ("add_on", "not-a-uuid"),
+        ("customer_phone", "   "),
+        ("number_of_guests", "four"),
+    ]));
+
+    assert_eq!(draft.menu_id, None);
+    assert_eq!(draft.add_on_ids, vec![add_on]);
+    assert_eq!(draft.customer_phone, None);
+    assert_eq!(draft.number_of_guests, None);
+}
+
+#[test]
+fn test_hidden_fields_round_trip() {
+    let draft = complete_draft();
+
+    let hidden = draft.hidden_fields(WizardStep::Guests);
+    assert!(hidden.iter().all(|(name, _)| *name != "number_of_guests" && *name != "dietary_notes"));
+    assert!(hidden.contains(&("slot", "2030-06-01 18:00".to_string())));
+
+    // Everything answered so far survives the trip through hidden inputs on the review step
+    let pairs: Vec<(String, String)> = draft
+        .hidden_fields(WizardStep::Review)
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+    assert_eq!(BookingDraft::from_form(&pairs), draft);
+}
+
+#[test]
+fn test_validate_step() {
+    let draft = complete_draft();
+    assert!(draft.validate_step(WizardStep::Review).is_ok());
+
+    let mut bad_slot = draft.clone();
+    bad_slot.slot = Some("tomorrow evening".to_string());
+    assert!(bad_slot.validate_step(WizardStep::Date).is_err());
+
+    let mut add_on_without_menu = draft.clone();
+    add_on_without_menu.add_on_ids.push(Uuid::new_v4());
+    assert!(add_on_without_menu.validate_step(WizardStep::Menu).is_err());
+
+    let mut too_long = draft.clone();
+    too_long.duration_hours = Some(14.0);
+    assert!(too_long.validate_step(WizardStep::Guests).is_err());
+    assert!(too_long.validate_step(WizardStep::Review).is_err());
+
+    let mut no_address = draft;
+    no_address.location_address = None;
+    assert!(no_address.validate_step(WizardStep::Details).is_err());
+}
+
+#[test]
+fn test_draft_to_create_booking() {
+    let menu_id = Uuid::new_v4();
+    let add_on = Uuid::new_v4();
+    let mut draft = complete_draft();
+    draft.menu_id = Some(menu_id);
+    draft.add_on_ids = vec![add_on];
+
+    let booking = draft.to_create_booking("alex@example.com").unwrap();
+    assert_eq!(booking.event_date, NaiveDate::from_ymd_opt(2030, 6, 1).unwrap());
+    assert_eq!(booking.event_time, NaiveTime::from_hms_opt(18, 0, 0).unwrap());
+    assert_eq!(booking.customer_email, "alex@example.com");
+    assert_eq!(booking.menu_id, Some(menu_id));
+    assert_eq!(booking.number_of_guests, 6);
+    assert_eq!(
+        booking.special_requests.as_deref(),
+        Some("Dietary: One vegetarian\nBirthday dinner")
+    );
+    assert_eq!(booking.add_ons.len(), 1);
+    assert_eq!(booking.add_ons[0].add_on_id, add_on);
+
+    assert!(BookingDraft::default().to_create_booking("alex@example.com").is_err());
+}
+
+#[test]
+fn test_booking_status_transitions() {
+    use BookingStatus::*;
+
+    assert!(Pending.can_transition_to(Confirmed));
+    assert!(Pending.can_transition_to(Declined));
+    assert!(Pending.can_transition_to(Cancelled));
+    assert!(Confirmed.can_transition_to(Completed));
+    assert!(Confirmed.can_transition_to(Cancelled));
+    assert!(!Pending.can_transition_to(Completed));
+    assert!(!Declined.can_transition_to(Confirmed));
+    assert!(!Cancelled.can_transition_to(Pending));
+    assert!(!Completed.can_transition_to(Cancelled));
+
+    assert_eq!("confirmed".parse::<BookingStatus>(), Ok(Confirmed));
+    assert!("archived".parse::<BookingStatus>().is_err());
+    assert_eq!(Declined.to_string(), "declined");
+}
+
+#[test]
+fn test_bookings_complete_from_the_event_day() {
+    let today = NaiveDate::from_ymd_opt(2030, 6, 1).unwrap();
+    let tomorrow = today.succ_opt().unwrap();
+    let yesterday = today.pred_opt().unwrap();
+
+    assert!(summary("confirmed", today).booking.can_become(BookingStatus::Completed, today));
+    assert!(summary("confirmed", yesterday).booking.can_become(BookingStatus::Completed, today));
+    assert!(!summary("confirmed", tomorrow).booking.can_become(BookingStatus::Completed, today));
+    assert!(!summary("pending", yesterday).booking.can_become(BookingStatus::Completed, today));
+    // Other changes don't depend on the date
+    assert!(summary("confirmed", tomorrow).booking.can_become(BookingStatus::Cancelled, today));
+    assert!(summary("pending", tomorrow).booking.can_become(BookingStatus::Confirmed, today));
+}
+
+#[test]
+fn test_can_cancel() {
+    let today = NaiveDate::from_ymd_opt(2030, 6, 1).unwrap();
+    let tomorrow = today.succ_opt().unwrap();
+
+    assert!(summary("pending", tomorrow).can_cancel(today));
+    assert!(summary("confirmed", tomorrow).can_cancel(today));
+    assert!(!summary("confirmed", today).can_cancel(today));
+    assert!(!summary("declined", tomorrow).can_cancel(today));
+    assert!(!summary("cancelled", tomorrow).can_cancel(today));
+}
+
+#[test]
+fn test_dashboard_renders_bookings_with_cancel_forms() {
+    let today = NaiveDate::from_ymd_opt(2030, 6, 1).unwrap();
+    let upcoming = summary("pending", today.succ_opt().unwrap());
+    let past = summary("completed", today.pred_opt().unwrap());
+    let upcoming_id = upcoming.booking.id;
+    let past_id = past.booking.id;
+
+    let html = DashboardTemplate {
+        ctx: page_context(
+            Some(UserResponse {
+                id: Uuid::new_v4(),
+                email: "alex@example.com".to_string(),
+                role: Role::Diner,
+                email_verified: true,
+                two_factor_enabled: false,
+                created_at: Utc::now(),
+            }),
+            vec![FlashMessage::success("Booking request sent")],
+        ),
+        upcoming_bookings: vec![upcoming],
+        past_bookings: vec![past],
+        assigned_events: vec![],
+        today,
+    }
+    .render()
+    .unwrap();
+
+    assert!(html.contains("Booking request sent"));
+    assert!(html.contains("Camille &lt;Rose&gt;"));
+    assert!(html.contains("href=\"/chef/camille-rose\""));
+    assert!(html.contains(&format!("/dashboard/bookings/{}/cancel", upcoming_id)));
+    assert!(!html.contains(&format!("/dashboard/bookings/{}/cancel", past_id)));
+    assert!(html.contains("Past Bookings"));
+}
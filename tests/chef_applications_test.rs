@@ -0,0 +1,220 @@
+// Tests for chef applications: who may apply and review, the checks on the
+// uploaded credentials, and private storage. Everything here happens before
+// the database is reached (the pool is lazy and Redis is unreachable).
+
+use actix_web::{http::StatusCode, test};
+use askama::Template;
+use chrono::Utc;
+use privatechefspace_backend::errors::AppError;
+use privatechefspace_backend::handlers::auth::registration_role;
+use privatechefspace_backend::handlers::chef_application::document_content_type;
+use privatechefspace_backend::models::{
+    ChefApplication, ChefApplicationDetail, ChefApplicationDocument, DocumentKind, Role,
+};
+use privatechefspace_backend::storage::{BlobStore, LocalBlobStore};
+use privatechefspace_backend::templates::{AdminApplicationsTemplate, AdminQuery, PageContext};
+use uuid::Uuid;
+
+mod common;
+use common::{call, signed_in, test_config};
+
+const BOUNDARY: &str = "application-boundary";
+const PDF: &[u8] = b"%PDF-1.7\n1 0 obj\n<<>>\nendobj\n";
+
+/// A multipart body with the given text fields and (name, file name, bytes) files
+fn multipart(fields: &[(&str, &str)], files: &[(&str, &str, &[u8])]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", BOUNDARY, name, value).as_bytes(),
+        );
+    }
+    for (name, file_name, data) in files {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+                BOUNDARY, name, file_name
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+    body
+}
+
+fn apply(body: Vec<u8>) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/chef-applications")
+        .insert_header(("Content-Type", format!("multipart/form-data; boundary={}", BOUNDARY)))
+        .set_payload(body)
+}
+
+#[actix_web::test]
+async fn test_registration_is_for_diners_only() {
+    assert_eq!(registration_role(None).unwrap(), Role::Diner);
+    assert_eq!(registration_role(Some(Role::Diner)).unwrap(), Role::Diner);
+    for role in [Role::Chef, Role::Mod, Role::Admin] {
+        let err = registration_role(Some(role)).unwrap_err();
+        assert!(matches!(err, AppError::ValidationError(_)), "{}", role);
+    }
+}
+
+#[actix_web::test]
+async fn test_document_types_are_sniffed() {
+    assert_eq!(document_content_type(PDF), Some("application/pdf"));
+    assert_eq!(document_content_type(&[0xFF, 0xD8, 0xFF, 0xE0, 0, 0]), Some("image/jpeg"));
+    assert_eq!(document_content_type(b"\x89PNG\r\n\x1a\n\0\0"), Some("image/png"));
+    // The file name doesn't matter, only the contents
+    assert_eq!(document_content_type(b"<html><script>alert(1)</script>"), None);
+    assert_eq!(document_content_type(b""), None);
+}
+
+#[actix_web::test]
+async fn test_document_kinds() {
+    for kind in DocumentKind::ALL {
+        assert_eq!(kind.to_string().parse::<DocumentKind>().unwrap(), kind);
+    }
+    assert!("passport".parse::<DocumentKind>().is_err());
+}
+
+#[actix_web::test]
+async fn test_applying_requires_a_diner() {
+    let body = multipart(
+        &[("experience", "Ten years in restaurant kitchens")],
+        &[("food_safety_certificate", "cert.pdf", PDF), ("insurance", "policy.pdf", PDF)],
+    );
+    let res = call(apply(body.clone()), None).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    for role in [Role::Chef, Role::Mod, Role::Admin] {
+        let res = call(apply(body.clone()), Some(signed_in(role))).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", role);
+    }
+}
+
+#[actix_web::test]
+async fn test_application_needs_experience_and_both_documents() {
+    let diner = signed_in(Role::Diner);
+    let cases = [
+        multipart(&[], &[("food_safety_certificate", "cert.pdf", PDF), ("insurance", "policy.pdf", PDF)]),
+        multipart(&[("experience", "  ")], &[("food_safety_certificate", "cert.pdf", PDF), ("insurance", "policy.pdf", PDF)]),
+        multipart(&[("experience", "Caterer")], &[("food_safety_certificate", "cert.pdf", PDF)]),
+        multipart(&[("experience", "Caterer")], &[("insurance", "policy.pdf", PDF)]),
+    ];
+    for (i, body) in cases.into_iter().enumerate() {
+        let res = call(apply(body), Some(diner)).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "case {}", i);
+    }
+}
+
+#[actix_web::test]
+async fn test_application_rejects_other_file_types_and_large_files() {
+    let diner = signed_in(Role::Diner);
+    let body = multipart(
+        &[("experience", "Caterer")],
+        &[("food_safety_certificate", "cert.pdf", b"MZ\x90\0 not a pdf"), ("insurance", "policy.pdf", PDF)],
+    );
+    assert_eq!(call(apply(body), Some(diner)).await.status(), StatusCode::BAD_REQUEST);
+
+    let mut large = PDF.to_vec();
+    large.resize(test_config().storage.max_upload_bytes + 1, b' ');
+    let body = multipart(
+        &[("experience", "Caterer")],
+        &[("food_safety_certificate", "cert.pdf", &large), ("insurance", "policy.pdf", PDF)],
+    );
+    assert_eq!(call(apply(body), Some(diner)).await.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_only_staff_review_applications() {
+    let id = Uuid::new_v4();
+    for role in [Role::Chef, Role::Diner] {
+        let who = Some(signed_in(role));
+        let res = call(test::TestRequest::get().uri("/api/admin/chef-applications"), who).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", role);
+        let res = call(test::TestRequest::post().uri(&format!("/api/admin/chef-applications/{}/approve", id)), who).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", role);
+        let res = call(
+            test::TestRequest::get().uri(&format!("/api/admin/chef-applications/{}/documents/{}", id, Uuid::new_v4())),
+            who,
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", role);
+    }
+}
+
+#[actix_web::test]
+async fn test_rejection_needs_a_reason() {
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/admin/chef-applications/{}/reject", Uuid::new_v4()))
+        .set_json(serde_json::json!({ "reason": " " }));
+    let res = call(req, Some(signed_in(Role::Mod))).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::get().uri("/api/admin/chef-applications?status=archived");
+    let res = call(req, Some(signed_in(Role::Admin))).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn test_local_blob_store_reads_back() {
+    let root = std::env::temp_dir().join(format!("pcs-private-{}", Uuid::new_v4()));
+    let store = LocalBlobStore::new(&root, "/uploads");
+
+    store.put("private/chef-applications/a/b", PDF.to_vec(), "application/pdf").await.unwrap();
+    assert_eq!(store.get("private/chef-applications/a/b").await.unwrap(), PDF);
+    assert!(matches!(store.get("private/missing").await, Err(AppError::NotFound(_))));
+    assert!(store.get("../escape").await.is_err());
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[actix_web::test]
+async fn test_console_lists_documents_and_pending_actions() {
+    let application = ChefApplication {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        experience: "Private dinners for ten years".to_string(),
+        status: "pending".to_string(),
+        reviewed_by: None,
+        reviewed_at: None,
+        rejection_reason: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    let document = ChefApplicationDocument {
+        id: Uuid::new_v4(),
+        application_id: application.id,
+        kind: "food_safety_certificate".to_string(),
+        file_name: "cert.pdf".to_string(),
+        content_type: "application/pdf".to_string(),
+        size_bytes: 1024,
+        storage_key: "private/chef-applications/x/y".to_string(),
+        created_at: Utc::now(),
+    };
+    let detail = ChefApplicationDetail {
+        application: application.clone(),
+        applicant_email: "cook@example.com".to_string(),
+        documents: vec![document.clone()],
+    };
+
+    // The storage key stays on the server
+    let json = serde_json::to_value(&detail).unwrap();
+    assert!(json["documents"][0].get("storage_key").is_none());
+    assert_eq!(json["applicant_email"], "cook@example.com");
+
+    let html = AdminApplicationsTemplate {
+        ctx: PageContext { user: None, csrf_token: "test-token".to_string(), flash: vec![], impersonation: None },
+        section: "applications",
+        query: AdminQuery(std::iter::once(("status".to_string(), "pending".to_string())).collect()),
+        applications: vec![detail],
+    }
+    .render()
+    .unwrap();
+    assert!(html.contains("Food safety certificate"));
+    assert!(html.contains(&format!("/admin/applications/{}/documents/{}", application.id, document.id)));
+    assert!(html.contains(&format!("/admin/applications/{}/approve", application.id)));
+    assert!(!html.contains("private/chef-applications"));
+}
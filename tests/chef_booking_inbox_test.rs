@@ -0,0 +1,159 @@
+// Tests for the chef dashboard booking inbox
+// Covers tab selection, the month calendar and rendering of the inbox actions
+
+use askama::Template;
+use chrono::{NaiveDate, NaiveTime, Utc};
+use privatechefspace_backend::models::{
+    Booking, BookingCalendar, BookingSummary, BookingTab, Role, TeamRole, UserResponse,
+};
+use privatechefspace_backend::templates::ChefDashboardTemplate;
+use uuid::Uuid;
+
+mod common;
+use common::{chef, page_context};
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn summary(status: &str, event_date: NaiveDate, hour: u32) -> BookingSummary {
+    BookingSummary {
+        booking: Booking {
+            id: Uuid::new_v4(),
+            chef_id: Uuid::new_v4(),
+            customer_id: None,
+            menu_id: None,
+            customer_name: format!("Guest {}", status),
+            customer_email: "guest@example.com".to_string(),
+            customer_phone: None,
+            event_date,
+            event_time: NaiveTime::from_hms_opt(hour, 0, 0).unwrap(),
+            duration_hours: 3.0,
+            number_of_guests: 4,
+            location_address: "1 Main Street".to_string(),
+            special_requests: None,
+            total_price: 300.0,
+            status: status.to_string(),
+            payment_status: "pending".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        },
+        chef_name: "Camille Rose".to_string(),
+        chef_slug: Some("camille-rose".to_string()),
+        menu_name: None,
+    }
+}
+
+#[test]
+fn test_booking_tabs() {
+    let today = date(2030, 6, 15);
+    let bookings = vec![
+        summary("pending", date(2030, 6, 20), 19),
+        summary("pending", date(2030, 6, 10), 19),
+        summary("confirmed", date(2030, 6, 15), 19),
+        summary("confirmed", date(2030, 6, 14), 19),
+        summary("declined", date(2030, 6, 25), 19),
+        summary("completed", date(2030, 6, 1), 19),
+    ];
+
+    let pending = BookingTab::Pending.select(&bookings, today);
+    assert_eq!(pending.len(), 2);
+    assert_eq!(pending[0].booking.event_date, date(2030, 6, 10));
+
+    let upcoming = BookingTab::Upcoming.select(&bookings, today);
+    assert_eq!(upcoming.len(), 1);
+    assert_eq!(upcoming[0].booking.event_date, today);
+
+    // Most recent first, including confirmed events whose day has passed
+    let past = BookingTab::Past.select(&bookings, today);
+    let dates: Vec<NaiveDate> = past.iter().map(|b| b.booking.event_date).collect();
+    assert_eq!(dates, vec![date(2030, 6, 25), date(2030, 6, 14), date(2030, 6, 1)]);
+
+    assert_eq!(BookingTab::from_query(Some("past")), BookingTab::Past);
+    assert_eq!(BookingTab::from_query(Some("nonsense")), BookingTab::Pending);
+    assert_eq!(BookingTab::from_query(None), BookingTab::Pending);
+}
+
+#[test]
+fn test_can_complete_and_awaits_reply() {
+    let today = date(2030, 6, 15);
+    assert!(summary("confirmed", today, 19).can_complete(today));
+    assert!(!summary("confirmed", date(2030, 6, 16), 19).can_complete(today));
+    assert!(!summary("pending", today, 19).can_complete(today));
+    assert!(summary("pending", today, 19).awaits_reply());
+    assert!(!summary("confirmed", today, 19).awaits_reply());
+}
+
+#[test]
+fn test_calendar_layout() {
+    // June 2030 starts on a Saturday and ends on a Sunday
+    let calendar = BookingCalendar::build(date(2030, 6, 15), &[], date(2030, 6, 15));
+
+    assert_eq!(calendar.month, date(2030, 6, 1));
+    assert_eq!(calendar.title(), "June 2030");
+    assert_eq!(calendar.weeks.len(), 5);
+    assert!(calendar.weeks.iter().all(|w| w.len() == 7));
+    assert_eq!(calendar.range(), (date(2030, 5, 27), date(2030, 6, 30)));
+    assert!(!calendar.weeks[0][0].in_month);
+    assert!(calendar.weeks[0][5].in_month);
+    assert_eq!(calendar.weeks.iter().flatten().filter(|d| d.is_today).count(), 1);
+
+    assert_eq!(calendar.previous_month(), "2030-05");
+    assert_eq!(calendar.next_month(), "2030-07");
+    assert_eq!(BookingCalendar::parse_month("2030-12"), Some(date(2030, 12, 1)));
+    assert_eq!(BookingCalendar::parse_month("2030-13"), None);
+}
+
+#[test]
+fn test_calendar_entries_skip_declined_and_cancelled() {
+    let day = date(2030, 6, 20);
+    let bookings = vec![
+        summary("confirmed", day, 19),
+        summary("pending", day, 12),
+        summary("cancelled", day, 18),
+        summary("declined", day, 20),
+    ];
+    let calendar = BookingCalendar::build(day, &bookings, date(2030, 6, 1));
+
+    let cell = calendar.weeks.iter().flatten().find(|d| d.date == day).unwrap();
+    let times: Vec<String> = cell.entries.iter().map(|e| e.time.format("%H:%M").to_string()).collect();
+    assert_eq!(times, vec!["12:00", "19:00"]);
+}
+
+#[test]
+fn test_inbox_renders_actions_for_tab() {
+    let today = date(2030, 6, 15);
+    let pending = summary("pending", date(2030, 6, 20), 19);
+    let pending_id = pending.booking.id;
+    let bookings = vec![pending];
+
+    let html = ChefDashboardTemplate {
+        ctx: page_context(
+            Some(UserResponse {
+                id: Uuid::new_v4(),
+                email: "chef@example.com".to_string(),
+                role: Role::Chef,
+                email_verified: true,
+                two_factor_enabled: false,
+                created_at: Utc::now(),
+            }),
+            vec![],
+        ),
+        chef: Some(chef()),
+        menus_with_items: Vec::new(),
+        booking_tab: BookingTab::Pending,
+        bookings: BookingTab::Pending.select(&bookings, today),
+        pending_count: 1,
+        calendar: Some(BookingCalendar::build(today, &bookings, today)),
+        today,
+        team_role: Some(TeamRole::Owner),
+    }
+    .render()
+    .unwrap();
+
+    assert!(html.contains(&format!("/chef-dashboard/bookings/{}/confirm", pending_id)));
+    assert!(html.contains(&format!("/chef-dashboard/bookings/{}/decline", pending_id)));
+    assert!(!html.contains("/complete\""));
+    assert!(html.contains("June 2030"));
+    assert!(html.contains("month=2030-07"));
+}
@@ -0,0 +1,193 @@
+// Rendering tests for the public chef and menu pages
+// Checks SEO/Open Graph tags, escaping of chef-provided text and the booking form
+
+use askama::Template;
+use chrono::{NaiveDate, Utc};
+use privatechefspace_backend::models::{
+    BookingAvailability, ChefPublicProfile, Menu, MenuItem, PublicMenu, RatingSummary, Review,
+};
+use privatechefspace_backend::middleware::flash::FlashMessage;
+use privatechefspace_backend::templates::{BookingForm, ChefMenuTemplate, ChefProfileTemplate, PageMeta};
+use uuid::Uuid;
+
+mod common;
+use common::page_context;
+
+fn chef() -> ChefPublicProfile {
+    ChefPublicProfile {
+        id: Uuid::new_v4(),
+        business_name: Some("Maison Rose".to_string()),
+        chef_name: "Camille Rose".to_string(),
+        bio: Some("Classic French cooking <script>alert(1)</script>".to_string()),
+        cuisine_types: Some(vec!["French".to_string(), "Seafood".to_string()]),
+        location: Some("Lyon".to_string()),
+        profile_image_url: None,
+        cover_image_url: Some("/uploads/chefs/1/cover/1600.jpg".to_string()),
+        hourly_rate: Some(85.0),
+        minimum_hours: 3,
+        slug: Some("camille-rose".to_string()),
+        featured_menu_items: Vec::new(),
+    }
+}
+
+fn menu(chef_id: Uuid) -> PublicMenu {
+    let menu = Menu {
+        id: Uuid::new_v4(),
+        chef_id,
+        name: "Tasting Menu".to_string(),
+        description: Some("Seven seasonal courses".to_string()),
+        price_per_person: Some(120.0),
+        minimum_guests: 2,
+        cuisine_type: Some("French".to_string()),
+        dietary_options: Some(vec!["vegetarian".to_string()]),
+        duration_hours: Some(3.5),
+        is_active: true,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    let item = MenuItem {
+        id: Uuid::new_v4(),
+        menu_id: menu.id,
+        name: "Lobster Bisque".to_string(),
+        description: None,
+        course_type: Some("soup".to_string()),
+        image_url: None,
+        is_featured: true,
+        display_order: 0,
+        quantity: None,
+        choice_group_id: None,
+        price_adjustment: Some(12.5),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    PublicMenu::build(menu, vec![item], Vec::new(), Vec::new())
+}
+
+fn meta() -> PageMeta {
+    PageMeta {
+        title: "Camille Rose - Private Chef | PrivateChefSpace".to_string(),
+        description: "Classic French cooking".to_string(),
+        url: "https://example.com/chef/camille-rose".to_string(),
+        image: Some("https://example.com/uploads/chefs/1/cover/1600.jpg".to_string()),
+        og_type: "profile",
+    }
+}
+
+fn availability() -> Vec<BookingAvailability> {
+    vec![BookingAvailability {
+        date: NaiveDate::from_ymd_opt(2030, 5, 17).unwrap(),
+        available: true,
+        available_times: vec!["14:00".to_string(), "18:00".to_string()],
+    }]
+}
+
+fn booking_form(menus: &[PublicMenu], selected: Option<Uuid>) -> BookingForm {
+    BookingForm {
+        action: "/chef/camille-rose/book".to_string(),
+        return_path: "/chef/camille-rose".to_string(),
+        menus: menus.iter().map(|m| (m.id, m.name.clone())).collect(),
+        selected_menu_id: selected,
+        number_of_guests: 2,
+        duration_hours: 3.0,
+        customer_email: "diner@example.com".to_string(),
+    }
+}
+
+#[test]
+fn test_chef_profile_page_renders_meta_and_booking_form() {
+    let chef = chef();
+    let menus = vec![menu(chef.id), menu(chef.id)];
+    let selected = menus[1].id;
+    let review = Review {
+        id: Uuid::new_v4(),
+        chef_id: chef.id,
+        booking_id: None,
+        customer_id: None,
+        reviewer_name: "Alex".to_string(),
+        rating: 5,
+        comment: Some("Unforgettable evening".to_string()),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+
+    let html = ChefProfileTemplate {
+        ctx: page_context(None, vec![FlashMessage::success("Booking request sent!")]),
+        meta: meta(),
+        booking_form: booking_form(&menus, Some(selected)),
+        chef,
+        menus,
+        reviews: vec![review],
+        rating: RatingSummary { average: Some(4.8), count: 12 },
+        availability: availability(),
+    }
+    .render()
+    .unwrap();
+
+    assert!(html.contains(r#"<meta property="og:title" content="Camille Rose - Private Chef | PrivateChefSpace">"#));
+    assert!(html.contains(r#"<meta property="og:type" content="profile">"#));
+    assert!(html.contains(r#"<link rel="canonical" href="https://example.com/chef/camille-rose">"#));
+    assert!(html.contains("og:image"));
+
+    // Chef-provided text is escaped
+    assert!(!html.contains("<script>alert(1)</script>"));
+    assert!(html.contains("&lt;script&gt;"));
+
+    assert!(html.contains("Lobster Bisque"));
+    assert!(html.contains("(+$12.50)"));
+    assert!(html.contains("4.8 (12 reviews)"));
+    assert!(html.contains("Unforgettable evening"));
+    assert!(html.contains("Booking request sent!"));
+
+    // Booking form posts to the chef and offers the open slots
+    assert!(html.contains(r#"action="/chef/camille-rose/book""#));
+    assert!(html.contains(r#"value="2030-05-17 14:00""#));
+    assert!(html.contains(r#"value="2030-05-17 18:00""#));
+    assert!(html.contains(&format!(r#"<option value="{}" selected>"#, selected)));
+}
+
+#[test]
+fn test_menu_page_fixes_the_booked_menu() {
+    let chef = chef();
+    let menu = menu(chef.id);
+    let menu_id = menu.id;
+    let form = booking_form(std::slice::from_ref(&menu), Some(menu_id));
+
+    let html = ChefMenuTemplate {
+        ctx: page_context(None, vec![FlashMessage::error("That time slot is no longer available")]),
+        meta: meta(),
+        chef,
+        menu,
+        availability: availability(),
+        booking_form: form,
+    }
+    .render()
+    .unwrap();
+
+    assert!(html.contains("Tasting Menu"));
+    assert!(html.contains("Minimum 2 guests"));
+    assert!(html.contains("vegetarian"));
+    assert!(html.contains(&format!(r#"<input type="hidden" name="menu_id" value="{}">"#, menu_id)));
+    assert!(!html.contains("<select id=\"menu_id\""));
+    assert!(html.contains("That time slot is no longer available"));
+}
+
+#[test]
+fn test_booking_form_hidden_without_availability() {
+    let chef = chef();
+    let menu = menu(chef.id);
+    let form = booking_form(std::slice::from_ref(&menu), None);
+
+    let html = ChefMenuTemplate {
+        ctx: page_context(None, vec![]),
+        meta: meta(),
+        chef,
+        menu,
+        availability: Vec::new(),
+        booking_form: form,
+    }
+    .render()
+    .unwrap();
+
+    assert!(html.contains("No open dates"));
+    assert!(!html.contains("/chef/camille-rose/book"));
+}
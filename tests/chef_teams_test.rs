@@ -0,0 +1,194 @@
+// Tests for chef teams: what each team role allows, the team pages, and the
+// checks made before the database is reached (the pool is lazy and Redis is
+// unreachable). The signed-in user is seeded on the request.
+
+use actix_web::{http::StatusCode, test as actix_test};
+use askama::Template;
+use chrono::{Duration as ChronoDuration, NaiveDate, Utc};
+use privatechefspace_backend::middleware::auth::Identity;
+use privatechefspace_backend::middleware::roles::Permission;
+use privatechefspace_backend::models::{
+    BookingTab, Chef, ChefInvitation, Impersonation, Role, TeamMember, TeamRole,
+};
+use privatechefspace_backend::templates::{
+    ChefDashboardTemplate, ChefTeamTemplate, TeamInvitationView, TeamJoinTemplate,
+};
+use uuid::Uuid;
+
+mod common;
+use common::{call, page_context, signed_in, user};
+
+fn chef() -> Chef {
+    Chef { business_name: Some("Rose Catering".to_string()), ..common::chef() }
+}
+
+fn member(chef_id: Uuid, email: &str, role: TeamRole) -> TeamMember {
+    TeamMember { chef_id, user_id: Uuid::new_v4(), email: email.to_string(), role: role.to_string(), created_at: Utc::now() }
+}
+
+fn dashboard(team_role: TeamRole) -> String {
+    let today = NaiveDate::from_ymd_opt(2030, 6, 15).unwrap();
+    ChefDashboardTemplate {
+        ctx: page_context(Some(user(Role::Chef)), vec![]),
+        chef: Some(chef()),
+        menus_with_items: Vec::new(),
+        booking_tab: BookingTab::Pending,
+        bookings: Vec::new(),
+        pending_count: 0,
+        calendar: None,
+        today,
+        team_role: Some(team_role),
+    }
+    .render()
+    .unwrap()
+}
+
+#[test]
+fn test_team_roles() {
+    for permission in [
+        Permission::ManageOwnChefProfile,
+        Permission::ManageOwnMenus,
+        Permission::ManageOwnBookings,
+        Permission::ManageOwnPayments,
+        Permission::ManageOwnTeam,
+    ] {
+        assert!(TeamRole::Owner.allows(permission), "{:?}", permission);
+    }
+
+    assert!(TeamRole::Manager.allows(Permission::ManageOwnMenus));
+    assert!(TeamRole::Manager.allows(Permission::ManageOwnChefProfile));
+    assert!(TeamRole::Manager.allows(Permission::ManageOwnPayments));
+    assert!(!TeamRole::Manager.allows(Permission::ManageOwnTeam));
+
+    assert!(TeamRole::Staff.allows(Permission::ManageOwnBookings));
+    assert!(!TeamRole::Staff.allows(Permission::ManageOwnPayments));
+    assert!(!TeamRole::Staff.allows(Permission::ManageOwnMenus));
+
+    // Team roles never grant anything beyond the chef's own resources
+    for role in [TeamRole::Owner, TeamRole::Manager, TeamRole::Staff] {
+        assert!(!role.allows(Permission::RefundBookings), "{}", role);
+        assert_eq!(role.to_string().parse::<TeamRole>(), Ok(role));
+    }
+    assert!(!TeamRole::INVITABLE.contains(&TeamRole::Owner));
+    assert!("admin".parse::<TeamRole>().is_err());
+}
+
+#[test]
+fn test_dashboard_shows_what_the_team_role_allows() {
+    let html = dashboard(TeamRole::Owner);
+    assert!(html.contains("href=\"/chef-dashboard/team\""));
+    assert!(html.contains("href=\"/chef-dashboard/profile/edit\""));
+    assert!(html.contains("action=\"/chef-dashboard/create-menu\""));
+    assert!(!html.contains("action=\"/chef-dashboard/team/leave\""));
+
+    let html = dashboard(TeamRole::Manager);
+    assert!(!html.contains("href=\"/chef-dashboard/team\""));
+    assert!(html.contains("action=\"/chef-dashboard/create-menu\""));
+    assert!(html.contains("action=\"/chef-dashboard/team/leave\""));
+
+    let html = dashboard(TeamRole::Staff);
+    assert!(!html.contains("href=\"/chef-dashboard/profile/edit\""));
+    assert!(!html.contains("action=\"/chef-dashboard/create-menu\""));
+    assert!(html.contains("action=\"/chef-dashboard/team/leave\""));
+}
+
+#[test]
+fn test_team_page_lists_members_and_invitations() {
+    let chef = chef();
+    let owner = member(chef.id, "camille@example.com", TeamRole::Owner);
+    let staff = member(chef.id, "sous@example.com", TeamRole::Staff);
+    let staff_id = staff.user_id;
+    let invitation = ChefInvitation {
+        id: Uuid::new_v4(),
+        chef_id: chef.id,
+        email: "server@example.com".to_string(),
+        role: "manager".to_string(),
+        token_hash: "hash".to_string(),
+        invited_by: Some(owner.user_id),
+        expires_at: Utc::now() + ChronoDuration::days(7),
+        accepted_at: None,
+        accepted_by: None,
+        created_at: Utc::now(),
+    };
+    let invitation_id = invitation.id;
+    let owner_id = owner.user_id;
+
+    let html = ChefTeamTemplate {
+        ctx: page_context(Some(user(Role::Chef)), vec![]),
+        chef,
+        members: vec![owner, staff],
+        invitations: vec![invitation],
+        roles: TeamRole::INVITABLE,
+        invitation_days: 7,
+    }
+    .render()
+    .unwrap();
+
+    assert!(html.contains("sous@example.com"));
+    assert!(html.contains(&format!("/chef-dashboard/team/members/{}/remove", staff_id)));
+    assert!(!html.contains(&format!("/chef-dashboard/team/members/{}/remove", owner_id)));
+    assert!(html.contains("server@example.com"));
+    assert!(html.contains(&format!("/chef-dashboard/team/invitations/{}/revoke", invitation_id)));
+    assert!(html.contains("action=\"/chef-dashboard/team/invite\""));
+    assert!(!html.contains("value=\"owner\""));
+}
+
+#[test]
+fn test_join_page_states() {
+    let render = |user, invitation| {
+        TeamJoinTemplate { ctx: page_context(user, vec![]), token: "abc".to_string(), invitation }.render().unwrap()
+    };
+    let view = || TeamInvitationView {
+        chef_name: "Camille Rose".to_string(),
+        email: "sam@example.com".to_string(),
+        role_label: "Staff".to_string(),
+    };
+
+    let html = render(Some(user(Role::Diner)), Some(view()));
+    assert!(html.contains("action=\"/team/join\""));
+    assert!(html.contains("as staff"));
+
+    let html = render(None, Some(view()));
+    assert!(!html.contains("action=\"/team/join\""));
+    assert!(html.contains("href=\"/login\""));
+
+    assert!(render(None, None).contains("Invitation not found"));
+}
+
+#[actix_web::test]
+async fn test_join_link_without_token_finds_nothing() {
+    let res = call(actix_test::TestRequest::get().uri("/team/join"), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = actix_test::read_body(res).await;
+    assert!(String::from_utf8_lossy(&body).contains("Invitation not found"));
+}
+
+#[actix_web::test]
+async fn test_team_api_is_for_chefs() {
+    let res = call(actix_test::TestRequest::get().uri("/api/chefs/team"), None).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    for role in [Role::Diner, Role::Mod] {
+        let res = call(actix_test::TestRequest::get().uri("/api/chefs/team"), Some(signed_in(role))).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", role);
+    }
+}
+
+#[actix_web::test]
+async fn test_joining_needs_the_account_holder() {
+    let req = actix_test::TestRequest::post().uri("/api/chefs/team/join").set_json(serde_json::json!({ "token": "abc" }));
+    assert_eq!(call(req, None).await.status(), StatusCode::UNAUTHORIZED);
+
+    // Support can't sign a user up to a team while impersonating them
+    let admin = signed_in(Role::Admin);
+    let diner = Identity {
+        impersonation: Some(Impersonation {
+            admin_id: admin.id,
+            admin_token_version: 0,
+            expires_at: Utc::now() + ChronoDuration::minutes(30),
+        }),
+        ..signed_in(Role::Diner)
+    };
+    let req = actix_test::TestRequest::post().uri("/api/chefs/team/join").set_json(serde_json::json!({ "token": "abc" }));
+    assert_eq!(call(req, Some(diner)).await.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,161 @@
+// Fixtures shared by the integration tests. Each test file is its own crate
+// and uses only some of these, hence the dead_code allowance.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::{test as actix_test, web, App, HttpMessage};
+use chrono::Utc;
+use privatechefspace_backend::config::{
+    Config, DatabaseConfig, JwtConfig, MailConfig, OidcConfig, RedisConfig, ServerConfig, StorageConfig,
+    TwoFactorConfig,
+};
+use privatechefspace_backend::db::DbPool;
+use privatechefspace_backend::mail::{LogMailer, Mailer};
+use privatechefspace_backend::middleware::auth::Identity;
+use privatechefspace_backend::middleware::flash::FlashMessage;
+use privatechefspace_backend::models::{Chef, Role, UserResponse};
+use privatechefspace_backend::oidc::OidcProviders;
+use privatechefspace_backend::storage::{BlobStore, LocalBlobStore};
+use privatechefspace_backend::templates::PageContext;
+use privatechefspace_backend::{configure_admin, configure_api, configure_auth, configure_web, RedisClient};
+use sqlx::postgres::PgPoolOptions;
+use uuid::Uuid;
+
+/// Configuration pointing at a database and a Redis that aren't there
+pub fn test_config() -> Config {
+    Config {
+        server: ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+        },
+        database: DatabaseConfig {
+            url: "postgres://localhost/privatechefspace_test".to_string(),
+            max_connections: 1,
+        },
+        redis: RedisConfig {
+            url: "redis://127.0.0.1:1".to_string(),
+        },
+        jwt: JwtConfig {
+            secret: "test-secret".to_string(),
+            expiration: 3600,
+            refresh_expiration: 86400,
+        },
+        storage: StorageConfig {
+            backend: "local".to_string(),
+            local_path: "./uploads".to_string(),
+            public_url: "/uploads".to_string(),
+            max_upload_bytes: 1024 * 1024,
+            s3_bucket: None,
+            s3_region: "us-east-1".to_string(),
+            s3_endpoint: None,
+            s3_access_key: None,
+            s3_secret_key: None,
+        },
+        mail: MailConfig {
+            backend: "log".to_string(),
+            from: "PrivateChefSpace <no-reply@example.com>".to_string(),
+            smtp_url: None,
+            app_url: "http://localhost:8080".to_string(),
+        },
+        two_factor: TwoFactorConfig {
+            issuer: "PrivateChefSpace".to_string(),
+            required_roles: vec![],
+        },
+        oidc: OidcConfig::default(),
+    }
+}
+
+/// A pool that only fails once a query reaches it
+pub fn lazy_pool() -> DbPool {
+    PgPoolOptions::new()
+        .acquire_timeout(Duration::from_millis(200))
+        .connect_lazy("postgres://localhost/privatechefspace_test")
+        .unwrap()
+}
+
+pub fn signed_in(role: Role) -> Identity {
+    Identity { id: Uuid::new_v4(), role, two_factor_enabled: true, impersonation: None }
+}
+
+pub fn user(role: Role) -> UserResponse {
+    UserResponse {
+        id: Uuid::new_v4(),
+        email: "sam@example.com".to_string(),
+        role,
+        email_verified: true,
+        two_factor_enabled: false,
+        created_at: Utc::now(),
+    }
+}
+
+pub fn page_context(user: Option<UserResponse>, flash: Vec<FlashMessage>) -> PageContext {
+    PageContext { user, csrf_token: "test-token".to_string(), flash, impersonation: None }
+}
+
+pub fn chef() -> Chef {
+    Chef {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        business_name: None,
+        chef_name: "Camille Rose".to_string(),
+        bio: None,
+        cuisine_types: None,
+        location: None,
+        phone: None,
+        email: None,
+        website: None,
+        profile_image_url: None,
+        cover_image_url: None,
+        hourly_rate: Some(85.0),
+        minimum_hours: 3,
+        travel_radius: None,
+        is_active: true,
+        slug: Some("camille-rose".to_string()),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+/// Sends the request through every route, as the server mounts them, with
+/// the identity seeded on the request
+pub async fn call(req: actix_test::TestRequest, identity: Option<Identity>) -> ServiceResponse {
+    call_with(test_config(), req, identity).await
+}
+
+/// Like `call`, for tests that need a different configuration
+pub async fn call_with(config: Config, req: actix_test::TestRequest, identity: Option<Identity>) -> ServiceResponse {
+    let pool = web::Data::new(lazy_pool());
+    let config = web::Data::new(config);
+    let redis = web::Data::new(RedisClient::new(&config.redis).await.unwrap());
+    let mailer: web::Data<dyn Mailer> = web::Data::from(Arc::new(LogMailer) as Arc<dyn Mailer>);
+    let root = std::env::temp_dir().join(format!("pcs-tests-{}", Uuid::new_v4()));
+    let blob_store: web::Data<dyn BlobStore> =
+        web::Data::from(Arc::new(LocalBlobStore::new(root, "/uploads")) as Arc<dyn BlobStore>);
+    let oidc = web::Data::new(OidcProviders::new(&config.oidc));
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(pool.clone())
+            .app_data(config.clone())
+            .app_data(redis.clone())
+            .app_data(blob_store)
+            .app_data(mailer)
+            .app_data(oidc)
+            .wrap_fn(move |req, srv| {
+                if let Some(identity) = identity {
+                    req.extensions_mut().insert(identity);
+                }
+                srv.call(req)
+            })
+            .configure(|cfg| {
+                configure_web(cfg, pool.clone(), config.clone(), redis.clone());
+                configure_auth(cfg, pool.clone(), config.clone());
+                configure_admin(cfg, pool.clone(), config.clone());
+                configure_api(cfg, pool.clone(), config.clone());
+            }),
+    )
+    .await;
+    actix_test::call_service(&app, req.to_request()).await
+}
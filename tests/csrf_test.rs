@@ -0,0 +1,136 @@
+// Tests for the CSRF middleware on web form routes
+// No session cookie is sent, so these exercise the anonymous cookie token and
+// never touch Redis.
+
+use actix_web::cookie::Cookie;
+use actix_web::{http::StatusCode, test, web, App, HttpRequest, HttpResponse};
+use privatechefspace_backend::middleware::csrf::{csrf_token, CsrfProtection, CSRF_HEADER};
+use std::collections::HashMap;
+
+async fn show_token(req: HttpRequest) -> HttpResponse {
+    HttpResponse::Ok().body(csrf_token(&req))
+}
+
+async fn echo_form(form: web::Form<HashMap<String, String>>) -> HttpResponse {
+    HttpResponse::Ok().body(form.get("name").cloned().unwrap_or_default())
+}
+
+macro_rules! csrf_app {
+    () => {
+        test::init_service(
+            App::new().service(
+                web::resource("/form")
+                    .wrap(CsrfProtection)
+                    .route(web::get().to(show_token))
+                    .route(web::post().to(echo_form)),
+            ),
+        )
+        .await
+    };
+}
+
+#[actix_web::test]
+async fn test_get_issues_token_cookie() {
+    let app = csrf_app!();
+    let res = test::call_service(&app, test::TestRequest::get().uri("/form").to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let cookie = res
+        .response()
+        .cookies()
+        .find(|c| c.name() == "csrf_token")
+        .expect("csrf cookie should be set")
+        .into_owned();
+    let body = test::read_body(res).await;
+    assert_eq!(body, cookie.value().as_bytes());
+    assert_eq!(cookie.http_only(), Some(true));
+}
+
+#[actix_web::test]
+async fn test_existing_cookie_is_reused() {
+    let app = csrf_app!();
+    let req = test::TestRequest::get()
+        .uri("/form")
+        .cookie(Cookie::new("csrf_token", "known-token"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.response().cookies().next().is_none());
+    assert_eq!(test::read_body(res).await, "known-token");
+}
+
+#[actix_web::test]
+async fn test_post_without_token_is_rejected() {
+    let app = csrf_app!();
+    let req = test::TestRequest::post()
+        .uri("/form")
+        .cookie(Cookie::new("csrf_token", "known-token"))
+        .set_form([("name", "Camille")])
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_post_with_wrong_token_is_rejected() {
+    let app = csrf_app!();
+    let req = test::TestRequest::post()
+        .uri("/form")
+        .cookie(Cookie::new("csrf_token", "known-token"))
+        .set_form([("name", "Camille"), ("csrf_token", "other-token")])
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_post_without_cookie_is_rejected() {
+    // A forged form can't know the token that a fresh cookie would carry
+    let app = csrf_app!();
+    let req = test::TestRequest::post()
+        .uri("/form")
+        .set_form([("name", "Camille"), ("csrf_token", "guessed-token")])
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn test_post_with_form_token_reaches_handler() {
+    let app = csrf_app!();
+    let req = test::TestRequest::post()
+        .uri("/form")
+        .cookie(Cookie::new("csrf_token", "known-token"))
+        .set_form([("csrf_token", "known-token"), ("name", "Camille")])
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    // The body was put back after reading the token
+    assert_eq!(test::read_body(res).await, "Camille");
+}
+
+#[actix_web::test]
+async fn test_post_with_header_token_is_accepted() {
+    let app = csrf_app!();
+    let req = test::TestRequest::post()
+        .uri("/form")
+        .cookie(Cookie::new("csrf_token", "known-token"))
+        .insert_header((CSRF_HEADER, "known-token"))
+        .set_form([("name", "Camille")])
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, "Camille");
+}
+
+#[actix_web::test]
+async fn test_multipart_needs_header() {
+    let app = csrf_app!();
+    let req = test::TestRequest::post()
+        .uri("/form")
+        .cookie(Cookie::new("csrf_token", "known-token"))
+        .insert_header(("content-type", "multipart/form-data; boundary=xyz"))
+        .set_payload("--xyz\r\nContent-Disposition: form-data; name=\"csrf_token\"\r\n\r\nknown-token\r\n--xyz--\r\n")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,189 @@
+// Tests for the chef dashboard edit and delete flows
+// Covers reading the edit forms into the update models and rendering the edit pages
+
+use std::collections::HashMap;
+
+use askama::Template;
+use chrono::Utc;
+use privatechefspace_backend::models::{Chef, Menu, UpdateChef, UpdateMenu, UpdateMenuItem};
+use privatechefspace_backend::middleware::flash::FlashMessage;
+use privatechefspace_backend::templates::{ChefProfileEditTemplate, ConfirmDeleteTemplate, MenuEditTemplate};
+use uuid::Uuid;
+
+mod common;
+use common::page_context;
+
+fn form(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+fn chef() -> Chef {
+    Chef {
+        business_name: Some("Maison \"Rose\"".to_string()),
+        cuisine_types: Some(vec!["French".to_string(), "Seafood".to_string()]),
+        location: Some("Lyon".to_string()),
+        email: Some("camille@example.com".to_string()),
+        hourly_rate: Some(85.5),
+        travel_radius: Some(25),
+        is_active: false,
+        ..common::chef()
+    }
+}
+
+#[test]
+fn test_update_chef_from_form_reads_every_field() {
+    let data = UpdateChef::from_form(&form(&[
+        ("chef_name", "  Camille Rose "),
+        ("business_name", ""),
+        ("bio", "Classic French cooking"),
+        ("cuisine_types", "French, , Seafood"),
+        ("location", "Lyon"),
+        ("phone", "+33 1 23 45 67 89"),
+        ("email", "camille@example.com"),
+        ("website", "https://camille.example.com"),
+        ("profile_image_url", "/uploads/p.jpg"),
+        ("cover_image_url", ""),
+        ("hourly_rate", "85.50"),
+        ("minimum_hours", "3"),
+        ("travel_radius", "25"),
+        ("is_active", "on"),
+    ]))
+    .unwrap();
+
+    assert_eq!(data.chef_name.as_deref(), Some("Camille Rose"));
+    assert_eq!(data.business_name, None);
+    assert_eq!(data.cuisine_types, Some(vec!["French".to_string(), "Seafood".to_string()]));
+    assert_eq!(data.phone.as_deref(), Some("+33 1 23 45 67 89"));
+    assert_eq!(data.website.as_deref(), Some("https://camille.example.com"));
+    assert_eq!(data.profile_image_url.as_deref(), Some("/uploads/p.jpg"));
+    assert_eq!(data.cover_image_url, None);
+    assert_eq!(data.hourly_rate, Some(85.5));
+    assert_eq!(data.minimum_hours, Some(3));
+    assert_eq!(data.travel_radius, Some(25));
+    assert_eq!(data.is_active, Some(true));
+
+    // Unticked checkbox isn't submitted at all
+    let hidden = UpdateChef::from_form(&form(&[("chef_name", "Camille"), ("minimum_hours", "2")])).unwrap();
+    assert_eq!(hidden.is_active, Some(false));
+}
+
+#[test]
+fn test_update_chef_from_form_rejects_bad_input() {
+    let base = [("chef_name", "Camille"), ("minimum_hours", "2")];
+    let with = |extra: (&str, &str)| {
+        let mut pairs = base.to_vec();
+        pairs.retain(|(k, _)| *k != extra.0);
+        pairs.push(extra);
+        UpdateChef::from_form(&form(&pairs))
+    };
+
+    assert!(with(("chef_name", "   ")).is_err());
+    assert!(with(("minimum_hours", "")).is_err());
+    assert!(with(("minimum_hours", "0")).is_err());
+    assert!(with(("hourly_rate", "lots")).is_err());
+    assert!(with(("hourly_rate", "-5")).is_err());
+    assert!(with(("travel_radius", "-1")).is_err());
+    assert!(with(("email", "not-an-email")).is_err());
+}
+
+#[test]
+fn test_update_menu_from_form() {
+    let data = UpdateMenu::from_form(&form(&[
+        ("name", "Tasting Menu"),
+        ("description", ""),
+        ("price_per_person", "120"),
+        ("minimum_guests", "4"),
+        ("dietary_options", "vegetarian, gluten-free"),
+        ("duration_hours", "3.5"),
+    ]))
+    .unwrap();
+
+    assert_eq!(data.name.as_deref(), Some("Tasting Menu"));
+    assert_eq!(data.description, None);
+    assert_eq!(data.price_per_person, Some(120.0));
+    assert_eq!(data.minimum_guests, Some(4));
+    assert_eq!(data.dietary_options, Some(vec!["vegetarian".to_string(), "gluten-free".to_string()]));
+    assert_eq!(data.is_active, Some(false));
+
+    assert!(UpdateMenu::from_form(&form(&[("name", "Menu"), ("minimum_guests", "0")])).is_err());
+    assert!(UpdateMenu::from_form(&form(&[("name", "Menu"), ("minimum_guests", "2"), ("duration_hours", "0")])).is_err());
+    assert!(UpdateMenu::from_form(&form(&[("minimum_guests", "2")])).is_err());
+}
+
+#[test]
+fn test_update_menu_item_from_form() {
+    let group_id = Uuid::new_v4();
+    let data = UpdateMenuItem::from_form(&form(&[
+        ("name", "Sole meunière"),
+        ("course_type", "main"),
+        ("quantity", "2"),
+        ("display_order", ""),
+        ("choice_group_id", &group_id.to_string()),
+        ("price_adjustment", "12.5"),
+        ("is_featured", "on"),
+    ]))
+    .unwrap();
+
+    assert_eq!(data.name.as_deref(), Some("Sole meunière"));
+    assert_eq!(data.display_order, Some(0));
+    assert_eq!(data.choice_group_id, Some(group_id));
+    assert_eq!(data.price_adjustment, Some(12.5));
+    assert_eq!(data.is_featured, Some(true));
+
+    assert!(UpdateMenuItem::from_form(&form(&[("name", "Dish"), ("quantity", "0")])).is_err());
+    assert!(UpdateMenuItem::from_form(&form(&[("name", "Dish"), ("choice_group_id", "main")])).is_err());
+}
+
+#[test]
+fn test_profile_edit_form_is_prefilled() {
+    let html = ChefProfileEditTemplate {
+        ctx: page_context(None, vec![FlashMessage::error("Minimum hours is required")]),
+        chef: chef(),
+    }
+    .render()
+    .unwrap();
+
+    assert!(html.contains("value=\"Maison &quot;Rose&quot;\""));
+    assert!(html.contains("value=\"French, Seafood\""));
+    assert!(html.contains("value=\"85.5\""));
+    assert!(html.contains("name=\"travel_radius\" min=\"0\" value=\"25\""));
+    assert!(!html.contains("name=\"is_active\" checked"));
+    assert!(html.contains("Minimum hours is required"));
+    assert!(html.contains("<input type=\"hidden\" name=\"csrf_token\" value=\"test-token\">"));
+}
+
+#[test]
+fn test_menu_edit_and_confirm_delete_pages() {
+    let menu = Menu {
+        id: Uuid::new_v4(),
+        chef_id: Uuid::new_v4(),
+        name: "Tasting Menu".to_string(),
+        description: None,
+        price_per_person: Some(120.0),
+        minimum_guests: 2,
+        cuisine_type: None,
+        dietary_options: None,
+        duration_hours: None,
+        is_active: true,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    let menu_id = menu.id;
+
+    let html = MenuEditTemplate { ctx: page_context(None, vec![]), menu }.render().unwrap();
+    assert!(html.contains(&format!("action=\"/chef-dashboard/menus/{}/edit\"", menu_id)));
+    assert!(html.contains(&format!("href=\"/chef-dashboard/menus/{}/delete\"", menu_id)));
+    assert!(html.contains("name=\"is_active\" checked"));
+
+    let html = ConfirmDeleteTemplate {
+        ctx: page_context(None, vec![]),
+        title: "Delete menu?".to_string(),
+        message: "\"Tasting Menu\" will be deleted.".to_string(),
+        action: format!("/chef-dashboard/menus/{}/delete", menu_id),
+        cancel_path: format!("/chef-dashboard/menus/{}/edit", menu_id),
+    }
+    .render()
+    .unwrap();
+    assert!(html.contains(&format!("method=\"POST\" action=\"/chef-dashboard/menus/{}/delete\"", menu_id)));
+    assert!(html.contains("&quot;Tasting Menu&quot; will be deleted."));
+}
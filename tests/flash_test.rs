@@ -0,0 +1,165 @@
+// Tests for flash messages carried across redirects in a signed cookie
+
+use actix_web::cookie::Cookie;
+use actix_web::{http::StatusCode, test, web, App, HttpRequest, HttpResponse};
+use askama::Template;
+use privatechefspace_backend::middleware::flash::{
+    flash_messages, redirect_with_flash, FlashLevel, FlashMessage, FlashMessages,
+};
+use privatechefspace_backend::templates::{HomeTemplate, PageContext};
+
+async fn save(req: HttpRequest) -> HttpResponse {
+    redirect_with_flash(&req, "/page", FlashMessage::success("Menu \"Spring\" saved"))
+}
+
+async fn bounce(req: HttpRequest) -> HttpResponse {
+    redirect_with_flash(&req, "/page", FlashMessage::error("Something went wrong"))
+}
+
+async fn page(req: HttpRequest) -> HttpResponse {
+    let text: Vec<String> = flash_messages(&req)
+        .into_iter()
+        .map(|m| format!("{:?}:{}", m.level, m.text))
+        .collect();
+    HttpResponse::Ok().body(text.join("|"))
+}
+
+async fn login_redirect() -> HttpResponse {
+    HttpResponse::SeeOther().append_header(("Location", "/login")).finish()
+}
+
+macro_rules! flash_app {
+    () => {
+        test::init_service(
+            App::new()
+                .wrap(FlashMessages::new("test-secret"))
+                .route("/save", web::post().to(save))
+                .route("/bounce", web::post().to(bounce))
+                .route("/page", web::get().to(page))
+                .route("/guarded", web::get().to(login_redirect)),
+        )
+        .await
+    };
+}
+
+fn flash_cookie(res: &actix_web::dev::ServiceResponse) -> Option<Cookie<'static>> {
+    res.response()
+        .cookies()
+        .find(|c| c.name() == "flash")
+        .map(|c| c.into_owned())
+}
+
+#[actix_web::test]
+async fn test_message_survives_redirect_once() {
+    let app = flash_app!();
+
+    let res = test::call_service(&app, test::TestRequest::post().uri("/save").to_request()).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers().get("Location").unwrap(), "/page");
+    let cookie = flash_cookie(&res).expect("flash cookie should be set");
+    assert_eq!(cookie.http_only(), Some(true));
+
+    let req = test::TestRequest::get().uri("/page").cookie(cookie).to_request();
+    let res = test::call_service(&app, req).await;
+    // Shown once, then the cookie is cleared
+    let cleared = flash_cookie(&res).expect("flash cookie should be cleared");
+    assert_eq!(cleared.value(), "");
+    assert_eq!(cleared.max_age(), Some(actix_web::cookie::time::Duration::ZERO));
+    assert_eq!(test::read_body(res).await, "Success:Menu \"Spring\" saved");
+}
+
+#[actix_web::test]
+async fn test_messages_accumulate_across_redirects() {
+    let app = flash_app!();
+
+    let res = test::call_service(&app, test::TestRequest::post().uri("/save").to_request()).await;
+    let cookie = flash_cookie(&res).unwrap();
+
+    let req = test::TestRequest::post().uri("/bounce").cookie(cookie).to_request();
+    let res = test::call_service(&app, req).await;
+    let cookie = flash_cookie(&res).unwrap();
+
+    let req = test::TestRequest::get().uri("/page").cookie(cookie).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(
+        test::read_body(res).await,
+        "Success:Menu \"Spring\" saved|Error:Something went wrong"
+    );
+}
+
+#[actix_web::test]
+async fn test_plain_redirect_keeps_pending_messages() {
+    let app = flash_app!();
+
+    let res = test::call_service(&app, test::TestRequest::post().uri("/save").to_request()).await;
+    let cookie = flash_cookie(&res).unwrap();
+
+    let req = test::TestRequest::get().uri("/guarded").cookie(cookie).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert!(flash_cookie(&res).is_none());
+}
+
+#[actix_web::test]
+async fn test_forged_cookie_is_ignored() {
+    let app = flash_app!();
+
+    // A message crafted without the secret, as a link could carry in ?error= before
+    let json = r#"[{"level":"error","text":"Your account is locked, call 555-0100"}]"#;
+    let forged = format!("{}.{}", "00".repeat(32), urlencoding::encode(json));
+    let req = test::TestRequest::get()
+        .uri("/page")
+        .cookie(Cookie::new("flash", forged))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, "");
+
+    // A genuine cookie signed with another secret is rejected too
+    let other = test::init_service(
+        App::new()
+            .wrap(FlashMessages::new("another-secret"))
+            .route("/save", web::post().to(save)),
+    )
+    .await;
+    let res = test::call_service(&other, test::TestRequest::post().uri("/save").to_request()).await;
+    let cookie = flash_cookie(&res).unwrap();
+    let req = test::TestRequest::get().uri("/page").cookie(cookie).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(test::read_body(res).await, "");
+}
+
+#[actix_web::test]
+async fn test_query_string_is_not_a_message() {
+    let app = flash_app!();
+    let req = test::TestRequest::get()
+        .uri("/page?error=Your%20account%20is%20locked")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(flash_cookie(&res).is_none());
+    assert_eq!(test::read_body(res).await, "");
+}
+
+#[actix_web::test]
+async fn test_base_template_renders_flash_messages() {
+    let html = HomeTemplate {
+        ctx: PageContext {
+            user: None,
+            csrf_token: String::new(),
+            flash: vec![
+                FlashMessage::success("Profile updated"),
+                FlashMessage::error("<b>Dish</b> not found"),
+            ],
+            impersonation: None,
+        },
+    }
+    .render()
+    .unwrap();
+
+    assert!(html.contains("bg-green-50"));
+    assert!(html.contains("Profile updated"));
+    assert!(html.contains("bg-red-50"));
+    // Messages are escaped like any other template value
+    assert!(html.contains("&lt;b&gt;Dish&lt;/b&gt; not found"));
+    assert_eq!(FlashMessage::info("x").level, FlashLevel::Info);
+}
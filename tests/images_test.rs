@@ -0,0 +1,195 @@
+// Tests for the image upload pipeline
+// Covers validation, metadata stripping, resized variants and local storage
+
+use std::io::Cursor;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_trait::async_trait;
+use image::{DynamicImage, ImageFormat, RgbImage, RgbaImage};
+use privatechefspace_backend::errors::AppError;
+use privatechefspace_backend::handlers::upload::{store_variants, variant_keys};
+use privatechefspace_backend::images::{process_image, ImageKind};
+use privatechefspace_backend::storage::{BlobStore, LocalBlobStore};
+
+const MAX_BYTES: usize = 5 * 1024 * 1024;
+
+fn encode(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    image.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+    bytes
+}
+
+fn jpeg(width: u32, height: u32) -> Vec<u8> {
+    encode(DynamicImage::ImageRgb8(RgbImage::new(width, height)), ImageFormat::Jpeg)
+}
+
+/// Insert an APP1 EXIF segment right after the JPEG SOI marker
+fn with_exif(jpeg: &[u8]) -> Vec<u8> {
+    // "Exif\0\0" followed by an empty little-endian TIFF header
+    let payload: &[u8] = b"Exif\0\0II*\0\x08\0\0\0\0\0";
+    let length = (payload.len() + 2) as u16;
+    let mut bytes = jpeg[..2].to_vec();
+    bytes.extend_from_slice(&[0xFF, 0xE1]);
+    bytes.extend_from_slice(&length.to_be_bytes());
+    bytes.extend_from_slice(payload);
+    bytes.extend_from_slice(&jpeg[2..]);
+    bytes
+}
+
+#[test]
+fn test_rejects_non_images_and_oversized_files() {
+    assert!(process_image(b"", ImageKind::MenuItem, MAX_BYTES).is_err());
+    assert!(process_image(b"%PDF-1.7 not an image", ImageKind::MenuItem, MAX_BYTES).is_err());
+    // A declared image type doesn't matter: a script renamed to .jpg is still rejected
+    assert!(process_image(b"<script>alert(1)</script>", ImageKind::MenuItem, MAX_BYTES).is_err());
+
+    let bytes = jpeg(64, 64);
+    assert!(process_image(&bytes, ImageKind::MenuItem, bytes.len() - 1).is_err());
+    assert!(process_image(&bytes, ImageKind::MenuItem, bytes.len()).is_ok());
+}
+
+#[test]
+fn test_strips_exif_metadata() {
+    let bytes = with_exif(&jpeg(64, 48));
+    assert!(bytes.windows(4).any(|w| w == b"Exif"));
+
+    let variants = process_image(&bytes, ImageKind::MenuItem, MAX_BYTES).unwrap();
+    assert!(!variants.is_empty());
+    for variant in variants {
+        assert!(!variant.data.windows(4).any(|w| w == b"Exif"), "{}", variant.content_type);
+    }
+}
+
+#[test]
+fn test_renders_resized_variants_with_webp() {
+    let variants = process_image(&jpeg(1000, 500), ImageKind::MenuItem, MAX_BYTES).unwrap();
+    let sizes: Vec<(u32, u32, &str)> = variants.iter().map(|v| (v.width, v.height, v.content_type)).collect();
+    assert_eq!(
+        sizes,
+        vec![
+            (400, 200, "image/jpeg"),
+            (400, 200, "image/webp"),
+            (800, 400, "image/jpeg"),
+            (800, 400, "image/webp"),
+        ]
+    );
+
+    for variant in &variants {
+        let format = image::guess_format(&variant.data).unwrap();
+        let expected = if variant.content_type == "image/webp" { ImageFormat::WebP } else { ImageFormat::Jpeg };
+        assert_eq!(format, expected);
+    }
+}
+
+#[test]
+fn test_small_images_are_not_upscaled() {
+    let variants = process_image(&jpeg(300, 200), ImageKind::MenuItem, MAX_BYTES).unwrap();
+    // Both target widths collapse to the original size
+    assert_eq!(variants.len(), 2);
+    assert!(variants.iter().all(|v| v.width == 300 && v.height == 200));
+}
+
+#[test]
+fn test_profile_images_are_square() {
+    let variants = process_image(&jpeg(600, 300), ImageKind::ChefProfile, MAX_BYTES).unwrap();
+    assert!(variants.iter().all(|v| v.width == v.height));
+    let widths: Vec<u32> = variants.iter().filter(|v| v.extension == "jpg").map(|v| v.width).collect();
+    assert_eq!(widths, vec![128, 256, 300]);
+}
+
+#[test]
+fn test_transparent_images_stay_png() {
+    let bytes = encode(DynamicImage::ImageRgba8(RgbaImage::new(50, 50)), ImageFormat::Png);
+    let variants = process_image(&bytes, ImageKind::MenuItem, MAX_BYTES).unwrap();
+    assert_eq!(variants[0].content_type, "image/png");
+    assert_eq!(variants[0].extension, "png");
+}
+
+#[actix_web::test]
+async fn test_local_blob_store_writes_and_deletes() {
+    let root = std::env::temp_dir().join(format!("pcs-uploads-{}", uuid::Uuid::new_v4()));
+    let store = LocalBlobStore::new(&root, "/uploads/");
+
+    let url = store.put("menus/abc/400.jpg", vec![1, 2, 3], "image/jpeg").await.unwrap();
+    assert_eq!(url, "/uploads/menus/abc/400.jpg");
+    assert_eq!(store.key_of(&url).as_deref(), Some("menus/abc/400.jpg"));
+    assert_eq!(store.key_of("https://elsewhere.example/menus/abc/400.jpg"), None);
+    assert_eq!(store.key_of("/uploads"), None);
+    assert_eq!(std::fs::read(root.join("menus/abc/400.jpg")).unwrap(), vec![1, 2, 3]);
+
+    store.delete("menus/abc/400.jpg").await.unwrap();
+    assert!(!root.join("menus/abc/400.jpg").exists());
+    // Deleting twice is fine
+    store.delete("menus/abc/400.jpg").await.unwrap();
+
+    // Keys can't escape the upload directory
+    assert!(store.put("../escape.jpg", vec![0], "image/jpeg").await.is_err());
+    assert!(store.put("/etc/passwd", vec![0], "image/jpeg").await.is_err());
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_variant_keys_match_the_rendered_variants() {
+    let cases = [
+        (ImageKind::MenuItem, jpeg(1200, 900)),
+        (ImageKind::MenuItem, jpeg(300, 200)),
+        (ImageKind::ChefProfile, jpeg(300, 200)),
+        (ImageKind::ChefCover, encode(DynamicImage::ImageRgba8(RgbaImage::new(1000, 400)), ImageFormat::Png)),
+    ];
+    for (kind, bytes) in cases {
+        let variants = process_image(&bytes, kind, MAX_BYTES).unwrap();
+        let rendered: Vec<String> =
+            variants.iter().map(|v| format!("up/1/{}.{}", v.width, v.extension)).collect();
+        let largest = rendered.iter().rev().find(|key| !key.ends_with(".webp")).unwrap();
+
+        let mut keys = variant_keys(kind, largest).unwrap();
+        keys.sort();
+        let mut expected = rendered.clone();
+        expected.sort();
+        assert_eq!(keys, expected, "{:?}", kind);
+    }
+
+    assert_eq!(variant_keys(ImageKind::MenuItem, "up/1/large.jpg"), None);
+}
+
+/// A local store whose puts start failing after `puts_left` succeed
+struct FailingStore {
+    inner: LocalBlobStore,
+    puts_left: AtomicUsize,
+}
+
+#[async_trait]
+impl BlobStore for FailingStore {
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<String, AppError> {
+        if self.puts_left.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)).is_err() {
+            return Err(AppError::InternalError("Storage is unavailable".to_string()));
+        }
+        self.inner.put(key, data, content_type).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, AppError> {
+        self.inner.get(key).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        self.inner.delete(key).await
+    }
+
+    fn key_of(&self, url: &str) -> Option<String> {
+        self.inner.key_of(url)
+    }
+}
+
+#[actix_web::test]
+async fn test_a_failed_upload_leaves_no_variants_behind() {
+    let root = std::env::temp_dir().join(format!("pcs-uploads-{}", uuid::Uuid::new_v4()));
+    let store = FailingStore { inner: LocalBlobStore::new(&root, "/uploads"), puts_left: AtomicUsize::new(2) };
+    let variants = process_image(&jpeg(1200, 900), ImageKind::MenuItem, MAX_BYTES).unwrap();
+    assert!(variants.len() > 2);
+
+    assert!(store_variants(&store, "menus/abc/1", variants).await.is_err());
+    assert_eq!(std::fs::read_dir(root.join("menus/abc/1")).unwrap().count(), 0);
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,200 @@
+// Tests for admin impersonation: who may be impersonated, the tokens and
+// audit events it produces, the banner, and the actions it blocks. The
+// signed-in user is seeded on the request, as in admin_api_test.
+
+use actix_web::{http::StatusCode, test};
+use askama::Template;
+use chrono::{Duration as ChronoDuration, Utc};
+use privatechefspace_backend::audit::AuditEvent;
+use privatechefspace_backend::errors::AppError;
+use privatechefspace_backend::handlers::impersonation::{can_impersonate, IMPERSONATION_MINUTES};
+use privatechefspace_backend::middleware::auth::Identity;
+use privatechefspace_backend::models::{Impersonation, Role, User};
+use privatechefspace_backend::templates::{HomeTemplate, PageContext};
+use privatechefspace_backend::utils::{verify_token, Claims};
+use uuid::Uuid;
+
+mod common;
+use common::{call, signed_in, test_config};
+
+fn impersonation(admin: &Identity) -> Impersonation {
+    Impersonation {
+        admin_id: admin.id,
+        admin_token_version: 0,
+        expires_at: Utc::now() + ChronoDuration::minutes(IMPERSONATION_MINUTES),
+    }
+}
+
+/// `user` as an admin is seeing them
+fn impersonated(user: Identity, admin: &Identity) -> Identity {
+    Identity { impersonation: Some(impersonation(admin)), ..user }
+}
+
+fn user(role: Role) -> User {
+    User {
+        id: Uuid::new_v4(),
+        email: "sam@example.com".to_string(),
+        password_hash: None,
+        role,
+        token_version: 3,
+        email_verified: true,
+        totp_secret: None,
+        totp_enabled: false,
+        totp_last_step: None,
+        suspended_at: None,
+        suspension_reason: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+#[actix_web::test]
+async fn test_admins_may_impersonate_active_chefs_and_diners() {
+    let admin = signed_in(Role::Admin);
+    assert!(can_impersonate(&admin, &user(Role::Chef)));
+    assert!(can_impersonate(&admin, &user(Role::Diner)));
+
+    // Staff accounts would hand out their privileges
+    assert!(!can_impersonate(&admin, &user(Role::Admin)));
+    assert!(!can_impersonate(&admin, &user(Role::Mod)));
+
+    let suspended = User { suspended_at: Some(Utc::now()), ..user(Role::Diner) };
+    assert!(!can_impersonate(&admin, &suspended));
+
+    let own = User { id: admin.id, ..user(Role::Chef) };
+    assert!(!can_impersonate(&admin, &own));
+}
+
+#[actix_web::test]
+async fn test_only_admins_impersonate_and_never_in_a_chain() {
+    for role in [Role::Mod, Role::Chef, Role::Diner] {
+        assert!(!can_impersonate(&signed_in(role), &user(Role::Diner)), "{}", role);
+    }
+
+    // An admin already acting as someone can't start another impersonation
+    let admin = signed_in(Role::Admin);
+    let acting = impersonated(signed_in(Role::Admin), &admin);
+    assert!(!can_impersonate(&acting, &user(Role::Diner)));
+}
+
+#[actix_web::test]
+async fn test_token_expires_with_the_impersonation() {
+    let admin = signed_in(Role::Admin);
+    let diner = user(Role::Diner);
+    let imp = impersonation(&admin);
+
+    let token = Claims::impersonation(&diner, imp).encode("test-secret").unwrap();
+    let claims = verify_token(&token, "test-secret").unwrap();
+
+    assert_eq!(claims.sub, diner.id.to_string());
+    assert_eq!(claims.exp, imp.expires_at.timestamp());
+    assert_eq!(claims.imp.map(|i| i.admin_id), Some(admin.id));
+
+    // Ordinary tokens carry no impersonation at all
+    let token = Claims::access(&diner, &test_config().jwt).encode("test-secret").unwrap();
+    assert!(verify_token(&token, "test-secret").unwrap().imp.is_none());
+}
+
+#[actix_web::test]
+async fn test_impersonation_expiry() {
+    let admin = signed_in(Role::Admin);
+    assert!(!impersonation(&admin).is_expired());
+
+    let ended = Impersonation { expires_at: Utc::now() - ChronoDuration::seconds(1), ..impersonation(&admin) };
+    assert!(ended.is_expired());
+}
+
+#[actix_web::test]
+async fn test_audit_events_name_the_impersonating_admin() {
+    let admin = signed_in(Role::Admin);
+    let diner = impersonated(signed_in(Role::Diner), &admin);
+
+    let event = AuditEvent::new("booking_created").by(&diner);
+    assert_eq!(event.actor_id, Some(diner.id));
+    assert_eq!(event.metadata["impersonated_by"], admin.id.to_string());
+
+    let event = AuditEvent::new("booking_created").by(&signed_in(Role::Diner));
+    assert!(!event.metadata.contains_key("impersonated_by"));
+}
+
+#[actix_web::test]
+async fn test_sensitive_actions_are_refused_while_impersonating() {
+    let admin = signed_in(Role::Admin);
+    let diner = impersonated(signed_in(Role::Diner), &admin);
+
+    assert!(signed_in(Role::Diner).forbid_impersonation().is_ok());
+    assert!(matches!(diner.forbid_impersonation(), Err(AppError::Forbidden(_))));
+
+    for path in [
+        "/api/auth/two-factor/setup",
+        "/api/auth/two-factor/enable",
+        "/api/auth/two-factor/disable",
+        "/api/auth/two-factor/recovery-codes",
+        "/api/auth/logout-all",
+        "/api/chef-applications",
+    ] {
+        let req = test::TestRequest::post().uri(path).set_json(serde_json::json!({ "code": "123456" }));
+        assert_eq!(call(req, Some(diner)).await.status(), StatusCode::FORBIDDEN, "{}", path);
+    }
+
+    // Managing the team
+    let chef = impersonated(signed_in(Role::Chef), &admin);
+    let member = format!("/api/chefs/team/members/{}", Uuid::new_v4());
+    for req in [
+        test::TestRequest::post()
+            .uri("/api/chefs/team/invitations")
+            .set_json(serde_json::json!({ "email": "sous@example.com", "role": "staff" })),
+        test::TestRequest::delete().uri(&format!("/api/chefs/team/invitations/{}", Uuid::new_v4())),
+        test::TestRequest::put().uri(&member).set_json(serde_json::json!({ "role": "manager" })),
+        test::TestRequest::delete().uri(&member),
+    ] {
+        assert_eq!(call(req, Some(chef)).await.status(), StatusCode::FORBIDDEN);
+    }
+}
+
+#[actix_web::test]
+async fn test_banner_shows_while_impersonating() {
+    let admin = signed_in(Role::Admin);
+    let render = |impersonation| {
+        HomeTemplate {
+            ctx: PageContext {
+                user: Some(user(Role::Diner).into()),
+                csrf_token: "test-token".to_string(),
+                flash: vec![],
+                impersonation,
+            },
+        }
+        .render()
+        .unwrap()
+    };
+
+    let html = render(Some(impersonation(&admin)));
+    assert!(html.contains("Impersonating sam@example.com"));
+    assert!(html.contains("action=\"/dashboard/stop-impersonating\""));
+
+    assert!(!render(None).contains("stop-impersonating"));
+}
+
+#[actix_web::test]
+async fn test_impersonation_api_is_admin_only() {
+    let uri = format!("/api/admin/users/{}/impersonate", Uuid::new_v4());
+    let body = serde_json::json!({ "reason": "Customer can't see their booking" });
+
+    let res = call(test::TestRequest::post().uri(&uri).set_json(&body), None).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    for role in [Role::Mod, Role::Chef, Role::Diner] {
+        let res = call(test::TestRequest::post().uri(&uri).set_json(&body), Some(signed_in(role))).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", role);
+    }
+}
+
+#[actix_web::test]
+async fn test_impersonation_needs_a_reason() {
+    let uri = format!("/api/admin/users/{}/impersonate", Uuid::new_v4());
+    for reason in ["", "   ", &"x".repeat(501)] {
+        let req = test::TestRequest::post().uri(&uri).set_json(serde_json::json!({ "reason": reason }));
+        let res = call(req, Some(signed_in(Role::Admin))).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
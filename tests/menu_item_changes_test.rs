@@ -0,0 +1,223 @@
+// Tests for reordering and bulk-editing menu items.
+// The checks on the request run anywhere. The rest need a migrated database
+// and are ignored by default; run them with
+// `TEST_DATABASE_URL=postgres://... cargo test --test menu_item_changes_test -- --ignored`.
+
+use actix_web::dev::Service;
+use actix_web::{http::StatusCode, test as actix_test, web, App, HttpMessage};
+use privatechefspace_backend::configure_api;
+use privatechefspace_backend::db::DbPool;
+use privatechefspace_backend::middleware::auth::Identity;
+use privatechefspace_backend::models::{BulkMenuItemChanges, ReorderMenuItems, Role};
+use sqlx::postgres::PgPoolOptions;
+use uuid::Uuid;
+
+mod common;
+use common::test_config;
+
+fn bulk(changes: serde_json::Value) -> BulkMenuItemChanges {
+    serde_json::from_value(changes).unwrap()
+}
+
+#[test]
+fn test_reorder_lists_every_item_once() {
+    let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+    let reorder = |item_ids: Vec<Uuid>| ReorderMenuItems { item_ids }.check(&ids);
+
+    assert!(reorder(vec![ids[2], ids[0], ids[1]]).is_ok());
+    assert!(reorder(vec![ids[2], ids[0]]).is_err());
+    assert!(reorder(vec![ids[2], ids[0], ids[1], Uuid::new_v4()]).is_err());
+    assert!(reorder(vec![ids[2], ids[0], Uuid::new_v4()]).is_err());
+    assert_eq!(reorder(vec![ids[2], ids[0], ids[0]]), Err("Item IDs must not repeat".to_string()));
+}
+
+#[test]
+fn test_bulk_changes_must_not_repeat_items() {
+    let id = Uuid::new_v4();
+    let other = Uuid::new_v4();
+
+    assert!(bulk(serde_json::json!({ "delete": [id, other], "update": [{ "id": Uuid::new_v4(), "name": "Soup" }] }))
+        .check()
+        .is_ok());
+    assert_eq!(
+        bulk(serde_json::json!({ "delete": [id, other, id] })).check(),
+        Err("Items to delete must not repeat".to_string())
+    );
+    assert!(bulk(serde_json::json!({ "update": [{ "id": id }, { "id": id, "name": "Soup" }] })).check().is_err());
+    assert!(bulk(serde_json::json!({ "update": [{ "id": id }], "delete": [id] })).check().is_err());
+    assert!(bulk(serde_json::json!({ "create": [{ "name": "  " }] })).check().is_err());
+}
+
+#[test]
+fn test_an_update_either_sets_or_clears_the_choice_group() {
+    let id = Uuid::new_v4();
+    let group = Uuid::new_v4();
+
+    assert!(bulk(serde_json::json!({ "update": [{ "id": id, "clear_choice_group": true }] })).check().is_ok());
+    assert!(bulk(serde_json::json!({ "update": [{ "id": id, "choice_group_id": group }] })).check().is_ok());
+    assert_eq!(
+        bulk(serde_json::json!({ "update": [{ "id": id, "choice_group_id": group, "clear_choice_group": true }] }))
+            .check(),
+        Err("Set either choice_group_id or clear_choice_group, not both".to_string())
+    );
+}
+
+/// A chef's menu with three items, plus an item on another chef's menu
+struct Seeded {
+    chef: Identity,
+    menu_id: Uuid,
+    items: Vec<Uuid>,
+    foreign_item: Uuid,
+}
+
+async fn database() -> DbPool {
+    let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must point at a migrated database");
+    PgPoolOptions::new().max_connections(2).connect(&url).await.unwrap()
+}
+
+async fn seed_menu(pool: &DbPool) -> (Uuid, Uuid) {
+    let (user_id,): (Uuid,) =
+        sqlx::query_as("INSERT INTO users (email, password_hash, role) VALUES ($1, 'x', 'chef') RETURNING id")
+            .bind(format!("{}@example.com", Uuid::new_v4()))
+            .fetch_one(pool)
+            .await
+            .unwrap();
+    let (chef_id,): (Uuid,) = sqlx::query_as("INSERT INTO chefs (user_id, chef_name) VALUES ($1, 'Camille') RETURNING id")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    let (menu_id,): (Uuid,) =
+        sqlx::query_as("INSERT INTO menus (chef_id, name, price_per_person) VALUES ($1, 'Tasting', 95) RETURNING id")
+            .bind(chef_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+    (user_id, menu_id)
+}
+
+async fn add_item(pool: &DbPool, menu_id: Uuid, name: &str, order: i32) -> Uuid {
+    let (id,): (Uuid,) =
+        sqlx::query_as("INSERT INTO menu_items (menu_id, name, display_order) VALUES ($1, $2, $3) RETURNING id")
+            .bind(menu_id)
+            .bind(name)
+            .bind(order)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+    id
+}
+
+async fn seed(pool: &DbPool) -> Seeded {
+    let (user_id, menu_id) = seed_menu(pool).await;
+    let items = vec![
+        add_item(pool, menu_id, "Oysters", 0).await,
+        add_item(pool, menu_id, "Beef", 1).await,
+        add_item(pool, menu_id, "Tart", 2).await,
+    ];
+
+    let (_, other_menu) = seed_menu(pool).await;
+    let foreign_item = add_item(pool, other_menu, "Not yours", 0).await;
+
+    Seeded {
+        chef: Identity { id: user_id, role: Role::Chef, two_factor_enabled: false, impersonation: None },
+        menu_id,
+        items,
+        foreign_item,
+    }
+}
+
+async fn call(pool: &DbPool, identity: Identity, req: actix_test::TestRequest) -> (StatusCode, serde_json::Value) {
+    let pool = web::Data::new(pool.clone());
+    let config = web::Data::new(test_config());
+    let app = actix_test::init_service(
+        App::new()
+            .app_data(pool.clone())
+            .app_data(config.clone())
+            .wrap_fn(move |req, srv| {
+                req.extensions_mut().insert(identity);
+                srv.call(req)
+            })
+            .configure(|cfg| configure_api(cfg, pool.clone(), config.clone())),
+    )
+    .await;
+    let res = actix_test::call_service(&app, req.to_request()).await;
+    let status = res.status();
+    let body = actix_test::read_body(res).await;
+    (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+}
+
+/// Item names of the menu, in display order
+async fn item_names(pool: &DbPool, menu_id: Uuid) -> Vec<String> {
+    sqlx::query_as::<_, (String,)>("SELECT name FROM menu_items WHERE menu_id = $1 ORDER BY display_order, name")
+        .bind(menu_id)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(name,)| name)
+        .collect()
+}
+
+#[actix_web::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn test_reorder_rejects_missing_and_foreign_items() {
+    let pool = database().await;
+    let s = seed(&pool).await;
+    let uri = format!("/api/menus/{}/items/order", s.menu_id);
+    let reorder = |item_ids: Vec<Uuid>| {
+        actix_test::TestRequest::put().uri(&uri).set_json(serde_json::json!({ "item_ids": item_ids }))
+    };
+
+    for item_ids in [
+        vec![s.items[2], s.items[0]],
+        vec![s.items[2], s.items[0], s.foreign_item],
+        vec![s.items[2], s.items[0], s.items[1], s.foreign_item],
+    ] {
+        let (status, _) = call(&pool, s.chef, reorder(item_ids)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    assert_eq!(item_names(&pool, s.menu_id).await, ["Oysters", "Beef", "Tart"]);
+}
+
+#[actix_web::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn test_bulk_changes_apply_all_or_nothing() {
+    let pool = database().await;
+    let s = seed(&pool).await;
+    let uri = format!("/api/menus/{}/items/bulk", s.menu_id);
+    let post = |changes: serde_json::Value| actix_test::TestRequest::post().uri(&uri).set_json(changes);
+
+    // The last update is for another chef's item, so nothing before it sticks
+    let (status, _) = call(
+        &pool,
+        s.chef,
+        post(serde_json::json!({
+            "delete": [s.items[0]],
+            "update": [{ "id": s.items[1], "name": "Wagyu" }, { "id": s.foreign_item, "name": "Mine now" }],
+            "create": [{ "name": "Sorbet" }],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(item_names(&pool, s.menu_id).await, ["Oysters", "Beef", "Tart"]);
+
+    // Repeating an item to delete is refused rather than reported as missing
+    let (status, body) = call(&pool, s.chef, post(serde_json::json!({ "delete": [s.items[0], s.items[0]] }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("must not repeat"));
+    assert_eq!(item_names(&pool, s.menu_id).await, ["Oysters", "Beef", "Tart"]);
+}
+
+#[actix_web::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn test_deleting_an_item_through_another_menu_is_not_found() {
+    let pool = database().await;
+    let s = seed(&pool).await;
+    let admin = Identity { id: Uuid::new_v4(), role: Role::Admin, two_factor_enabled: true, impersonation: None };
+    let uri = format!("/api/menus/{}/items/{}", Uuid::new_v4(), s.items[0]);
+
+    let (status, _) = call(&pool, admin, actix_test::TestRequest::delete().uri(&uri)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(item_names(&pool, s.menu_id).await, ["Oysters", "Beef", "Tart"]);
+}
@@ -1,6 +1,5 @@
 /// Integration tests for menu item endpoints
 /// Tests the create, read, update, and delete functionality for menu items
-use std::env;
 
 #[tokio::test]
 async fn test_create_menu_item() {
@@ -0,0 +1,235 @@
+// Tests for choice groups, add-ons and per-guest selections
+// Covers booking price calculation and the chef's prep sheet
+
+use chrono::Utc;
+use privatechefspace_backend::models::{
+    AddOnSelection, BookingGuestSelection, GuestSelection, MenuAddOn, MenuChoiceGroup, MenuItem, PrepSheet,
+};
+use privatechefspace_backend::pricing::{
+    base_price, quote_booking, resolve_add_ons, validate_guest_selections, MAX_PER_BOOKING_QUANTITY,
+};
+use uuid::Uuid;
+
+fn item(menu_id: Uuid, name: &str, group: Option<Uuid>, price_adjustment: f64) -> MenuItem {
+    MenuItem {
+        id: Uuid::new_v4(),
+        menu_id,
+        name: name.to_string(),
+        description: None,
+        course_type: None,
+        image_url: None,
+        is_featured: false,
+        display_order: 0,
+        quantity: None,
+        choice_group_id: group,
+        price_adjustment: Some(price_adjustment),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+fn group(menu_id: Uuid, name: &str, min_choices: i32, max_choices: i32) -> MenuChoiceGroup {
+    MenuChoiceGroup {
+        id: Uuid::new_v4(),
+        menu_id,
+        name: name.to_string(),
+        description: None,
+        course_type: Some("main".to_string()),
+        min_choices,
+        max_choices,
+        display_order: Some(0),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+fn add_on(menu_id: Uuid, name: &str, price: f64, pricing_unit: &str) -> MenuAddOn {
+    MenuAddOn {
+        id: Uuid::new_v4(),
+        menu_id,
+        name: name.to_string(),
+        description: None,
+        price,
+        pricing_unit: pricing_unit.to_string(),
+        is_active: true,
+        display_order: Some(0),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+fn pick(guest_number: i32, item: &MenuItem) -> GuestSelection {
+    GuestSelection {
+        guest_number,
+        guest_name: None,
+        menu_item_id: item.id,
+        dietary_notes: None,
+    }
+}
+
+#[test]
+fn test_base_price_uses_minimum_hours() {
+    assert_eq!(base_price(Some(50.0), 3, 2.0, 4), 600.0);
+    assert_eq!(base_price(Some(50.0), 2, 4.0, 4), 800.0);
+    assert_eq!(base_price(None, 2, 1.0, 1), 200.0);
+}
+
+#[test]
+fn test_selections_must_cover_every_guest() {
+    let menu_id = Uuid::new_v4();
+    let mains = group(menu_id, "Choose your main", 1, 1);
+    let beef = item(menu_id, "Beef", Some(mains.id), 15.0);
+    let fish = item(menu_id, "Fish", Some(mains.id), 0.0);
+    let items = vec![beef.clone(), fish.clone()];
+    let groups = vec![mains];
+
+    // No selections at all is allowed (choices collected later)
+    assert!(validate_guest_selections(&groups, &items, &[], 2).is_ok());
+
+    // Both guests pick one main
+    assert!(validate_guest_selections(&groups, &items, &[pick(1, &beef), pick(2, &fish)], 2).is_ok());
+
+    // Guest 2 didn't pick
+    assert!(validate_guest_selections(&groups, &items, &[pick(1, &beef)], 2).is_err());
+
+    // Guest 1 picked two mains from a choose-one group
+    assert!(validate_guest_selections(&groups, &items, &[pick(1, &beef), pick(1, &fish), pick(2, &fish)], 2).is_err());
+
+    // Guest number out of range
+    assert!(validate_guest_selections(&groups, &items, &[pick(1, &beef), pick(3, &fish)], 2).is_err());
+}
+
+#[test]
+fn test_selections_reject_fixed_or_foreign_items() {
+    let menu_id = Uuid::new_v4();
+    let mains = group(menu_id, "Choose your main", 1, 1);
+    let soup = item(menu_id, "Soup", None, 0.0);
+    let other_menu_item = item(Uuid::new_v4(), "Elsewhere", Some(Uuid::new_v4()), 0.0);
+    let items = vec![soup.clone()];
+    let groups = vec![mains];
+
+    assert!(validate_guest_selections(&groups, &items, &[pick(1, &soup)], 1).is_err());
+    assert!(validate_guest_selections(&groups, &items, &[pick(1, &other_menu_item)], 1).is_err());
+}
+
+#[test]
+fn test_add_on_quantities_and_totals() {
+    let menu_id = Uuid::new_v4();
+    let wine = add_on(menu_id, "Wine pairing", 35.0, "per_guest");
+    let cake = add_on(menu_id, "Celebration cake", 60.0, "per_booking");
+    let available = vec![wine.clone(), cake.clone()];
+
+    let requested = vec![
+        AddOnSelection { add_on_id: wine.id, quantity: None },
+        AddOnSelection { add_on_id: cake.id, quantity: None },
+    ];
+    let priced = resolve_add_ons(&available, &requested, 4).unwrap();
+    assert_eq!(priced[0].quantity, 4);
+    assert_eq!(priced[0].total_price, 140.0);
+    assert_eq!(priced[1].quantity, 1);
+    assert_eq!(priced[1].total_price, 60.0);
+
+    // Per-guest quantities run up to the guest count, per-booking ones to a fixed cap
+    let requested = vec![AddOnSelection { add_on_id: wine.id, quantity: Some(4) }];
+    assert_eq!(resolve_add_ons(&available, &requested, 4).unwrap()[0].total_price, 140.0);
+    let requested = vec![AddOnSelection { add_on_id: cake.id, quantity: Some(6) }];
+    assert_eq!(resolve_add_ons(&available, &requested, 4).unwrap()[0].total_price, 360.0);
+    let out_of_range = [
+        (&wine, 0),
+        (&wine, 5),
+        (&cake, 0),
+        (&cake, MAX_PER_BOOKING_QUANTITY + 1),
+        (&cake, i32::MAX),
+    ];
+    for (add_on, quantity) in out_of_range {
+        let requested = vec![AddOnSelection { add_on_id: add_on.id, quantity: Some(quantity) }];
+        assert!(resolve_add_ons(&available, &requested, 4).is_err(), "{} x{}", add_on.name, quantity);
+    }
+
+    // Unknown and inactive add-ons are rejected
+    let mut retired = add_on(menu_id, "Retired", 10.0, "per_guest");
+    retired.is_active = false;
+    let available = vec![retired.clone()];
+    let requested = vec![AddOnSelection { add_on_id: retired.id, quantity: Some(1) }];
+    assert!(resolve_add_ons(&available, &requested, 2).is_err());
+
+    let requested = vec![AddOnSelection { add_on_id: Uuid::new_v4(), quantity: Some(1) }];
+    assert!(resolve_add_ons(&available, &requested, 2).is_err());
+}
+
+#[test]
+fn test_quote_includes_supplements_and_add_ons() {
+    let menu_id = Uuid::new_v4();
+    let mains = group(menu_id, "Choose your main", 1, 1);
+    let beef = item(menu_id, "Wagyu", Some(mains.id), 25.0);
+    let veg = item(menu_id, "Risotto", Some(mains.id), 0.0);
+    let items = vec![beef.clone(), veg.clone()];
+    let wine = add_on(menu_id, "Wine pairing", 35.5, "per_guest");
+    let available = vec![wine.clone()];
+    let priced = resolve_add_ons(&available, &[AddOnSelection { add_on_id: wine.id, quantity: Some(2) }], 3).unwrap();
+
+    let selections = vec![pick(1, &beef), pick(2, &beef), pick(3, &veg)];
+    let quote = quote_booking(600.0, &items, &selections, &priced);
+
+    assert_eq!(quote.base_price, 600.0);
+    assert_eq!(quote.selections_total, 50.0);
+    assert_eq!(quote.add_ons_total, 71.0);
+    assert_eq!(quote.total_price, 721.0);
+}
+
+#[test]
+fn test_prep_sheet_counts_servings() {
+    let menu_id = Uuid::new_v4();
+    let booking_id = Uuid::new_v4();
+    let mains = group(menu_id, "Choose your main", 1, 1);
+    let soup = item(menu_id, "Soup", None, 0.0);
+    let beef = item(menu_id, "Beef", Some(mains.id), 0.0);
+    let fish = item(menu_id, "Fish", Some(mains.id), 0.0);
+    let items = vec![soup.clone(), beef.clone(), fish.clone()];
+
+    let selection = |guest_number: i32, item: &MenuItem, notes: Option<&str>| BookingGuestSelection {
+        id: Uuid::new_v4(),
+        booking_id,
+        guest_number,
+        guest_name: None,
+        choice_group_id: item.choice_group_id,
+        menu_item_id: Some(item.id),
+        item_name: item.name.clone(),
+        price_adjustment: 0.0,
+        dietary_notes: notes.map(str::to_string),
+        created_at: Utc::now(),
+    };
+    let selections = vec![
+        selection(1, &beef, None),
+        selection(2, &fish, Some("No shellfish")),
+        selection(3, &beef, None),
+    ];
+
+    let sheet = PrepSheet::build(booking_id, 3, &items, &selections, Vec::new());
+    let servings: Vec<(&str, i32)> = sheet.dishes.iter().map(|d| (d.name.as_str(), d.servings)).collect();
+    assert_eq!(servings, vec![("Soup", 3), ("Beef", 2), ("Fish", 1)]);
+    assert_eq!(sheet.dietary_notes.len(), 1);
+    assert_eq!(sheet.dietary_notes[0].guest_number, 2);
+}
+
+#[test]
+fn test_prep_sheet_keeps_removed_dishes() {
+    let booking_id = Uuid::new_v4();
+    let removed = BookingGuestSelection {
+        id: Uuid::new_v4(),
+        booking_id,
+        guest_number: 1,
+        guest_name: Some("Sam".to_string()),
+        choice_group_id: None,
+        menu_item_id: None,
+        item_name: "Lobster".to_string(),
+        price_adjustment: 20.0,
+        dietary_notes: None,
+        created_at: Utc::now(),
+    };
+
+    let sheet = PrepSheet::build(booking_id, 1, &[], &[removed], Vec::new());
+    assert_eq!(sheet.dishes.len(), 1);
+    assert_eq!(sheet.dishes[0].name, "Lobster");
+    assert_eq!(sheet.dishes[0].servings, 1);
+}
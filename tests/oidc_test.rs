@@ -0,0 +1,220 @@
+// Tests for OIDC login: the provider layer runs against a mock provider
+// (discovery document, JWKS and token endpoint), and the routes are checked
+// up to the point they need Redis or the database.
+
+use std::collections::HashMap;
+
+use actix_web::{http::header, http::StatusCode, test};
+use askama::Template;
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use privatechefspace_backend::config::{Config, OidcConfig, OidcProviderConfig};
+use privatechefspace_backend::errors::AppError;
+use privatechefspace_backend::models::Role;
+use privatechefspace_backend::oidc::OidcProvider;
+use privatechefspace_backend::templates::{LoginTemplate, OidcProviderLink, PageContext};
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+
+const CLIENT_ID: &str = "privatechefspace";
+const REDIRECT_URI: &str = "http://localhost:8080/auth/oidc/mock/callback";
+const SIGNING_KEY: &str = include_str!("fixtures/oidc_test_key.pem");
+/// Modulus of the fixture key, base64url encoded for the JWKS
+const SIGNING_KEY_MODULUS: &str = "q6Vu64HoIyo3k-pLSK07XNBUArwk3hl3rzGSOUF9gwpSZePVQB3m6zrq3xyvIWfmQBYoEE9SpkAbj10IReNyWWqmaLQFoHyKbOsitgLXDN85dWO0KhcQp34tBUNKZM53FStnMAn08cNfOcQSHuDKcpAzKftMq2ha0yWDEBBS5lW-3nEvMyHpT585LjZ-qThrsxYD4H9RulNrx-vc0B6c6mFYJEjrXS4Ku0BBO44a1JTVw5JVk9M9sAz8RlSj9a4eW64lJpOGv97671K6dNFtI4pA49JL6os7v_fqVscFoXJYgxjpL1g08ah8glBhEbGVfDoRsb7aLmMLImtt8Susow";
+
+fn provider_config(issuer_url: &str) -> OidcProviderConfig {
+    OidcProviderConfig {
+        name: "mock".to_string(),
+        display_name: "Mock ID".to_string(),
+        issuer_url: issuer_url.to_string(),
+        client_id: CLIENT_ID.to_string(),
+        client_secret: Some("client-secret".to_string()),
+        scopes: vec!["openid".to_string(), "email".to_string()],
+    }
+}
+
+fn test_config() -> Config {
+    let mut config = common::test_config();
+    config.two_factor.required_roles = vec![Role::Admin];
+    config.oidc = OidcConfig { providers: vec![provider_config("http://127.0.0.1:1")] };
+    config
+}
+
+async fn call(req: test::TestRequest) -> actix_web::dev::ServiceResponse {
+    common::call_with(test_config(), req, None).await
+}
+
+/// A provider serving discovery and keys; tests mount the token endpoint
+async fn mock_provider() -> MockServer {
+    let server = MockServer::start().await;
+    let issuer = server.uri();
+    Mock::given(method("GET"))
+        .and(path("/.well-known/openid-configuration"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "jwks_uri": format!("{}/jwks", issuer),
+            "response_types_supported": ["code"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["RS256"]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/jwks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "keys": [{ "kty": "RSA", "alg": "RS256", "use": "sig", "kid": "test", "n": SIGNING_KEY_MODULUS, "e": "AQAB" }]
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn id_token(issuer: &str, nonce: &str) -> String {
+    let now = Utc::now().timestamp();
+    let claims = serde_json::json!({
+        "iss": issuer,
+        "aud": CLIENT_ID,
+        "sub": "user-123",
+        "email": "sam@example.com",
+        "email_verified": true,
+        "nonce": nonce,
+        "iat": now,
+        "exp": now + 300
+    });
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some("test".to_string());
+    encode(&header, &claims, &EncodingKey::from_rsa_pem(SIGNING_KEY.as_bytes()).unwrap()).unwrap()
+}
+
+async fn mount_token_endpoint(server: &MockServer, verifier: &str, id_token: String) {
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains("code=auth-code"))
+        .and(body_string_contains(format!("code_verifier={}", verifier)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "access_token": "access",
+            "token_type": "Bearer",
+            "expires_in": 300,
+            "id_token": id_token
+        })))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+#[actix_web::test]
+async fn test_authorization_url_uses_pkce_and_nonce() {
+    let server = mock_provider().await;
+    let provider = OidcProvider::new(provider_config(&server.uri()));
+
+    let request = provider.authorization_request(REDIRECT_URI).await.unwrap();
+    let url = openidconnect::url::Url::parse(&request.url).unwrap();
+    assert_eq!(url.path(), "/authorize");
+    let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+    assert_eq!(params["response_type"], "code");
+    assert_eq!(params["client_id"], CLIENT_ID);
+    assert_eq!(params["redirect_uri"], REDIRECT_URI);
+    assert_eq!(params["scope"], "openid email");
+    assert_eq!(params["code_challenge_method"], "S256");
+    assert!(!params["code_challenge"].is_empty());
+    assert_eq!(params["state"], request.state);
+    assert_eq!(params["nonce"], request.login.nonce);
+
+    assert_eq!(request.login.provider, "mock");
+    assert_eq!(request.login.redirect_uri, REDIRECT_URI);
+    assert_ne!(params["code_challenge"], request.login.pkce_verifier);
+}
+
+#[actix_web::test]
+async fn test_code_exchange_returns_verified_identity() {
+    let server = mock_provider().await;
+    let provider = OidcProvider::new(provider_config(&server.uri()));
+    let request = provider.authorization_request(REDIRECT_URI).await.unwrap();
+    mount_token_endpoint(&server, &request.login.pkce_verifier, id_token(&server.uri(), &request.login.nonce)).await;
+
+    let identity = provider.exchange_code("auth-code", &request.login).await.unwrap();
+    assert_eq!(identity.subject, "user-123");
+    assert_eq!(identity.email.as_deref(), Some("sam@example.com"));
+    assert!(identity.email_verified);
+}
+
+#[actix_web::test]
+async fn test_id_token_with_wrong_nonce_is_rejected() {
+    let server = mock_provider().await;
+    let provider = OidcProvider::new(provider_config(&server.uri()));
+    let request = provider.authorization_request(REDIRECT_URI).await.unwrap();
+    mount_token_endpoint(&server, &request.login.pkce_verifier, id_token(&server.uri(), "replayed-nonce")).await;
+
+    let result = provider.exchange_code("auth-code", &request.login).await;
+    assert!(matches!(result, Err(AppError::Unauthorized(_))));
+}
+
+#[actix_web::test]
+async fn test_id_token_from_another_issuer_is_rejected() {
+    let server = mock_provider().await;
+    let provider = OidcProvider::new(provider_config(&server.uri()));
+    let request = provider.authorization_request(REDIRECT_URI).await.unwrap();
+    mount_token_endpoint(&server, &request.login.pkce_verifier, id_token("https://evil.example.com", &request.login.nonce))
+        .await;
+
+    let result = provider.exchange_code("auth-code", &request.login).await;
+    assert!(matches!(result, Err(AppError::Unauthorized(_))));
+}
+
+#[actix_web::test]
+async fn test_login_page_offers_providers() {
+    let html = LoginTemplate {
+        ctx: PageContext { user: None, csrf_token: "test-token".to_string(), flash: vec![], impersonation: None },
+        is_register: false,
+        error: None,
+        loading: false,
+        oidc_providers: vec![OidcProviderLink { name: "mock".to_string(), display_name: "Mock ID".to_string() }],
+    }
+    .render()
+    .unwrap();
+    assert!(html.contains("href=\"/auth/oidc/mock\""));
+    assert!(html.contains("Continue with Mock ID"));
+}
+
+#[actix_web::test]
+async fn test_providers_are_listed() {
+    let res = call(test::TestRequest::get().uri("/api/auth/oidc/providers")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["providers"][0]["name"], "mock");
+    assert_eq!(body["providers"][0]["display_name"], "Mock ID");
+}
+
+#[actix_web::test]
+async fn test_unknown_provider_is_not_found() {
+    let res = call(test::TestRequest::post().uri("/api/auth/oidc/nope/authorize")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = call(test::TestRequest::get().uri("/auth/oidc/nope")).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/login");
+}
+
+#[actix_web::test]
+async fn test_callback_needs_matching_state_cookie() {
+    let res = call(test::TestRequest::get().uri("/auth/oidc/mock/callback?code=auth-code&state=abc")).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/login");
+
+    let req = test::TestRequest::get()
+        .uri("/auth/oidc/mock/callback?code=auth-code&state=abc")
+        .cookie(actix_web::cookie::Cookie::new("oidc_state", "other"));
+    let res = call(req).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/login");
+}
+
+#[actix_web::test]
+async fn test_callback_with_provider_error_goes_to_login() {
+    let res = call(test::TestRequest::get().uri("/auth/oidc/mock/callback?error=access_denied")).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/login");
+}
@@ -0,0 +1,122 @@
+// Tests for the authorization policy: which actors may use a permission on
+// a resource, given who owns it and who is on the chef's team. Looking owners up needs the database, so
+// these exercise the decision itself and the checks made before any query.
+
+use privatechefspace_backend::errors::AppError;
+use privatechefspace_backend::middleware::auth::Identity;
+use privatechefspace_backend::middleware::roles::Permission;
+use privatechefspace_backend::models::{Role, TeamRole};
+use privatechefspace_backend::policy::{acting_chef, allows, require, Owner};
+use uuid::Uuid;
+
+mod common;
+use common::lazy_pool;
+
+fn actor(role: Role) -> Identity {
+    Identity { id: Uuid::new_v4(), role, two_factor_enabled: false, impersonation: None }
+}
+
+fn owned_by(user: &Identity) -> Owner {
+    Owner { chef_id: Uuid::new_v4(), team: vec![(user.id, TeamRole::Owner)], customer_id: None }
+}
+
+#[test]
+fn test_chefs_may_manage_only_their_own_resources() {
+    let chef = actor(Role::Chef);
+    let other = actor(Role::Chef);
+    let owner = owned_by(&chef);
+
+    for permission in [Permission::ManageOwnMenus, Permission::ManageOwnBookings, Permission::ManageOwnChefProfile] {
+        assert!(allows(&chef, permission, &owner), "{:?}", permission);
+        assert!(!allows(&other, permission, &owner), "{:?}", permission);
+    }
+}
+
+#[test]
+fn test_admins_may_act_on_any_resource() {
+    let admin = actor(Role::Admin);
+    let owner = owned_by(&actor(Role::Chef));
+
+    assert!(allows(&admin, Permission::ManageOwnMenus, &owner));
+    assert!(allows(&admin, Permission::ManageOwnBookings, &owner));
+    assert!(allows(&admin, Permission::ViewOwnBookings, &owner));
+}
+
+#[test]
+fn test_ownership_does_not_grant_missing_permissions() {
+    // A diner who somehow owns a chef profile still can't manage its menus
+    let diner = actor(Role::Diner);
+    assert!(!allows(&diner, Permission::ManageOwnMenus, &owned_by(&diner)));
+
+    // Moderators moderate content without owning it, but can't edit menus
+    let moderator = actor(Role::Mod);
+    let owner = owned_by(&actor(Role::Chef));
+    assert!(allows(&moderator, Permission::ManageContent, &owner));
+    assert!(!allows(&moderator, Permission::ManageOwnMenus, &owner));
+}
+
+#[test]
+fn test_diners_own_their_bookings() {
+    let diner = actor(Role::Diner);
+    let chef = actor(Role::Chef);
+    let booking = Owner { customer_id: Some(diner.id), ..owned_by(&chef) };
+
+    assert!(allows(&diner, Permission::ViewOwnBookings, &booking));
+    assert!(!allows(&actor(Role::Diner), Permission::ViewOwnBookings, &booking));
+    assert!(!allows(&diner, Permission::ManageOwnBookings, &booking));
+    assert!(allows(&chef, Permission::ManageOwnBookings, &booking));
+}
+
+#[test]
+fn test_team_members_act_within_their_team_role() {
+    let owner = actor(Role::Chef);
+    let manager = actor(Role::Chef);
+    let staff = actor(Role::Chef);
+    let mut team = owned_by(&owner);
+    team.team.push((manager.id, TeamRole::Manager));
+    team.team.push((staff.id, TeamRole::Staff));
+
+    for member in [&owner, &manager, &staff] {
+        assert!(allows(member, Permission::ManageOwnBookings, &team));
+    }
+
+    assert!(allows(&manager, Permission::ManageOwnMenus, &team));
+    assert!(allows(&manager, Permission::ManageOwnChefProfile, &team));
+    assert!(!allows(&staff, Permission::ManageOwnMenus, &team));
+    assert!(!allows(&staff, Permission::ManageOwnChefProfile, &team));
+
+    // Staff handle bookings but not their payments
+    assert!(allows(&owner, Permission::ManageOwnPayments, &team));
+    assert!(allows(&manager, Permission::ManageOwnPayments, &team));
+    assert!(!allows(&staff, Permission::ManageOwnPayments, &team));
+
+    // Only the owner manages the team
+    assert!(allows(&owner, Permission::ManageOwnTeam, &team));
+    assert!(!allows(&manager, Permission::ManageOwnTeam, &team));
+    assert!(!allows(&staff, Permission::ManageOwnTeam, &team));
+    assert!(!allows(&actor(Role::Chef), Permission::ManageOwnBookings, &team));
+}
+
+#[test]
+fn test_team_roles_need_the_chef_role_too() {
+    // A member whose account lost the chef role keeps no access
+    let demoted = actor(Role::Diner);
+    let team = Owner { team: vec![(demoted.id, TeamRole::Manager)], ..owned_by(&actor(Role::Chef)) };
+    assert!(!allows(&demoted, Permission::ManageOwnMenus, &team));
+    assert_eq!(team.team_role(demoted.id), Some(TeamRole::Manager));
+}
+
+#[test]
+fn test_require_checks_the_role() {
+    assert!(require(&actor(Role::Mod), Permission::ManageContent).is_ok());
+    assert!(matches!(require(&actor(Role::Mod), Permission::RefundBookings), Err(AppError::Forbidden(_))));
+    assert!(require(&actor(Role::Admin), Permission::RefundBookings).is_ok());
+}
+
+#[actix_web::test]
+async fn test_acting_chef_checks_permission_before_lookup() {
+    let pool = lazy_pool();
+
+    let result = acting_chef(&pool, &actor(Role::Diner), Permission::ManageOwnMenus, None).await;
+    assert!(matches!(result, Err(AppError::Forbidden(_))));
+}
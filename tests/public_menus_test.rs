@@ -0,0 +1,101 @@
+// Tests for the public menu view
+// Covers course grouping, hidden add-ons and the last-modified timestamp
+
+use chrono::{Duration, Utc};
+use privatechefspace_backend::models::{Menu, MenuAddOn, MenuItem, PublicMenu};
+use uuid::Uuid;
+
+fn menu() -> Menu {
+    Menu {
+        id: Uuid::new_v4(),
+        chef_id: Uuid::new_v4(),
+        name: "Tasting Menu".to_string(),
+        description: None,
+        price_per_person: Some(120.0),
+        minimum_guests: 2,
+        cuisine_type: Some("French".to_string()),
+        dietary_options: Some(vec!["vegetarian".to_string()]),
+        duration_hours: Some(3.0),
+        is_active: true,
+        created_at: Utc::now() - Duration::days(2),
+        updated_at: Utc::now() - Duration::days(2),
+    }
+}
+
+fn item(menu_id: Uuid, name: &str, course_type: Option<&str>) -> MenuItem {
+    MenuItem {
+        id: Uuid::new_v4(),
+        menu_id,
+        name: name.to_string(),
+        description: None,
+        course_type: course_type.map(str::to_string),
+        image_url: None,
+        is_featured: false,
+        display_order: 0,
+        quantity: None,
+        choice_group_id: None,
+        price_adjustment: None,
+        created_at: Utc::now() - Duration::days(2),
+        updated_at: Utc::now() - Duration::days(2),
+    }
+}
+
+#[test]
+fn test_items_are_grouped_by_course_in_display_order() {
+    let menu = menu();
+    let items = vec![
+        item(menu.id, "Oyster", Some("appetizer")),
+        item(menu.id, "Tartare", Some("appetizer")),
+        item(menu.id, "Duck", Some("main")),
+        item(menu.id, "Petit fours", None),
+        item(menu.id, "Soufflé", Some("dessert")),
+    ];
+
+    let public = PublicMenu::build(menu, items, Vec::new(), Vec::new());
+    let courses: Vec<(Option<&str>, Vec<&str>)> = public
+        .courses
+        .iter()
+        .map(|c| (c.course_type.as_deref(), c.items.iter().map(|i| i.name.as_str()).collect()))
+        .collect();
+
+    assert_eq!(
+        courses,
+        vec![
+            (Some("appetizer"), vec!["Oyster", "Tartare"]),
+            (Some("main"), vec!["Duck"]),
+            (None, vec!["Petit fours"]),
+            (Some("dessert"), vec!["Soufflé"]),
+        ]
+    );
+    assert_eq!(public.price_per_person, Some(120.0));
+    assert_eq!(public.duration_hours, Some(3.0));
+}
+
+#[test]
+fn test_inactive_add_ons_are_hidden_and_updated_at_tracks_latest_change() {
+    let menu = menu();
+    let menu_updated = menu.updated_at;
+    let mut edited = item(menu.id, "Duck", Some("main"));
+    edited.updated_at = Utc::now();
+
+    let add_on = |name: &str, is_active: bool| MenuAddOn {
+        id: Uuid::new_v4(),
+        menu_id: menu.id,
+        name: name.to_string(),
+        description: None,
+        price: 30.0,
+        pricing_unit: "per_guest".to_string(),
+        is_active,
+        display_order: Some(0),
+        created_at: menu_updated,
+        updated_at: menu_updated,
+    };
+    let add_ons = vec![add_on("Wine pairing", true), add_on("Retired", false)];
+
+    let latest = edited.updated_at;
+    let public = PublicMenu::build(menu, vec![edited], Vec::new(), add_ons);
+
+    assert_eq!(public.add_ons.len(), 1);
+    assert_eq!(public.add_ons[0].name, "Wine pairing");
+    assert_eq!(public.updated_at, latest);
+}
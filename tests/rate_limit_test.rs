@@ -0,0 +1,75 @@
+// Tests for the RateLimit middleware and the login failure policy.
+// Redis is unreachable here, so these cover what happens around the
+// limiter: which requests are counted, failing open, and the delays and
+// errors the login lockout produces.
+
+use actix_web::{http::StatusCode, test, web, App, HttpResponse, ResponseError};
+use privatechefspace_backend::config::RedisConfig;
+use privatechefspace_backend::errors::AppError;
+use privatechefspace_backend::handlers::auth::login_failure_delay;
+use privatechefspace_backend::middleware::rate_limit::RateLimit;
+use privatechefspace_backend::RedisClient;
+use std::time::Duration;
+
+async fn ok() -> HttpResponse {
+    HttpResponse::Ok().body("ok")
+}
+
+#[actix_web::test]
+async fn test_requests_pass_when_redis_is_unavailable() {
+    let redis = RedisClient::new(&RedisConfig { url: "redis://127.0.0.1:1".to_string() })
+        .await
+        .unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(redis))
+            .service(
+                web::resource("/limited")
+                    .wrap(RateLimit::new("test", 1, Duration::from_secs(60)))
+                    .route(web::post().to(ok)),
+            ),
+    )
+    .await;
+
+    for _ in 0..3 {
+        let res = test::call_service(&app, test::TestRequest::post().uri("/limited").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
+
+#[actix_web::test]
+async fn test_safe_methods_are_not_counted() {
+    // Without any Redis client a counted request would still pass, so this
+    // only checks GETs are served normally through the middleware
+    let app = test::init_service(
+        App::new().service(
+            web::resource("/page")
+                .wrap(RateLimit::login())
+                .route(web::get().to(ok))
+                .route(web::post().to(ok)),
+        ),
+    )
+    .await;
+
+    let res = test::call_service(&app, test::TestRequest::get().uri("/page").to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, test::TestRequest::post().uri("/page").to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[::core::prelude::v1::test]
+fn test_login_failure_delay_grows_then_caps() {
+    assert_eq!(login_failure_delay(1), Duration::ZERO);
+    assert_eq!(login_failure_delay(3), Duration::ZERO);
+    assert_eq!(login_failure_delay(4), Duration::from_millis(500));
+    assert_eq!(login_failure_delay(5), Duration::from_secs(1));
+    assert_eq!(login_failure_delay(7), Duration::from_secs(4));
+    assert_eq!(login_failure_delay(8), Duration::from_secs(8));
+    assert_eq!(login_failure_delay(50), Duration::from_secs(8));
+}
+
+#[::core::prelude::v1::test]
+fn test_too_many_requests_is_429() {
+    let error = AppError::TooManyRequests("Slow down".to_string());
+    assert_eq!(error.error_response().status(), StatusCode::TOO_MANY_REQUESTS);
+}
@@ -51,8 +51,15 @@ fn test_user_response_includes_role() {
     let user = User {
         id: Uuid::new_v4(),
         email: "test@example.com".to_string(),
-        password_hash: "hash".to_string(),
+        password_hash: Some("hash".to_string()),
         role: Role::Chef,
+        token_version: 0,
+        email_verified: false,
+        totp_secret: None,
+        totp_enabled: false,
+        totp_last_step: None,
+        suspended_at: None,
+        suspension_reason: None,
         created_at: Utc::now(),
         updated_at: Utc::now(),
     };
@@ -78,30 +85,37 @@ fn test_create_user_defaults_to_diner() {
 
 #[test]
 fn test_role_permissions() {
-    use privatechefspace_backend::middleware::roles::has_permission;
+    use privatechefspace_backend::middleware::roles::{has_permission, Permission::*};
     
     // Admin can do everything
-    assert!(has_permission(Role::Admin, "manage_content"));
-    assert!(has_permission(Role::Admin, "manage_users"));
-    assert!(has_permission(Role::Admin, "view_reports"));
-    assert!(has_permission(Role::Admin, "manage_own_chef_profile"));
+    assert!(has_permission(Role::Admin, ManageContent));
+    assert!(has_permission(Role::Admin, ManageUsers));
+    assert!(has_permission(Role::Admin, ViewReports));
+    assert!(has_permission(Role::Admin, ManageOwnChefProfile));
+    assert!(has_permission(Role::Admin, RefundBookings));
     
     // Mod can manage content and users
-    assert!(has_permission(Role::Mod, "manage_content"));
-    assert!(has_permission(Role::Mod, "manage_users"));
-    assert!(has_permission(Role::Mod, "view_reports"));
-    assert!(!has_permission(Role::Mod, "manage_own_chef_profile"));
+    assert!(has_permission(Role::Mod, ManageContent));
+    assert!(has_permission(Role::Mod, ManageUsers));
+    assert!(has_permission(Role::Mod, ViewReports));
+    assert!(!has_permission(Role::Mod, ManageOwnChefProfile));
+    assert!(has_permission(Role::Mod, ReviewChefApplications));
+    assert!(!has_permission(Role::Mod, RefundBookings));
     
     // Chef can manage own content
-    assert!(has_permission(Role::Chef, "manage_own_chef_profile"));
-    assert!(has_permission(Role::Chef, "manage_own_menus"));
-    assert!(has_permission(Role::Chef, "manage_own_bookings"));
-    assert!(!has_permission(Role::Chef, "manage_content"));
+    assert!(has_permission(Role::Chef, ManageOwnChefProfile));
+    assert!(has_permission(Role::Chef, ManageOwnMenus));
+    assert!(has_permission(Role::Chef, ManageOwnBookings));
+    assert!(has_permission(Role::Chef, ManageOwnPayments));
+    assert!(has_permission(Role::Chef, ManageOwnTeam));
+    assert!(has_permission(Role::Chef, ManageOwnStaff));
+    assert!(!has_permission(Role::Chef, ManageContent));
+    assert!(!has_permission(Role::Chef, ReviewChefApplications));
     
     // Diner can view and book
-    assert!(has_permission(Role::Diner, "view_chefs"));
-    assert!(has_permission(Role::Diner, "create_booking"));
-    assert!(has_permission(Role::Diner, "view_own_bookings"));
-    assert!(!has_permission(Role::Diner, "manage_content"));
+    assert!(has_permission(Role::Diner, ViewChefs));
+    assert!(has_permission(Role::Diner, CreateBooking));
+    assert!(has_permission(Role::Diner, ViewOwnBookings));
+    assert!(!has_permission(Role::Diner, ManageContent));
+    assert!(!has_permission(Role::Diner, ManageOwnMenus));
 }
-
@@ -0,0 +1,107 @@
+// Route resolution tests for the JSON API
+// These only exercise routing and the auth middleware, so no database is needed:
+// the pool is created lazily and never connects.
+
+use actix_web::{http::StatusCode, test, web, App};
+use privatechefspace_backend::configure_api;
+
+mod common;
+use common::{lazy_pool, test_config};
+
+async fn status_of(method: test::TestRequest, uri: &str) -> StatusCode {
+    // Short acquire timeout so routes that reach the database fail fast
+    let pool = web::Data::new(lazy_pool());
+    let config = web::Data::new(test_config());
+    let app = test::init_service(
+        App::new().configure(|cfg| configure_api(cfg, pool.clone(), config.clone())),
+    )
+    .await;
+
+    match test::try_call_service(&app, method.uri(uri).to_request()).await {
+        Ok(res) => res.status(),
+        Err(err) => err.as_response_error().status_code(),
+    }
+}
+
+#[actix_web::test]
+async fn test_nested_menu_routes_require_auth() {
+    let menu_id = "550e8400-e29b-41d4-a716-446655440000";
+    let item_id = "660e8400-e29b-41d4-a716-446655440000";
+
+    let cases = [
+        (test::TestRequest::get(), format!("/api/menus/{}/items", menu_id)),
+        (test::TestRequest::put(), format!("/api/menus/{}/items/{}", menu_id, item_id)),
+        (test::TestRequest::get(), format!("/api/menus/{}/choice-groups", menu_id)),
+        (test::TestRequest::post(), format!("/api/menus/{}/add-ons", menu_id)),
+        (test::TestRequest::put(), format!("/api/menus/{}/items/order", menu_id)),
+        (test::TestRequest::post(), format!("/api/menus/{}/items/bulk", menu_id)),
+        (test::TestRequest::post(), format!("/api/menus/{}/duplicate", menu_id)),
+        (test::TestRequest::get(), "/api/menus".to_string()),
+        (test::TestRequest::put(), format!("/api/menus/{}", menu_id)),
+        (test::TestRequest::delete(), format!("/api/menus/{}", menu_id)),
+        (test::TestRequest::get(), format!("/api/bookings/{}/prep-sheet", menu_id)),
+        (test::TestRequest::post(), format!("/api/menus/{}/items/{}/image", menu_id, item_id)),
+    ];
+
+    for (method, uri) in cases {
+        assert_eq!(status_of(method, &uri).await, StatusCode::UNAUTHORIZED, "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn test_chef_profile_routes_require_auth() {
+    assert_eq!(status_of(test::TestRequest::get(), "/api/chefs/profile").await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status_of(test::TestRequest::post(), "/api/chefs").await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status_of(test::TestRequest::post(), "/api/chefs/profile/image").await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        status_of(test::TestRequest::post(), "/api/chefs/profile/cover-image").await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[actix_web::test]
+async fn test_chef_bookings_get_requires_auth() {
+    let chef_id = "550e8400-e29b-41d4-a716-446655440000";
+    assert_eq!(
+        status_of(test::TestRequest::get(), &format!("/api/chefs/{}/bookings", chef_id)).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[actix_web::test]
+async fn test_public_menu_routes_skip_auth() {
+    let menu_id = "550e8400-e29b-41d4-a716-446655440000";
+    // No database is running, so these fail past the auth layer rather than with 401
+    for uri in [format!("/api/menus/{}", menu_id), "/api/chefs/some-chef/menus".to_string()] {
+        let status = status_of(test::TestRequest::get(), &uri).await;
+        assert_ne!(status, StatusCode::UNAUTHORIZED, "{}", uri);
+        assert_ne!(status, StatusCode::NOT_FOUND, "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn test_cookie_requests_need_csrf_header() {
+    let menu_id = "550e8400-e29b-41d4-a716-446655440000";
+    let cookie = actix_web::cookie::Cookie::new("session_id", "stolen-by-a-cross-site-form");
+
+    // A form posted from another site carries the cookie but can't set headers
+    let cases = [
+        (test::TestRequest::delete(), format!("/api/menus/{}", menu_id)),
+        (test::TestRequest::post(), "/api/chefs/team/invitations".to_string()),
+        (test::TestRequest::put(), "/api/chefs/profile".to_string()),
+    ];
+    for (method, uri) in cases {
+        let status = status_of(method.cookie(cookie.clone()), &uri).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{}", uri);
+    }
+
+    // With the header the session is looked up as usual (no Redis here)
+    let req = test::TestRequest::delete()
+        .cookie(cookie.clone())
+        .insert_header(("X-CSRF-Token", "token"));
+    assert_eq!(status_of(req, &format!("/api/menus/{}", menu_id)).await, StatusCode::UNAUTHORIZED);
+
+    // Reads don't change anything, so they don't need it
+    let req = test::TestRequest::get().cookie(cookie);
+    assert_eq!(status_of(req, "/api/menus").await, StatusCode::UNAUTHORIZED);
+}
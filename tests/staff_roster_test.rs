@@ -0,0 +1,403 @@
+// Tests for the staff roster: helper roles and forms, shift overlaps, staff
+// in booking prices, the staffing and helper pages, and the checks made
+// before the database is reached (the pool is lazy and Redis is unreachable)
+
+use std::collections::HashMap;
+
+use actix_web::{http::StatusCode, test as actix_test};
+use askama::Template;
+use chrono::{NaiveDate, NaiveTime, Utc};
+use privatechefspace_backend::middleware::auth::Identity;
+use privatechefspace_backend::middleware::roles::{has_permission, Permission};
+use privatechefspace_backend::models::{
+    AssignHelper, AssignedEvent, Booking, BookingAddOn, BookingGuestSelection, BookingSummary, BookingTab,
+    CreateHelper, Helper, HelperAvailability, HelperRole, PrepSheet, Role, StaffAssignment, StaffShift, TeamRole,
+    UpdateHelper,
+};
+use privatechefspace_backend::pricing::{booked_quote, quote_booking, staff_cost};
+use privatechefspace_backend::templates::{
+    AssignedEventTemplate, BookingStaffTemplate, ChefDashboardTemplate, ChefStaffTemplate, DashboardTemplate,
+};
+use uuid::Uuid;
+
+mod common;
+use common::{call, chef, page_context, user};
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn time(h: u32, m: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(h, m, 0).unwrap()
+}
+
+fn form(fields: &[(&str, &str)]) -> HashMap<String, String> {
+    fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+fn summary(status: &str, event_date: NaiveDate) -> BookingSummary {
+    BookingSummary {
+        booking: Booking {
+            id: Uuid::new_v4(),
+            chef_id: Uuid::new_v4(),
+            customer_id: None,
+            menu_id: None,
+            customer_name: "Jordan Lee".to_string(),
+            customer_email: "jordan@example.com".to_string(),
+            customer_phone: Some("555-0100".to_string()),
+            event_date,
+            event_time: time(18, 0),
+            duration_hours: 4.0,
+            number_of_guests: 40,
+            location_address: "1 Main Street".to_string(),
+            special_requests: None,
+            total_price: 1200.0,
+            status: status.to_string(),
+            payment_status: "pending".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        },
+        chef_name: "Camille Rose".to_string(),
+        chef_slug: Some("camille-rose".to_string()),
+        menu_name: None,
+    }
+}
+
+fn helper(name: &str, role: HelperRole, rate: f64) -> Helper {
+    Helper {
+        id: Uuid::new_v4(),
+        chef_id: Uuid::new_v4(),
+        name: name.to_string(),
+        email: None,
+        phone: None,
+        role: role.to_string(),
+        hourly_rate: rate,
+        is_active: true,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+fn assignment(booking_id: Uuid, helper: &Helper, hours: f64) -> StaffAssignment {
+    StaffAssignment {
+        id: Uuid::new_v4(),
+        booking_id,
+        helper_id: Some(helper.id),
+        name: helper.name.clone(),
+        role: helper.role.clone(),
+        hourly_rate: helper.hourly_rate,
+        hours,
+        total_cost: staff_cost(helper.hourly_rate, hours),
+        created_at: Utc::now(),
+    }
+}
+
+fn shift(event_date: NaiveDate, event_time: NaiveTime, hours: f64) -> StaffShift {
+    StaffShift { booking_id: Uuid::new_v4(), event_date, event_time, hours }
+}
+
+#[test]
+fn test_helper_roles() {
+    for role in HelperRole::ALL {
+        assert_eq!(role.to_string().parse::<HelperRole>(), Ok(role));
+    }
+    assert_eq!(HelperRole::SousChef.to_string(), "sous_chef");
+    assert_eq!(helper("Ana", HelperRole::SousChef, 30.0).role_label(), "Sous chef");
+    assert!("chef".parse::<HelperRole>().is_err());
+}
+
+#[test]
+fn test_roster_is_for_owners_and_managers() {
+    assert!(has_permission(Role::Chef, Permission::ManageOwnStaff));
+    assert!(!has_permission(Role::Diner, Permission::ManageOwnStaff));
+    assert!(!has_permission(Role::Mod, Permission::ManageOwnStaff));
+    assert!(Permission::ManageOwnStaff.is_ownership_scoped());
+
+    assert!(TeamRole::Owner.allows(Permission::ManageOwnStaff));
+    assert!(TeamRole::Manager.allows(Permission::ManageOwnStaff));
+    assert!(!TeamRole::Staff.allows(Permission::ManageOwnStaff));
+}
+
+#[test]
+fn test_helper_forms() {
+    let data = CreateHelper::from_form(&form(&[
+        ("name", " Ana Ruiz "),
+        ("role", "server"),
+        ("email", ""),
+        ("hourly_rate", "28.50"),
+    ]))
+    .unwrap();
+    assert_eq!(data.name, "Ana Ruiz");
+    assert_eq!(data.role, HelperRole::Server);
+    assert_eq!(data.email, None);
+    assert_eq!(data.hourly_rate, 28.5);
+
+    assert!(CreateHelper::from_form(&form(&[("name", "Ana"), ("role", "server")])).is_err());
+    assert!(CreateHelper::from_form(&form(&[("name", "Ana"), ("role", "chef"), ("hourly_rate", "20")])).is_err());
+
+    // Unticked checkboxes aren't submitted
+    let update = UpdateHelper::from_form(&form(&[("role", "bartender"), ("hourly_rate", "35")])).unwrap();
+    assert_eq!(update.role, Some(HelperRole::Bartender));
+    assert_eq!(update.is_active, Some(false));
+    assert_eq!(update.name, None);
+
+    let helper_id = Uuid::new_v4();
+    let assign = AssignHelper::from_form(&form(&[("helper_id", &helper_id.to_string()), ("hours", "")])).unwrap();
+    assert_eq!(assign.helper_id, helper_id);
+    assert_eq!(assign.hours, None);
+    assert!(AssignHelper::from_form(&form(&[("helper_id", "nobody")])).is_err());
+}
+
+#[test]
+fn test_shift_overlaps() {
+    let evening = shift(date(2030, 6, 15), time(18, 0), 4.0);
+
+    assert!(evening.overlaps(&shift(date(2030, 6, 15), time(21, 30), 2.0)));
+    assert!(evening.overlaps(&shift(date(2030, 6, 15), time(12, 0), 6.5)));
+    // Back to back is fine
+    assert!(!evening.overlaps(&shift(date(2030, 6, 15), time(12, 0), 6.0)));
+    assert!(!evening.overlaps(&shift(date(2030, 6, 15), time(22, 0), 3.0)));
+    // Shifts running past midnight reach into the next day
+    let late = shift(date(2030, 6, 15), time(21, 0), 5.0);
+    assert!(late.overlaps(&shift(date(2030, 6, 16), time(1, 0), 2.0)));
+    assert!(!late.overlaps(&shift(date(2030, 6, 16), time(2, 0), 2.0)));
+}
+
+#[test]
+fn test_staff_in_booking_price() {
+    assert_eq!(staff_cost(27.5, 4.5), 123.75);
+    assert_eq!(staff_cost(0.0, 4.0), 0.0);
+
+    // New quotes have no staff yet
+    assert_eq!(quote_booking(600.0, &[], &[], &[]).staff_total, 0.0);
+
+    let booking_id = Uuid::new_v4();
+    let selection = BookingGuestSelection {
+        id: Uuid::new_v4(),
+        booking_id,
+        guest_number: 1,
+        guest_name: None,
+        choice_group_id: None,
+        menu_item_id: None,
+        item_name: "Wagyu".to_string(),
+        price_adjustment: 25.0,
+        dietary_notes: None,
+        created_at: Utc::now(),
+    };
+    let add_on = BookingAddOn {
+        id: Uuid::new_v4(),
+        booking_id,
+        add_on_id: None,
+        name: "Wine pairing".to_string(),
+        quantity: 2,
+        unit_price: 35.5,
+        total_price: 71.0,
+        created_at: Utc::now(),
+    };
+    let staff = vec![
+        assignment(booking_id, &helper("Ana", HelperRole::Server, 25.0), 4.0),
+        assignment(booking_id, &helper("Ben", HelperRole::SousChef, 40.0), 5.5),
+    ];
+
+    let quote = booked_quote(1016.0, &[selection], &[add_on], &staff);
+    assert_eq!(quote.selections_total, 25.0);
+    assert_eq!(quote.add_ons_total, 71.0);
+    assert_eq!(quote.staff_total, 320.0);
+    assert_eq!(quote.base_price, 600.0);
+    assert_eq!(quote.total_price, 1016.0);
+}
+
+#[test]
+fn test_booking_staff_page() {
+    let booking = summary("confirmed", date(2030, 6, 15));
+    let ana = helper("Ana", HelperRole::Server, 25.0);
+    let ben = helper("Ben", HelperRole::SousChef, 40.0);
+    let cleo = helper("Cleo", HelperRole::Bartender, 30.0);
+    let assigned = assignment(booking.booking.id, &ana, 4.0);
+    let assigned_id = assigned.id;
+    let ben_id = ben.id;
+    let cleo_id = cleo.id;
+    let helpers = vec![
+        HelperAvailability { helper: ana, assigned: true, conflict: None },
+        HelperAvailability { helper: ben, assigned: false, conflict: None },
+        HelperAvailability {
+            helper: cleo,
+            assigned: false,
+            conflict: Some(shift(date(2030, 6, 15), time(17, 0), 3.0)),
+        },
+    ];
+    assert!(!helpers[0].is_available());
+    assert!(helpers[1].is_available());
+    assert!(!helpers[2].is_available());
+
+    let render = |can_change: bool| {
+        BookingStaffTemplate {
+            ctx: page_context(Some(user(Role::Chef)), vec![]),
+            booking: booking.clone(),
+            staff: vec![assigned.clone()],
+            helpers: helpers.clone(),
+            quote: booked_quote(1300.0, &[], &[], std::slice::from_ref(&assigned)),
+            can_change,
+        }
+        .render()
+        .unwrap()
+    };
+
+    let html = render(true);
+    assert!(html.contains("Staff for Jordan Lee"));
+    assert!(html.contains("$100.00"));
+    assert!(html.contains("$1200.00"));
+    assert!(html.contains(&format!("/staff/{}/remove", assigned_id)));
+    assert!(html.contains(&format!("value=\"{}\"", ben_id)));
+    assert!(!html.contains(&format!("value=\"{}\"", cleo_id)));
+    assert!(html.contains("Busy: another event on Jun 15 at 17:00"));
+
+    let html = render(false);
+    assert!(!html.contains("/remove"));
+    assert!(!html.contains("/staff/assign"));
+}
+
+#[test]
+fn test_roster_page() {
+    let mut retired = helper("Dana", HelperRole::Server, 22.0);
+    retired.is_active = false;
+    let mut ana = helper("Ana <Ruiz>", HelperRole::SousChef, 40.0);
+    ana.email = Some("ana@example.com".to_string());
+    let ana_id = ana.id;
+
+    let html = ChefStaffTemplate {
+        ctx: page_context(Some(user(Role::Chef)), vec![]),
+        chef: chef(),
+        helpers: vec![ana, retired],
+        roles: HelperRole::ALL,
+    }
+    .render()
+    .unwrap();
+
+    assert!(html.contains("Ana &lt;Ruiz&gt;"));
+    assert!(html.contains("ana@example.com"));
+    assert!(html.contains("value=\"40.00\""));
+    assert!(html.contains(&format!("/chef-dashboard/staff/{}/edit", ana_id)));
+    assert!(html.contains("Dana (inactive)"));
+    assert!(html.contains("action=\"/chef-dashboard/staff\""));
+}
+
+fn assigned_event() -> AssignedEvent {
+    AssignedEvent {
+        booking_id: Uuid::new_v4(),
+        chef_name: "Camille Rose".to_string(),
+        menu_name: Some("Summer tasting".to_string()),
+        customer_name: "Jordan Lee".to_string(),
+        event_date: date(2030, 6, 15),
+        event_time: time(18, 0),
+        duration_hours: 4.0,
+        number_of_guests: 40,
+        location_address: "1 Main Street".to_string(),
+        special_requests: Some("Garden setup".to_string()),
+        status: "confirmed".to_string(),
+        role: "server".to_string(),
+        hours: 5.0,
+    }
+}
+
+#[test]
+fn test_helpers_see_their_events_read_only() {
+    let event = assigned_event();
+    let event_id = event.booking_id;
+
+    let html = DashboardTemplate {
+        ctx: page_context(Some(user(Role::Diner)), vec![]),
+        upcoming_bookings: vec![],
+        past_bookings: vec![],
+        assigned_events: vec![event.clone()],
+        today: date(2030, 6, 1),
+    }
+    .render()
+    .unwrap();
+    assert!(html.contains("Events You're Working"));
+    assert!(html.contains(&format!("href=\"/events/{}\"", event_id)));
+
+    let ana = helper("Ana", HelperRole::Server, 25.0);
+    let html = AssignedEventTemplate {
+        ctx: page_context(Some(user(Role::Diner)), vec![]),
+        staff: vec![assignment(event_id, &ana, 5.0)],
+        prep_sheet: PrepSheet::build(event_id, 40, &[], &[], Vec::new()),
+        event,
+    }
+    .render()
+    .unwrap();
+    assert!(html.contains("working as server for 5 hours"));
+    assert!(html.contains("1 Main Street"));
+    assert!(html.contains("Ana &middot; Server"));
+    // No rates, prices or diner contact details
+    assert!(!html.contains("$"));
+    assert!(!html.contains("jordan@example.com"));
+}
+
+#[test]
+fn test_chef_dashboard_links_to_staffing() {
+    let today = date(2030, 6, 15);
+    let render = |team_role, booking: BookingSummary| {
+        ChefDashboardTemplate {
+            ctx: page_context(Some(user(Role::Chef)), vec![]),
+            chef: Some(chef()),
+            menus_with_items: Vec::new(),
+            booking_tab: BookingTab::Upcoming,
+            bookings: vec![booking],
+            pending_count: 0,
+            calendar: None,
+            today,
+            team_role: Some(team_role),
+        }
+        .render()
+        .unwrap()
+    };
+
+    let upcoming = summary("confirmed", date(2030, 6, 20));
+    let html = render(TeamRole::Manager, upcoming.clone());
+    assert!(html.contains("href=\"/chef-dashboard/staff\""));
+    assert!(html.contains(&format!("/chef-dashboard/bookings/{}/staff", upcoming.booking.id)));
+
+    let html = render(TeamRole::Staff, upcoming.clone());
+    assert!(!html.contains("href=\"/chef-dashboard/staff\""));
+    assert!(html.contains(&format!("/chef-dashboard/bookings/{}/staff", upcoming.booking.id)));
+
+    let done = summary("completed", date(2030, 6, 10));
+    let html = render(TeamRole::Owner, done.clone());
+    assert!(!html.contains(&format!("/chef-dashboard/bookings/{}/staff", done.booking.id)));
+}
+
+#[actix_web::test]
+async fn test_roster_api_is_for_chefs() {
+    let res = call(actix_test::TestRequest::get().uri("/api/chefs/helpers"), None).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let diner = Identity { id: Uuid::new_v4(), role: Role::Diner, two_factor_enabled: false, impersonation: None };
+    let res = call(actix_test::TestRequest::get().uri("/api/chefs/helpers"), Some(diner)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // Staffing a booking is roster work too
+    let booking_id = Uuid::new_v4();
+    for req in [
+        actix_test::TestRequest::post()
+            .uri(&format!("/api/bookings/{}/staff", booking_id))
+            .set_json(serde_json::json!({ "helper_id": Uuid::new_v4() })),
+        actix_test::TestRequest::delete().uri(&format!("/api/bookings/{}/staff/{}", booking_id, Uuid::new_v4())),
+    ] {
+        assert_eq!(call(req, Some(diner)).await.status(), StatusCode::FORBIDDEN);
+    }
+}
+
+#[actix_web::test]
+async fn test_staff_endpoints_need_sign_in() {
+    let booking_id = Uuid::new_v4();
+    for req in [
+        actix_test::TestRequest::get().uri(&format!("/api/bookings/{}/staff", booking_id)),
+        actix_test::TestRequest::post()
+            .uri(&format!("/api/bookings/{}/staff", booking_id))
+            .set_json(serde_json::json!({ "helper_id": Uuid::new_v4() })),
+        actix_test::TestRequest::get().uri("/api/users/me/events"),
+    ] {
+        assert_eq!(call(req, None).await.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,119 @@
+// Tests for refresh token claims and the refresh/logout endpoints.
+// Redis and the database are unreachable here, so these cover the checks
+// made on the presented token before any state is looked up.
+
+use actix_web::{http::header, http::StatusCode, test, web, App};
+use chrono::Utc;
+use privatechefspace_backend::models::{Role, User};
+use privatechefspace_backend::utils::{create_token, verify_token, Claims, TokenKind};
+use privatechefspace_backend::{configure_api, configure_auth, RedisClient};
+use uuid::Uuid;
+
+mod common;
+use common::{lazy_pool, test_config};
+
+fn test_user() -> User {
+    User {
+        id: Uuid::new_v4(),
+        email: "diner@example.com".to_string(),
+        password_hash: None,
+        role: Role::Diner,
+        token_version: 0,
+        email_verified: false,
+        totp_secret: None,
+        totp_enabled: false,
+        totp_last_step: None,
+        suspended_at: None,
+        suspension_reason: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+async fn status_of(req: test::TestRequest) -> StatusCode {
+    let pool = web::Data::new(lazy_pool());
+    let config = web::Data::new(test_config());
+    let redis = web::Data::new(RedisClient::new(&config.redis).await.unwrap());
+    let app = test::init_service(
+        App::new()
+            .app_data(redis)
+            .configure(|cfg| {
+                configure_auth(cfg, pool.clone(), config.clone());
+                configure_api(cfg, pool.clone(), config.clone());
+            }),
+    )
+    .await;
+
+    match test::try_call_service(&app, req.to_request()).await {
+        Ok(res) => res.status(),
+        Err(err) => err.as_response_error().status_code(),
+    }
+}
+
+fn refresh_request(uri: &str, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(uri)
+        .set_json(serde_json::json!({ "refresh_token": token }))
+}
+
+#[actix_web::test]
+async fn test_refresh_claims_identify_their_family() {
+    let config = test_config();
+    let user = test_user();
+    let family = Uuid::new_v4();
+
+    let first = Claims::refresh(&user, family, &config.jwt);
+    let second = Claims::refresh(&user, family, &config.jwt);
+    assert_eq!(first.typ, TokenKind::Refresh);
+    assert_eq!(first.family(), Some(family));
+    assert_ne!(first.jti, second.jti);
+
+    let decoded = verify_token(&first.encode(&config.jwt.secret).unwrap(), &config.jwt.secret).unwrap();
+    assert_eq!(decoded.jti, first.jti);
+    assert_eq!(decoded.family(), Some(family));
+}
+
+#[actix_web::test]
+async fn test_access_tokens_have_no_family() {
+    let config = test_config();
+    let token = create_token(&test_user(), &config.jwt).unwrap();
+    let claims = verify_token(&token, &config.jwt.secret).unwrap();
+    assert_eq!(claims.typ, TokenKind::Access);
+    assert_eq!(claims.family(), None);
+}
+
+#[actix_web::test]
+async fn test_refresh_rejects_invalid_tokens() {
+    assert_eq!(status_of(refresh_request("/api/auth/refresh", "not-a-jwt")).await, StatusCode::UNAUTHORIZED);
+
+    let wrong_secret = Claims::refresh(&test_user(), Uuid::new_v4(), &test_config().jwt)
+        .encode("some-other-secret")
+        .unwrap();
+    assert_eq!(status_of(refresh_request("/api/auth/refresh", &wrong_secret)).await, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_refresh_rejects_access_tokens() {
+    let access = create_token(&test_user(), &test_config().jwt).unwrap();
+    assert_eq!(status_of(refresh_request("/api/auth/refresh", &access)).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status_of(refresh_request("/api/auth/logout", &access)).await, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_refresh_token_is_not_a_bearer_token() {
+    let refresh = Claims::refresh(&test_user(), Uuid::new_v4(), &test_config().jwt)
+        .encode("test-secret")
+        .unwrap();
+    let req = test::TestRequest::get()
+        .uri("/api/users/me")
+        .insert_header((header::AUTHORIZATION, format!("Bearer {}", refresh)));
+    assert_eq!(status_of(req).await, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn test_logout_all_requires_auth() {
+    assert_eq!(
+        status_of(test::TestRequest::post().uri("/api/auth/logout-all")).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
@@ -0,0 +1,183 @@
+// Tests for TOTP two-factor authentication: which roles must use it, the
+// provisioning details, the pages, and the checks made before the database
+// is reached (the pool is lazy and Redis is unreachable).
+
+use actix_web::{http::header, http::StatusCode, test};
+use askama::Template;
+use chrono::Utc;
+use privatechefspace_backend::config::{Config, TwoFactorConfig};
+use privatechefspace_backend::handlers::two_factor::two_factor_setup;
+use privatechefspace_backend::middleware::auth::Identity;
+use privatechefspace_backend::models::{Role, User};
+use privatechefspace_backend::templates::{TwoFactorLoginTemplate, TwoFactorSetupView, TwoFactorTemplate};
+use uuid::Uuid;
+
+mod common;
+use common::{page_context, signed_in};
+
+fn test_config() -> Config {
+    let mut config = common::test_config();
+    config.two_factor.required_roles = vec![Role::Chef];
+    config
+}
+
+async fn call(req: test::TestRequest) -> actix_web::dev::ServiceResponse {
+    common::call_with(test_config(), req, None).await
+}
+
+fn chef(totp_secret: Option<&str>) -> User {
+    User {
+        id: Uuid::new_v4(),
+        email: "sam@example.com".to_string(),
+        password_hash: None,
+        role: Role::Chef,
+        token_version: 0,
+        email_verified: true,
+        totp_secret: totp_secret.map(str::to_string),
+        totp_enabled: false,
+        totp_last_step: None,
+        suspended_at: None,
+        suspension_reason: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+#[actix_web::test]
+async fn test_required_roles_always_include_admins() {
+    let config = test_config().two_factor;
+    assert!(config.requires(Role::Admin));
+    assert!(config.requires(Role::Chef));
+    assert!(!config.requires(Role::Diner));
+
+    let admins_only = TwoFactorConfig { issuer: "PrivateChefSpace".to_string(), required_roles: vec![] };
+    assert!(admins_only.requires(Role::Admin));
+    assert!(!admins_only.requires(Role::Chef));
+}
+
+#[actix_web::test]
+async fn test_provisioning_uri_and_qr_code() {
+    let setup = two_factor_setup(&test_config(), &chef(Some("JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP"))).unwrap();
+    assert_eq!(setup.secret, "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP");
+    assert!(setup.provisioning_uri.starts_with("otpauth://totp/PrivateChefSpace:sam%40example.com?"));
+    assert!(setup.provisioning_uri.contains("secret=JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP"));
+    assert!(setup.provisioning_uri.contains("issuer=PrivateChefSpace"));
+    assert!(setup.qr_code_svg().unwrap().contains("<svg"));
+}
+
+#[actix_web::test]
+async fn test_setup_needs_a_stored_secret() {
+    assert!(two_factor_setup(&test_config(), &chef(None)).is_err());
+}
+
+#[actix_web::test]
+async fn test_settings_page_states() {
+    let render = |enabled, required, setup, recovery_codes| {
+        TwoFactorTemplate {
+            ctx: page_context(None, vec![]),
+            enabled,
+            required,
+            has_password: true,
+            setup,
+            recovery_codes,
+            error: None,
+        }
+        .render()
+        .unwrap()
+    };
+
+    let html = render(false, true, None, vec![]);
+    assert!(html.contains("action=\"/dashboard/two-factor/setup\""));
+    assert!(html.contains("must use two-factor authentication"));
+
+    let setup = TwoFactorSetupView { secret: "ABCDEF".to_string(), qr_code_svg: "<svg></svg>".to_string() };
+    let html = render(false, false, Some(setup), vec![]);
+    assert!(html.contains("action=\"/dashboard/two-factor/enable\""));
+    assert!(html.contains("<svg></svg>"));
+
+    let html = render(true, false, None, vec!["abcde-12345".to_string()]);
+    assert!(html.contains("abcde-12345"));
+    assert!(html.contains("action=\"/dashboard/two-factor/disable\""));
+
+    let html = render(true, true, None, vec![]);
+    assert!(!html.contains("action=\"/dashboard/two-factor/disable\""));
+
+    let html = TwoFactorTemplate {
+        ctx: page_context(None, vec![]),
+        enabled: true,
+        required: false,
+        has_password: false,
+        setup: None,
+        recovery_codes: vec![],
+        error: None,
+    }
+    .render()
+    .unwrap();
+    assert!(html.contains("action=\"/dashboard/two-factor/disable\""));
+    assert!(!html.contains("name=\"password\""));
+}
+
+#[actix_web::test]
+async fn test_login_code_page_renders_form() {
+    let html = TwoFactorLoginTemplate { ctx: page_context(None, vec![]), error: Some("Invalid authentication code".to_string()) }
+        .render()
+        .unwrap();
+    assert!(html.contains("action=\"/login/two-factor\""));
+    assert!(html.contains("Invalid authentication code"));
+}
+
+#[actix_web::test]
+async fn test_login_code_page_without_challenge_goes_to_login() {
+    let res = call(test::TestRequest::get().uri("/login/two-factor")).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/login");
+}
+
+#[actix_web::test]
+async fn test_enrollment_requires_auth() {
+    for path in ["/api/auth/two-factor/setup", "/api/auth/two-factor/enable", "/api/auth/two-factor/disable"] {
+        let req = test::TestRequest::post().uri(path).set_json(serde_json::json!({ "code": "123456" }));
+        assert_eq!(call(req).await.status(), StatusCode::UNAUTHORIZED, "{}", path);
+    }
+}
+
+/// Signed in with a role that must use 2FA, before setting it up
+fn without_two_factor(role: Role) -> Identity {
+    Identity { two_factor_enabled: false, ..signed_in(role) }
+}
+
+#[actix_web::test]
+async fn test_every_request_waits_for_two_factor_setup() {
+    let booking = format!("/api/bookings/{}", Uuid::new_v4());
+    let req = test::TestRequest::put().uri(&booking).set_json(serde_json::json!({ "payment_status": "paid" }));
+    let res = common::call_with(test_config(), req, Some(without_two_factor(Role::Admin))).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert!(body["error"].as_str().unwrap().contains("Two-factor authentication is required"));
+
+    let req = test::TestRequest::get().uri(&format!("{}/staff", booking));
+    let res = common::call_with(test_config(), req, Some(without_two_factor(Role::Chef))).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get().uri("/dashboard").insert_header((header::ACCEPT, "text/html"));
+    let res = common::call_with(test_config(), req, Some(without_two_factor(Role::Chef))).await;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+    assert_eq!(res.headers().get(header::LOCATION).unwrap(), "/dashboard/two-factor");
+
+    // Diners don't need 2FA here, so they get as far as the missing database
+    let req = test::TestRequest::get().uri("/dashboard").insert_header((header::ACCEPT, "text/html"));
+    let res = common::call_with(test_config(), req, Some(without_two_factor(Role::Diner))).await;
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[actix_web::test]
+async fn test_setting_up_two_factor_stays_open() {
+    // These get past the 2FA check and fail on the missing database instead
+    for path in ["/api/auth/two-factor/setup", "/api/auth/two-factor/enable"] {
+        let req = test::TestRequest::post().uri(path).set_json(serde_json::json!({ "code": "123456" }));
+        let res = common::call_with(test_config(), req, Some(without_two_factor(Role::Admin))).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR, "{}", path);
+    }
+    let req = test::TestRequest::post().uri("/api/auth/two-factor/disable").set_json(serde_json::json!({ "code": "123456" }));
+    let res = common::call_with(test_config(), req, Some(without_two_factor(Role::Admin))).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}